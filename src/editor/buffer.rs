@@ -0,0 +1,1134 @@
+use std::{
+    cell::{Ref, RefCell},
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use crate::{
+    editor::{
+        fold::{Fold, FoldAction, FoldMap},
+        gutter::Sign,
+        history::{Edit, History},
+        row_index::RowIndex,
+        text_format::TextFormat,
+    },
+    node_tree::{
+        cursor::{Cursor, CursorSeek, Selection},
+        diagnostic::Diagnostic,
+        node::{InMemoryNode, NodeMetadata, OffsetUnit, ParseOptionsTrait, TokenKindTrait},
+        outline::Symbol,
+        reparse_scheduler::ReparseScheduler,
+        style::{split_styled_lines, underline_styled_range, HighlightGroup, StyleSpan},
+        utils::{split_key_path, Direction, NEWLINE},
+    },
+};
+
+/// The range of rows that a change to a buffer touched, so that only those rows need to be
+/// redrawn. Rows are one indexed, like `Cursor::to_rows_cols`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineChange {
+    pub start_row: usize,
+    pub old_end_row: usize,
+    pub new_end_row: usize,
+}
+
+/// A diagnostic within a buffer, along with where it starts and ends as one indexed rows and
+/// columns, like `Cursor::to_rows_cols`. The diagnostic's range is in chars from the start of the
+/// buffer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BufferDiagnostic {
+    pub diagnostic: Diagnostic,
+    pub start: (usize, usize),
+    pub end: (usize, usize),
+}
+
+/// A Buffer is a document that is loaded into the editor - the token tree representing the
+/// document's contents, along with metadata about where it came from and how to write it back
+/// out.
+///
+/// The token tree is always a lossless representation of the document, so writing a buffer
+/// that has not been modified reproduces the original file byte for byte.
+pub struct Buffer<TokenKind: TokenKindTrait> {
+    pub number: usize,
+    pub root: Rc<RefCell<InMemoryNode<TokenKind>>>,
+    pub path: Option<PathBuf>,
+    pub text_format: TextFormat,
+    pub cursor: Cursor<TokenKind>,
+    pub history: History,
+
+    // Incremented each time the contents of the buffer change, along with the rows the last change
+    // touched (or None if the whole buffer changed)
+    pub change_id: usize,
+    pub last_change: Option<LineChange>,
+
+    // Where each row of the buffer starts, rebuilt lazily after the buffer changes
+    row_index: RefCell<Option<RowIndex<TokenKind>>>,
+
+    // The diagnostics in the buffer, along with the `change_id` they were collected at
+    diagnostics: RefCell<Option<(usize, Vec<BufferDiagnostic>)>>,
+
+    // The folds in the buffer, along with the `change_id` they were found at
+    folds: RefCell<Option<(usize, Vec<Fold<TokenKind>>)>>,
+
+    // The parts of the token tree that have been edited, but not yet reparsed
+    reparse_scheduler: ReparseScheduler<TokenKind>,
+
+    // Named positions in the buffer set with `m{a-z}`, as offsets (in chars) from the start of the
+    // buffer, which are moved along as edits are made before them
+    marks: BTreeMap<char, usize>,
+
+    // The history state the buffer was in when it was last read from / written to disk
+    saved_state_id: usize,
+
+    // The edits made since `take_edit_log` was last called, so that positions held outside of the
+    // buffer (ie, the cursors of other windows showing it) can be moved along with them. None if
+    // the whole buffer was replaced since, in which case positions stay on the same rows and
+    // columns instead.
+    edit_log: Option<Vec<Edit>>,
+}
+
+impl<TokenKind: TokenKindTrait> Buffer<TokenKind> {
+    pub fn new_from_literal(literal: &str) -> Self {
+        Self::new_from_literal_with_parse_options(literal, TokenKind::ParseOptions::default())
+    }
+
+    pub fn new_from_literal_with_parse_options(
+        literal: &str,
+        parse_options: TokenKind::ParseOptions,
+    ) -> Self {
+        let root = InMemoryNode::new_from_parsed_with_options(literal, parse_options);
+        let history = History::new();
+        Self {
+            number: 0,
+            cursor: Cursor::new(root.clone()),
+            root,
+            path: None,
+            text_format: TextFormat::detect(literal),
+            saved_state_id: history.current_state_id(),
+            history,
+            change_id: 0,
+            last_change: None,
+            row_index: RefCell::new(None),
+            diagnostics: RefCell::new(None),
+            folds: RefCell::new(None),
+            reparse_scheduler: ReparseScheduler::new(),
+            marks: BTreeMap::new(),
+            edit_log: Some(vec![]),
+        }
+    }
+
+    /// When called, reads the file at `path` from disk and parses it into a new buffer.
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let literal = Self::read(path)?;
+        let mut buffer = Self::new_from_literal_with_parse_options(
+            &literal,
+            ParseOptionsTrait::detect(path, &literal),
+        );
+        buffer.path = Some(path.to_path_buf());
+        Ok(buffer)
+    }
+
+    fn read(path: &Path) -> Result<String, String> {
+        let bytes = std::fs::read(path)
+            .map_err(|err| format!("Buffer::open: unable to read {}: {err}", path.display()))?;
+        String::from_utf8(bytes)
+            .map_err(|err| format!("Buffer::open: {} is not valid utf-8: {err}", path.display()))
+    }
+
+    /// When called, returns the full text of the buffer, exactly as it would be written to disk.
+    pub fn literal(&self) -> String {
+        InMemoryNode::deep_literal(&self.root)
+    }
+
+    /// When called, returns the text of `count` rows starting at the (zero indexed) row `start`,
+    /// without their trailing newlines. Fewer than `count` rows are returned if the end of the
+    /// buffer is reached.
+    pub fn lines(&self, start: usize, count: usize) -> Vec<String> {
+        self.styled_lines(start, count)
+            .into_iter()
+            .map(|(line, _spans)| line)
+            .collect()
+    }
+
+    /// When called, returns up to `count` lines starting at the given (zero indexed) row, like
+    /// `lines`, along with the syntax highlighting spans within each line.
+    pub fn styled_lines(&self, start: usize, count: usize) -> Vec<(String, Vec<StyleSpan>)> {
+        if count == 0 {
+            return vec![];
+        }
+        let row_index = self.row_index();
+        let Some(start_cursor) = row_index.row_start(start) else {
+            return vec![];
+        };
+        let end_cursor = match row_index.row_start(start + count) {
+            Some(cursor) => cursor.clone(),
+            None => start_cursor.seek_forwards_until(|_c, _i| CursorSeek::Continue),
+        };
+
+        let (text, spans) = Selection {
+            primary: start_cursor.clone(),
+            secondary: end_cursor,
+        }
+        .literal_spans();
+        let mut lines = split_styled_lines(&text, &spans);
+        lines.truncate(count);
+
+        // Underline the text that each diagnostic is about
+        let diagnostics = self.diagnostics();
+        for (index, (line, spans)) in lines.iter_mut().enumerate() {
+            let row = start + index + 1;
+            for diagnostic in diagnostics.iter() {
+                if diagnostic.start.0 > row || diagnostic.end.0 < row {
+                    continue;
+                }
+                let line_length = line.chars().count();
+                let start_col = if diagnostic.start.0 == row {
+                    diagnostic.start.1 - 1
+                } else {
+                    0
+                };
+                let end_col = if diagnostic.end.0 == row {
+                    diagnostic.end.1 - 1
+                } else {
+                    line_length
+                };
+                // A diagnostic about a position rather than a range of text (ie, a missing
+                // semicolon) underlines the character after it
+                let end_col = end_col.max(start_col + 1).min(line_length);
+                *spans = underline_styled_range(spans, start_col..end_col);
+            }
+        }
+        lines
+    }
+
+    /// When called, returns all diagnostics in the buffer, ordered by where they start. Parts of
+    /// the buffer that haven't been reparsed since they were edited have no diagnostics.
+    pub fn diagnostics(&self) -> Ref<'_, Vec<BufferDiagnostic>> {
+        let is_stale = !matches!(
+            &*self.diagnostics.borrow(),
+            Some((change_id, _)) if *change_id == self.change_id
+        );
+        if is_stale {
+            let diagnostics = InMemoryNode::diagnostics(&self.root)
+                .into_iter()
+                .map(|diagnostic| {
+                    let rows_cols_at = |offset| {
+                        Cursor::from_offset(self.root.clone(), offset, OffsetUnit::Char)
+                            .to_rows_cols()
+                    };
+                    BufferDiagnostic {
+                        start: rows_cols_at(diagnostic.range.start),
+                        end: rows_cols_at(diagnostic.range.end),
+                        diagnostic,
+                    }
+                })
+                .collect();
+            *self.diagnostics.borrow_mut() = Some((self.change_id, diagnostics));
+        }
+        Ref::map(self.diagnostics.borrow(), |diagnostics| {
+            &diagnostics
+                .as_ref()
+                .expect("diagnostics were just collected")
+                .1
+        })
+    }
+
+    /// When called, reparses the buffer and then moves the cursor to the start of the next
+    /// diagnostic in `direction`, like vim's `]d` and `[d`. The search wraps around the ends of
+    /// the buffer. Returns the diagnostic's message.
+    pub fn jump_to_diagnostic(&mut self, direction: Direction) -> Result<String, String> {
+        self.reparse_pending()?;
+
+        let rows_cols = self.cursor.to_rows_cols();
+        let diagnostic = {
+            let diagnostics = self.diagnostics();
+            let found = match direction {
+                Direction::Forwards => diagnostics
+                    .iter()
+                    .find(|diagnostic| diagnostic.start > rows_cols)
+                    .or_else(|| diagnostics.first()),
+                Direction::Backwards => diagnostics
+                    .iter()
+                    .rev()
+                    .find(|diagnostic| diagnostic.start < rows_cols)
+                    .or_else(|| diagnostics.last()),
+            };
+            found.cloned().ok_or_else(|| "E42: No Errors".to_string())?
+        };
+
+        self.cursor = Cursor::new_at_rows_cols(self.root.clone(), diagnostic.start);
+        Ok(format!(
+            "{}: {}",
+            diagnostic.diagnostic.severity.name(),
+            diagnostic.diagnostic.message
+        ))
+    }
+
+    /// When called, reparses the buffer and then moves the cursor to the start of the first node
+    /// with the given key path, ie, `dependencies.foo` in a TOML file. See
+    /// `TokenKindTrait::key_path_segments` for how languages define the key paths of their nodes.
+    pub fn jump_to_key_path(&mut self, path: &str) -> Result<(), String> {
+        self.reparse_pending()?;
+
+        let segments = split_key_path(path);
+        let Some(node) = InMemoryNode::find_key_path(&self.root, &segments) else {
+            return Err(format!("E486: Pattern not found: {path}"));
+        };
+
+        let offset = Cursor::new(node).to_offset(OffsetUnit::Char);
+        self.cursor = Cursor::from_offset(self.root.clone(), offset, OffsetUnit::Char);
+        Ok(())
+    }
+
+    /// When called, reparses the buffer and then returns the symbols defined in it (see
+    /// `Symbol::outline`).
+    pub fn outline(&mut self) -> Result<Vec<Symbol<TokenKind>>, String> {
+        self.reparse_pending()?;
+        Symbol::outline(&self.root)
+    }
+
+    /// When called, returns the folds in the buffer (see `Fold::collect`), finding them again if
+    /// the buffer has changed since they were last found.
+    pub fn folds(&self) -> Ref<'_, Vec<Fold<TokenKind>>> {
+        let is_stale = !matches!(
+            &*self.folds.borrow(),
+            Some((change_id, _)) if *change_id == self.change_id
+        );
+        if is_stale {
+            *self.folds.borrow_mut() = Some((self.change_id, Fold::collect(&self.root)));
+        }
+        Ref::map(self.folds.borrow(), |folds| {
+            &folds.as_ref().expect("folds were just found").1
+        })
+    }
+
+    /// When called, returns the map between the rows of the buffer and the rows they are shown on
+    /// once its closed folds are hidden.
+    pub fn fold_map(&self) -> FoldMap {
+        FoldMap::new(&self.folds())
+    }
+
+    /// When called, reparses the buffer and then opens or closes the folds around the given (one
+    /// indexed) row, like vim's `zo` / `zc` / `za`. Opening opens the outermost closed fold around
+    /// the row, and closing closes the innermost open fold around it (that isn't already hidden
+    /// within a closed fold).
+    pub fn fold(&mut self, row: usize, action: FoldAction) -> Result<(), String> {
+        self.reparse_pending()?;
+
+        let folds = self.folds();
+        let around = folds
+            .iter()
+            .filter(|fold| fold.start_row <= row && row <= fold.end_row)
+            .collect::<Vec<_>>();
+        if around.is_empty() {
+            return Err("E490: No fold found".into());
+        }
+
+        let first_closed = around.iter().position(|fold| fold.is_closed());
+        let innermost_open = around[..first_closed.unwrap_or(around.len())].last();
+        match (action, first_closed) {
+            (FoldAction::Open | FoldAction::Toggle, Some(index)) => around[index].set_closed(false),
+            (FoldAction::Close | FoldAction::Toggle, _) => {
+                if let Some(fold) = innermost_open {
+                    fold.set_closed(true);
+                }
+            }
+            (FoldAction::Open, None) => {}
+        }
+        Ok(())
+    }
+
+    /// When called, reparses the buffer and then opens or closes every fold in it, like vim's
+    /// `zR` / `zM`.
+    pub fn set_all_folds_closed(&mut self, is_closed: bool) -> Result<(), String> {
+        self.reparse_pending()?;
+        for fold in self.folds().iter() {
+            fold.set_closed(is_closed);
+        }
+        Ok(())
+    }
+
+    /// When called, returns the index of where each row in the buffer starts, building it first
+    /// if the buffer has changed since it was last built.
+    pub fn row_index(&self) -> Ref<'_, RowIndex<TokenKind>> {
+        let is_stale = !matches!(
+            &*self.row_index.borrow(),
+            Some(row_index) if row_index.change_id == self.change_id
+        );
+        if is_stale {
+            *self.row_index.borrow_mut() = Some(RowIndex::build(&self.root, self.change_id));
+        }
+        Ref::map(self.row_index.borrow(), |row_index| {
+            row_index.as_ref().expect("row index was just built")
+        })
+    }
+
+    /// When called, returns the number of rows in the buffer. Note that a trailing newline starts
+    /// a new (empty) row.
+    pub fn line_count(&self) -> usize {
+        self.row_index().line_count()
+    }
+
+    /// When called, sets the mark named `name` to the given (one indexed) row and column, like
+    /// vim's `m{a-z}`.
+    pub fn set_mark(&mut self, name: char, rows_cols: (usize, usize)) -> Result<(), String> {
+        if !name.is_ascii_lowercase() {
+            return Err("E191: Argument must be a letter or forward/backward quote".into());
+        }
+        let offset =
+            Cursor::new_at_rows_cols(self.root.clone(), rows_cols).to_offset(OffsetUnit::Char);
+        self.marks.insert(name, offset);
+        Ok(())
+    }
+
+    /// When called, returns the row and column of the mark named `name`.
+    pub fn mark(&self, name: char) -> Result<(usize, usize), String> {
+        self.marks
+            .get(&name)
+            .map(|offset| {
+                Cursor::from_offset(self.root.clone(), *offset, OffsetUnit::Char).to_rows_cols()
+            })
+            .ok_or_else(|| "E20: Mark not set".into())
+    }
+
+    /// When called, returns the signs that should be shown in the sign column next to the rows of
+    /// the buffer.
+    ///
+    /// Each row with diagnostics on it gets a sign for the most severe of them, which is listed
+    /// before (and so is shown instead of) any marks on that row.
+    pub fn signs(&self) -> Vec<Sign> {
+        let mut row_severities = BTreeMap::new();
+        for diagnostic in self.diagnostics().iter() {
+            let severity = diagnostic.diagnostic.severity;
+            row_severities
+                .entry(diagnostic.start.0)
+                .and_modify(|existing| *existing = severity.min(*existing))
+                .or_insert(severity);
+        }
+
+        row_severities
+            .into_iter()
+            .map(|(row, severity)| Sign {
+                row,
+                text: severity.sign_text().into(),
+                group: severity.highlight_group(),
+            })
+            .chain(self.marks.iter().map(|(name, offset)| {
+                Sign {
+                    row: Cursor::from_offset(self.root.clone(), *offset, OffsetUnit::Char)
+                        .to_rows_cols()
+                        .0,
+                    text: format!("'{name}"),
+                    group: HighlightGroup::Mark,
+                }
+            }))
+            .collect()
+    }
+
+    /// When called, returns the name of the language the buffer's contents are parsed as.
+    pub fn language(&self) -> &'static str {
+        self.parse_options()
+            .filetype()
+            .unwrap_or(TokenKind::language_name())
+    }
+
+    /// When called, returns the options the buffer's contents are parsed with, ie, whether a
+    /// javascript file is parsed as a script or as a module.
+    pub fn parse_options(&self) -> TokenKind::ParseOptions {
+        InMemoryNode::parse_options(&self.root)
+    }
+
+    /// When called, changes the options the buffer's contents are parsed with, and reparses the
+    /// whole buffer with them.
+    pub fn set_parse_options(&mut self, options: TokenKind::ParseOptions) -> Result<(), String> {
+        if self.parse_options() == options {
+            return Ok(());
+        }
+        InMemoryNode::set_metadata(&self.root, NodeMetadata::Root(options));
+        self.replace_literal(&self.literal())
+    }
+
+    /// When called, returns the name of the buffer as shown to the user, ie, in `:ls`.
+    pub fn name(&self) -> String {
+        match &self.path {
+            Some(path) => path.display().to_string(),
+            None => "[No Name]".into(),
+        }
+    }
+
+    /// Returns true if the buffer has been changed since it was last read from or written to
+    /// disk.
+    pub fn is_modified(&self) -> bool {
+        self.history.current_state_id() != self.saved_state_id
+    }
+
+    /// When called, writes the buffer out to `path`, or to the path the buffer was opened from if
+    /// `path` is None.
+    pub fn write(&mut self, path: Option<&Path>) -> Result<PathBuf, String> {
+        let Some(path) = path.map(|p| p.to_path_buf()).or_else(|| self.path.clone()) else {
+            return Err("Buffer::write: no file name".into());
+        };
+
+        std::fs::write(&path, self.literal())
+            .map_err(|err| format!("Buffer::write: unable to write {}: {err}", path.display()))?;
+
+        // Like vim, writing a copy of the buffer somewhere else doesn't count as saving it
+        if self.path.is_none() || self.path.as_ref() == Some(&path) {
+            self.path = Some(path.clone());
+            self.saved_state_id = self.history.current_state_id();
+        }
+        Ok(path)
+    }
+
+    /// When called, rereads the buffer's contents from disk, discarding any changes that have
+    /// been made since it was last written. The reload is recorded in the undo history, so it can
+    /// be undone.
+    pub fn reload(&mut self) -> Result<(), String> {
+        let Some(path) = self.path.clone() else {
+            return Err("Buffer::reload: no file name".into());
+        };
+        let literal = Self::read(&path)?;
+
+        self.record_change();
+        self.replace_literal(&literal)?;
+        self.text_format = TextFormat::detect(&literal);
+        self.saved_state_id = self.history.current_state_id();
+        Ok(())
+    }
+
+    /// When called, starts a new change in the undo history. This must be called before each
+    /// change is made to the buffer so that the change can be undone. The edits making up the
+    /// change are recorded as they are made by `splice` and `replace_literal`.
+    pub fn record_change(&mut self) {
+        self.history.record(self.cursor.to_rows_cols());
+    }
+
+    /// When called, undoes the last change made to the buffer. Returns false if there was nothing
+    /// to undo.
+    pub fn undo(&mut self) -> Result<bool, String> {
+        let Some(entry) = self.history.undo(self.cursor.to_rows_cols()) else {
+            return Ok(false);
+        };
+        for edit in entry.edits.iter().rev() {
+            self.apply_edit(edit.offset, &edit.new, &edit.old)?;
+        }
+        self.finish_history_change(entry.rows_cols)?;
+        Ok(true)
+    }
+
+    /// When called, redoes the last change that was undone. Returns false if there was nothing to
+    /// redo.
+    pub fn redo(&mut self) -> Result<bool, String> {
+        let Some(entry) = self.history.redo(self.cursor.to_rows_cols()) else {
+            return Ok(false);
+        };
+        for edit in entry.edits.iter() {
+            self.apply_edit(edit.offset, &edit.old, &edit.new)?;
+        }
+        self.finish_history_change(entry.rows_cols)?;
+        Ok(true)
+    }
+
+    /// When called, replaces the text `old` found `offset` chars into the buffer with `new`,
+    /// without recording it in the undo history.
+    fn apply_edit(&mut self, offset: usize, old: &str, new: &str) -> Result<(), String> {
+        let selection = Selection::new_from_cursor_pair(
+            Cursor::from_offset(self.root.clone(), offset, OffsetUnit::Char),
+            Cursor::from_offset(
+                self.root.clone(),
+                offset + old.chars().count(),
+                OffsetUnit::Char,
+            ),
+        );
+        self.splice_without_history(&selection, new)?;
+        Ok(())
+    }
+
+    /// When called after the edits of an undo / redo have been applied, reparses the parts of the
+    /// buffer they touched and moves the cursor back to where it was when the change was made.
+    fn finish_history_change(&mut self, rows_cols: (usize, usize)) -> Result<(), String> {
+        self.reparse_pending()?;
+        self.text_format = TextFormat::detect(&self.literal());
+        self.cursor = Cursor::new_at_rows_cols(self.root.clone(), rows_cols);
+        Ok(())
+    }
+
+    /// When called, deletes the character underneath the cursor, like vim's `x`. A character made
+    /// up of multiple chars (ie, an emoji with a skin tone modifier) is deleted all at once.
+    /// Newlines are never deleted, so `x` at the end of a line does nothing.
+    pub fn delete_char_under_cursor(&mut self) -> Result<(), String> {
+        let mut selection = self.cursor.selection();
+        selection.set_secondary(selection.secondary.seek_graphemes(Direction::Forwards, 1));
+
+        let literal = selection.literal();
+        if literal.is_empty() || literal.contains(*NEWLINE) {
+            return Ok(());
+        }
+
+        let rows_cols = self.cursor.to_rows_cols();
+        self.record_change();
+        self.splice(&selection, "")?;
+
+        // The delete may have removed the node the cursor was in, so relocate it into the new
+        // token tree
+        self.cursor = Cursor::new_at_rows_cols(self.root.clone(), rows_cols);
+        Ok(())
+    }
+
+    /// When called, replaces the text covered by `selection` with `literal`, keeping track of
+    /// which rows were changed. The edit is added to the change that was last recorded with
+    /// `record_change`. The result isn't reparsed until `reparse_pending` is called.
+    pub fn splice(
+        &mut self,
+        selection: &Selection<TokenKind>,
+        literal: &str,
+    ) -> Result<(), String> {
+        let edit = self.splice_without_history(selection, literal)?;
+        self.history.push_edit(edit);
+        Ok(())
+    }
+
+    fn selection_start(selection: &Selection<TokenKind>) -> &Cursor<TokenKind> {
+        if selection.primary < selection.secondary {
+            &selection.primary
+        } else {
+            &selection.secondary
+        }
+    }
+
+    /// When called, replaces the text covered by `selection` with `literal` like `splice`, but
+    /// without recording it in the undo history. Returns the edit that was made.
+    fn splice_without_history(
+        &mut self,
+        selection: &Selection<TokenKind>,
+        literal: &str,
+    ) -> Result<Edit, String> {
+        let start = Self::selection_start(selection);
+        let (start_row, _) = start.to_rows_cols();
+        let edit = Edit {
+            offset: start.to_offset(OffsetUnit::Char),
+            old: selection.literal(),
+            new: literal.into(),
+        };
+        let old_newline_count = edit.old.matches(*NEWLINE).count();
+        let new_newline_count = literal.matches(*NEWLINE).count();
+
+        selection.replace_deferred(literal, &mut self.reparse_scheduler)?;
+
+        let change = LineChange {
+            start_row,
+            old_end_row: start_row + old_newline_count,
+            new_end_row: start_row + new_newline_count,
+        };
+        self.change_id += 1;
+        self.last_change = Some(change);
+
+        // Like vim, marks after the change move along with the text they were set on, and marks
+        // within text that was replaced move to the end of the change
+        for offset in self.marks.values_mut() {
+            *offset = edit.shift(*offset);
+        }
+        if let Some(edit_log) = &mut self.edit_log {
+            edit_log.push(edit.clone());
+        }
+        Ok(edit)
+    }
+
+    /// When called, returns the edits made to the buffer since this was last called, in the order
+    /// they were made, or None if the whole buffer was replaced in the meantime.
+    pub fn take_edit_log(&mut self) -> Option<Vec<Edit>> {
+        self.edit_log.replace(vec![])
+    }
+
+    /// Returns true if the buffer has been edited since it was last reparsed.
+    pub fn has_pending_reparse(&self) -> bool {
+        self.reparse_scheduler.is_pending()
+    }
+
+    /// When called, reparses all parts of the buffer that have been edited since it was last
+    /// reparsed, so that the token tree is up to date. This should be called before using anything
+    /// that relies on the structure of the token tree. Returns false if there was nothing to
+    /// reparse.
+    pub fn reparse_pending(&mut self) -> Result<bool, String> {
+        if !self.has_pending_reparse() {
+            return Ok(false);
+        }
+
+        // The text doesn't change, so the cursor is still the same number of chars into it
+        let offset = self.cursor.to_offset(OffsetUnit::Char);
+        self.reparse_scheduler.flush()?;
+
+        // The text didn't change, but the nodes (and how they are highlighted) may have
+        self.change_id += 1;
+        self.last_change = None;
+
+        self.cursor = Cursor::from_offset(self.root.clone(), offset, OffsetUnit::Char);
+        Ok(true)
+    }
+
+    /// When called, converts the document to be in the given text format - ie, switching line
+    /// endings from "\r\n" to "\n" - and reparses the result.
+    pub fn set_text_format(&mut self, text_format: TextFormat) -> Result<(), String> {
+        if self.text_format == text_format {
+            return Ok(());
+        }
+
+        let converted = text_format.apply(&self.literal());
+        self.record_change();
+        self.replace_literal(&converted)?;
+        self.text_format = text_format;
+        Ok(())
+    }
+
+    /// When called, replaces the whole contents of the buffer with a freshly parsed token tree
+    /// generated from `literal`. The cursor is moved into the new token tree at the same row and
+    /// column it was at before. Only the part of the buffer that differs is added to the change
+    /// that was last recorded with `record_change`.
+    ///
+    /// Note that the root node is kept in place so that anything holding a reference to it
+    /// continues to see the buffer's contents.
+    pub fn replace_literal(&mut self, literal: &str) -> Result<(), String> {
+        let rows_cols = self.cursor.to_rows_cols();
+        let mark_rows_cols = self
+            .marks
+            .keys()
+            .map(|name| (*name, self.mark(*name).unwrap()))
+            .collect::<Vec<_>>();
+        if let Some(edit) = Edit::between(&self.literal(), literal) {
+            self.history.push_edit(edit);
+        }
+
+        let new_child = TokenKind::parse(literal, Some(self.root.clone()));
+        if self.root.borrow().children.is_empty() {
+            InMemoryNode::append_child(&self.root, new_child);
+        } else {
+            InMemoryNode::swap_child_at_index(&self.root, 0, new_child)?;
+        }
+        self.reparse_scheduler.clear();
+        self.change_id += 1;
+        self.last_change = None;
+        self.edit_log = None;
+
+        // Most of the buffer may have been replaced (ie, by switching its line endings), so keep
+        // the cursor and marks on the same rows and columns rather than moving them to the end of
+        // the replaced text
+        self.cursor = Cursor::new_at_rows_cols(self.root.clone(), rows_cols);
+        for (name, rows_cols) in mark_rows_cols {
+            self.set_mark(name, rows_cols)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        languages::{self, typescript::ParseMode},
+        node_tree::{
+            diagnostic::Severity,
+            style::{HighlightGroup, Style},
+            theme::Theme,
+        },
+    };
+
+    #[test]
+    fn undo_and_redo_track_modified() {
+        let mut buffer = Buffer::<languages::raw::SyntaxKind>::new_from_literal("abc\ndef\n");
+        assert!(!buffer.is_modified());
+
+        buffer.delete_char_under_cursor().unwrap();
+        buffer.delete_char_under_cursor().unwrap();
+        assert_eq!(buffer.literal(), "c\ndef\n");
+        assert!(buffer.is_modified());
+
+        assert!(buffer.undo().unwrap());
+        assert_eq!(buffer.literal(), "bc\ndef\n");
+        assert!(buffer.undo().unwrap());
+        assert_eq!(buffer.literal(), "abc\ndef\n");
+        assert!(!buffer.is_modified());
+        assert!(!buffer.undo().unwrap());
+
+        assert!(buffer.redo().unwrap());
+        assert_eq!(buffer.literal(), "bc\ndef\n");
+        assert!(buffer.is_modified());
+
+        // A new change clears the redo stack
+        buffer.delete_char_under_cursor().unwrap();
+        assert!(!buffer.redo().unwrap());
+        assert_eq!(buffer.literal(), "c\ndef\n");
+    }
+
+    #[test]
+    fn undo_and_redo_replay_edited_ranges() {
+        let mut buffer =
+            Buffer::<languages::raw::SyntaxKind>::new_from_literal("one\ntwo\nthree\n");
+
+        // Each change only keeps the text that it edited, not the whole buffer
+        buffer.record_change();
+        let two = Selection::new_from_cursor_pair(
+            Cursor::new_at_rows_cols(buffer.root.clone(), (2, 1)),
+            Cursor::new_at_rows_cols(buffer.root.clone(), (2, 4)),
+        );
+        buffer.splice(&two, "2").unwrap();
+        let start = Cursor::new(buffer.root.clone()).selection();
+        buffer.splice(&start, "zero\n").unwrap();
+        assert_eq!(buffer.literal(), "zero\none\n2\nthree\n");
+
+        buffer
+            .set_text_format(TextFormat {
+                has_trailing_newline: true,
+                ..TextFormat::detect("a\r\n")
+            })
+            .unwrap();
+        assert_eq!(buffer.literal(), "zero\r\none\r\n2\r\nthree\r\n");
+
+        let entry = buffer.history.undo((1, 1)).unwrap();
+        assert_eq!(
+            entry.edits,
+            vec![Edit {
+                offset: 4,
+                old: "\none\n2\nthree".into(),
+                new: "\r\none\r\n2\r\nthree\r".into(),
+            }]
+        );
+        buffer.history.redo((1, 1)).unwrap();
+
+        assert!(buffer.undo().unwrap());
+        assert_eq!(buffer.literal(), "zero\none\n2\nthree\n");
+        assert!(buffer.undo().unwrap());
+        assert_eq!(buffer.literal(), "one\ntwo\nthree\n");
+        assert!(!buffer.is_modified());
+
+        assert!(buffer.redo().unwrap());
+        assert_eq!(buffer.literal(), "zero\none\n2\nthree\n");
+        assert!(buffer.redo().unwrap());
+        assert_eq!(buffer.literal(), "zero\r\none\r\n2\r\nthree\r\n");
+        assert_eq!(buffer.text_format.file_format.name(), "dos");
+    }
+
+    #[test]
+    fn lines() {
+        let buffer = Buffer::<languages::raw::SyntaxKind>::new_from_literal("one\ntwo\n\nfour");
+        assert_eq!(buffer.lines(0, 2), vec!["one", "two"]);
+        assert_eq!(buffer.lines(1, 10), vec!["two", "", "four"]);
+        assert_eq!(buffer.lines(3, 1), vec!["four"]);
+        assert_eq!(buffer.lines(4, 1), Vec::<String>::new());
+    }
+
+    #[test]
+    fn marks_follow_changes() {
+        let mut buffer = Buffer::<languages::raw::SyntaxKind>::new_from_literal("one\ntwo\nthree");
+        buffer.set_mark('a', (1, 2)).unwrap();
+        buffer.set_mark('b', (3, 1)).unwrap();
+        buffer.set_mark('c', (2, 3)).unwrap();
+        assert_eq!(buffer.mark('d'), Err("E20: Mark not set".into()));
+        assert_eq!(buffer.line_count(), 3);
+
+        let mut selection = Cursor::new_at_rows_cols(buffer.root.clone(), (2, 1)).selection();
+        selection.set_secondary(
+            selection
+                .secondary
+                .seek_forwards(CursorSeek::AdvanceByCharCount(1)),
+        );
+        buffer.splice(&selection, "new\nrows\nt").unwrap();
+        assert_eq!(buffer.literal(), "one\nnew\nrows\ntwo\nthree");
+        assert_eq!(buffer.line_count(), 5);
+        assert_eq!(buffer.lines(3, 2), vec!["two", "three"]);
+        assert_eq!(buffer.mark('a'), Ok((1, 2)));
+        assert_eq!(buffer.mark('b'), Ok((5, 1)));
+        assert_eq!(
+            buffer
+                .signs()
+                .iter()
+                .map(|sign| sign.row)
+                .collect::<Vec<_>>(),
+            vec![1, 5, 4]
+        );
+
+        // A mark stays on the character it was set on, even when the text before it on the same
+        // row changes
+        assert_eq!(buffer.mark('c'), Ok((4, 3)));
+        buffer.reparse_pending().unwrap();
+        assert_eq!(buffer.mark('c'), Ok((4, 3)));
+    }
+
+    #[test]
+    fn styled_lines() {
+        let buffer = Buffer::<languages::typescript::SyntaxKind>::new_from_literal(
+            "let a = 1;\n// two\nfunction b() { return 'three'; }",
+        );
+        let lines = buffer.styled_lines(0, 3);
+        assert_eq!(
+            lines
+                .iter()
+                .map(|(line, _)| line.as_str())
+                .collect::<Vec<_>>(),
+            vec!["let a = 1;", "// two", "function b() { return 'three'; }"]
+        );
+
+        let theme = Theme::dark();
+        let style = |group| theme.style(Some(group));
+        let styled = |(line, spans): &(String, Vec<StyleSpan>)| {
+            spans
+                .iter()
+                .filter(|span| span.style != Style::default())
+                .map(|span| {
+                    let text = line
+                        .chars()
+                        .skip(span.range.start)
+                        .take(span.range.len())
+                        .collect::<String>();
+                    (text, span.style)
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            styled(&lines[0]),
+            vec![
+                ("let".to_string(), style(HighlightGroup::Keyword)),
+                ("a".to_string(), style(HighlightGroup::VariableDecl)),
+                ("1".to_string(), style(HighlightGroup::Number)),
+            ]
+        );
+        assert_eq!(
+            styled(&lines[1]),
+            vec![("// two".to_string(), style(HighlightGroup::Comment))]
+        );
+        assert_eq!(
+            styled(&lines[2]),
+            vec![
+                ("function".to_string(), style(HighlightGroup::Keyword)),
+                ("b".to_string(), style(HighlightGroup::FunctionName)),
+                ("return".to_string(), style(HighlightGroup::Keyword)),
+                ("'three'".to_string(), style(HighlightGroup::String)),
+            ]
+        );
+    }
+
+    #[test]
+    fn delete_char_under_cursor_keeps_newlines() {
+        let mut buffer = Buffer::<languages::raw::SyntaxKind>::new_from_literal("\nabc");
+        buffer.delete_char_under_cursor().unwrap();
+        assert_eq!(buffer.literal(), "\nabc");
+        assert!(!buffer.is_modified());
+
+        buffer.cursor = Cursor::new_at_rows_cols(buffer.root.clone(), (2, 2));
+        buffer.delete_char_under_cursor().unwrap();
+        assert_eq!(buffer.literal(), "\nac");
+        assert_eq!(
+            buffer.last_change,
+            Some(LineChange {
+                start_row: 2,
+                old_end_row: 2,
+                new_end_row: 2
+            })
+        );
+    }
+
+    #[test]
+    fn delete_char_under_cursor_deletes_grapheme_clusters() {
+        let mut buffer = Buffer::<languages::raw::SyntaxKind>::new_from_literal("a👍🏽e\u{301}b\r\n");
+        buffer.cursor = Cursor::new_at_rows_cols(buffer.root.clone(), (1, 2));
+        buffer.delete_char_under_cursor().unwrap();
+        assert_eq!(buffer.literal(), "ae\u{301}b\r\n");
+        buffer.delete_char_under_cursor().unwrap();
+        assert_eq!(buffer.literal(), "ab\r\n");
+
+        // A `\r\n` line ending is a single grapheme cluster, and is kept like a `\n` would be
+        buffer.cursor = Cursor::new_at_rows_cols(buffer.root.clone(), (1, 3));
+        buffer.delete_char_under_cursor().unwrap();
+        assert_eq!(buffer.literal(), "ab\r\n");
+    }
+
+    #[test]
+    fn edits_are_reparsed_when_requested() {
+        let mut buffer = Buffer::<languages::typescript::SyntaxKind>::new_from_literal(
+            "let a = 1;
+let c;
+",
+        );
+        let selection = Selection::new_from_cursor_pair(
+            Cursor::new_at_rows_cols(buffer.root.clone(), (1, 5)),
+            Cursor::new_at_rows_cols(buffer.root.clone(), (1, 10)),
+        );
+        buffer.splice(&selection, "b = 'two'").unwrap();
+        buffer.cursor = Cursor::new_at_rows_cols(buffer.root.clone(), (2, 5));
+        assert_eq!(buffer.literal(), "let b = 'two';\nlet c;\n");
+
+        // Until the buffer is reparsed, the edited text isn't highlighted
+        let is_string_highlighted = |buffer: &Buffer<_>| {
+            let (_, spans) = &buffer.styled_lines(0, 1)[0];
+            let style = Theme::dark().style(Some(HighlightGroup::String));
+            spans.iter().any(|span| span.style == style)
+        };
+        assert!(buffer.has_pending_reparse());
+        assert!(!is_string_highlighted(&buffer));
+
+        assert!(buffer.reparse_pending().unwrap());
+        assert!(!buffer.has_pending_reparse());
+        assert!(!buffer.reparse_pending().unwrap());
+        assert!(is_string_highlighted(&buffer));
+        assert_eq!(buffer.literal(), "let b = 'two';\nlet c;\n");
+        assert_eq!(buffer.cursor.to_rows_cols(), (2, 5));
+    }
+
+    #[test]
+    fn diagnostics() {
+        let mut buffer = Buffer::<languages::typescript::SyntaxKind>::new_from_literal(
+            "let a = 1;\nlet b = ;\nlet c = 3;\n",
+        );
+        let message = "expected an expression, but found `;` instead";
+        assert_eq!(
+            *buffer.diagnostics(),
+            vec![BufferDiagnostic {
+                diagnostic: Diagnostic::new(19..20, Severity::Error, message),
+                start: (2, 9),
+                end: (2, 10),
+            }]
+        );
+        assert_eq!(
+            buffer.signs(),
+            vec![Sign {
+                row: 2,
+                text: "E".into(),
+                group: HighlightGroup::DiagnosticError,
+            }]
+        );
+        let (_, spans) = &buffer.styled_lines(1, 1)[0];
+        let underlined = spans
+            .iter()
+            .filter(|span| span.style.underline)
+            .map(|span| span.range.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(underlined, vec![8..9]);
+
+        // Introduce a second error, which is found once the edit is reparsed
+        let selection = Selection::new_from_cursor_pair(
+            Cursor::new_at_rows_cols(buffer.root.clone(), (3, 9)),
+            Cursor::new_at_rows_cols(buffer.root.clone(), (3, 10)),
+        );
+        buffer.splice(&selection, "").unwrap();
+        assert_eq!(
+            buffer.jump_to_diagnostic(Direction::Forwards),
+            Ok(format!("error: {message}"))
+        );
+        assert_eq!(buffer.cursor.to_rows_cols(), (2, 9));
+        assert_eq!(
+            buffer.jump_to_diagnostic(Direction::Forwards),
+            Ok(format!("error: {message}"))
+        );
+        assert_eq!(buffer.cursor.to_rows_cols(), (3, 9));
+        // Jumping wraps around the ends of the buffer
+        buffer.jump_to_diagnostic(Direction::Forwards).unwrap();
+        assert_eq!(buffer.cursor.to_rows_cols(), (2, 9));
+        buffer.jump_to_diagnostic(Direction::Backwards).unwrap();
+        assert_eq!(buffer.cursor.to_rows_cols(), (3, 9));
+
+        // Fixing both errors clears their diagnostics
+        for row in [2, 3] {
+            let selection = Selection::new_from_cursor_pair(
+                Cursor::new_at_rows_cols(buffer.root.clone(), (row, 9)),
+                Cursor::new_at_rows_cols(buffer.root.clone(), (row, 10)),
+            );
+            buffer.splice(&selection, "2;").unwrap();
+        }
+        assert_eq!(buffer.literal(), "let a = 1;\nlet b = 2;\nlet c = 2;\n");
+        assert_eq!(
+            buffer.jump_to_diagnostic(Direction::Forwards),
+            Err("E42: No Errors".into())
+        );
+        assert!(buffer.signs().is_empty());
+    }
+
+    #[test]
+    fn parse_options() {
+        let literal = "import { a } from './a';\nlet b: number = a;\n";
+        let mut buffer = Buffer::<languages::typescript::SyntaxKind>::new_from_literal(literal);
+        assert_eq!(buffer.parse_options(), ParseMode::TypeScript);
+        assert!(buffer.diagnostics().is_empty());
+
+        // Edits are reparsed with the same options as the rest of the buffer
+        let selection = Selection::new_from_cursor_pair(
+            Cursor::new_at_rows_cols(buffer.root.clone(), (2, 8)),
+            Cursor::new_at_rows_cols(buffer.root.clone(), (2, 14)),
+        );
+        buffer.splice(&selection, "string").unwrap();
+        buffer.reparse_pending().unwrap();
+        assert!(buffer.diagnostics().is_empty());
+
+        buffer.set_parse_options(ParseMode::Script).unwrap();
+        assert_eq!(buffer.parse_options(), ParseMode::Script);
+        assert_eq!(
+            buffer.literal(),
+            "import { a } from './a';\nlet b: string = a;\n"
+        );
+        assert!(!buffer.diagnostics().is_empty());
+    }
+
+    #[test]
+    fn jumps_to_key_paths() {
+        let mut buffer = Buffer::<languages::toml::SyntaxKind>::new_from_literal(
+            "[package]\nname = \"a\"\n\n[dependencies]\nfoo = { version = \"1\" }\n",
+        );
+        buffer.jump_to_key_path("dependencies.foo.version").unwrap();
+        assert_eq!(buffer.cursor.to_rows_cols(), (5, 9));
+        buffer.jump_to_key_path("package").unwrap();
+        assert_eq!(buffer.cursor.to_rows_cols(), (1, 1));
+        assert_eq!(
+            buffer.jump_to_key_path("package.missing"),
+            Err("E486: Pattern not found: package.missing".into())
+        );
+        assert_eq!(buffer.cursor.to_rows_cols(), (1, 1));
+    }
+
+    #[test]
+    fn folds() {
+        let mut buffer = Buffer::<languages::typescript::SyntaxKind>::new_from_literal(
+            "let a = 1;\nfunction foo() {\n  if (a) {\n    return 1;\n  }\n}\n",
+        );
+        let closed_rows = |buffer: &Buffer<_>| {
+            buffer
+                .folds()
+                .iter()
+                .filter(|fold| fold.is_closed())
+                .map(|fold| (fold.start_row, fold.end_row))
+                .collect::<Vec<_>>()
+        };
+
+        // Closing closes the innermost open fold, and opening opens the outermost closed fold
+        buffer.fold(4, FoldAction::Close).unwrap();
+        assert_eq!(closed_rows(&buffer), vec![(3, 5)]);
+        buffer.fold(4, FoldAction::Close).unwrap();
+        assert_eq!(closed_rows(&buffer), vec![(2, 6), (3, 5)]);
+        assert_eq!(buffer.fold_map().to_display_row(7), 3);
+        buffer.fold(4, FoldAction::Toggle).unwrap();
+        assert_eq!(closed_rows(&buffer), vec![(3, 5)]);
+        assert_eq!(
+            buffer.fold(1, FoldAction::Toggle),
+            Err("E490: No fold found".into())
+        );
+
+        // Folds stay closed when rows are added before them
+        let selection = Selection::new_from_cursor_pair(
+            Cursor::new_at_rows_cols(buffer.root.clone(), (1, 9)),
+            Cursor::new_at_rows_cols(buffer.root.clone(), (1, 10)),
+        );
+        buffer.record_change();
+        buffer.splice(&selection, "1;\nlet b = 2").unwrap();
+        buffer.reparse_pending().unwrap();
+        assert_eq!(closed_rows(&buffer), vec![(4, 6)]);
+
+        // Undoing and redoing the edit only reparses the text it touched, so the fold stays closed
+        assert!(buffer.undo().unwrap());
+        assert_eq!(closed_rows(&buffer), vec![(3, 5)]);
+        assert!(buffer.redo().unwrap());
+        assert_eq!(closed_rows(&buffer), vec![(4, 6)]);
+
+        buffer.set_all_folds_closed(true).unwrap();
+        assert_eq!(closed_rows(&buffer), vec![(3, 7), (4, 6)]);
+        buffer.set_all_folds_closed(false).unwrap();
+        assert!(buffer.fold_map().is_empty());
+    }
+}
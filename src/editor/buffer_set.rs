@@ -0,0 +1,232 @@
+use std::path::Path;
+
+use crate::{
+    editor::buffer::Buffer,
+    node_tree::node::{ParseOptionsTrait, TokenKindTrait},
+};
+
+/// A BufferSet holds all the buffers that are currently loaded into the editor, along with which
+/// one of them is currently being shown.
+///
+/// Like vim, each buffer is given a number when it is added to the set, and that number stays the
+/// same for as long as the buffer is loaded, even if other buffers are deleted.
+pub struct BufferSet<TokenKind: TokenKindTrait> {
+    buffers: Vec<Buffer<TokenKind>>,
+    current_index: usize,
+    next_number: usize,
+}
+
+impl<TokenKind: TokenKindTrait> BufferSet<TokenKind> {
+    pub fn new(buffer: Buffer<TokenKind>) -> Self {
+        let mut buffer_set = Self {
+            buffers: vec![],
+            current_index: 0,
+            next_number: 1,
+        };
+        buffer_set.add(buffer);
+        buffer_set
+    }
+
+    /// When called, adds the given buffer into the set and makes it the current buffer, returning
+    /// the number that it was assigned.
+    pub fn add(&mut self, mut buffer: Buffer<TokenKind>) -> usize {
+        buffer.number = self.next_number;
+        self.next_number += 1;
+
+        self.buffers.push(buffer);
+        self.current_index = self.buffers.len() - 1;
+        self.buffers[self.current_index].number
+    }
+
+    pub fn current(&self) -> &Buffer<TokenKind> {
+        &self.buffers[self.current_index]
+    }
+
+    pub fn current_mut(&mut self) -> &mut Buffer<TokenKind> {
+        &mut self.buffers[self.current_index]
+    }
+
+    pub fn get(&self, number: usize) -> Option<&Buffer<TokenKind>> {
+        self.buffers.iter().find(|buffer| buffer.number == number)
+    }
+
+    pub fn get_mut(&mut self, number: usize) -> Option<&mut Buffer<TokenKind>> {
+        self.buffers
+            .iter_mut()
+            .find(|buffer| buffer.number == number)
+    }
+
+    fn index_of(&self, number: usize) -> Result<usize, String> {
+        self.buffers
+            .iter()
+            .position(|buffer| buffer.number == number)
+            .ok_or_else(|| format!("E86: Buffer {number} does not exist"))
+    }
+
+    /// When called, makes the buffer with the given number the current buffer.
+    pub fn switch_to(&mut self, number: usize) -> Result<(), String> {
+        self.current_index = self.index_of(number)?;
+        Ok(())
+    }
+
+    /// When called, makes the buffer after the current buffer the current buffer, wrapping around
+    /// to the first buffer after the last one.
+    pub fn next(&mut self) {
+        self.current_index = (self.current_index + 1) % self.buffers.len();
+    }
+
+    /// When called, makes the buffer before the current buffer the current buffer, wrapping around
+    /// to the last buffer before the first one.
+    pub fn previous(&mut self) {
+        self.current_index = (self.current_index + self.buffers.len() - 1) % self.buffers.len();
+    }
+
+    /// When called, makes the buffer for the file at `path` the current buffer. If the file isn't
+    /// already loaded, it is read from disk, and if it doesn't exist on disk, a new empty buffer is
+    /// created that will be written to `path`.
+    pub fn edit(&mut self, path: &Path) -> Result<(), String> {
+        let canonical_path = std::fs::canonicalize(path).ok();
+        let existing_index = self.buffers.iter().position(|buffer| {
+            let Some(buffer_path) = &buffer.path else {
+                return false;
+            };
+            buffer_path == path
+                || (canonical_path.is_some()
+                    && std::fs::canonicalize(buffer_path).ok() == canonical_path)
+        });
+        if let Some(index) = existing_index {
+            self.current_index = index;
+            return Ok(());
+        }
+
+        let buffer = if path.exists() {
+            Buffer::open(path)?
+        } else {
+            let mut buffer =
+                Buffer::new_from_literal_with_parse_options("", ParseOptionsTrait::from_path(path));
+            buffer.path = Some(path.to_path_buf());
+            buffer
+        };
+        self.add(buffer);
+        Ok(())
+    }
+
+    /// When called, removes the buffer with the given number (or the current buffer, if None) from
+    /// the set. Unless `force` is set, buffers with unsaved changes are not deleted.
+    ///
+    /// Like vim, deleting the last remaining buffer replaces it with a new empty buffer.
+    pub fn delete(&mut self, number: Option<usize>, force: bool) -> Result<(), String> {
+        let index = match number {
+            Some(number) => self.index_of(number)?,
+            None => self.current_index,
+        };
+
+        let buffer = &self.buffers[index];
+        if buffer.is_modified() && !force {
+            return Err(format!(
+                "E89: No write since last change for buffer {} (add ! to override)",
+                buffer.number
+            ));
+        }
+
+        self.buffers.remove(index);
+        if self.buffers.is_empty() {
+            self.add(Buffer::new_from_literal(""));
+        } else if self.current_index > index || self.current_index == self.buffers.len() {
+            self.current_index -= 1;
+        }
+        Ok(())
+    }
+
+    /// When called, returns an error describing the first buffer with unsaved changes, if there
+    /// is one. This is used to stop the editor from exiting while there is unsaved work.
+    pub fn check_all_written(&self) -> Result<(), String> {
+        if self.current().is_modified() {
+            return Err("E37: No write since last change (add ! to override)".into());
+        }
+        match self.buffers.iter().find(|buffer| buffer.is_modified()) {
+            Some(buffer) => Err(format!(
+                "E162: No write since last change for buffer \"{}\"",
+                buffer.name()
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// When called, generates the buffer list shown by `:ls`, one buffer per line. Like vim, the
+    /// current buffer is marked with `%` and buffers with unsaved changes are marked with `+`.
+    pub fn list(&self) -> String {
+        self.buffers
+            .iter()
+            .enumerate()
+            .map(|(index, buffer)| {
+                let (rows, _) = buffer.cursor.to_rows_cols();
+                format!(
+                    "{:>3} {}{} \"{}\" [{}] line {rows}",
+                    buffer.number,
+                    if index == self.current_index {
+                        '%'
+                    } else {
+                        ' '
+                    },
+                    if buffer.is_modified() { '+' } else { ' ' },
+                    buffer.name(),
+                    buffer.language(),
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::languages;
+
+    fn new_buffer_set() -> BufferSet<languages::raw::SyntaxKind> {
+        let mut buffer_set = BufferSet::new(Buffer::new_from_literal("one"));
+        buffer_set.add(Buffer::new_from_literal("two"));
+        buffer_set.add(Buffer::new_from_literal("three"));
+        buffer_set
+    }
+
+    #[test]
+    fn switching_buffers() {
+        let mut buffer_set = new_buffer_set();
+        assert_eq!(buffer_set.current().literal(), "three");
+
+        buffer_set.next();
+        assert_eq!(buffer_set.current().literal(), "one");
+        buffer_set.previous();
+        assert_eq!(buffer_set.current().literal(), "three");
+        buffer_set.switch_to(2).unwrap();
+        assert_eq!(buffer_set.current().literal(), "two");
+        assert!(buffer_set.switch_to(4).is_err());
+    }
+
+    #[test]
+    fn deleting_buffers() {
+        let mut buffer_set = new_buffer_set();
+
+        // Buffers with unsaved changes can only be deleted with `force`
+        buffer_set.switch_to(2).unwrap();
+        buffer_set.current_mut().delete_char_under_cursor().unwrap();
+        assert!(buffer_set.delete(None, false).is_err());
+        assert!(buffer_set.check_all_written().is_err());
+        buffer_set.delete(None, true).unwrap();
+        assert!(buffer_set.check_all_written().is_ok());
+
+        // Buffer numbers stay the same after a delete
+        assert_eq!(buffer_set.current().number, 3);
+        buffer_set.delete(Some(1), false).unwrap();
+        assert_eq!(buffer_set.current().number, 3);
+        assert!(buffer_set.switch_to(1).is_err());
+
+        // Deleting the last buffer leaves an empty buffer behind
+        buffer_set.delete(None, false).unwrap();
+        assert_eq!(buffer_set.list().lines().count(), 1);
+        assert_eq!(buffer_set.current().literal(), "");
+        assert_eq!(buffer_set.current().number, 4);
+    }
+}
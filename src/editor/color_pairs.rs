@@ -0,0 +1,123 @@
+use std::{cell::RefCell, collections::HashMap};
+
+use pancurses::{
+    chtype, has_colors, init_pair, start_color, use_default_colors, A_BOLD, A_ITALIC, A_REVERSE,
+    A_UNDERLINE, COLORS, COLOR_BLACK, COLOR_BLUE, COLOR_CYAN, COLOR_GREEN, COLOR_MAGENTA,
+    COLOR_PAIR, COLOR_PAIRS, COLOR_RED, COLOR_WHITE, COLOR_YELLOW,
+};
+
+use crate::node_tree::style::{Color, Style};
+
+// The foreground and background colors of a color pair
+type ColorPairKey = (Option<Color>, Option<Color>);
+
+/// ColorPairs converts terminal-agnostic `Style`s into curses attributes.
+///
+/// Curses can only draw text in a color by first registering a "color pair" (a foreground and
+/// background color) with a numeric id, so pairs are registered lazily the first time a style
+/// needs them and cached from then on.
+pub struct ColorPairs {
+    enabled: bool,
+    pairs: RefCell<HashMap<ColorPairKey, i16>>,
+}
+
+impl ColorPairs {
+    /// When called, sets up color support for the current terminal. Must be called after
+    /// `initscr`. If the terminal does not support colors, styles will only apply bold / italic.
+    pub fn new() -> Self {
+        let enabled = has_colors();
+        if enabled {
+            start_color();
+            use_default_colors();
+        }
+        Self {
+            enabled,
+            pairs: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// When called, returns the curses attributes that text should be drawn with to render it
+    /// with the given style.
+    pub fn attributes(&self, style: Style) -> chtype {
+        let mut attributes = 0;
+        if style.bold {
+            attributes |= A_BOLD;
+        }
+        if style.italic {
+            attributes |= A_ITALIC;
+        }
+        if style.reverse {
+            attributes |= A_REVERSE;
+        }
+        if style.underline {
+            attributes |= A_UNDERLINE;
+        }
+        if !self.enabled || (style.fg.is_none() && style.bg.is_none()) {
+            return attributes;
+        }
+
+        // Terminals with only 8 colors can't show the bright variants, so approximate them by
+        // drawing the text in bold instead
+        let bright = |color: Option<Color>| color.is_some_and(|c| Self::color_number(c) >= 8);
+        if COLORS() < 16 && bright(style.fg) {
+            attributes |= A_BOLD;
+        }
+
+        let key = (style.fg, style.bg);
+        let mut pairs = self.pairs.borrow_mut();
+        let pair = match pairs.get(&key) {
+            Some(pair) => *pair,
+            None => {
+                let pair = pairs.len() as i16 + 1;
+                if i32::from(pair) >= COLOR_PAIRS() {
+                    return attributes;
+                }
+                init_pair(
+                    pair,
+                    Self::curses_color(style.fg),
+                    Self::curses_color(style.bg),
+                );
+                pairs.insert(key, pair);
+                pair
+            }
+        };
+        attributes | COLOR_PAIR(pair as chtype)
+    }
+
+    /// When called, returns the standard terminal color number for the given color, where the
+    /// bright variants are numbered 8 through 15.
+    fn color_number(color: Color) -> i16 {
+        match color {
+            Color::Black => COLOR_BLACK,
+            Color::Red => COLOR_RED,
+            Color::Green => COLOR_GREEN,
+            Color::Yellow => COLOR_YELLOW,
+            Color::Blue => COLOR_BLUE,
+            Color::Magenta => COLOR_MAGENTA,
+            Color::Cyan => COLOR_CYAN,
+            Color::White => COLOR_WHITE,
+            Color::BrightBlack => COLOR_BLACK + 8,
+            Color::BrightRed => COLOR_RED + 8,
+            Color::BrightGreen => COLOR_GREEN + 8,
+            Color::BrightYellow => COLOR_YELLOW + 8,
+            Color::BrightBlue => COLOR_BLUE + 8,
+            Color::BrightMagenta => COLOR_MAGENTA + 8,
+            Color::BrightCyan => COLOR_CYAN + 8,
+            Color::BrightWhite => COLOR_WHITE + 8,
+        }
+    }
+
+    /// When called, returns the curses color number to draw the given color with. None maps to
+    /// the terminal's default color.
+    fn curses_color(color: Option<Color>) -> i16 {
+        let Some(color) = color else {
+            return -1;
+        };
+        let number = Self::color_number(color);
+        if number >= 8 && COLORS() < 16 {
+            number - 8
+        } else {
+            number
+        }
+    }
+}
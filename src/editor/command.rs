@@ -0,0 +1,265 @@
+use std::path::PathBuf;
+
+use crate::editor::{gutter::SignColumn, text_format::FileFormat};
+
+/// An option that can be changed with `:set`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SetOption {
+    FileFormat(Option<FileFormat>), // `:set fileformat=dos`, or `:set fileformat?` to query
+    ByteOrderMark(bool),            // `:set bomb` / `:set nobomb`
+    EndOfLine(bool),                // `:set eol` / `:set noeol`
+    ScrollOff(Option<usize>),       // `:set scrolloff=5`, or `:set scrolloff?` to query
+    Number(bool),                   // `:set number` / `:set nonumber`
+    RelativeNumber(bool),           // `:set relativenumber` / `:set norelativenumber`
+    SignColumn(Option<SignColumn>), // `:set signcolumn=yes`, or `:set signcolumn?` to query
+    FoldColumn(Option<usize>),      // `:set foldcolumn=2`, or `:set foldcolumn?` to query
+    TabStop(Option<usize>),         // `:set tabstop=4`, or `:set tabstop?` to query
+    ParseMode(Option<String>),      // `:set parsemode=module`, or `:set parsemode?` to query
+    FileType(Option<String>),       // `:set filetype=json`, or `:set filetype?` to query
+}
+
+/// A Command is the parsed representation of a line entered into the editor's command line, ie,
+/// `:w` or `:set fileformat=unix`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Write(Option<PathBuf>),
+    Quit { force: bool },
+    WriteQuit,
+    Set(SetOption),
+    Edit { path: Option<PathBuf>, force: bool },
+    ListBuffers,
+    Buffer(usize),
+    NextBuffer,
+    PreviousBuffer,
+    DeleteBuffer { number: Option<usize>, force: bool },
+    QuitAll { force: bool },
+    Split(Option<PathBuf>),
+    VerticalSplit(Option<PathBuf>),
+    Close,
+    Only,
+    TabNew(Option<PathBuf>),
+    TabNext,
+    TabPrevious,
+    TabClose,
+    ColorScheme(Option<String>),
+    Goto(usize),
+    ListDiagnostics,
+    KeyPath(String),         // `:keypath dependencies.foo`
+    Outline(Option<String>), // `:outline`, or `:outline foo` to start off filtering for `foo`
+}
+
+impl Command {
+    /// When called, parses the text entered into the command line (without the leading `:`) into
+    /// a Command.
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let input = input.trim();
+        let (name, argument) = match input.split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, Some(argument.trim())),
+            None => (input, None),
+        };
+
+        // A trailing `!` on a command name forces the command, ie, `:q!` quits even if there are
+        // unsaved changes
+        let (name, force) = match name.strip_suffix('!') {
+            Some(name) => (name, true),
+            None => (name, false),
+        };
+
+        // Like vim, `:b2` is the same as `:b 2`
+        let (name, argument) = match name.strip_prefix('b') {
+            Some(number) if argument.is_none() && number.parse::<usize>().is_ok() => {
+                ("b", Some(number))
+            }
+            _ => (name, argument),
+        };
+
+        match (name, argument) {
+            ("w" | "write", argument) => Ok(Command::Write(argument.map(PathBuf::from))),
+            ("q" | "quit", None) => Ok(Command::Quit { force }),
+            ("wq" | "x", None) => Ok(Command::WriteQuit),
+            ("set" | "se", Some(argument)) => Self::parse_set_option(argument).map(Command::Set),
+            ("e" | "edit", argument) => Ok(Command::Edit {
+                path: argument.map(PathBuf::from),
+                force,
+            }),
+            ("ls" | "buffers" | "files", None) => Ok(Command::ListBuffers),
+            ("b" | "buffer", Some(argument)) => {
+                Self::parse_buffer_number(argument).map(Command::Buffer)
+            }
+            ("bn" | "bnext", None) => Ok(Command::NextBuffer),
+            ("bp" | "bprevious" | "bN" | "bNext", None) => Ok(Command::PreviousBuffer),
+            ("bd" | "bdelete", argument) => Ok(Command::DeleteBuffer {
+                number: argument.map(Self::parse_buffer_number).transpose()?,
+                force,
+            }),
+            ("qa" | "qall" | "quitall", None) => Ok(Command::QuitAll { force }),
+            ("sp" | "split", argument) => Ok(Command::Split(argument.map(PathBuf::from))),
+            ("vs" | "vsplit", argument) => Ok(Command::VerticalSplit(argument.map(PathBuf::from))),
+            ("clo" | "close", None) => Ok(Command::Close),
+            ("on" | "only", None) => Ok(Command::Only),
+            ("tabnew" | "tabe" | "tabedit", argument) => {
+                Ok(Command::TabNew(argument.map(PathBuf::from)))
+            }
+            ("tabn" | "tabnext", None) => Ok(Command::TabNext),
+            ("tabp" | "tabprevious" | "tabN" | "tabNext", None) => Ok(Command::TabPrevious),
+            ("tabc" | "tabclose", None) => Ok(Command::TabClose),
+            ("colo" | "colorscheme", argument) => {
+                Ok(Command::ColorScheme(argument.map(String::from)))
+            }
+            ("go" | "goto", None) => Ok(Command::Goto(1)),
+            ("go" | "goto", Some(argument)) => match argument.parse() {
+                Ok(byte) => Ok(Command::Goto(byte)),
+                Err(_) => Err(format!("E474: Invalid argument: {argument}")),
+            },
+            ("diagnostics", None) => Ok(Command::ListDiagnostics),
+            ("keypath" | "kp", Some(argument)) => Ok(Command::KeyPath(argument.into())),
+            ("outline" | "ol", argument) => Ok(Command::Outline(argument.map(String::from))),
+            ("", _) => Err("E471: Argument required".into()),
+            _ => Err(format!("E492: Not an editor command: {input}")),
+        }
+    }
+
+    fn parse_buffer_number(argument: &str) -> Result<usize, String> {
+        argument
+            .parse()
+            .map_err(|_| format!("E94: No matching buffer for {argument}"))
+    }
+
+    fn parse_set_option(argument: &str) -> Result<SetOption, String> {
+        let (name, value) = match argument.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (argument.trim_end_matches('?'), None),
+        };
+
+        match (name, value) {
+            ("fileformat" | "ff", None) => Ok(SetOption::FileFormat(None)),
+            ("fileformat" | "ff", Some(value)) => match FileFormat::from_name(value) {
+                Some(file_format) => Ok(SetOption::FileFormat(Some(file_format))),
+                None => Err(format!("E474: Invalid argument: {argument}")),
+            },
+            ("scrolloff" | "so", None) => Ok(SetOption::ScrollOff(None)),
+            ("scrolloff" | "so", Some(value)) => match value.parse() {
+                Ok(lines) => Ok(SetOption::ScrollOff(Some(lines))),
+                Err(_) => Err(format!("E521: Number required after =: {argument}")),
+            },
+            ("signcolumn" | "scl", None) => Ok(SetOption::SignColumn(None)),
+            ("signcolumn" | "scl", Some(value)) => match SignColumn::from_name(value) {
+                Some(sign_column) => Ok(SetOption::SignColumn(Some(sign_column))),
+                None => Err(format!("E474: Invalid argument: {argument}")),
+            },
+            ("foldcolumn" | "fdc", None) => Ok(SetOption::FoldColumn(None)),
+            ("foldcolumn" | "fdc", Some(value)) => match value.parse() {
+                Ok(width) => Ok(SetOption::FoldColumn(Some(width))),
+                Err(_) => Err(format!("E521: Number required after =: {argument}")),
+            },
+            ("tabstop" | "ts", None) => Ok(SetOption::TabStop(None)),
+            ("tabstop" | "ts", Some(value)) => match value.parse() {
+                Ok(0) => Err(format!("E487: Argument must be positive: {argument}")),
+                Ok(columns) => Ok(SetOption::TabStop(Some(columns))),
+                Err(_) => Err(format!("E521: Number required after =: {argument}")),
+            },
+            ("parsemode" | "pm", value) => Ok(SetOption::ParseMode(value.map(String::from))),
+            ("filetype" | "ft", value) => Ok(SetOption::FileType(value.map(String::from))),
+            ("number" | "nu", None) => Ok(SetOption::Number(true)),
+            ("nonumber" | "nonu", None) => Ok(SetOption::Number(false)),
+            ("relativenumber" | "rnu", None) => Ok(SetOption::RelativeNumber(true)),
+            ("norelativenumber" | "nornu", None) => Ok(SetOption::RelativeNumber(false)),
+            ("bomb", None) => Ok(SetOption::ByteOrderMark(true)),
+            ("nobomb", None) => Ok(SetOption::ByteOrderMark(false)),
+            ("eol" | "endofline", None) => Ok(SetOption::EndOfLine(true)),
+            ("noeol" | "noendofline", None) => Ok(SetOption::EndOfLine(false)),
+            _ => Err(format!("E518: Unknown option: {name}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_buffer_commands() {
+        assert_eq!(
+            Command::parse("e! foo.ts"),
+            Ok(Command::Edit {
+                path: Some(PathBuf::from("foo.ts")),
+                force: true
+            })
+        );
+        assert_eq!(Command::parse("b 2"), Ok(Command::Buffer(2)));
+        assert_eq!(Command::parse("b2"), Ok(Command::Buffer(2)));
+        assert_eq!(Command::parse("bn"), Ok(Command::NextBuffer));
+        assert_eq!(
+            Command::parse("bd! 3"),
+            Ok(Command::DeleteBuffer {
+                number: Some(3),
+                force: true
+            })
+        );
+        assert_eq!(Command::parse("q!"), Ok(Command::Quit { force: true }));
+        assert!(Command::parse("b foo").is_err());
+    }
+
+    #[test]
+    fn parse_window_commands() {
+        assert_eq!(Command::parse("sp"), Ok(Command::Split(None)));
+        assert_eq!(
+            Command::parse("vsplit foo.ts"),
+            Ok(Command::VerticalSplit(Some(PathBuf::from("foo.ts"))))
+        );
+        assert_eq!(Command::parse("tabe"), Ok(Command::TabNew(None)));
+        assert_eq!(Command::parse("qa!"), Ok(Command::QuitAll { force: true }));
+        assert_eq!(Command::parse("go"), Ok(Command::Goto(1)));
+        assert_eq!(Command::parse("goto 20"), Ok(Command::Goto(20)));
+        assert_eq!(Command::parse("diagnostics"), Ok(Command::ListDiagnostics));
+        assert_eq!(
+            Command::parse("kp dependencies.foo"),
+            Ok(Command::KeyPath("dependencies.foo".into()))
+        );
+        assert_eq!(Command::parse("outline"), Ok(Command::Outline(None)));
+        assert_eq!(
+            Command::parse("ol foo"),
+            Ok(Command::Outline(Some("foo".into())))
+        );
+        assert_eq!(
+            Command::parse("keypath"),
+            Err("E492: Not an editor command: keypath".into())
+        );
+        assert_eq!(
+            Command::parse("set rnu"),
+            Ok(Command::Set(SetOption::RelativeNumber(true)))
+        );
+        assert_eq!(
+            Command::parse("set scl=yes"),
+            Ok(Command::Set(SetOption::SignColumn(Some(SignColumn::Yes))))
+        );
+        assert_eq!(
+            Command::parse("set fdc=x"),
+            Err("E521: Number required after =: fdc=x".into())
+        );
+        assert_eq!(
+            Command::parse("set ts=4"),
+            Ok(Command::Set(SetOption::TabStop(Some(4))))
+        );
+        assert_eq!(
+            Command::parse("set ts=0"),
+            Err("E487: Argument must be positive: ts=0".into())
+        );
+        assert_eq!(
+            Command::parse("set parsemode=module"),
+            Ok(Command::Set(SetOption::ParseMode(Some("module".into()))))
+        );
+        assert_eq!(
+            Command::parse("set ft=json"),
+            Ok(Command::Set(SetOption::FileType(Some("json".into()))))
+        );
+        assert_eq!(
+            Command::parse("set filetype?"),
+            Ok(Command::Set(SetOption::FileType(None)))
+        );
+        assert_eq!(
+            Command::parse("colo light"),
+            Ok(Command::ColorScheme(Some("light".into())))
+        );
+    }
+}
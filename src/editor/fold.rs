@@ -0,0 +1,362 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::node_tree::{
+    node::{InMemoryNode, NodeMetadata, TokenKindTrait},
+    utils::NEWLINE,
+};
+
+/// What to do with the folds around a row, ie, for `zo` / `zc` / `za`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FoldAction {
+    Open,
+    Close,
+    Toggle,
+}
+
+/// A Fold is a node in a buffer's token tree that can be folded (see
+/// `TokenKindTrait::is_foldable`), along with the (one indexed) rows that it spans. When a fold is
+/// closed, only its first row is shown, in place of all of the rows within it.
+///
+/// Whether a fold is closed is stored on its node (`InMemoryNode::is_folded`) rather than on the
+/// fold, so that folds stay closed when the buffer is edited outside of them.
+#[derive(Debug, Clone)]
+pub struct Fold<TokenKind: TokenKindTrait> {
+    pub node: Rc<RefCell<InMemoryNode<TokenKind>>>,
+    pub start_row: usize,
+    pub end_row: usize,
+    pub depth: usize, // How many other folds this fold is within
+}
+
+impl<TokenKind: TokenKindTrait> Fold<TokenKind> {
+    /// When called, finds every node under `root` that can be folded and spans more than one row.
+    /// Folds are in document order, so each fold comes after the folds that it is within.
+    pub fn collect(root: &Rc<RefCell<InMemoryNode<TokenKind>>>) -> Vec<Self> {
+        let mut folds = vec![];
+        let (mut row, mut ends_with_newline) = (1, false);
+        Self::collect_under(root, 0, &mut row, &mut ends_with_newline, &mut folds);
+        folds
+    }
+
+    fn collect_under(
+        node: &Rc<RefCell<InMemoryNode<TokenKind>>>,
+        depth: usize,
+        row: &mut usize,
+        ends_with_newline: &mut bool,
+        folds: &mut Vec<Self>,
+    ) {
+        // Nothing within a subtree without any newlines in it spans more than one row
+        let (newlines, chars) = {
+            let metrics = &node.borrow().subtree_metrics;
+            (metrics.newlines, metrics.chars)
+        };
+        if newlines == 0 {
+            if chars > 0 {
+                *ends_with_newline = false;
+            }
+            return;
+        }
+
+        let is_foldable = matches!(
+            &node.borrow().metadata,
+            NodeMetadata::AstNode { kind, .. } if kind.is_foldable()
+        );
+        let (start_row, index) = (*row, folds.len());
+        if is_foldable {
+            folds.push(Self {
+                node: node.clone(),
+                start_row,
+                end_row: start_row,
+                depth,
+            });
+        }
+
+        let literal = InMemoryNode::literal(node);
+        *row += literal.chars().filter(|c| *c == *NEWLINE).count();
+        if let Some(last) = literal.chars().last() {
+            *ends_with_newline = last == *NEWLINE;
+        }
+        let children = node.borrow().children.clone();
+        for child in children.iter() {
+            Self::collect_under(
+                child,
+                depth + usize::from(is_foldable),
+                row,
+                ends_with_newline,
+                folds,
+            );
+        }
+
+        if is_foldable {
+            // A trailing newline ends the node's last row, rather than starting a new one
+            let end_row = *row - usize::from(*ends_with_newline);
+            if end_row > start_row {
+                folds[index].end_row = end_row;
+            } else {
+                folds.truncate(index);
+            }
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.node.borrow().is_folded
+    }
+
+    pub fn set_closed(&self, is_closed: bool) {
+        self.node.borrow_mut().is_folded = is_closed;
+    }
+}
+
+/// A closed fold that is shown on screen, ie, that isn't hidden within another closed fold.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClosedFold {
+    pub start_row: usize,
+    pub end_row: usize,
+    pub depth: usize,
+}
+
+/// A FoldMap maps between the rows of a buffer and the rows shown on screen once its closed folds
+/// are hidden. Each closed fold is shown on a single row, so the rows after it are shown further
+/// up the screen than they would be otherwise. All rows are one indexed.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FoldMap {
+    // In order, and never overlapping
+    closed: Vec<ClosedFold>,
+}
+
+impl FoldMap {
+    pub fn new<TokenKind: TokenKindTrait>(folds: &[Fold<TokenKind>]) -> Self {
+        let mut closed: Vec<ClosedFold> = vec![];
+        for fold in folds.iter().filter(|fold| fold.is_closed()) {
+            match closed.last_mut() {
+                // A fold starting on a row that is hidden by (or shown in place of) the previous
+                // closed fold is hidden too, ie, the `else` block after a closed `if` block
+                Some(last) if fold.start_row <= last.end_row => {
+                    last.end_row = last.end_row.max(fold.end_row);
+                }
+                _ => closed.push(ClosedFold {
+                    start_row: fold.start_row,
+                    end_row: fold.end_row,
+                    depth: fold.depth,
+                }),
+            }
+        }
+        Self { closed }
+    }
+
+    /// When called, returns true if there aren't any closed folds, so each row of the buffer is
+    /// shown on a row of its own.
+    pub fn is_empty(&self) -> bool {
+        self.closed.is_empty()
+    }
+
+    /// When called, returns the closed fold that `row` is within, if there is one.
+    pub fn closed_fold_at(&self, row: usize) -> Option<&ClosedFold> {
+        self.closed
+            .iter()
+            .take_while(|fold| fold.start_row <= row)
+            .find(|fold| row <= fold.end_row)
+    }
+
+    /// When called, returns the row that the given buffer row is shown on. Rows within a closed
+    /// fold are shown on the same row as the fold.
+    pub fn to_display_row(&self, row: usize) -> usize {
+        let hidden_rows = self
+            .closed
+            .iter()
+            .take_while(|fold| fold.start_row < row)
+            .map(|fold| fold.end_row.min(row) - fold.start_row)
+            .sum::<usize>();
+        row - hidden_rows
+    }
+
+    /// When called, returns the buffer row that is shown on the given row, which is the first row
+    /// of the fold for rows showing a closed fold.
+    pub fn to_buffer_row(&self, display_row: usize) -> usize {
+        let mut row = display_row;
+        for fold in self.closed.iter() {
+            if fold.start_row >= row {
+                break;
+            }
+            row += fold.end_row - fold.start_row;
+        }
+        row
+    }
+}
+
+/// When called, returns the text to show in a `width` column wide fold column next to `row`. Like
+/// vim, each fold that the row is within is marked with a `|`, nested folds to the right of the
+/// folds they are within, and the first row of a fold is marked with a `-` (or a `+` if the fold
+/// is closed). If there are more folds than columns, only the innermost folds are shown.
+pub fn fold_column<TokenKind: TokenKindTrait>(
+    folds: &[Fold<TokenKind>],
+    row: usize,
+    width: usize,
+) -> String {
+    let mut marks = vec![];
+    for fold in folds
+        .iter()
+        .filter(|fold| fold.start_row <= row && row <= fold.end_row)
+    {
+        // Folds within a closed fold are hidden along with it
+        if marks.iter().take(fold.depth).any(|mark| *mark == '+') {
+            continue;
+        }
+        marks.truncate(fold.depth);
+        marks.push(if fold.is_closed() {
+            '+'
+        } else if fold.start_row == row {
+            '-'
+        } else {
+            '|'
+        });
+    }
+
+    let marks = marks[marks.len().saturating_sub(width)..]
+        .iter()
+        .collect::<String>();
+    format!("{marks:<width$}")
+}
+
+/// When called, returns the text shown in place of a closed fold whose first row is `line`, like
+/// vim's `+--  5 lines: function foo() {-----`. The text is filled out to `width` columns.
+pub fn fold_line(line: &str, fold: &ClosedFold, width: usize) -> String {
+    let text = format!(
+        "+-{}{:>3} lines: {}",
+        "-".repeat(fold.depth + 1),
+        fold.end_row - fold.start_row + 1,
+        line.trim().replace('\t', " "),
+    );
+    format!("{text:-<width$}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::languages::{brackets, json, markdown, typescript, yaml};
+
+    const SOURCE: &str = "/* A
+ * comment */
+function foo(a) {
+  if (a) {
+    return { b: 1,
+      c: 2 };
+  } else {
+    return 3;
+  }
+}
+const d = { e: 4 };
+";
+
+    fn rows<TokenKind: TokenKindTrait>(literal: &str) -> Vec<(usize, usize, usize)> {
+        let root = InMemoryNode::<TokenKind>::new_from_parsed(literal);
+        Fold::collect(&root)
+            .iter()
+            .map(|fold| (fold.start_row, fold.end_row, fold.depth))
+            .collect()
+    }
+
+    #[test]
+    fn collects_folds() {
+        let root = InMemoryNode::<typescript::SyntaxKind>::new_from_parsed(SOURCE);
+        let folds = Fold::collect(&root);
+        assert_eq!(
+            folds
+                .iter()
+                .map(|fold| (fold.start_row, fold.end_row, fold.depth))
+                .collect::<Vec<_>>(),
+            vec![(1, 2, 0), (3, 10, 0), (4, 7, 1), (5, 6, 2), (7, 9, 1)]
+        );
+
+        // Closing a fold hides every row after its first
+        assert!(FoldMap::new(&folds).is_empty());
+        folds[2].set_closed(true);
+        let fold_map = FoldMap::new(&folds);
+        assert_eq!(
+            (1..=11)
+                .map(|row| fold_map.to_display_row(row))
+                .collect::<Vec<_>>(),
+            vec![1, 2, 3, 4, 4, 4, 4, 5, 6, 7, 8]
+        );
+        assert_eq!(
+            (1..=8)
+                .map(|row| fold_map.to_buffer_row(row))
+                .collect::<Vec<_>>(),
+            vec![1, 2, 3, 4, 8, 9, 10, 11]
+        );
+
+        // The `else` block starts on a row hidden by the closed `if` block, so it is hidden too
+        folds[4].set_closed(true);
+        folds[0].set_closed(true);
+        let fold_map = FoldMap::new(&folds);
+        assert_eq!(
+            fold_map.closed_fold_at(8),
+            Some(&ClosedFold {
+                start_row: 4,
+                end_row: 9,
+                depth: 1
+            })
+        );
+        assert_eq!(fold_map.closed_fold_at(3), None);
+        assert_eq!(
+            (1..=5)
+                .map(|row| fold_map.to_buffer_row(row))
+                .collect::<Vec<_>>(),
+            vec![1, 3, 4, 10, 11]
+        );
+        assert_eq!(fold_map.to_display_row(11), 5);
+    }
+
+    #[test]
+    fn renders_folds() {
+        let root = InMemoryNode::<typescript::SyntaxKind>::new_from_parsed(SOURCE);
+        let folds = Fold::collect(&root);
+        folds[3].set_closed(true);
+        let column = |row| fold_column(&folds, row, 3);
+        assert_eq!(column(3), "-  ");
+        assert_eq!(column(4), "|- ");
+        assert_eq!(column(5), "||+");
+        assert_eq!(column(7), "|- ");
+        assert_eq!(column(11), "   ");
+        assert_eq!(fold_column(&folds, 5, 1), "+");
+
+        // Folds within a closed fold are hidden along with it
+        folds[1].set_closed(true);
+        assert_eq!(column(5), "+  ");
+
+        let fold_map = FoldMap::new(&folds);
+        let fold = fold_map.closed_fold_at(3).unwrap();
+        assert_eq!(
+            fold_line("function foo(a) {", fold, 40),
+            "+--  8 lines: function foo(a) {---------"
+        );
+    }
+
+    #[test]
+    fn collects_folds_in_other_languages() {
+        assert_eq!(
+            rows::<json::SyntaxKind>("{\n  \"a\": [\n    1,\n    2\n  ],\n  \"b\": {}\n}\n"),
+            vec![(1, 7, 0), (2, 5, 1)]
+        );
+
+        // Nested mappings and sequences are folded along with the entry they are the value of
+        assert_eq!(
+            rows::<yaml::SyntaxKind>(
+                "jobs:\n  test:\n    steps:\n      - uses: a\n        with: b\n      - run: c\nname: d\n"
+            ),
+            vec![(1, 6, 0), (2, 6, 1), (3, 6, 2), (4, 5, 3)]
+        );
+
+        assert_eq!(
+            rows::<brackets::SyntaxKind>(
+                "def foo(a):\n    if a:\n        return 1\n    return 2\n\nfoo(\n  1)\n"
+            ),
+            vec![(2, 5, 0), (6, 7, 0)]
+        );
+
+        // Sections are folded up until the next heading of the same level or higher
+        assert_eq!(
+            rows::<markdown::SyntaxKind>("# A\n\ntext\n\n## B\n\n- a\n- b\n\n# C\n"),
+            vec![(1, 9, 0), (5, 9, 1), (7, 8, 2)]
+        );
+    }
+}
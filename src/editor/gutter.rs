@@ -0,0 +1,216 @@
+use crate::node_tree::{
+    style::{HighlightGroup, Style},
+    theme::current_style,
+};
+
+// The width of the sign column, in cells
+const SIGN_COLUMN_WIDTH: usize = 2;
+
+// Like vim's `numberwidth`, the minimum width of the line number column, including the space
+// between the numbers and the text
+const MIN_NUMBER_WIDTH: usize = 4;
+
+/// When the sign column should be shown, like vim's `signcolumn` option.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SignColumn {
+    Auto, // Only when the buffer has signs to show
+    Yes,
+    No,
+}
+
+impl SignColumn {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "auto" => Some(SignColumn::Auto),
+            "yes" => Some(SignColumn::Yes),
+            "no" => Some(SignColumn::No),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SignColumn::Auto => "auto",
+            SignColumn::Yes => "yes",
+            SignColumn::No => "no",
+        }
+    }
+}
+
+/// A Sign is a short piece of text shown in the sign column next to a row, ie, to point out a
+/// mark or a diagnostic on that row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sign {
+    pub row: usize, // One indexed, like `Cursor::to_rows_cols`
+    pub text: String,
+    pub group: HighlightGroup,
+}
+
+/// The gutter is the area drawn to the left of the text in each window. From left to right it is
+/// made up of the fold column, the sign column, and the line numbers, each of which can be turned
+/// on or off.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GutterOptions {
+    pub number: bool,
+    pub relative_number: bool,
+    pub sign_column: SignColumn,
+    pub fold_column: usize,
+}
+
+impl Default for GutterOptions {
+    fn default() -> Self {
+        Self {
+            number: false,
+            relative_number: false,
+            sign_column: SignColumn::Auto,
+            fold_column: 0,
+        }
+    }
+}
+
+impl GutterOptions {
+    fn shows_signs(&self, has_signs: bool) -> bool {
+        match self.sign_column {
+            SignColumn::Auto => has_signs,
+            SignColumn::Yes => true,
+            SignColumn::No => false,
+        }
+    }
+
+    fn number_width(&self, line_count: usize) -> usize {
+        if !self.number && !self.relative_number {
+            return 0;
+        }
+        (line_count.to_string().len() + 1).max(MIN_NUMBER_WIDTH)
+    }
+
+    /// When called, returns how many columns wide the gutter is for a buffer with `line_count`
+    /// rows.
+    pub fn width(&self, line_count: usize, has_signs: bool) -> usize {
+        let sign_width = if self.shows_signs(has_signs) {
+            SIGN_COLUMN_WIDTH
+        } else {
+            0
+        };
+        self.fold_column + sign_width + self.number_width(line_count)
+    }
+
+    /// When called, generates the gutter for the given (one indexed) row as a list of pieces of
+    /// text along with the style each should be drawn with. `row` is None for rows past the end of
+    /// the buffer, and `fold_marks` is what to show in the fold column (see `fold::fold_column`).
+    pub fn render(
+        &self,
+        row: Option<usize>,
+        cursor_row: usize,
+        line_count: usize,
+        signs: &[Sign],
+        fold_marks: &str,
+    ) -> Vec<(String, Style)> {
+        let mut pieces = vec![];
+        if self.fold_column > 0 {
+            pieces.push((
+                format!("{fold_marks:<width$.width$}", width = self.fold_column),
+                current_style(Some(HighlightGroup::FoldColumn)),
+            ));
+        }
+
+        if self.shows_signs(!signs.is_empty()) {
+            let sign = row.and_then(|row| signs.iter().find(|sign| sign.row == row));
+            pieces.push(match sign {
+                Some(sign) => (
+                    format!("{:<width$.width$}", sign.text, width = SIGN_COLUMN_WIDTH),
+                    current_style(Some(sign.group)),
+                ),
+                None => (
+                    " ".repeat(SIGN_COLUMN_WIDTH),
+                    current_style(Some(HighlightGroup::SignColumn)),
+                ),
+            });
+        }
+
+        let width = self.number_width(line_count);
+        if width > 0 {
+            let number_width = width - 1;
+            let (number, group) = match row {
+                // Like vim, with both `number` and `relativenumber` set, the cursor's row shows
+                // its absolute number aligned to the left
+                Some(row) if row == cursor_row && self.number && self.relative_number => (
+                    format!("{row:<number_width$} "),
+                    HighlightGroup::CursorLineNumber,
+                ),
+                Some(row) if row == cursor_row && self.number => (
+                    format!("{row:>number_width$} "),
+                    HighlightGroup::CursorLineNumber,
+                ),
+                Some(row) if self.relative_number => (
+                    format!("{:>number_width$} ", row.abs_diff(cursor_row)),
+                    if row == cursor_row {
+                        HighlightGroup::CursorLineNumber
+                    } else {
+                        HighlightGroup::LineNumber
+                    },
+                ),
+                Some(row) => (format!("{row:>number_width$} "), HighlightGroup::LineNumber),
+                None => (" ".repeat(width), HighlightGroup::LineNumber),
+            };
+            pieces.push((number, current_style(Some(group))));
+        }
+
+        pieces
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(options: &GutterOptions, row: Option<usize>, signs: &[Sign]) -> String {
+        options
+            .render(row, 10, 120, signs, "")
+            .into_iter()
+            .map(|(text, _style)| text)
+            .collect()
+    }
+
+    #[test]
+    fn render_gutter() {
+        let mut options = GutterOptions::default();
+        assert_eq!(options.width(120, false), 0);
+        assert_eq!(render(&options, Some(3), &[]), "");
+
+        options.number = true;
+        assert_eq!(options.width(120, false), 4);
+        assert_eq!(options.width(12345, false), 6);
+        assert_eq!(render(&options, Some(3), &[]), "  3 ");
+        assert_eq!(render(&options, None, &[]), "    ");
+
+        options.relative_number = true;
+        assert_eq!(render(&options, Some(3), &[]), "  7 ");
+        assert_eq!(render(&options, Some(10), &[]), "10  ");
+        options.number = false;
+        assert_eq!(render(&options, Some(10), &[]), "  0 ");
+
+        let signs = vec![Sign {
+            row: 12,
+            text: "'a".into(),
+            group: HighlightGroup::Mark,
+        }];
+        options.fold_column = 1;
+        assert_eq!(options.width(120, true), 7);
+        assert_eq!(render(&options, Some(12), &signs), " 'a  2 ");
+        assert_eq!(render(&options, Some(11), &signs), "     1 ");
+        options.sign_column = SignColumn::No;
+        assert_eq!(render(&options, Some(12), &signs), "   2 ");
+
+        options.fold_column = 2;
+        let marks = |fold_marks| {
+            options
+                .render(Some(12), 10, 120, &[], fold_marks)
+                .into_iter()
+                .map(|(text, _style)| text)
+                .collect::<String>()
+        };
+        assert_eq!(marks("|"), "|   2 ");
+        assert_eq!(marks("||-"), "||  2 ");
+    }
+}
@@ -0,0 +1,142 @@
+/// A single edit made to a buffer: the text `old`, which started `offset` chars into the buffer,
+/// was replaced with the text `new`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edit {
+    pub offset: usize,
+    pub old: String,
+    pub new: String,
+}
+
+impl Edit {
+    /// When called, computes the smallest edit that turns the text `old` into the text `new`,
+    /// leaving out the text at their start and end that they have in common. Returns None if the
+    /// two are the same.
+    pub fn between(old: &str, new: &str) -> Option<Self> {
+        if old == new {
+            return None;
+        }
+
+        let prefix = old
+            .char_indices()
+            .zip(new.chars())
+            .find(|((_, a), b)| a != b)
+            .map(|((index, _), _)| index)
+            .unwrap_or(old.len().min(new.len()));
+        let (old_rest, new_rest) = (&old[prefix..], &new[prefix..]);
+        let suffix = old_rest
+            .chars()
+            .rev()
+            .zip(new_rest.chars().rev())
+            .take_while(|(a, b)| a == b)
+            .map(|(a, _)| a.len_utf8())
+            .sum::<usize>();
+
+        Some(Self {
+            offset: old[..prefix].chars().count(),
+            old: old_rest[..old_rest.len() - suffix].into(),
+            new: new_rest[..new_rest.len() - suffix].into(),
+        })
+    }
+
+    /// When called, returns where the character `offset` chars into the buffer before the edit
+    /// is found after the edit is made. Characters before the edit stay where they are, those
+    /// after it move along with it, and those within the text that was replaced move to the end
+    /// of the new text.
+    pub fn shift(&self, offset: usize) -> usize {
+        let old_length = self.old.chars().count();
+        let new_length = self.new.chars().count();
+        if offset >= self.offset + old_length {
+            offset + new_length - old_length
+        } else if offset > self.offset {
+            self.offset + new_length
+        } else {
+            offset
+        }
+    }
+}
+
+/// A change made to a buffer, which can be undone / redone as a unit. This is made up of each of
+/// the edits made between one call to `History::record` and the next, in the order they were
+/// made, along with where the cursor was when the change was recorded.
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    pub edits: Vec<Edit>,
+    pub rows_cols: (usize, usize),
+    state_id: usize,
+}
+
+/// History stores the undo / redo stacks for a single buffer.
+///
+/// Each distinct state the buffer has been in is given a unique `state_id`, so that whether a
+/// buffer is modified can be computed by comparing the current state to the state that was last
+/// written to disk - ie, undoing all changes made since a write will cause the buffer to no longer
+/// be considered modified.
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    undo_stack: Vec<HistoryEntry>,
+    redo_stack: Vec<HistoryEntry>,
+    current_state_id: usize,
+    next_state_id: usize,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self {
+            next_state_id: 1,
+            ..Default::default()
+        }
+    }
+
+    pub fn current_state_id(&self) -> usize {
+        self.current_state_id
+    }
+
+    /// When called, starts recording a new change made to the buffer, so that it can be undone
+    /// later. Each edit made until the next call to `record` is added to this change with
+    /// `push_edit`.
+    pub fn record(&mut self, rows_cols: (usize, usize)) {
+        self.undo_stack.push(HistoryEntry {
+            edits: vec![],
+            rows_cols,
+            state_id: self.current_state_id,
+        });
+        self.redo_stack.clear();
+
+        self.current_state_id = self.next_state_id;
+        self.next_state_id += 1;
+    }
+
+    /// When called, adds an edit on to the change that was last recorded. Edits made before any
+    /// change was recorded can't be undone, so they are dropped.
+    pub fn push_edit(&mut self, edit: Edit) {
+        if let Some(entry) = self.undo_stack.last_mut() {
+            entry.edits.push(edit);
+        }
+    }
+
+    /// When called with the current position of the cursor, returns the change that should be
+    /// undone (by applying its edits in reverse), or None if there is nothing to undo.
+    pub fn undo(&mut self, rows_cols: (usize, usize)) -> Option<HistoryEntry> {
+        let entry = self.undo_stack.pop()?;
+        self.redo_stack.push(HistoryEntry {
+            edits: entry.edits.clone(),
+            rows_cols,
+            state_id: self.current_state_id,
+        });
+        self.current_state_id = entry.state_id;
+        Some(entry)
+    }
+
+    /// When called with the current position of the cursor, returns the change that should be
+    /// redone (by applying its edits again), or None if there is nothing to redo.
+    pub fn redo(&mut self, rows_cols: (usize, usize)) -> Option<HistoryEntry> {
+        let entry = self.redo_stack.pop()?;
+        self.undo_stack.push(HistoryEntry {
+            edits: entry.edits.clone(),
+            rows_cols,
+            state_id: self.current_state_id,
+        });
+        self.current_state_id = entry.state_id;
+        Some(entry)
+    }
+}
@@ -0,0 +1,474 @@
+/// A rectangular region of the screen, in character cells.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Rect {
+    pub row: usize,
+    pub col: usize,
+    pub height: usize,
+    pub width: usize,
+}
+
+/// The direction that the children of a split are laid out in. Like vim, a "horizontal" split
+/// (`:split`) stacks windows on top of each other, and a "vertical" split (`:vsplit`) places them
+/// side by side.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SplitDirection {
+    Horizontal,
+    Vertical,
+}
+
+/// Which side of a window to look for a neighboring window on, ie, for `Ctrl-W h`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Side {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// A Layout is a tree which describes how the windows in a tab page are arranged on screen. Each
+/// leaf is a window (referenced by its id), and each branch splits its area between its children.
+///
+/// The size of each child of a split (in rows for horizontal splits, and columns for vertical
+/// splits) is stored alongside it. When the area a split is laid out into changes size - ie, when
+/// the terminal is resized - the sizes are scaled proportionally to fit.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Layout {
+    Window(usize),
+    Split {
+        direction: SplitDirection,
+        children: Vec<Layout>,
+        sizes: Vec<usize>,
+    },
+}
+
+impl Layout {
+    /// Returns true if the window with the given id is somewhere within this layout.
+    pub fn contains(&self, window_id: usize) -> bool {
+        match self {
+            Layout::Window(id) => *id == window_id,
+            Layout::Split { children, .. } => {
+                children.iter().any(|child| child.contains(window_id))
+            }
+        }
+    }
+
+    /// When called, returns the ids of all windows in the layout, in order from top left to bottom
+    /// right.
+    pub fn window_ids(&self) -> Vec<usize> {
+        match self {
+            Layout::Window(id) => vec![*id],
+            Layout::Split { children, .. } => children
+                .iter()
+                .flat_map(|child| child.window_ids())
+                .collect(),
+        }
+    }
+
+    /// When called, splits the window with id `window_id` in two, placing a new window with id
+    /// `new_window_id` above it (for horizontal splits) or to the left of it (for vertical splits).
+    /// Returns false if `window_id` could not be found.
+    pub fn split(
+        &mut self,
+        window_id: usize,
+        new_window_id: usize,
+        direction: SplitDirection,
+    ) -> bool {
+        match self {
+            Layout::Window(id) if *id == window_id => {
+                *self = Layout::Split {
+                    direction,
+                    children: vec![Layout::Window(new_window_id), Layout::Window(window_id)],
+                    sizes: vec![0, 0],
+                };
+                true
+            }
+            Layout::Window(_) => false,
+            Layout::Split {
+                direction: split_direction,
+                children,
+                sizes,
+            } => {
+                // If the window is already in a split going the same way, add the new window
+                // alongside it rather than nesting another split
+                let index = children
+                    .iter()
+                    .position(|child| *child == Layout::Window(window_id));
+                if let (true, Some(index)) = (*split_direction == direction, index) {
+                    let new_size = sizes[index] / 2;
+                    sizes[index] -= new_size;
+                    sizes.insert(index, new_size);
+                    children.insert(index, Layout::Window(new_window_id));
+                    return true;
+                }
+
+                children
+                    .iter_mut()
+                    .any(|child| child.split(window_id, new_window_id, direction))
+            }
+        }
+    }
+
+    /// When called, removes the window with id `window_id` from the layout, giving the space it
+    /// took up to its neighbor. Returns false if `window_id` could not be found, or if it is the
+    /// only window in the layout.
+    pub fn remove(&mut self, window_id: usize) -> bool {
+        let Layout::Split {
+            children, sizes, ..
+        } = self
+        else {
+            return false;
+        };
+
+        let Some(index) = children
+            .iter()
+            .position(|child| *child == Layout::Window(window_id))
+        else {
+            return children.iter_mut().any(|child| child.remove(window_id));
+        };
+
+        children.remove(index);
+        let size = sizes.remove(index);
+        sizes[index.saturating_sub(1)] += size;
+
+        // A split with only one child left is no longer a split
+        if children.len() == 1 {
+            *self = children.remove(0);
+        }
+        true
+    }
+
+    /// When called, grows (or shrinks, if `delta` is negative) the window with id `window_id` in
+    /// the given direction, by taking space from (or giving space to) the window next to it.
+    /// Returns false if the window isn't in a split going in that direction.
+    pub fn resize(&mut self, window_id: usize, direction: SplitDirection, delta: isize) -> bool {
+        let Layout::Split {
+            direction: split_direction,
+            children,
+            sizes,
+        } = self
+        else {
+            return false;
+        };
+        let Some(index) = children.iter().position(|child| child.contains(window_id)) else {
+            return false;
+        };
+
+        // Resize the split closest to the window first
+        if children[index].resize(window_id, direction, delta) {
+            return true;
+        }
+        if *split_direction != direction || children.len() < 2 {
+            return false;
+        }
+
+        let neighbor_index = if index + 1 < children.len() {
+            index + 1
+        } else {
+            index - 1
+        };
+
+        // Each window must keep at least one cell
+        let delta = if delta > 0 {
+            delta.min(sizes[neighbor_index] as isize - 1)
+        } else {
+            delta.max(1 - sizes[index] as isize)
+        };
+        sizes[index] = (sizes[index] as isize + delta) as usize;
+        sizes[neighbor_index] = (sizes[neighbor_index] as isize - delta) as usize;
+        true
+    }
+
+    /// When called, makes all windows in the layout (roughly) the same size, like `Ctrl-W =`.
+    pub fn equalize(&mut self) {
+        if let Layout::Split {
+            children, sizes, ..
+        } = self
+        {
+            sizes.iter_mut().for_each(|size| *size = 0);
+            children.iter_mut().for_each(|child| child.equalize());
+        }
+    }
+
+    /// When called, computes where on screen each window in the layout should be drawn, given the
+    /// area that the layout takes up. Vertical splits leave a one column gap between each child to
+    /// draw a separator in.
+    pub fn layout(&mut self, rect: Rect) -> Vec<(usize, Rect)> {
+        let (direction, children, sizes) = match self {
+            Layout::Window(id) => return vec![(*id, rect)],
+            Layout::Split {
+                direction,
+                children,
+                sizes,
+            } => (*direction, children, sizes),
+        };
+
+        let separators = match direction {
+            SplitDirection::Horizontal => 0,
+            SplitDirection::Vertical => children.len() - 1,
+        };
+        let available = match direction {
+            SplitDirection::Horizontal => rect.height,
+            SplitDirection::Vertical => rect.width.saturating_sub(separators),
+        };
+        Self::fit_sizes(sizes, available);
+
+        let mut rects = vec![];
+        let mut offset = 0;
+        for (child, size) in children.iter_mut().zip(sizes.iter()) {
+            let child_rect = match direction {
+                SplitDirection::Horizontal => Rect {
+                    row: rect.row + offset,
+                    height: *size,
+                    ..rect
+                },
+                SplitDirection::Vertical => Rect {
+                    col: rect.col + offset,
+                    width: *size,
+                    ..rect
+                },
+            };
+            rects.extend(child.layout(child_rect));
+
+            offset += size;
+            if direction == SplitDirection::Vertical {
+                offset += 1;
+            }
+        }
+        rects
+    }
+
+    /// When called, scales `sizes` so that they add up to `available`. If any size is zero (ie,
+    /// the split was just created or equalized), the space is divided up evenly instead.
+    fn fit_sizes(sizes: &mut [usize], available: usize) {
+        let total: usize = sizes.iter().sum();
+        if total == available && !sizes.contains(&0) {
+            return;
+        }
+
+        if sizes.contains(&0) {
+            sizes.iter_mut().for_each(|size| *size = 1);
+        }
+        let total: usize = sizes.iter().sum();
+
+        let mut remaining = available;
+        for size in sizes.iter_mut() {
+            *size = (*size * available / total).min(remaining);
+            remaining -= *size;
+        }
+        // Give any space left over from rounding to the last child
+        if let Some(last) = sizes.last_mut() {
+            *last += remaining;
+        }
+    }
+}
+
+/// When called, finds the window on the given side of the window with id `window_id`. If there
+/// are multiple windows on that side, the one next to `position` (the cursor's position on screen)
+/// is picked.
+pub fn find_neighbor(
+    rects: &[(usize, Rect)],
+    window_id: usize,
+    side: Side,
+    position: (usize, usize),
+) -> Option<usize> {
+    let (_, rect) = rects.iter().find(|(id, _)| *id == window_id)?;
+    let (row, col) = position;
+
+    rects
+        .iter()
+        .filter(|(id, _)| *id != window_id)
+        .filter(|(_, other)| match side {
+            // Vertically split windows have a one column separator between them
+            Side::Left => other.col + other.width + 1 == rect.col,
+            Side::Right => rect.col + rect.width + 1 == other.col,
+            Side::Up => other.row + other.height == rect.row,
+            Side::Down => rect.row + rect.height == other.row,
+        })
+        .min_by_key(|(_, other)| match side {
+            Side::Left | Side::Right => distance(row, other.row, other.height),
+            Side::Up | Side::Down => distance(col, other.col, other.width),
+        })
+        .map(|(id, _)| *id)
+}
+
+/// When called, returns how far `value` is from the range `start..start + length`.
+fn distance(value: usize, start: usize, length: usize) -> usize {
+    if value < start {
+        start - value
+    } else if value >= start + length {
+        value + 1 - (start + length)
+    } else {
+        0
+    }
+}
+
+/// A TabPage is a set of windows that are shown on screen together.
+#[derive(Debug, Clone)]
+pub struct TabPage {
+    pub layout: Layout,
+    pub current_window_id: usize,
+}
+
+impl TabPage {
+    pub fn new(window_id: usize) -> Self {
+        Self {
+            layout: Layout::Window(window_id),
+            current_window_id: window_id,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCREEN: Rect = Rect {
+        row: 0,
+        col: 0,
+        height: 20,
+        width: 41,
+    };
+
+    #[test]
+    fn split_and_remove_windows() {
+        // New windows take half the space of the window they are split from
+        let mut layout = Layout::Window(1);
+        assert!(layout.split(1, 2, SplitDirection::Vertical));
+        layout.layout(SCREEN);
+        assert!(layout.split(1, 3, SplitDirection::Horizontal));
+        layout.layout(SCREEN);
+        assert!(layout.split(3, 4, SplitDirection::Horizontal));
+        assert_eq!(layout.window_ids(), vec![2, 4, 3, 1]);
+
+        let rects = layout.layout(SCREEN);
+        assert_eq!(
+            rects,
+            vec![
+                (
+                    2,
+                    Rect {
+                        row: 0,
+                        col: 0,
+                        height: 20,
+                        width: 20
+                    }
+                ),
+                (
+                    4,
+                    Rect {
+                        row: 0,
+                        col: 21,
+                        height: 5,
+                        width: 20
+                    }
+                ),
+                (
+                    3,
+                    Rect {
+                        row: 5,
+                        col: 21,
+                        height: 5,
+                        width: 20
+                    }
+                ),
+                (
+                    1,
+                    Rect {
+                        row: 10,
+                        col: 21,
+                        height: 10,
+                        width: 20
+                    }
+                ),
+            ]
+        );
+
+        assert!(layout.remove(4));
+        assert!(layout.remove(3));
+        assert!(!layout.remove(5));
+        assert_eq!(
+            layout.layout(SCREEN),
+            vec![
+                (
+                    2,
+                    Rect {
+                        row: 0,
+                        col: 0,
+                        height: 20,
+                        width: 20
+                    }
+                ),
+                (
+                    1,
+                    Rect {
+                        row: 0,
+                        col: 21,
+                        height: 20,
+                        width: 20
+                    }
+                ),
+            ]
+        );
+
+        assert!(layout.remove(2));
+        assert_eq!(layout, Layout::Window(1));
+        assert!(!layout.remove(1));
+    }
+
+    #[test]
+    fn resize_and_navigate_windows() {
+        let mut layout = Layout::Window(1);
+        layout.split(1, 2, SplitDirection::Vertical);
+        layout.split(1, 3, SplitDirection::Horizontal);
+        layout.layout(SCREEN);
+
+        assert!(layout.resize(3, SplitDirection::Horizontal, 4));
+        assert!(layout.resize(3, SplitDirection::Vertical, -5));
+        let rects = layout.layout(SCREEN);
+        assert_eq!(
+            rects,
+            vec![
+                (
+                    2,
+                    Rect {
+                        row: 0,
+                        col: 0,
+                        height: 20,
+                        width: 25
+                    }
+                ),
+                (
+                    3,
+                    Rect {
+                        row: 0,
+                        col: 26,
+                        height: 14,
+                        width: 15
+                    }
+                ),
+                (
+                    1,
+                    Rect {
+                        row: 14,
+                        col: 26,
+                        height: 6,
+                        width: 15
+                    }
+                ),
+            ]
+        );
+
+        assert_eq!(find_neighbor(&rects, 1, Side::Up, (15, 20)), Some(3));
+        assert_eq!(find_neighbor(&rects, 1, Side::Left, (15, 20)), Some(2));
+        assert_eq!(find_neighbor(&rects, 1, Side::Down, (15, 20)), None);
+        assert_eq!(find_neighbor(&rects, 2, Side::Right, (15, 5)), Some(1));
+        assert_eq!(find_neighbor(&rects, 2, Side::Right, (2, 5)), Some(3));
+
+        // Windows can't be shrunk to nothing
+        layout.resize(1, SplitDirection::Horizontal, -100);
+        assert_eq!(layout.layout(SCREEN)[2].1.height, 1);
+
+        layout.equalize();
+        assert_eq!(layout.layout(SCREEN)[2].1.height, 10);
+    }
+}
@@ -0,0 +1,13 @@
+pub mod buffer;
+pub mod buffer_set;
+pub mod color_pairs;
+pub mod command;
+pub mod fold;
+pub mod gutter;
+pub mod history;
+pub mod layout;
+pub mod picker;
+pub mod row_index;
+pub mod text_format;
+pub mod tui;
+pub mod window;
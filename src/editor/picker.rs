@@ -0,0 +1,212 @@
+/// An item that can be picked from a Picker. The label is what is shown in the list, and the
+/// filter text is what the pattern is matched against, ie, `Foo.bar` for a method shown as
+/// `  method bar`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PickerItem {
+    pub label: String,
+    pub filter_text: String,
+}
+
+/// A Picker is a list of items that can be narrowed down by typing a fuzzy pattern, ie, the
+/// symbols shown by `:outline`. Items whose filter text contains all of the characters of the
+/// pattern in order match it, and are listed from the best match to the worst. If the pattern is
+/// empty, every item is listed in its original order.
+pub struct Picker {
+    items: Vec<PickerItem>,
+    pattern: String,
+
+    // The indices of the items that match the pattern, best first, and which of them is selected
+    matches: Vec<usize>,
+    selected: usize,
+}
+
+impl Picker {
+    pub fn new(items: Vec<PickerItem>, pattern: &str) -> Self {
+        let mut picker = Self {
+            items,
+            pattern: String::new(),
+            matches: vec![],
+            selected: 0,
+        };
+        picker.set_pattern(pattern);
+        picker
+    }
+
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// When called, changes the pattern, filtering the items again and selecting the best match.
+    pub fn set_pattern(&mut self, pattern: &str) {
+        self.pattern = pattern.into();
+        let mut scores = self
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(index, item)| Some((index, fuzzy_score(pattern, &item.filter_text)?)))
+            .collect::<Vec<(usize, isize)>>();
+        // NOTE: the sort is stable, so items that match equally well stay in their original order
+        scores.sort_by_key(|(_, score)| -score);
+        self.matches = scores.into_iter().map(|(index, _)| index).collect();
+        self.selected = 0;
+    }
+
+    /// When called, returns the items that match the pattern, best first, along with their
+    /// indices.
+    pub fn matches(&self) -> Vec<(usize, &PickerItem)> {
+        self.matches
+            .iter()
+            .map(|index| (*index, &self.items[*index]))
+            .collect()
+    }
+
+    /// When called, returns the position of the selected item within `matches`.
+    pub fn selected_position(&self) -> usize {
+        self.selected
+    }
+
+    /// When called, returns the index of the selected item, or None if nothing matches.
+    pub fn selected(&self) -> Option<usize> {
+        self.matches.get(self.selected).copied()
+    }
+
+    /// When called, moves the selection by `delta` items, wrapping around the ends of the list.
+    pub fn move_selection(&mut self, delta: isize) {
+        if self.matches.is_empty() {
+            return;
+        }
+        let length = self.matches.len() as isize;
+        self.selected = (self.selected as isize + delta).rem_euclid(length) as usize;
+    }
+
+    pub fn item_count(&self) -> usize {
+        self.items.len()
+    }
+}
+
+/// When called, scores how well `text` matches the fuzzy `pattern`, or returns None if it doesn't
+/// contain all of the pattern's characters in order. Like vim's `smartcase`, the match ignores
+/// case unless the pattern has an uppercase letter in it.
+///
+/// Characters that match at the start of a word (ie, `b` in `foo_bar`, `fooBar`, or `Foo.bar`),
+/// or right after the previous match, score more than ones in the middle of a word, and matches
+/// further into the text score less.
+pub fn fuzzy_score(pattern: &str, text: &str) -> Option<isize> {
+    let is_case_sensitive = pattern.chars().any(char::is_uppercase);
+    let normalize = |c: char| {
+        if is_case_sensitive {
+            c
+        } else {
+            c.to_lowercase().next().unwrap_or(c)
+        }
+    };
+
+    let mut pattern = pattern.chars().map(normalize).peekable();
+    let (mut score, mut has_matched) = (0, false);
+    let (mut previous, mut previous_matched) = (None, false);
+    for (index, c) in text.chars().enumerate() {
+        let Some(expected) = pattern.peek() else {
+            break;
+        };
+        let is_match = normalize(c) == *expected;
+        if is_match {
+            pattern.next();
+            let is_word_start = match previous {
+                None => true,
+                Some(previous) => {
+                    !char::is_alphanumeric(previous)
+                        || (char::is_lowercase(previous) && c.is_uppercase())
+                }
+            };
+            score += 1;
+            if is_word_start {
+                score += 8;
+            }
+            if previous_matched {
+                score += 4;
+            }
+            if !has_matched {
+                // The first match costs a point for each character skipped to get to it
+                score -= index.min(8) as isize;
+                has_matched = true;
+            }
+        }
+        previous_matched = is_match;
+        previous = Some(c);
+    }
+
+    match pattern.peek() {
+        Some(_) => None,
+        None => Some(score),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items(filter_texts: &[&str]) -> Vec<PickerItem> {
+        filter_texts
+            .iter()
+            .map(|text| PickerItem {
+                label: format!("label {text}"),
+                filter_text: text.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fuzzy_matches() {
+        assert!(fuzzy_score("", "anything").is_some());
+        assert!(fuzzy_score("fb", "fooBar").is_some());
+        assert!(fuzzy_score("bf", "fooBar").is_none());
+
+        // Smart case
+        assert!(fuzzy_score("foobar", "FooBar").is_some());
+        assert!(fuzzy_score("Foo", "foo").is_none());
+
+        // Word starts and consecutive characters score higher
+        assert!(fuzzy_score("fb", "fooBar") > fuzzy_score("fb", "fxxbxx"));
+        assert!(fuzzy_score("bar", "foo.bar") > fuzzy_score("bar", "bxaxrx"));
+        assert!(fuzzy_score("foo", "foo") > fuzzy_score("foo", "xfoo"));
+    }
+
+    #[test]
+    fn filters_and_selects_items() {
+        let mut picker = Picker::new(items(&["Baz", "Baz.render", "reduce", "bar"]), "");
+        assert_eq!(picker.item_count(), 4);
+        assert_eq!(
+            picker
+                .matches()
+                .iter()
+                .map(|(index, _)| *index)
+                .collect::<Vec<_>>(),
+            vec![0, 1, 2, 3]
+        );
+
+        picker.set_pattern("re");
+        assert_eq!(
+            picker
+                .matches()
+                .iter()
+                .map(|(_, item)| item.filter_text.as_str())
+                .collect::<Vec<_>>(),
+            vec!["reduce", "Baz.render"]
+        );
+        assert_eq!(picker.selected(), Some(2));
+
+        // The selection wraps around the ends of the list
+        picker.move_selection(1);
+        assert_eq!(picker.selected(), Some(1));
+        picker.move_selection(1);
+        assert_eq!(picker.selected(), Some(2));
+        picker.move_selection(-1);
+        assert_eq!(picker.selected_position(), 1);
+
+        picker.set_pattern("xyz");
+        assert!(picker.matches().is_empty());
+        assert_eq!(picker.selected(), None);
+        picker.move_selection(1);
+        assert_eq!(picker.selected(), None);
+    }
+}
@@ -0,0 +1,58 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::node_tree::{
+    cursor::Cursor,
+    node::{InMemoryNode, NodeSeek, TokenKindTrait},
+    utils::{Inclusivity, NEWLINE},
+};
+
+/// A RowIndex caches where each row of a document starts, so that a run of consecutive rows can
+/// be looked up at once (ie, when drawing a window) without descending the token tree for each row
+/// like `Cursor::new_at_rows_cols` does.
+///
+/// A RowIndex is only valid until the document is next changed, so it records the `change_id` of
+/// the buffer it was built from.
+pub struct RowIndex<TokenKind: TokenKindTrait> {
+    pub change_id: usize,
+
+    // A cursor at the start of each row, zero indexed. The first row starts at the root.
+    row_starts: Vec<Cursor<TokenKind>>,
+}
+
+impl<TokenKind: TokenKindTrait> RowIndex<TokenKind> {
+    /// When called, walks the whole document under `root` to find where each row starts.
+    pub fn build(root: &Rc<RefCell<InMemoryNode<TokenKind>>>, change_id: usize) -> Self {
+        let mut row_starts = vec![Cursor::new(root.clone())];
+        let nodes = InMemoryNode::seek_forwards_until(root, Inclusivity::Inclusive, |node, _ct| {
+            NodeSeek::Continue(node.clone())
+        })
+        .expect("RowIndex::build seek_forwards_until failed");
+
+        for node in nodes {
+            let literal = InMemoryNode::literal(&node);
+            for (offset, c) in literal.chars().enumerate() {
+                if c == *NEWLINE {
+                    // The next row starts right after the newline
+                    row_starts.push(Cursor::new_at(node.clone(), offset + 1));
+                }
+            }
+        }
+
+        Self {
+            change_id,
+            row_starts,
+        }
+    }
+
+    /// When called, returns the number of rows in the document. Note that a trailing newline
+    /// starts a new (empty) row.
+    pub fn line_count(&self) -> usize {
+        self.row_starts.len()
+    }
+
+    /// When called, returns a cursor at the start of the given (zero indexed) row, or None if the
+    /// document doesn't have that many rows.
+    pub fn row_start(&self, row: usize) -> Option<&Cursor<TokenKind>> {
+        self.row_starts.get(row)
+    }
+}
@@ -0,0 +1,148 @@
+/// The unicode byte order mark, which some editors (mostly on windows) write at the very start of
+/// utf-8 encoded files.
+pub const BYTE_ORDER_MARK: char = '\u{feff}';
+
+/// The line ending convention used by a document. This is the equivalent of vim's `fileformat`
+/// option.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileFormat {
+    Unix, // "\n"
+    Dos,  // "\r\n"
+}
+
+impl FileFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "unix" => Some(Self::Unix),
+            "dos" => Some(Self::Dos),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Unix => "unix",
+            Self::Dos => "dos",
+        }
+    }
+
+    pub fn line_ending(&self) -> &'static str {
+        match self {
+            Self::Unix => "\n",
+            Self::Dos => "\r\n",
+        }
+    }
+}
+
+/// A TextFormat stores all the properties of a document on disk that have to be preserved so that
+/// writing a document back out reproduces the exact same bytes that were read in - ie, its line
+/// endings, whether it starts with a byte order mark, and whether it ends with a newline.
+///
+/// Note that the token tree itself always stores the document text verbatim, so these properties
+/// are only detected (to be displayed / queried) and applied (when converting between formats).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextFormat {
+    pub file_format: FileFormat,
+    pub has_byte_order_mark: bool,
+    pub has_trailing_newline: bool,
+}
+
+impl Default for TextFormat {
+    fn default() -> Self {
+        Self {
+            file_format: FileFormat::Unix,
+            has_byte_order_mark: false,
+            has_trailing_newline: true,
+        }
+    }
+}
+
+impl TextFormat {
+    /// Given the full text of a document, figure out which format it was written in.
+    ///
+    /// Like vim, a document is only considered to be `dos` formatted if every line within it ends
+    /// with "\r\n". A document with mixed line endings is treated as `unix`.
+    pub fn detect(text: &str) -> Self {
+        let has_byte_order_mark = text.starts_with(BYTE_ORDER_MARK);
+        let content = text.strip_prefix(BYTE_ORDER_MARK).unwrap_or(text);
+
+        let newline_count = content.matches('\n').count();
+        let crlf_count = content.matches("\r\n").count();
+        let file_format = if newline_count > 0 && newline_count == crlf_count {
+            FileFormat::Dos
+        } else {
+            FileFormat::Unix
+        };
+
+        Self {
+            file_format,
+            has_byte_order_mark,
+            // NOTE: an empty document has nothing to terminate, so don't consider it to be missing
+            // a trailing newline
+            has_trailing_newline: content.is_empty() || content.ends_with('\n'),
+        }
+    }
+
+    /// When called, converts the given document text so that it is in this format, returning the
+    /// converted text.
+    pub fn apply(&self, text: &str) -> String {
+        // 1. Strip off the byte order mark and normalize all line endings to "\n"
+        let content = text.strip_prefix(BYTE_ORDER_MARK).unwrap_or(text);
+        let mut normalized = content.replace("\r\n", "\n");
+
+        // 2. Add or remove the final newline
+        if self.has_trailing_newline && !normalized.is_empty() && !normalized.ends_with('\n') {
+            normalized.push('\n');
+        } else if !self.has_trailing_newline && normalized.ends_with('\n') {
+            normalized.pop();
+        }
+
+        // 3. Convert line endings into the requested format, and add back the byte order mark
+        let converted = match self.file_format {
+            FileFormat::Unix => normalized,
+            FileFormat::Dos => normalized.replace('\n', FileFormat::Dos.line_ending()),
+        };
+        if self.has_byte_order_mark {
+            format!("{BYTE_ORDER_MARK}{converted}")
+        } else {
+            converted
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FileFormat, TextFormat, BYTE_ORDER_MARK};
+
+    #[test]
+    fn detect_text_format() {
+        let format = TextFormat::detect("a\nb\n");
+        assert_eq!(format, TextFormat::default());
+
+        let format = TextFormat::detect("\u{feff}a\r\nb");
+        assert_eq!(format.file_format, FileFormat::Dos);
+        assert!(format.has_byte_order_mark);
+        assert!(!format.has_trailing_newline);
+
+        // Mixed line endings are treated as unix
+        let format = TextFormat::detect("a\r\nb\n");
+        assert_eq!(format.file_format, FileFormat::Unix);
+    }
+
+    #[test]
+    fn apply_text_format() {
+        let text = format!("{BYTE_ORDER_MARK}a\r\nb\r\n");
+        let format = TextFormat::detect(&text);
+
+        // Converting a document into its own format should be a noop
+        assert_eq!(format.apply(&text), text);
+
+        let unix = TextFormat {
+            file_format: FileFormat::Unix,
+            has_byte_order_mark: false,
+            has_trailing_newline: false,
+        };
+        assert_eq!(unix.apply(&text), "a\nb");
+        assert_eq!(format.apply(&unix.apply(&text)), text);
+    }
+}
@@ -0,0 +1,1430 @@
+use std::path::PathBuf;
+
+use pancurses::{echo, endwin, initscr, noecho, resize_term, Input};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{
+    editor::{
+        buffer::Buffer,
+        buffer_set::BufferSet,
+        color_pairs::ColorPairs,
+        command::{Command, SetOption},
+        fold::{fold_column, fold_line, FoldAction, FoldMap},
+        gutter::GutterOptions,
+        layout::{find_neighbor, Layout, Rect, Side, SplitDirection, TabPage},
+        picker::{Picker, PickerItem},
+        text_format::TextFormat,
+        window::{Alignment, Window},
+    },
+    node_tree::{
+        cursor::{Cursor, CursorSeek},
+        node::{OffsetUnit, ParseOptionsTrait, TokenKindTrait},
+        outline::Symbol,
+        style::{HighlightGroup, Style, StyleSpan},
+        theme::{current_style, current_theme_name, set_current_theme, Theme},
+        utils::{grapheme_display_width, Direction, Inclusivity, DEFAULT_TABSTOP},
+    },
+};
+
+const CTRL_D: char = '\u{4}';
+const CTRL_E: char = '\u{5}';
+const CTRL_N: char = '\u{e}';
+const CTRL_P: char = '\u{10}';
+const CTRL_R: char = '\u{12}';
+const CTRL_U: char = '\u{15}';
+const CTRL_W: char = '\u{17}';
+const CTRL_Y: char = '\u{19}';
+
+// Like vim's `defaults.vim`, always keep a few rows visible above and below the cursor
+const DEFAULT_SCROLLOFF: usize = 5;
+
+/// When called, reads a line of text typed into the command line at the bottom of the screen.
+/// Returns None if the command line was dismissed.
+fn read_command_line(screen: &pancurses::Window, row: i32) -> Option<String> {
+    let mut input = String::new();
+    loop {
+        screen.mv(row, 0);
+        screen.clrtoeol();
+        screen.addstr(format!(":{input}"));
+        screen.refresh();
+
+        match screen.getch() {
+            Some(Input::Character('\n')) | Some(Input::KeyEnter) => return Some(input),
+            Some(Input::Character('\u{1b}')) => return None,
+            Some(Input::KeyBackspace)
+            | Some(Input::Character('\u{7f}'))
+            | Some(Input::Character('\u{8}')) => {
+                // Like vim, backspacing past the start of the command line dismisses it
+                input.pop()?;
+            }
+            Some(Input::Character(c)) => input.push(c),
+            _ => (),
+        }
+    }
+}
+
+/// When called, pads or truncates `text` so that it takes up exactly `width` columns when drawn.
+/// Wide characters (ie, CJK ideographs and emoji) that would be cut in half are left off.
+fn fit_to_width(text: &str, width: usize) -> String {
+    let mut fitted = String::new();
+    let mut display_col = 0;
+    for grapheme in text.graphemes(true) {
+        let grapheme_width = grapheme_display_width(grapheme, display_col, DEFAULT_TABSTOP);
+        if display_col + grapheme_width > width {
+            break;
+        }
+        fitted.push_str(grapheme);
+        display_col += grapheme_width;
+    }
+    fitted + &" ".repeat(width - display_col)
+}
+
+/// When called, runs a command that operates on the buffers (rather than on windows or tab pages)
+/// against the current buffer, returning a message to show to the user in the status line.
+fn execute_buffer_command<TokenKind: TokenKindTrait>(
+    buffers: &mut BufferSet<TokenKind>,
+    command: Command,
+) -> Result<String, String> {
+    let buffer = buffers.current_mut();
+    match command {
+        Command::Write(path) => {
+            let path = buffer.write(path.as_deref())?;
+            Ok(format!("\"{}\" written", path.display()))
+        }
+        Command::Set(SetOption::FileFormat(None)) => Ok(format!(
+            "fileformat={}",
+            buffer.text_format.file_format.name()
+        )),
+        Command::Set(SetOption::FileFormat(Some(file_format))) => {
+            buffer.set_text_format(TextFormat {
+                file_format,
+                ..buffer.text_format
+            })?;
+            Ok(format!("fileformat={}", file_format.name()))
+        }
+        Command::Set(SetOption::ParseMode(None)) => {
+            Ok(format!("parsemode={}", buffer.parse_options().name()))
+        }
+        Command::Set(SetOption::ParseMode(Some(name))) => {
+            let Some(options) = TokenKind::ParseOptions::from_name(&name) else {
+                return Err(format!("E474: Invalid argument: parsemode={name}"));
+            };
+            buffer.set_parse_options(options)?;
+            Ok(format!("parsemode={name}"))
+        }
+        Command::Set(SetOption::FileType(None)) => Ok(format!("filetype={}", buffer.language())),
+        Command::Set(SetOption::FileType(Some(name))) => {
+            // Buffers that can only be parsed as one language can still be set to that language
+            let options = match TokenKind::ParseOptions::from_filetype(&name) {
+                Some(options) => options,
+                None if name == buffer.language() => buffer.parse_options(),
+                None => return Err(format!("E474: Invalid argument: filetype={name}")),
+            };
+            buffer.set_parse_options(options)?;
+            Ok(format!("filetype={}", buffer.language()))
+        }
+        Command::Set(SetOption::ByteOrderMark(has_byte_order_mark)) => {
+            buffer.set_text_format(TextFormat {
+                has_byte_order_mark,
+                ..buffer.text_format
+            })?;
+            Ok("".into())
+        }
+        Command::Set(SetOption::EndOfLine(has_trailing_newline)) => {
+            buffer.set_text_format(TextFormat {
+                has_trailing_newline,
+                ..buffer.text_format
+            })?;
+            Ok("".into())
+        }
+        Command::Edit {
+            path: Some(path),
+            force: _,
+        } => {
+            buffers.edit(&path)?;
+            Ok(format!("\"{}\"", buffers.current().name()))
+        }
+        Command::Edit { path: None, force } => {
+            if buffer.is_modified() && !force {
+                return Err("E37: No write since last change (add ! to override)".into());
+            }
+            buffer.reload()?;
+            Ok(format!("\"{}\"", buffer.name()))
+        }
+        Command::KeyPath(path) => {
+            buffer.jump_to_key_path(&path)?;
+            Ok("".into())
+        }
+        Command::ListDiagnostics => {
+            buffer.reparse_pending()?;
+            let diagnostics = buffer.diagnostics();
+            if diagnostics.is_empty() {
+                return Err("E42: No Errors".into());
+            }
+            Ok(diagnostics
+                .iter()
+                .map(|diagnostic| {
+                    let (rows, cols) = diagnostic.start;
+                    format!(
+                        "{rows}:{cols} {}: {}",
+                        diagnostic.diagnostic.severity.name(),
+                        diagnostic.diagnostic.message
+                    )
+                })
+                .collect::<Vec<String>>()
+                .join("\n"))
+        }
+        Command::ListBuffers => Ok(buffers.list()),
+        Command::Buffer(number) => {
+            buffers.switch_to(number)?;
+            Ok(format!("\"{}\"", buffers.current().name()))
+        }
+        Command::NextBuffer => {
+            buffers.next();
+            Ok(format!("\"{}\"", buffers.current().name()))
+        }
+        Command::PreviousBuffer => {
+            buffers.previous();
+            Ok(format!("\"{}\"", buffers.current().name()))
+        }
+        Command::DeleteBuffer { number, force } => {
+            buffers.delete(number, force)?;
+            Ok("".into())
+        }
+        _ => unreachable!("execute_buffer_command: {command:?} is not a buffer command!"),
+    }
+}
+
+/// The Tui is the interactive, terminal based frontend to the editor. It owns all the buffers that
+/// are loaded, and the tab pages and windows that they are being shown in.
+pub struct Tui<TokenKind: TokenKindTrait> {
+    buffers: BufferSet<TokenKind>,
+    windows: Vec<Window<TokenKind>>,
+    tab_pages: Vec<TabPage>,
+    current_tab_page_index: usize,
+    next_window_id: usize,
+    message: Option<String>,
+    should_quit: bool,
+    scrolloff: usize,
+    tabstop: usize,
+    gutter: GutterOptions,
+
+    // Set when the whole screen must be cleared and redrawn, ie, after the terminal is resized
+    needs_full_redraw: bool,
+
+    // Set up once the screen has been initialized in `run`
+    color_pairs: Option<ColorPairs>,
+}
+
+impl<TokenKind: TokenKindTrait> Tui<TokenKind> {
+    pub fn new(buffers: BufferSet<TokenKind>) -> Self {
+        let buffer = buffers.current();
+        let window = Window::new(1, buffer.number, buffer.cursor.clone());
+        Self {
+            windows: vec![window],
+            tab_pages: vec![TabPage::new(1)],
+            current_tab_page_index: 0,
+            next_window_id: 2,
+            buffers,
+            message: None,
+            should_quit: false,
+            scrolloff: DEFAULT_SCROLLOFF,
+            tabstop: DEFAULT_TABSTOP,
+            gutter: GutterOptions::default(),
+            needs_full_redraw: true,
+            color_pairs: None,
+        }
+    }
+
+    fn tab_page(&self) -> &TabPage {
+        &self.tab_pages[self.current_tab_page_index]
+    }
+
+    fn tab_page_mut(&mut self) -> &mut TabPage {
+        &mut self.tab_pages[self.current_tab_page_index]
+    }
+
+    fn window_index(&self, window_id: usize) -> usize {
+        self.windows
+            .iter()
+            .position(|window| window.id == window_id)
+            .expect("Tui::window_index: window not found!")
+    }
+
+    fn window(&self) -> &Window<TokenKind> {
+        &self.windows[self.window_index(self.tab_page().current_window_id)]
+    }
+
+    fn window_mut(&mut self) -> &mut Window<TokenKind> {
+        let index = self.window_index(self.tab_page().current_window_id);
+        &mut self.windows[index]
+    }
+
+    fn new_window(&mut self, buffer_number: usize, cursor: Cursor<TokenKind>) -> usize {
+        let id = self.next_window_id;
+        self.next_window_id += 1;
+        self.windows.push(Window::new(id, buffer_number, cursor));
+        id
+    }
+
+    fn focus_window(&mut self, window_id: usize) {
+        self.tab_page_mut().current_window_id = window_id;
+        let buffer_number = self.window().buffer_number;
+        let _ = self.buffers.switch_to(buffer_number);
+    }
+
+    fn focus_tab_page(&mut self, index: usize) {
+        self.current_tab_page_index = index;
+        self.needs_full_redraw = true;
+        self.focus_window(self.tab_page().current_window_id);
+    }
+
+    /// When called, stores the current window's cursor into its buffer, so that the cursor ends
+    /// up in the same place next time the buffer is shown in a window.
+    fn save_cursor(&mut self) {
+        let window = self.window();
+        let cursor = window.cursor.clone();
+        if let Some(buffer) = self.buffers.get_mut(window.buffer_number) {
+            buffer.cursor = cursor;
+        }
+    }
+
+    /// When called, runs `f` against the buffers with the current window's buffer as the current
+    /// buffer, and with the window's cursor loaded into it.
+    ///
+    /// Afterwards, the cursors of all other windows showing the buffer are moved into the (possibly
+    /// reparsed) token tree, and any windows showing buffers that no longer exist are switched to
+    /// show the current buffer instead. Those other windows stay on the same text, so when `f`
+    /// adds or removes rows above them, their cursors and the rows they are scrolled to are
+    /// shifted to match.
+    fn with_buffers<R>(&mut self, f: impl FnOnce(&mut BufferSet<TokenKind>) -> R) -> R {
+        let window_id = self.window().id;
+        let buffer_number = self.window().buffer_number;
+        let _ = self.buffers.switch_to(buffer_number);
+        self.save_cursor();
+
+        let other_windows = {
+            let buffer = self.buffers.current();
+            let fold_map = buffer.fold_map();
+            self.windows
+                .iter()
+                .enumerate()
+                .filter(|(_, window)| {
+                    window.id != window_id && window.buffer_number == buffer_number
+                })
+                .map(|(index, window)| {
+                    let top_row = fold_map.to_buffer_row(window.viewport.top_row + 1);
+                    let top = Cursor::new_at_rows_cols(buffer.root.clone(), (top_row, 1));
+                    (
+                        index,
+                        (window.cursor.to_rows_cols(), top_row),
+                        (
+                            window.cursor.to_offset(OffsetUnit::Char),
+                            top.to_offset(OffsetUnit::Char),
+                        ),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        self.buffers.current_mut().take_edit_log();
+
+        let result = f(&mut self.buffers);
+
+        if let Some(buffer) = self.buffers.get_mut(buffer_number) {
+            let edit_log = buffer.take_edit_log();
+            let fold_map = buffer.fold_map();
+            let root = buffer.root.clone();
+            for (index, (rows_cols, top_row), (offset, top_offset)) in other_windows {
+                let (cursor, top_row) = match &edit_log {
+                    Some(edits) => {
+                        let shift = |offset| {
+                            let offset =
+                                edits.iter().fold(offset, |offset, edit| edit.shift(offset));
+                            Cursor::from_offset(root.clone(), offset, OffsetUnit::Char)
+                        };
+                        (shift(offset), shift(top_offset).to_rows())
+                    }
+                    None => (Cursor::new_at_rows_cols(root.clone(), rows_cols), top_row),
+                };
+                let window = &mut self.windows[index];
+                window.cursor = cursor;
+                window.viewport.top_row = fold_map.to_display_row(top_row).saturating_sub(1);
+            }
+        }
+
+        let buffer = self.buffers.current();
+        let (number, cursor) = (buffer.number, buffer.cursor.clone());
+        let window = self.window_mut();
+        window.buffer_number = number;
+        window.cursor = cursor;
+
+        for window in self.windows.iter_mut() {
+            if self.buffers.get(window.buffer_number).is_none() {
+                window.buffer_number = number;
+                window.cursor = Cursor::new(self.buffers.current().root.clone());
+            }
+        }
+
+        result
+    }
+
+    /// When called, reparses the edits made to all buffers shown in a window, moving the cursors of
+    /// the windows showing them into the reparsed token trees.
+    fn reparse_pending(&mut self) -> Result<(), String> {
+        self.save_cursor();
+
+        let window_positions = self
+            .windows
+            .iter()
+            .enumerate()
+            .map(|(index, window)| {
+                let offset = window.cursor.to_offset(OffsetUnit::Char);
+                (index, window.buffer_number, offset)
+            })
+            .collect::<Vec<(usize, usize, usize)>>();
+
+        for (_, buffer_number, _) in window_positions.iter() {
+            let Some(buffer) = self.buffers.get_mut(*buffer_number) else {
+                continue;
+            };
+            buffer.reparse_pending()?;
+        }
+
+        // Reparsing doesn't change the text, so each cursor is still the same number of chars
+        // into it
+        for (index, buffer_number, offset) in window_positions {
+            if let Some(buffer) = self.buffers.get(buffer_number) {
+                self.windows[index].cursor =
+                    Cursor::from_offset(buffer.root.clone(), offset, OffsetUnit::Char);
+            }
+        }
+        Ok(())
+    }
+
+    /// When called, splits the current window in two. The new window shows the file at `path`, or
+    /// the same buffer as the current window if `path` is None.
+    fn split(&mut self, direction: SplitDirection, path: Option<PathBuf>) -> Result<(), String> {
+        let window = self.window();
+        let (window_id, buffer_number) = (window.id, window.buffer_number);
+        let new_window_id = self.new_window(buffer_number, window.cursor.clone());
+
+        self.tab_page_mut()
+            .layout
+            .split(window_id, new_window_id, direction);
+        self.focus_window(new_window_id);
+
+        if let Some(path) = path {
+            self.with_buffers(|buffers| buffers.edit(&path))?;
+        }
+        Ok(())
+    }
+
+    /// When called, closes the current window. If it is the last window in its tab page, the tab
+    /// page is closed too. Returns an error if it is the last window.
+    fn close_window(&mut self) -> Result<(), String> {
+        let window_id = self.window().id;
+        let window_ids = self.tab_page().layout.window_ids();
+
+        if window_ids.len() > 1 {
+            self.save_cursor();
+            self.tab_page_mut().layout.remove(window_id);
+            self.windows.retain(|window| window.id != window_id);
+
+            // Focus the window that was before the closed window
+            let index = window_ids
+                .iter()
+                .position(|id| *id == window_id)
+                .unwrap_or(0);
+            let window_ids = self.tab_page().layout.window_ids();
+            self.focus_window(window_ids[index.saturating_sub(1)]);
+            Ok(())
+        } else {
+            self.close_tab_page()
+                .map_err(|_| "E444: Cannot close last window".into())
+        }
+    }
+
+    /// When called, closes the current tab page and all windows within it. Returns an error if it
+    /// is the last tab page.
+    fn close_tab_page(&mut self) -> Result<(), String> {
+        if self.tab_pages.len() == 1 {
+            return Err("E784: Cannot close last tab page".into());
+        }
+
+        self.save_cursor();
+        let window_ids = self.tab_page().layout.window_ids();
+        self.windows
+            .retain(|window| !window_ids.contains(&window.id));
+
+        self.tab_pages.remove(self.current_tab_page_index);
+        self.focus_tab_page(self.current_tab_page_index.min(self.tab_pages.len() - 1));
+        Ok(())
+    }
+
+    /// When called, closes the current window like `:q` - if it is the last window, the editor is
+    /// exited, as long as there aren't any unsaved changes (unless `force` is set).
+    fn quit_window(&mut self, force: bool) -> Result<(), String> {
+        if self.windows.len() > 1 {
+            return self.close_window();
+        }
+        if !force {
+            self.with_buffers(|buffers| buffers.check_all_written())?;
+        }
+        self.should_quit = true;
+        Ok(())
+    }
+
+    /// When called, closes all windows in the current tab page other than the current window.
+    fn only_window(&mut self) {
+        let window_id = self.window().id;
+        let window_ids = self.tab_page().layout.window_ids();
+        self.windows
+            .retain(|window| window.id == window_id || !window_ids.contains(&window.id));
+        self.tab_page_mut().layout = Layout::Window(window_id);
+    }
+
+    /// When called, opens a new tab page after the current one, showing the file at `path`, or a
+    /// new empty buffer if `path` is None.
+    fn new_tab_page(&mut self, path: Option<PathBuf>) -> Result<(), String> {
+        self.save_cursor();
+        match path {
+            Some(path) => self.buffers.edit(&path)?,
+            None => {
+                self.buffers.add(Buffer::new_from_literal(""));
+            }
+        };
+
+        let buffer = self.buffers.current();
+        let window_id = self.new_window(buffer.number, buffer.cursor.clone());
+        self.tab_pages
+            .insert(self.current_tab_page_index + 1, TabPage::new(window_id));
+        self.focus_tab_page(self.current_tab_page_index + 1);
+        Ok(())
+    }
+
+    /// When called, runs the given command, returning a message to show to the user in the status
+    /// line.
+    fn execute_command(&mut self, command: Command) -> Result<String, String> {
+        match command {
+            Command::Quit { force } => self.quit_window(force)?,
+            Command::QuitAll { force } => {
+                if !force {
+                    self.with_buffers(|buffers| buffers.check_all_written())?;
+                }
+                self.should_quit = true;
+            }
+            Command::WriteQuit => {
+                self.with_buffers(|buffers| buffers.current_mut().write(None))?;
+                self.quit_window(false)?;
+            }
+            Command::Split(path) => self.split(SplitDirection::Horizontal, path)?,
+            Command::VerticalSplit(path) => self.split(SplitDirection::Vertical, path)?,
+            Command::Close => self.close_window()?,
+            Command::Only => self.only_window(),
+            Command::TabNew(path) => self.new_tab_page(path)?,
+            Command::TabNext => {
+                self.save_cursor();
+                self.focus_tab_page((self.current_tab_page_index + 1) % self.tab_pages.len());
+            }
+            Command::TabPrevious => {
+                self.save_cursor();
+                self.focus_tab_page(
+                    (self.current_tab_page_index + self.tab_pages.len() - 1) % self.tab_pages.len(),
+                );
+            }
+            Command::TabClose => self.close_tab_page()?,
+            Command::Set(SetOption::ScrollOff(None)) => {
+                return Ok(format!("scrolloff={}", self.scrolloff));
+            }
+            Command::Set(SetOption::ScrollOff(Some(lines))) => self.scrolloff = lines,
+            Command::Set(SetOption::Number(number)) => self.gutter.number = number,
+            Command::Set(SetOption::RelativeNumber(relative_number)) => {
+                self.gutter.relative_number = relative_number;
+            }
+            Command::Set(SetOption::SignColumn(None)) => {
+                return Ok(format!("signcolumn={}", self.gutter.sign_column.name()));
+            }
+            Command::Set(SetOption::SignColumn(Some(sign_column))) => {
+                self.gutter.sign_column = sign_column;
+            }
+            Command::Set(SetOption::FoldColumn(None)) => {
+                return Ok(format!("foldcolumn={}", self.gutter.fold_column));
+            }
+            Command::Set(SetOption::FoldColumn(Some(width))) => self.gutter.fold_column = width,
+            Command::Set(SetOption::TabStop(None)) => {
+                return Ok(format!("tabstop={}", self.tabstop));
+            }
+            Command::Set(SetOption::TabStop(Some(columns))) => {
+                self.tabstop = columns;
+                self.needs_full_redraw = true;
+            }
+            Command::ColorScheme(None) => return Ok(current_theme_name()),
+            Command::ColorScheme(Some(name)) => {
+                set_current_theme(Theme::find(&name)?);
+                self.needs_full_redraw = true;
+            }
+            Command::Goto(byte) => {
+                // Like vim, `:goto` counts bytes starting from one
+                let root = self.buffers.current().root.clone();
+                self.window_mut().cursor =
+                    Cursor::from_offset(root, byte.saturating_sub(1), OffsetUnit::Utf8);
+            }
+            command => {
+                return self.with_buffers(|buffers| execute_buffer_command(buffers, command));
+            }
+        };
+        Ok("".into())
+    }
+
+    /// When called, runs the `Ctrl-W` command given by the key that was pressed after `Ctrl-W`.
+    fn execute_window_command(&mut self, input: Option<Input>) -> Result<(), String> {
+        let side = match input {
+            Some(Input::Character('s' | 'S' | '\u{13}')) => {
+                return self.split(SplitDirection::Horizontal, None)
+            }
+            Some(Input::Character('v' | '\u{16}')) => {
+                return self.split(SplitDirection::Vertical, None)
+            }
+            Some(Input::Character('c')) => return self.close_window(),
+            Some(Input::Character('q' | '\u{11}')) => return self.quit_window(false),
+            Some(Input::Character('o' | '\u{f}')) => {
+                self.only_window();
+                return Ok(());
+            }
+            Some(Input::Character(c @ ('w' | 'W' | CTRL_W))) => {
+                let window_ids = self.tab_page().layout.window_ids();
+                let index = window_ids
+                    .iter()
+                    .position(|id| *id == self.window().id)
+                    .unwrap_or(0);
+                let offset = if c == 'W' { window_ids.len() - 1 } else { 1 };
+                self.save_cursor();
+                self.focus_window(window_ids[(index + offset) % window_ids.len()]);
+                return Ok(());
+            }
+            Some(Input::Character(c @ ('+' | '-' | '>' | '<'))) => {
+                let direction = match c {
+                    '+' | '-' => SplitDirection::Horizontal,
+                    _ => SplitDirection::Vertical,
+                };
+                let delta = if c == '+' || c == '>' { 1 } else { -1 };
+                let window_id = self.window().id;
+                self.tab_page_mut()
+                    .layout
+                    .resize(window_id, direction, delta);
+                return Ok(());
+            }
+            Some(Input::Character('=')) => {
+                self.tab_page_mut().layout.equalize();
+                return Ok(());
+            }
+            Some(Input::Character('h' | '\u{8}')) | Some(Input::KeyLeft) => Side::Left,
+            Some(Input::Character('j' | '\n')) | Some(Input::KeyDown) => Side::Down,
+            Some(Input::Character('k' | '\u{b}')) | Some(Input::KeyUp) => Side::Up,
+            Some(Input::Character('l' | '\u{c}')) | Some(Input::KeyRight) => Side::Right,
+            _ => return Ok(()),
+        };
+
+        let window_ids = self.tab_page().layout.window_ids();
+        let rects = self
+            .windows
+            .iter()
+            .filter(|window| window_ids.contains(&window.id))
+            .map(|window| (window.id, window.rect))
+            .collect::<Vec<(usize, Rect)>>();
+        let fold_map = self.fold_map();
+        let window = self.window();
+        let position = window
+            .cursor_screen_position(self.tabstop, &fold_map)
+            .unwrap_or((window.rect.row, window.rect.col));
+
+        if let Some(window_id) = find_neighbor(&rects, window.id, side, position) {
+            self.save_cursor();
+            self.focus_window(window_id);
+        }
+        Ok(())
+    }
+
+    /// When called, scrolls the current window by `delta` rows. If `move_cursor` is set, the cursor
+    /// moves by the same number of rows (like `Ctrl-D` / `Ctrl-U`), otherwise it only moves if it
+    /// would otherwise end up too close to the edge of the window (like `Ctrl-E` / `Ctrl-Y`).
+    fn scroll(&mut self, delta: isize, move_cursor: bool) {
+        let Some(buffer) = self.buffers.get(self.window().buffer_number) else {
+            return;
+        };
+        let fold_map = buffer.fold_map();
+        let root = buffer.root.clone();
+        let line_count = fold_map.to_display_row(buffer.line_count());
+        let scrolloff = self.scrolloff;
+
+        let window = self.window_mut();
+        window.viewport.scroll_by(delta, line_count);
+
+        let (rows, cols) = window.cursor.to_rows_cols();
+        let rows = fold_map.to_display_row(rows);
+        let new_rows = if move_cursor {
+            rows.saturating_add_signed(delta).clamp(1, line_count)
+        } else {
+            rows
+        };
+        let (first_row, last_row) = window.viewport.cursor_row_bounds(scrolloff, line_count);
+        let new_rows = new_rows.clamp(first_row, last_row);
+        if new_rows != rows {
+            let new_rows = fold_map.to_buffer_row(new_rows);
+            window.cursor = Cursor::new_at_rows_cols(root, (new_rows, cols));
+        }
+    }
+
+    /// When called, scrolls the current window so the cursor is at the top, center or bottom of
+    /// it.
+    fn align(&mut self, alignment: Alignment) {
+        let (scrolloff, fold_map) = (self.scrolloff, self.fold_map());
+        let window = self.window_mut();
+        let rows = fold_map.to_display_row(window.cursor.to_rows());
+        window.viewport.align(rows, alignment, scrolloff);
+    }
+
+    /// When called, returns the fold map of the current window's buffer.
+    fn fold_map(&self) -> FoldMap {
+        self.buffers
+            .get(self.window().buffer_number)
+            .map(|buffer| buffer.fold_map())
+            .unwrap_or_default()
+    }
+
+    /// When called, moves the cursor down `delta` rows (or up, if `delta` is negative), like `j` /
+    /// `k`. A closed fold counts as a single row, so the cursor moves over it rather than into it.
+    fn move_by_rows(&mut self, delta: isize) {
+        let Some(buffer) = self.buffers.get(self.window().buffer_number) else {
+            return;
+        };
+        let fold_map = buffer.fold_map();
+        let cursor = &self.window().cursor;
+        let cursor = if fold_map.is_empty() {
+            match delta {
+                0.. => cursor.seek_forwards(CursorSeek::AdvanceByLines(delta.unsigned_abs())),
+                _ => cursor.seek_backwards(CursorSeek::AdvanceByLines(delta.unsigned_abs())),
+            }
+        } else {
+            let (rows, cols) = cursor.to_rows_cols();
+            let line_count = fold_map.to_display_row(buffer.line_count());
+            let rows = fold_map
+                .to_display_row(rows)
+                .saturating_add_signed(delta)
+                .clamp(1, line_count);
+            Cursor::new_at_rows_cols(buffer.root.clone(), (fold_map.to_buffer_row(rows), cols))
+        };
+        self.window_mut().cursor = cursor;
+    }
+
+    /// When called, moves the cursor to the mark named `name`. Like vim, `'a` jumps to the first
+    /// non-blank character of the mark's row, and `` `a `` jumps to its exact position.
+    fn jump_to_mark(&mut self, name: char, exact: bool) -> Result<(), String> {
+        let buffer = self.buffers.current();
+        let (rows, cols) = buffer.mark(name)?;
+        let cols = if exact {
+            cols
+        } else {
+            let line = buffer.lines(rows - 1, 1).pop().unwrap_or_default();
+            line.chars().take_while(|c| c.is_whitespace()).count() + 1
+        };
+        let cursor = Cursor::new_at_rows_cols(buffer.root.clone(), (rows, cols));
+        self.window_mut().cursor = cursor;
+        Ok(())
+    }
+
+    /// When called, shows the items in `picker` over the bottom of the screen, narrowing them down
+    /// as a pattern is typed after `prompt` in the command line. `Up` / `Down` (or `Ctrl-P` /
+    /// `Ctrl-N`) move the selection, `Enter` picks the selected item, and `Esc` dismisses the
+    /// picker. Returns the index of the item that was picked.
+    fn pick(
+        &mut self,
+        screen: &pancurses::Window,
+        prompt: &str,
+        mut picker: Picker,
+    ) -> Option<usize> {
+        self.needs_full_redraw = true;
+        loop {
+            let (height, width) = screen.get_max_yx();
+            let (height, width) = (height.max(0) as usize, width.max(0) as usize);
+            let list_height = (height / 2).clamp(1, picker.item_count().max(1));
+            let first_row = height.saturating_sub(1 + list_height);
+
+            // Scroll the list so that the selected item is always shown
+            let matches = picker.matches();
+            let selected = picker.selected_position();
+            let top_row = selected.saturating_sub(list_height - 1);
+            for row in 0..list_height {
+                screen.mv((first_row + row) as i32, 0);
+                screen.clrtoeol();
+                let Some((_, item)) = matches.get(top_row + row) else {
+                    continue;
+                };
+                let label = fit_to_width(&item.label, width);
+                let group = if top_row + row == selected {
+                    HighlightGroup::TabLineSelected
+                } else {
+                    HighlightGroup::TabLine
+                };
+                self.draw_styled(screen, &label, current_style(Some(group)));
+            }
+
+            let count = format!("{}/{}", matches.len(), picker.item_count());
+            let line = format!("{prompt}{}", picker.pattern());
+            screen.mv(height as i32 - 1, 0);
+            screen.clrtoeol();
+            screen.mvaddstr(
+                height as i32 - 1,
+                width.saturating_sub(count.len()) as i32,
+                &count,
+            );
+            screen.mvaddstr(height as i32 - 1, 0, &line);
+            screen.refresh();
+
+            match screen.getch() {
+                Some(Input::Character('\n')) | Some(Input::KeyEnter) => return picker.selected(),
+                Some(Input::Character('\u{1b}')) => return None,
+                Some(Input::KeyBackspace)
+                | Some(Input::Character('\u{7f}'))
+                | Some(Input::Character('\u{8}')) => {
+                    let mut pattern = picker.pattern().to_string();
+                    pattern.pop();
+                    picker.set_pattern(&pattern);
+                }
+                Some(Input::KeyUp) | Some(Input::Character(CTRL_P)) => picker.move_selection(-1),
+                Some(Input::KeyDown) | Some(Input::Character(CTRL_N)) => picker.move_selection(1),
+                Some(Input::KeyResize) => {
+                    resize_term(0, 0);
+                    screen.erase();
+                }
+                // Control characters (ie, a tab or ctrl-w) that aren't handled above are ignored,
+                // rather than being typed into the pattern
+                Some(Input::Character(c)) if !c.is_control() => {
+                    let pattern = format!("{}{c}", picker.pattern());
+                    picker.set_pattern(&pattern);
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// When called, lists the symbols in the current buffer (see `Buffer::outline`) in a picker,
+    /// starting off filtered by `pattern`, and moves the cursor to the symbol that is picked.
+    fn pick_symbol(
+        &mut self,
+        screen: &pancurses::Window,
+        pattern: Option<String>,
+    ) -> Result<(), String> {
+        let outline = self.with_buffers(|buffers| buffers.current_mut().outline())?;
+        let symbols = Symbol::flatten(&outline);
+        if symbols.is_empty() {
+            return Err("No symbols found".into());
+        }
+
+        // Symbols are matched by their name along with the names of the symbols they are within,
+        // ie, `Foo.bar` for the method `bar` in the class `Foo`
+        let mut path = vec![];
+        let items = symbols
+            .iter()
+            .map(|(depth, symbol)| {
+                path.truncate(*depth);
+                path.push(symbol.name.as_str());
+                PickerItem {
+                    label: format!("{}{}", "  ".repeat(*depth), symbol.label()),
+                    filter_text: path.join("."),
+                }
+            })
+            .collect();
+        let picker = Picker::new(items, pattern.as_deref().unwrap_or(""));
+        let Some(index) = self.pick(screen, ":outline ", picker) else {
+            return Ok(());
+        };
+
+        let rows_cols = symbols[index].1.selection.primary.to_rows_cols();
+        let root = self.buffers.current().root.clone();
+        self.window_mut().cursor = Cursor::new_at_rows_cols(root, rows_cols);
+        Ok(())
+    }
+
+    /// When called, handles a key that was pressed in normal mode.
+    fn handle_input(&mut self, screen: &pancurses::Window, input: Option<Input>) {
+        let result = match input {
+            Some(Input::KeyResize) => {
+                resize_term(0, 0);
+                self.needs_full_redraw = true;
+                Ok(())
+            }
+            Some(Input::Character('q')) => self.quit_window(false),
+
+            Some(Input::Character(':')) => {
+                let Some(line) = read_command_line(screen, screen.get_max_y() - 1) else {
+                    return;
+                };
+                let result = Command::parse(&line).and_then(|command| match command {
+                    Command::Outline(pattern) => {
+                        self.pick_symbol(screen, pattern).map(|_| "".into())
+                    }
+                    command => self.execute_command(command),
+                });
+                self.message = Some(result.unwrap_or_else(|err| err));
+                return;
+            }
+            Some(Input::Character(CTRL_W)) => {
+                let input = screen.getch();
+                self.execute_window_command(input)
+            }
+
+            Some(Input::Character(c @ (CTRL_E | CTRL_Y | CTRL_D | CTRL_U))) => {
+                let half_height = (self.window().viewport.height / 2).max(1) as isize;
+                match c {
+                    CTRL_E => self.scroll(1, false),
+                    CTRL_Y => self.scroll(-1, false),
+                    CTRL_D => self.scroll(half_height, true),
+                    _ => self.scroll(-half_height, true),
+                };
+                Ok(())
+            }
+            Some(Input::Character('z')) => match screen.getch() {
+                Some(Input::Character(c @ ('z' | '.' | 't' | '\n' | 'b' | '-'))) => {
+                    self.align(match c {
+                        'z' | '.' => Alignment::Center,
+                        't' | '\n' => Alignment::Top,
+                        _ => Alignment::Bottom,
+                    });
+                    Ok(())
+                }
+                Some(Input::Character(c @ ('o' | 'c' | 'a' | 'M' | 'R'))) => {
+                    // Opening or closing a fold moves every row after it, in every window showing
+                    // the buffer
+                    self.needs_full_redraw = true;
+                    let rows = self.window().cursor.to_rows();
+                    self.with_buffers(|buffers| {
+                        let buffer = buffers.current_mut();
+                        match c {
+                            'o' => buffer.fold(rows, FoldAction::Open),
+                            'c' => buffer.fold(rows, FoldAction::Close),
+                            'a' => buffer.fold(rows, FoldAction::Toggle),
+                            _ => buffer.set_all_folds_closed(c == 'M'),
+                        }
+                    })
+                }
+                _ => Ok(()),
+            },
+
+            Some(Input::Character('m')) => match screen.getch() {
+                Some(Input::Character(name)) => {
+                    let rows_cols = self.window().cursor.to_rows_cols();
+                    self.with_buffers(|buffers| buffers.current_mut().set_mark(name, rows_cols))
+                }
+                _ => Ok(()),
+            },
+            Some(Input::Character(jump @ ('\'' | '`'))) => match screen.getch() {
+                Some(Input::Character(name)) => self.jump_to_mark(name, jump == '`'),
+                _ => Ok(()),
+            },
+
+            Some(Input::Character(bracket @ (']' | '['))) => match screen.getch() {
+                Some(Input::Character('d')) => {
+                    let direction = if bracket == ']' {
+                        Direction::Forwards
+                    } else {
+                        Direction::Backwards
+                    };
+                    self.with_buffers(|buffers| buffers.current_mut().jump_to_diagnostic(direction))
+                        .map(|message| self.message = Some(message))
+                }
+                _ => Ok(()),
+            },
+
+            Some(Input::Character('x')) => {
+                self.with_buffers(|buffers| buffers.current_mut().delete_char_under_cursor())
+            }
+            Some(Input::Character('u')) => {
+                match self.with_buffers(|buffers| buffers.current_mut().undo()) {
+                    Ok(false) => Err("Already at oldest change".into()),
+                    result => result.map(|_| ()),
+                }
+            }
+            Some(Input::Character(CTRL_R)) => {
+                match self.with_buffers(|buffers| buffers.current_mut().redo()) {
+                    Ok(false) => Err("Already at newest change".into()),
+                    result => result.map(|_| ()),
+                }
+            }
+
+            Some(Input::Character(c @ ('j' | 'k'))) => {
+                self.move_by_rows(if c == 'j' { 1 } else { -1 });
+                Ok(())
+            }
+            Some(Input::Character(c)) => match self.move_cursor(c) {
+                Ok(false) => return,
+                result => result.map(|_| ()),
+            },
+            _ => Ok(()),
+        };
+
+        if let Err(err) = result {
+            self.message = Some(err);
+        }
+    }
+
+    /// When called, moves the current window's cursor with the motion bound to `c`, ie, `w` moves
+    /// to the start of the next word. Returns false if there is no motion bound to `c`.
+    fn move_cursor(&mut self, c: char) -> Result<bool, String> {
+        // Words and delimiters follow the structure of the document, so any edits need to be
+        // reparsed before moving by them
+        if matches!(c, 'w' | 'b' | '%') {
+            self.reparse_pending()?;
+        }
+
+        let cursor = &self.window().cursor;
+        let cursor = match c {
+            'l' => cursor.seek_graphemes(Direction::Forwards, 1),
+            'h' => cursor.seek_graphemes(Direction::Backwards, 1),
+            'w' => cursor.seek_forwards(CursorSeek::forwards_word(1, false, false)),
+            'b' => cursor.seek_backwards(CursorSeek::back_word(1, false, false)),
+            '%' => cursor.seek_forwards(CursorSeek::advance_until_matching_delimiter(
+                Inclusivity::Inclusive,
+            )),
+            '0' => cursor.seek_backwards(CursorSeek::advance_until_line_start()),
+            '^' => cursor
+                .seek_backwards(CursorSeek::advance_until_line_start_after_leading_whitespace()),
+            '$' => cursor.seek_forwards(CursorSeek::advance_until_line_end()),
+            'G' => cursor.seek_forwards(CursorSeek::advance_until_start_end()),
+            'g' => cursor.seek_backwards(CursorSeek::advance_until_start_end()),
+            _ => return Ok(false),
+        };
+        self.window_mut().cursor = cursor;
+        Ok(true)
+    }
+
+    /// When called, draws the given window, along with its status line and the separator to its
+    /// right (if there is a window to its right).
+    fn draw_window(
+        &self,
+        screen: &pancurses::Window,
+        window: &Window<TokenKind>,
+        is_current: bool,
+        area: Rect,
+    ) {
+        let Some(buffer) = self.buffers.get(window.buffer_number) else {
+            return;
+        };
+        let (rect, viewport) = (window.rect, window.viewport);
+        let (fold_map, folds) = (buffer.fold_map(), buffer.folds());
+
+        // Only redraw the rows that changed since the window was last drawn. If the window has
+        // moved or scrolled, or more than one change has been made since, everything is redrawn.
+        // Changes move rows in and out of closed folds, so everything is redrawn if there are any.
+        let rows_to_draw = match (window.last_drawn, buffer.last_change) {
+            (Some((_, last_viewport, last_rect)), _)
+                if last_viewport != viewport || last_rect != rect =>
+            {
+                0..viewport.height
+            }
+            (Some((change_id, _, _)), _) if change_id == buffer.change_id => 0..0,
+            (Some((change_id, _, _)), Some(change))
+                if change_id + 1 == buffer.change_id && fold_map.is_empty() =>
+            {
+                let start = (change.start_row - 1).saturating_sub(viewport.top_row);
+                let end = if change.old_end_row == change.new_end_row {
+                    change.new_end_row.saturating_sub(viewport.top_row)
+                } else {
+                    // Rows were added or removed, so every row after the change has moved
+                    viewport.height
+                };
+                start.min(viewport.height)..end.min(viewport.height)
+            }
+            _ => 0..viewport.height,
+        };
+
+        // The gutter is cheap to draw, and relative line numbers and signs can change without the
+        // buffer changing, so it is always drawn in full
+        let (rows, cols) = window.cursor.to_rows_cols();
+        let line_count = buffer.line_count();
+        let buffer_rows = (0..viewport.height)
+            .map(|row| {
+                Some(fold_map.to_buffer_row(viewport.top_row + row + 1))
+                    .filter(|row| *row <= line_count)
+            })
+            .collect::<Vec<Option<usize>>>();
+        if window.gutter_width > 0 {
+            let signs = buffer.signs();
+            for (row, buffer_row) in buffer_rows.iter().enumerate() {
+                let fold_marks = buffer_row.map_or(String::new(), |buffer_row| {
+                    fold_column(&folds, buffer_row, self.gutter.fold_column)
+                });
+                screen.mv((rect.row + row) as i32, rect.col as i32);
+                for (text, style) in
+                    self.gutter
+                        .render(*buffer_row, rows, line_count, &signs, &fold_marks)
+                {
+                    self.draw_styled(screen, &text, style);
+                }
+            }
+        }
+
+        // Rows are fetched in runs of consecutive buffer rows, which are only broken up by closed
+        // folds
+        let mut lines = vec![];
+        let rows_to_fetch = &buffer_rows[rows_to_draw.clone()];
+        let mut index = 0;
+        while index < rows_to_fetch.len() {
+            let Some(start) = rows_to_fetch[index] else {
+                lines.push(None);
+                index += 1;
+                continue;
+            };
+            let count = rows_to_fetch[index..]
+                .iter()
+                .enumerate()
+                .take_while(|(offset, row)| **row == Some(start + offset))
+                .count();
+            lines.extend(buffer.styled_lines(start - 1, count).into_iter().map(Some));
+            index += count;
+        }
+
+        for (row, line) in rows_to_draw.zip(lines) {
+            screen.mv(
+                (rect.row + row) as i32,
+                (rect.col + window.gutter_width) as i32,
+            );
+            // Like vim, a closed fold is shown as a single row summarizing the rows within it
+            let line = match (line, buffer_rows[row]) {
+                (Some((line, spans)), Some(buffer_row)) => {
+                    match fold_map.closed_fold_at(buffer_row) {
+                        Some(fold) => {
+                            let width = viewport.left_col + viewport.width;
+                            let line =
+                                fold_line(line.strip_suffix('\r').unwrap_or(&line), fold, width);
+                            let style = current_style(Some(HighlightGroup::Folded));
+                            let range = 0..line.chars().count();
+                            Some((line, vec![StyleSpan { range, style }]))
+                        }
+                        None => Some((line, spans)),
+                    }
+                }
+                (line, _) => line,
+            };
+            let mut drawn_width = 0;
+            match line {
+                Some((line, spans)) => {
+                    let line = line.strip_suffix('\r').unwrap_or(&line);
+                    let mut spans = spans.iter().peekable();
+                    let mut segment = String::new();
+                    let mut segment_style = Style::default();
+                    let (first_col, last_col) =
+                        (viewport.left_col, viewport.left_col + viewport.width);
+                    let (mut index, mut display_col) = (0, 0);
+                    for grapheme in line.graphemes(true) {
+                        let grapheme_index = index;
+                        let start_col = display_col;
+                        index += grapheme.chars().count();
+                        display_col += grapheme_display_width(grapheme, display_col, self.tabstop);
+                        if display_col <= first_col && start_col < first_col {
+                            continue;
+                        }
+                        if start_col >= last_col {
+                            break;
+                        }
+
+                        while spans
+                            .next_if(|span| span.range.end <= grapheme_index)
+                            .is_some()
+                        {}
+                        let style = spans
+                            .peek()
+                            .filter(|span| span.range.contains(&grapheme_index))
+                            .map(|span| span.style)
+                            .unwrap_or_default();
+                        if style != segment_style {
+                            self.draw_styled(screen, &segment, segment_style);
+                            segment.clear();
+                            segment_style = style;
+                        }
+
+                        // Tabs are expanded to spaces, as are wide characters that are cut off by
+                        // the edge of the viewport
+                        let visible_width = display_col.min(last_col) - start_col.max(first_col);
+                        if grapheme == "\t" || visible_width < display_col - start_col {
+                            segment.push_str(&" ".repeat(visible_width));
+                        } else {
+                            segment.push_str(grapheme);
+                        }
+                        drawn_width += visible_width;
+                    }
+                    self.draw_styled(screen, &segment, segment_style);
+                }
+                // Like vim, rows past the end of the buffer are marked with a `~`
+                None => {
+                    self.draw_styled(screen, "~", current_style(Some(HighlightGroup::NonText)));
+                    drawn_width += 1;
+                }
+            };
+            screen.addstr(" ".repeat(viewport.width.saturating_sub(drawn_width)));
+        }
+
+        // Like vim's ruler, if the cursor is drawn at a different column than it is at in the text
+        // (ie, after a tab or a wide character), show both
+        let display_cols = window.cursor.to_display_cols(self.tabstop);
+        let cols = if display_cols == cols {
+            cols.to_string()
+        } else {
+            format!("{cols}-{display_cols}")
+        };
+        let status = format!(
+            "{}{} ({rows}, {cols})",
+            buffer.name(),
+            if buffer.is_modified() { " [+]" } else { "" },
+        );
+        let status = format!("{status:<width$}", width = rect.width)
+            .chars()
+            .take(rect.width)
+            .collect::<String>();
+        let group = if is_current {
+            HighlightGroup::StatusLine
+        } else {
+            HighlightGroup::StatusLineInactive
+        };
+        screen.mv((rect.row + viewport.height) as i32, rect.col as i32);
+        self.draw_styled(screen, &status, current_style(Some(group)));
+
+        if rect.col + rect.width < area.col + area.width {
+            let style = current_style(Some(HighlightGroup::VerticalSplit));
+            for row in rect.row..rect.row + rect.height {
+                screen.mv(row as i32, (rect.col + rect.width) as i32);
+                self.draw_styled(screen, "|", style);
+            }
+        }
+    }
+
+    /// When called, draws `text` at the current screen position using the given style.
+    fn draw_styled(&self, screen: &pancurses::Window, text: &str, style: Style) {
+        if text.is_empty() {
+            return;
+        }
+        let attributes = match &self.color_pairs {
+            Some(color_pairs) => color_pairs.attributes(style),
+            None => 0,
+        };
+        screen.attron(attributes);
+        screen.addstr(text);
+        screen.attroff(attributes);
+    }
+
+    /// When called, draws the list of tab pages along the top of the screen.
+    fn draw_tab_line(&self, screen: &pancurses::Window, width: usize) {
+        let tab_line_style = current_style(Some(HighlightGroup::TabLine));
+        screen.mv(0, 0);
+        self.draw_styled(screen, &" ".repeat(width), tab_line_style);
+        screen.mv(0, 0);
+        for (index, tab_page) in self.tab_pages.iter().enumerate() {
+            let window = &self.windows[self.window_index(tab_page.current_window_id)];
+            let Some(buffer) = self.buffers.get(window.buffer_number) else {
+                continue;
+            };
+            let name = buffer.name();
+            let name = name.rsplit('/').next().unwrap_or(&name);
+            let label = format!(
+                " {} {name}{} ",
+                index + 1,
+                if buffer.is_modified() { " +" } else { "" }
+            );
+
+            let style = if index == self.current_tab_page_index {
+                current_style(Some(HighlightGroup::TabLineSelected))
+            } else {
+                tab_line_style
+            };
+            self.draw_styled(screen, &label, style);
+        }
+    }
+
+    /// When called, draws the whole screen - all windows in the current tab page, the tab line,
+    /// and the message line.
+    fn draw(&mut self, screen: &pancurses::Window) {
+        // Highlighting follows the structure of the document, so any edits need to be reparsed
+        // before they are drawn
+        if let Err(err) = self.reparse_pending() {
+            self.message = Some(err);
+        }
+
+        let (height, width) = screen.get_max_yx();
+        let (height, width) = (height.max(0) as usize, width.max(0) as usize);
+        if self.needs_full_redraw {
+            screen.erase();
+            self.windows
+                .iter_mut()
+                .for_each(|window| window.last_drawn = None);
+            self.needs_full_redraw = false;
+        }
+
+        let has_tab_line = self.tab_pages.len() > 1;
+        let area = Rect {
+            row: usize::from(has_tab_line),
+            col: 0,
+            height: height.saturating_sub(1 + usize::from(has_tab_line)),
+            width,
+        };
+        if has_tab_line {
+            self.draw_tab_line(screen, width);
+        }
+
+        let current_window_id = self.window().id;
+        let (scrolloff, tabstop) = (self.scrolloff, self.tabstop);
+        for (window_id, rect) in self.tab_page_mut().layout.layout(area) {
+            let index = self.window_index(window_id);
+            let gutter_width =
+                self.buffers
+                    .get(self.windows[index].buffer_number)
+                    .map_or(0, |buffer| {
+                        self.gutter
+                            .width(buffer.line_count(), !buffer.signs().is_empty())
+                    });
+            let window = &mut self.windows[index];
+            window.set_rect(rect, gutter_width);
+            let rows_cols = (
+                window.cursor.to_rows(),
+                window.cursor.to_display_cols(tabstop),
+            );
+            let fold_map = self
+                .buffers
+                .get(window.buffer_number)
+                .map(|buffer| buffer.fold_map())
+                .unwrap_or_default();
+            let rows_cols = (fold_map.to_display_row(rows_cols.0), rows_cols.1);
+            window.viewport.scroll_to(rows_cols, scrolloff);
+            self.draw_window(
+                screen,
+                &self.windows[index],
+                window_id == current_window_id,
+                area,
+            );
+
+            let window = &mut self.windows[index];
+            let change_id = self
+                .buffers
+                .get(window.buffer_number)
+                .map_or(0, |buffer| buffer.change_id);
+            window.last_drawn = Some((change_id, window.viewport, window.rect));
+        }
+
+        let message = self
+            .message
+            .take()
+            .unwrap_or_else(|| format!("{:?}", self.window().cursor));
+        let lines = message.lines().collect::<Vec<&str>>();
+        if lines.len() > 1 {
+            // Like vim, messages that span multiple lines (ie, the output of `:ls`) are drawn over
+            // the bottom of the screen and wait for a key to be pressed
+            let first_row = height.saturating_sub(1 + lines.len());
+            for (index, line) in lines.iter().enumerate() {
+                screen.mv((first_row + index) as i32, 0);
+                screen.clrtoeol();
+                screen.addstr(line);
+            }
+            screen.mvaddstr(
+                height as i32 - 1,
+                0,
+                "Press ENTER or type command to continue",
+            );
+            screen.refresh();
+            screen.getch();
+            self.needs_full_redraw = true;
+            return self.draw(screen);
+        }
+        screen.mv(height as i32 - 1, 0);
+        screen.clrtoeol();
+        screen.addstr(message);
+
+        let fold_map = self.fold_map();
+        if let Some((row, col)) = self
+            .window()
+            .cursor_screen_position(self.tabstop, &fold_map)
+        {
+            screen.mv(row as i32, col as i32);
+        }
+        screen.refresh();
+    }
+
+    /// When called, starts the editor, running until the user quits.
+    pub fn run(&mut self) {
+        let screen = initscr();
+        screen.keypad(true);
+        noecho();
+        self.color_pairs = Some(ColorPairs::new());
+
+        while !self.should_quit {
+            self.draw(&screen);
+            let input = screen.getch();
+            self.handle_input(&screen, input);
+        }
+
+        echo();
+        endwin();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::languages;
+
+    #[test]
+    fn motions_reparse_edits_first() {
+        let buffer = Buffer::<languages::typescript::SyntaxKind>::new_from_literal("f(a, (b));\n");
+        let mut tui = Tui::new(BufferSet::new(buffer));
+        let root = tui.buffers.current().root.clone();
+
+        // Deleting the inner `(` unbalances the brackets, but is only reparsed once it is needed
+        tui.window_mut().cursor = Cursor::from_offset(root.clone(), 5, OffsetUnit::Char);
+        tui.with_buffers(|buffers| buffers.current_mut().delete_char_under_cursor())
+            .unwrap();
+        assert_eq!(tui.buffers.current().literal(), "f(a, b));\n");
+        assert!(tui.buffers.current().has_pending_reparse());
+
+        tui.window_mut().cursor = Cursor::from_offset(root.clone(), 1, OffsetUnit::Char);
+        assert_eq!(tui.move_cursor('%'), Ok(true));
+        assert!(!tui.buffers.current().has_pending_reparse());
+        assert_eq!(tui.window().cursor.to_offset(OffsetUnit::Char), 6);
+    }
+
+    #[test]
+    fn other_windows_follow_rows_changed_above_them() {
+        let buffer = Buffer::<languages::raw::SyntaxKind>::new_from_literal("a\nb\nc\nd\ne\n");
+        let mut tui = Tui::new(BufferSet::new(buffer));
+        let root = tui.buffers.current().root.clone();
+        tui.split(SplitDirection::Horizontal, None).unwrap();
+
+        // The first window is scrolled down to `c`, with its cursor on `d`
+        tui.windows[0].cursor = Cursor::new_at_rows_cols(root.clone(), (4, 1));
+        tui.windows[0].viewport.top_row = 2;
+
+        // Adding rows above the first window in the second moves the first window down with them
+        tui.window_mut().cursor = Cursor::new_at_rows_cols(root.clone(), (2, 1));
+        tui.with_buffers(|buffers| {
+            let buffer = buffers.current_mut();
+            buffer.record_change();
+            let selection = buffer.cursor.selection();
+            buffer.splice(&selection, "x\ny\n")
+        })
+        .unwrap();
+        assert_eq!(tui.buffers.current().literal(), "a\nx\ny\nb\nc\nd\ne\n");
+        assert_eq!(tui.windows[0].cursor.to_rows_cols(), (6, 1));
+        assert_eq!(tui.windows[0].viewport.top_row, 4);
+
+        // Removing them again (with an undo) moves it back
+        tui.with_buffers(|buffers| buffers.current_mut().undo())
+            .unwrap();
+        assert_eq!(tui.windows[0].cursor.to_rows_cols(), (4, 1));
+        assert_eq!(tui.windows[0].viewport.top_row, 2);
+
+        // Edits below the first window leave it where it is
+        tui.window_mut().cursor = Cursor::new_at_rows_cols(root.clone(), (5, 1));
+        tui.with_buffers(|buffers| buffers.current_mut().delete_char_under_cursor())
+            .unwrap();
+        assert_eq!(tui.buffers.current().literal(), "a\nb\nc\nd\n\n");
+        assert_eq!(tui.windows[0].cursor.to_rows_cols(), (4, 1));
+        assert_eq!(tui.windows[0].viewport.top_row, 2);
+    }
+}
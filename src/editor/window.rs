@@ -0,0 +1,194 @@
+use crate::{
+    editor::{fold::FoldMap, layout::Rect},
+    node_tree::{cursor::Cursor, node::TokenKindTrait},
+};
+
+/// A Viewport is the part of a buffer that is visible within a window. Its rows are the rows the
+/// buffer is shown on once closed folds are hidden (see `FoldMap`), rather than the buffer's rows.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Viewport {
+    // The first row / column of the buffer that is visible, zero indexed
+    pub top_row: usize,
+    pub left_col: usize,
+
+    // The number of rows / columns of the buffer that fit in the window
+    pub height: usize,
+    pub width: usize,
+}
+
+/// Where in the viewport a row should be placed, ie, for `zt` / `zz` / `zb`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Alignment {
+    Top,
+    Center,
+    Bottom,
+}
+
+impl Viewport {
+    /// When called, returns the number of rows that should be kept visible above and below the
+    /// cursor. Like vim, this is limited to half the height of the viewport.
+    fn scrolloff(&self, scrolloff: usize) -> usize {
+        scrolloff.min(self.height.saturating_sub(1) / 2)
+    }
+
+    /// When called, scrolls the viewport the minimum amount required so that the given (one
+    /// indexed) row and column are visible, with at least `scrolloff` rows visible above and below
+    /// it.
+    pub fn scroll_to(&mut self, (rows, cols): (usize, usize), scrolloff: usize) {
+        let scrolloff = self.scrolloff(scrolloff);
+        let (row, col) = (rows - 1, cols - 1);
+        if row < self.top_row + scrolloff {
+            self.top_row = row.saturating_sub(scrolloff);
+        } else if self.height > 0 && row + scrolloff >= self.top_row + self.height {
+            self.top_row = row + scrolloff + 1 - self.height;
+        }
+        if col < self.left_col {
+            self.left_col = col;
+        } else if self.width > 0 && col >= self.left_col + self.width {
+            self.left_col = col + 1 - self.width;
+        }
+    }
+
+    /// When called, scrolls the viewport down by `delta` rows (or up, if `delta` is negative),
+    /// like `Ctrl-E` / `Ctrl-Y`. The viewport can be scrolled down until only the last row of the
+    /// buffer is visible.
+    pub fn scroll_by(&mut self, delta: isize, line_count: usize) {
+        self.top_row = self
+            .top_row
+            .saturating_add_signed(delta)
+            .min(line_count.saturating_sub(1));
+    }
+
+    /// When called, scrolls the viewport so that the given (one indexed) row is at the top, center,
+    /// or bottom of the viewport, like `zt` / `zz` / `zb`.
+    pub fn align(&mut self, rows: usize, alignment: Alignment, scrolloff: usize) {
+        let scrolloff = self.scrolloff(scrolloff);
+        let row = rows - 1;
+        self.top_row = match alignment {
+            Alignment::Top => row.saturating_sub(scrolloff),
+            Alignment::Center => row.saturating_sub(self.height.saturating_sub(1) / 2),
+            Alignment::Bottom => (row + scrolloff + 1).saturating_sub(self.height),
+        };
+    }
+
+    /// When called, returns the first and last (one indexed) rows that the cursor can be on without
+    /// the viewport needing to scroll to keep `scrolloff` rows visible around it.
+    pub fn cursor_row_bounds(&self, scrolloff: usize, line_count: usize) -> (usize, usize) {
+        let scrolloff = self.scrolloff(scrolloff);
+        let first_row = if self.top_row == 0 {
+            1
+        } else {
+            self.top_row + scrolloff + 1
+        };
+        let last_visible_row = self.top_row + self.height;
+        let last_row = if last_visible_row >= line_count {
+            line_count
+        } else {
+            last_visible_row.saturating_sub(scrolloff)
+        };
+        (
+            first_row.min(line_count),
+            last_row.max(first_row).min(line_count),
+        )
+    }
+}
+
+/// A Window shows a buffer on screen. Each window has its own cursor and viewport, so multiple
+/// windows can show different parts of the same buffer at once.
+///
+/// Windows refer to their buffer by number, so windows showing the same buffer share a token tree
+/// and each edit made in one window is visible in the others.
+pub struct Window<TokenKind: TokenKindTrait> {
+    pub id: usize,
+    pub buffer_number: usize,
+    pub cursor: Cursor<TokenKind>,
+    pub viewport: Viewport,
+
+    // Where the window was drawn on screen the last time the screen was rendered, including its
+    // gutter and status line
+    pub rect: Rect,
+    pub gutter_width: usize,
+
+    // The buffer's `change_id`, viewport and rect when the window was last drawn, so that the next
+    // draw only has to redraw rows that have changed since. None if the window must be redrawn
+    // in full.
+    pub last_drawn: Option<(usize, Viewport, Rect)>,
+}
+
+impl<TokenKind: TokenKindTrait> Window<TokenKind> {
+    pub fn new(id: usize, buffer_number: usize, cursor: Cursor<TokenKind>) -> Self {
+        Self {
+            id,
+            buffer_number,
+            cursor,
+            viewport: Viewport::default(),
+            rect: Rect::default(),
+            gutter_width: 0,
+            last_drawn: None,
+        }
+    }
+
+    /// When called, updates where the window is placed on screen. The last row of `rect` is
+    /// reserved for the window's status line, and the first `gutter_width` columns for its gutter.
+    pub fn set_rect(&mut self, rect: Rect, gutter_width: usize) {
+        let gutter_width = gutter_width.min(rect.width);
+        self.rect = rect;
+        self.gutter_width = gutter_width;
+        self.viewport.height = rect.height.saturating_sub(1);
+        self.viewport.width = rect.width - gutter_width;
+    }
+
+    /// When called, returns where on screen the cursor should be drawn, or None if the cursor is
+    /// scrolled out of view. Tabs are expanded to `tabstop` columns, and the rows hidden by closed
+    /// folds in `fold_map` are skipped.
+    pub fn cursor_screen_position(
+        &self,
+        tabstop: usize,
+        fold_map: &FoldMap,
+    ) -> Option<(usize, usize)> {
+        let rows = fold_map.to_display_row(self.cursor.to_rows());
+        let cols = self.cursor.to_display_cols(tabstop);
+        let row = (rows - 1).checked_sub(self.viewport.top_row)?;
+        let col = (cols - 1).checked_sub(self.viewport.left_col)?;
+        if row >= self.viewport.height || col >= self.viewport.width {
+            return None;
+        }
+        Some((self.rect.row + row, self.rect.col + self.gutter_width + col))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scroll_viewport() {
+        let mut viewport = Viewport {
+            height: 10,
+            width: 80,
+            ..Default::default()
+        };
+
+        // Moving the cursor past the bottom of the viewport keeps `scrolloff` rows below it
+        viewport.scroll_to((12, 1), 3);
+        assert_eq!(viewport.top_row, 5);
+        assert_eq!(viewport.cursor_row_bounds(3, 100), (9, 12));
+        viewport.scroll_to((7, 1), 3);
+        assert_eq!(viewport.top_row, 3);
+
+        viewport.align(50, Alignment::Center, 3);
+        assert_eq!(viewport.top_row, 45);
+        viewport.align(50, Alignment::Top, 3);
+        assert_eq!(viewport.top_row, 46);
+        viewport.align(50, Alignment::Bottom, 3);
+        assert_eq!(viewport.top_row, 43);
+
+        // Scrolling stops once the last row is at the top of the viewport
+        viewport.scroll_by(100, 60);
+        assert_eq!(viewport.top_row, 59);
+        assert_eq!(viewport.cursor_row_bounds(3, 60), (60, 60));
+        viewport.scroll_by(-100, 60);
+        assert_eq!(viewport.top_row, 0);
+        assert_eq!(viewport.cursor_row_bounds(3, 60), (1, 7));
+    }
+}
@@ -0,0 +1,752 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::node_tree::{
+    node::{InMemoryNode, NodeMetadata, TokenKindTrait},
+    style::HighlightGroup,
+    utils::{is_delimiter, Delimiter, DelimiterType, DELIMITER_LOOKBACK_BUFFER_LENGTH_CHARS},
+};
+
+/// The Brackets language definition is a fallback for languages that don't have a parser of their
+/// own (ie, C, Go, Rust, shell scripts). It doesn't know the grammar of any language, and instead
+/// builds a tree out of the balanced `()`, `[]`, and `{}` blocks in a document, along with the
+/// strings, comments, and C preprocessor conditionals (`#if` / `#else` / `#endif`) in it.
+///
+/// Lines that are indented further than the lines before them are grouped too, so that languages
+/// which use indentation rather than brackets (ie, python, makefiles) still get some structure.
+/// Indentation is only compared between lines within the same block, so the lines within a
+/// `{...}` block aren't grouped again just because they are indented.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SyntaxKind {
+    Document,
+    Block,       // A `(...)`, `[...]`, or `{...}` block, including its brackets
+    Conditional, // A C preprocessor `#if ... #endif` block
+    Indented,    // Lines indented further than the lines before them, and any blank lines within
+    Open,
+    Close,
+    ConditionalMarker, // `#if`, `#ifdef`, `#else`, `#elif`, `#endif`, etc
+
+    String,
+    Comment,
+    Word,
+    Number,
+    Punctuation,
+    Whitespace,
+}
+
+/// A block, conditional, or run of indented lines that the parser is currently within.
+struct Open {
+    node: Rc<RefCell<InMemoryNode<SyntaxKind>>>,
+    delimiter: Option<DelimiterType>, // None for indented lines, which aren't delimited
+
+    // How many chars the lines within it are indented by, once a line has started within it
+    indent: Option<usize>,
+}
+
+struct Parser {
+    chars: Vec<char>,
+    index: usize,
+
+    // The blocks, conditionals, and indented lines that are currently open, innermost last
+    open: Vec<Open>,
+
+    // How many chars the lines directly within the node being parsed into are indented by
+    indent: Option<usize>,
+}
+
+impl Parser {
+    fn new(literal: &str) -> Self {
+        Self {
+            chars: literal.chars().collect(),
+            index: 0,
+            open: vec![],
+            indent: None,
+        }
+    }
+
+    fn substring(&self, start: usize, end: usize) -> String {
+        self.chars[start..end].iter().collect()
+    }
+
+    /// When called, consumes `length` chars, and appends them to `parent` as a node of kind `kind`.
+    fn bump(
+        &mut self,
+        parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>,
+        kind: SyntaxKind,
+        length: usize,
+    ) {
+        let literal = self.substring(self.index, self.index + length);
+        self.index += length;
+        InMemoryNode::append_child(
+            parent,
+            InMemoryNode::new_with_metadata(NodeMetadata::AstNode {
+                kind,
+                literal: Some(literal),
+            }),
+        );
+    }
+
+    /// When called, returns the longest delimiter that starts at the current char, if any.
+    fn delimiter(&self) -> Option<Delimiter> {
+        (1..=DELIMITER_LOOKBACK_BUFFER_LENGTH_CHARS)
+            .rev()
+            .filter(|length| self.index + length <= self.chars.len())
+            .find_map(|length| {
+                // `is_delimiter` matches delimiters at the end of the buffer it is given, so only
+                // delimiters that take up the whole buffer start at the current char
+                match is_delimiter(&self.chars[self.index..self.index + length])? {
+                    delimiter @ (Delimiter::Start(_, n)
+                    | Delimiter::End(_, n)
+                    | Delimiter::EitherStartOrEnd(_, n)
+                    | Delimiter::Midpoint(_, n))
+                        if n == length =>
+                    {
+                        Some(delimiter)
+                    }
+                    _ => None,
+                }
+            })
+    }
+
+    /// When called, returns the number of chars from the current char up until (but not
+    /// including) the end of the line.
+    fn length_until_end_of_line(&self) -> usize {
+        self.chars[self.index..]
+            .iter()
+            .take_while(|c| **c != '\n' && **c != '\r')
+            .count()
+    }
+
+    /// When called, returns true if the current char is the first one on its line that isn't
+    /// whitespace.
+    fn is_at_start_of_line(&self) -> bool {
+        self.chars[..self.index]
+            .iter()
+            .rev()
+            .take_while(|c| **c != '\n')
+            .all(|c| c.is_whitespace())
+    }
+
+    /// When called, returns how many chars the lines in the innermost open block are indented by,
+    /// if a line has started within it yet.
+    fn current_indent(&self) -> Option<usize> {
+        match self.open.last() {
+            Some(open) => open.indent,
+            None => self.indent,
+        }
+    }
+
+    /// When called, returns true if starting a line indented by `indent` chars opens or closes
+    /// any indented lines.
+    fn changes_indentation(&self, indent: usize) -> bool {
+        let is_indented = self
+            .open
+            .last()
+            .is_some_and(|open| open.delimiter.is_none());
+        match self.current_indent() {
+            Some(current) => indent > current || (indent < current && is_indented),
+            None => false,
+        }
+    }
+
+    /// When called, starts a line indented by `indent` chars. Indented lines that are indented
+    /// further than the line are closed, and if the line is indented further than the lines
+    /// before it in the innermost block, a new run of indented lines is opened.
+    fn start_line(&mut self, container: &Rc<RefCell<InMemoryNode<SyntaxKind>>>, indent: usize) {
+        loop {
+            match self.current_indent() {
+                // The first line within a block sets how far the block's lines are indented
+                None => {
+                    match self.open.last_mut() {
+                        Some(open) => open.indent = Some(indent),
+                        None => self.indent = Some(indent),
+                    };
+                    return;
+                }
+                Some(current) if indent > current => {
+                    let parent = self
+                        .open
+                        .last()
+                        .map(|open| open.node.clone())
+                        .unwrap_or_else(|| container.clone());
+                    let node = InMemoryNode::append_child(
+                        &parent,
+                        InMemoryNode::new_with_metadata(NodeMetadata::AstNode {
+                            kind: SyntaxKind::Indented,
+                            literal: None,
+                        }),
+                    );
+                    self.open.push(Open {
+                        node,
+                        delimiter: None,
+                        indent: Some(indent),
+                    });
+                    return;
+                }
+                Some(_) if self.changes_indentation(indent) => {
+                    self.open.pop();
+                }
+                Some(_) => return,
+            }
+        }
+    }
+
+    /// When called, returns the number of chars in the whitespace at the current char up to and
+    /// including its last newline, followed by the number of chars that the line after it is
+    /// indented by. Returns None if the whitespace doesn't contain a newline, or if it runs to the
+    /// end of the document.
+    fn line_break(&self) -> Option<(usize, usize)> {
+        let length = self.chars[self.index..]
+            .iter()
+            .take_while(|c| c.is_whitespace())
+            .count();
+        if self.index + length == self.chars.len() {
+            return None;
+        }
+        let newlines = self.chars[self.index..self.index + length]
+            .iter()
+            .rposition(|c| *c == '\n')?
+            + 1;
+        Some((newlines, length - newlines))
+    }
+
+    /// When called, returns the length of the quoted string that starts at the current char, or
+    /// None if it isn't closed on the same line.
+    fn string_length(&self, quote: char) -> Option<usize> {
+        if quote == '\'' {
+            let is_word_char = |c: &char| c.is_alphanumeric() || *c == '_';
+            let previous = self.index.checked_sub(1).map(|index| self.chars[index]);
+            let word_length = self.chars[self.index + 1..]
+                .iter()
+                .take_while(|c| is_word_char(c))
+                .count();
+            let is_unquoted_word =
+                word_length > 0 && self.chars.get(self.index + 1 + word_length) != Some(&'\'');
+            // An apostrophe in a word (ie, `don't`), or a rust lifetime (ie, `&'a str`)
+            if previous.is_some_and(|c| is_word_char(&c))
+                || (previous.is_some_and(|c| c == '&' || c == '<') && is_unquoted_word)
+            {
+                return None;
+            }
+        }
+
+        let mut is_escaped = false;
+        for (offset, c) in self.chars[self.index + 1..].iter().enumerate() {
+            match c {
+                '\n' | '\r' => return None,
+                _ if is_escaped => is_escaped = false,
+                '\\' => is_escaped = true,
+                c if *c == quote => return Some(offset + 2),
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// When called, parses the rest of the document into `container`. If `is_block` is set,
+    /// `container` is a block, and its brackets are appended to it directly - the opening bracket
+    /// only if `is_block_start` is set.
+    fn parse(
+        &mut self,
+        container: &Rc<RefCell<InMemoryNode<SyntaxKind>>>,
+        is_block: bool,
+        is_block_start: bool,
+    ) {
+        if is_block && is_block_start {
+            if let Some(Delimiter::Start(
+                DelimiterType::Parenthesis | DelimiterType::Square | DelimiterType::Curly,
+                n,
+            )) = self.delimiter()
+            {
+                self.bump(container, SyntaxKind::Open, n);
+            }
+        }
+        let mut is_closed = false;
+
+        // A document's first line is at its start, but a block's first line starts after the line
+        // that the block was opened on
+        if !is_block {
+            let indent = self
+                .chars
+                .iter()
+                .take_while(|c| c.is_whitespace() && **c != '\n')
+                .count();
+            if indent < self.chars.len() && self.chars[indent] != '\n' {
+                self.indent = Some(indent);
+            }
+        }
+
+        while self.index < self.chars.len() {
+            let parent = self
+                .open
+                .last()
+                .map(|open| open.node.clone())
+                .unwrap_or_else(|| container.clone());
+            let c = self.chars[self.index];
+            let next = self.chars.get(self.index + 1).copied();
+
+            // The newlines before a line that opens or closes indented lines go before the
+            // indented lines, and the line's indentation goes within them
+            if let Some((newlines, indent)) = self.line_break() {
+                if self.changes_indentation(indent) {
+                    self.bump(&parent, SyntaxKind::Whitespace, newlines);
+                    self.start_line(container, indent);
+                    if indent > 0 {
+                        let parent = self
+                            .open
+                            .last()
+                            .map(|open| open.node.clone())
+                            .unwrap_or_else(|| container.clone());
+                        self.bump(&parent, SyntaxKind::Whitespace, indent);
+                    }
+                    continue;
+                }
+                self.start_line(container, indent);
+            }
+
+            // Line comments, ie, `// comment` and `# comment`
+            let is_hash_comment = c == '#'
+                && next.is_none_or(|next| next.is_whitespace() || next == '!')
+                && self
+                    .index
+                    .checked_sub(1)
+                    .is_none_or(|index| self.chars[index].is_whitespace());
+            if (c == '/' && next == Some('/')) || is_hash_comment {
+                let length = self.length_until_end_of_line();
+                self.bump(&parent, SyntaxKind::Comment, length);
+                continue;
+            }
+
+            match self.delimiter() {
+                Some(Delimiter::Start(
+                    kind @ (DelimiterType::Parenthesis
+                    | DelimiterType::Square
+                    | DelimiterType::Curly),
+                    n,
+                )) => {
+                    let block = InMemoryNode::append_child(
+                        &parent,
+                        InMemoryNode::new_with_metadata(NodeMetadata::AstNode {
+                            kind: SyntaxKind::Block,
+                            literal: None,
+                        }),
+                    );
+                    self.bump(&block, SyntaxKind::Open, n);
+                    self.open.push(Open {
+                        node: block,
+                        delimiter: Some(kind),
+                        indent: None,
+                    });
+                }
+                Some(Delimiter::End(
+                    kind @ (DelimiterType::Parenthesis
+                    | DelimiterType::Square
+                    | DelimiterType::Curly),
+                    n,
+                )) => {
+                    let position = self
+                        .open
+                        .iter()
+                        .rposition(|open| open.delimiter.as_ref() == Some(&kind));
+                    if let Some(position) = position {
+                        // Any blocks opened after the one being closed are missing their closing
+                        // brackets (or are indented lines within it), so close them too
+                        let block = self.open.drain(position..).next().unwrap().node;
+                        self.bump(&block, SyntaxKind::Close, n);
+                    } else if is_block
+                        && !is_closed
+                        && self.open.iter().all(|open| open.delimiter.is_none())
+                    {
+                        self.open.clear();
+                        self.bump(container, SyntaxKind::Close, n);
+                        is_closed = true;
+                    } else {
+                        self.bump(&parent, SyntaxKind::Punctuation, n);
+                    }
+                }
+                Some(Delimiter::EitherStartOrEnd(
+                    DelimiterType::SingleQuote | DelimiterType::DoubleQuote,
+                    _,
+                )) => match self.string_length(c) {
+                    Some(length) => self.bump(&parent, SyntaxKind::String, length),
+                    None => self.bump(&parent, SyntaxKind::Punctuation, 1),
+                },
+                Some(Delimiter::Start(DelimiterType::CMultiLineComment, n)) => {
+                    let length = (self.index + n..self.chars.len())
+                        .find(|index| self.chars[*index..].starts_with(&['*', '/']))
+                        .map(|index| index + 2 - self.index)
+                        .unwrap_or(self.chars.len() - self.index);
+                    self.bump(&parent, SyntaxKind::Comment, length);
+                }
+                Some(
+                    delimiter @ (Delimiter::Start(DelimiterType::CPreprocesserConditional, n)
+                    | Delimiter::Midpoint(DelimiterType::CPreprocesserConditional, n)
+                    | Delimiter::End(DelimiterType::CPreprocesserConditional, n)),
+                ) if self.is_at_start_of_line() => {
+                    // Include the rest of the directive's name, ie, the `ndef` in `#ifndef`
+                    let length = n + self.chars[self.index + n..]
+                        .iter()
+                        .take_while(|c| c.is_alphanumeric())
+                        .count();
+                    let position = self.open.iter().rposition(|open| {
+                        open.delimiter == Some(DelimiterType::CPreprocesserConditional)
+                    });
+                    match (delimiter, position) {
+                        (Delimiter::Start(..), _) => {
+                            let conditional = InMemoryNode::append_child(
+                                &parent,
+                                InMemoryNode::new_with_metadata(NodeMetadata::AstNode {
+                                    kind: SyntaxKind::Conditional,
+                                    literal: None,
+                                }),
+                            );
+                            self.bump(&conditional, SyntaxKind::ConditionalMarker, length);
+                            self.open.push(Open {
+                                node: conditional,
+                                delimiter: Some(DelimiterType::CPreprocesserConditional),
+                                indent: None,
+                            });
+                        }
+                        (Delimiter::Midpoint(..), Some(position))
+                            if position == self.open.len() - 1 =>
+                        {
+                            self.bump(&parent, SyntaxKind::ConditionalMarker, length);
+                        }
+                        (Delimiter::End(..), Some(position)) => {
+                            let conditional = self.open.drain(position..).next().unwrap().node;
+                            self.bump(&conditional, SyntaxKind::ConditionalMarker, length);
+                        }
+                        _ => self.bump(&parent, SyntaxKind::Punctuation, 1),
+                    }
+                }
+                _ => self.parse_text(&parent),
+            };
+        }
+    }
+
+    /// When called, parses a run of whitespace, a word, a number, or punctuation.
+    fn parse_text(&mut self, parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>) {
+        let c = self.chars[self.index];
+        let is_word_char = |c: &char| c.is_alphanumeric() || *c == '_';
+        let (kind, length) = if c.is_whitespace() {
+            let length = self.chars[self.index..]
+                .iter()
+                .take_while(|c| c.is_whitespace())
+                .count();
+            (SyntaxKind::Whitespace, length)
+        } else if is_word_char(&c) {
+            let length = self.chars[self.index..]
+                .iter()
+                .take_while(|c| is_word_char(c))
+                .count();
+            let kind = if c.is_ascii_digit() {
+                SyntaxKind::Number
+            } else {
+                SyntaxKind::Word
+            };
+            (kind, length)
+        } else {
+            (SyntaxKind::Punctuation, 1)
+        };
+        self.bump(parent, kind, length);
+    }
+}
+
+impl TokenKindTrait for SyntaxKind {
+    type ParseOptions = ();
+
+    fn language_name() -> &'static str {
+        "brackets"
+    }
+
+    fn highlight_group(ancestry: std::vec::IntoIter<SyntaxKind>) -> Option<HighlightGroup> {
+        match ancestry.as_slice().first()? {
+            SyntaxKind::Open | SyntaxKind::Close => Some(HighlightGroup::Punctuation),
+            SyntaxKind::ConditionalMarker => Some(HighlightGroup::Keyword),
+            SyntaxKind::String => Some(HighlightGroup::String),
+            SyntaxKind::Comment => Some(HighlightGroup::Comment),
+            SyntaxKind::Number => Some(HighlightGroup::Number),
+            _ => None,
+        }
+    }
+
+    fn is_reparsable(&self) -> bool {
+        matches!(self, SyntaxKind::Document | SyntaxKind::Block)
+    }
+
+    fn is_foldable(&self) -> bool {
+        matches!(
+            self,
+            SyntaxKind::Block
+                | SyntaxKind::Conditional
+                | SyntaxKind::Indented
+                | SyntaxKind::Comment
+        )
+    }
+
+    fn parse(
+        literal: &str,
+        parent: Option<Rc<RefCell<InMemoryNode<Self>>>>,
+    ) -> Rc<RefCell<InMemoryNode<Self>>> {
+        let parent_kind = parent
+            .as_ref()
+            .and_then(|parent| match parent.borrow().metadata {
+                NodeMetadata::AstNode { kind, .. } => Some(kind),
+                _ => None,
+            });
+        let kind = match parent_kind {
+            Some(SyntaxKind::Block) => SyntaxKind::Block,
+            _ => SyntaxKind::Document,
+        };
+
+        // When reparsing part of a block, the block's opening bracket is only part of the text if
+        // it is the block's first child that is being reparsed - in which case the first child
+        // won't be the opening bracket anymore.
+        let is_block_start = parent.is_some_and(|parent| {
+            !parent.borrow().children.first().is_some_and(|child| {
+                matches!(
+                    child.borrow().metadata,
+                    NodeMetadata::AstNode {
+                        kind: SyntaxKind::Open,
+                        ..
+                    }
+                )
+            })
+        });
+
+        let node = InMemoryNode::new_with_metadata(NodeMetadata::AstNode {
+            kind,
+            literal: None,
+        });
+        Parser::new(literal).parse(&node, kind == SyntaxKind::Block, is_block_start);
+        node
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SyntaxKind;
+    use crate::{
+        languages::test_utils::{child, child_kinds},
+        node_tree::{
+            cursor::{Cursor, Selection},
+            node::InMemoryNode,
+        },
+    };
+    use std::rc::Rc;
+
+    #[test]
+    fn parses_balanced_blocks() {
+        let root = InMemoryNode::<SyntaxKind>::new_from_parsed(
+            "int main() {\n  puts(\"{ not a block\"); /* ( */ a[0];\n}\n",
+        );
+        use SyntaxKind::*;
+        let document = child(&root, 0);
+        assert_eq!(
+            child_kinds(&document),
+            vec![Word, Whitespace, Word, Block, Whitespace, Block, Whitespace]
+        );
+        assert_eq!(child_kinds(&child(&document, 3)), vec![Open, Close]);
+
+        let body = child(&document, 5);
+        assert_eq!(
+            child_kinds(&body),
+            vec![
+                Open,
+                Whitespace,
+                Word,
+                Block,
+                Punctuation,
+                Whitespace,
+                Comment,
+                Whitespace,
+                Word,
+                Block,
+                Punctuation,
+                Whitespace,
+                Close
+            ]
+        );
+        assert_eq!(child_kinds(&child(&body, 3)), vec![Open, String, Close]);
+        assert_eq!(child_kinds(&child(&body, 9)), vec![Open, Number, Close]);
+    }
+
+    #[test]
+    fn parses_comments_strings_and_conditionals() {
+        use SyntaxKind::*;
+        let root = InMemoryNode::<SyntaxKind>::new_from_parsed(
+            "#ifndef FOO\nx(1);\n#else\n// }\n#endif\n",
+        );
+        let document = child(&root, 0);
+        assert_eq!(child_kinds(&document), vec![Conditional, Whitespace]);
+        assert_eq!(
+            child_kinds(&child(&document, 0)),
+            vec![
+                ConditionalMarker,
+                Whitespace,
+                Word,
+                Whitespace,
+                Word,
+                Block,
+                Punctuation,
+                Whitespace,
+                ConditionalMarker,
+                Whitespace,
+                Comment,
+                Whitespace,
+                ConditionalMarker
+            ]
+        );
+
+        // Apostrophes and lifetimes aren't the start of a string, but shell comments are comments
+        let root = InMemoryNode::<SyntaxKind>::new_from_parsed(
+            "fn f<'a>(x: &'a str) { 'b' }\necho 'a b' # don't (\n",
+        );
+        let document = child(&root, 0);
+        assert_eq!(
+            child_kinds(&document),
+            vec![
+                Word,
+                Whitespace,
+                Word,
+                Punctuation,
+                Punctuation,
+                Word,
+                Punctuation,
+                Block,
+                Whitespace,
+                Block,
+                Whitespace,
+                Word,
+                Whitespace,
+                String,
+                Whitespace,
+                Comment,
+                Whitespace
+            ]
+        );
+        assert_eq!(
+            child_kinds(&child(&document, 9)),
+            vec![Open, Whitespace, String, Whitespace, Close]
+        );
+    }
+
+    #[test]
+    fn groups_indented_lines() {
+        use SyntaxKind::*;
+        let literal = "def f(a):\n    if a:\n        b\n\n    c\nd\n";
+        let root = InMemoryNode::<SyntaxKind>::new_from_parsed(literal);
+        assert_eq!(InMemoryNode::deep_literal(&root), literal);
+        let document = child(&root, 0);
+        assert_eq!(
+            child_kinds(&document),
+            vec![
+                Word,
+                Whitespace,
+                Word,
+                Block,
+                Punctuation,
+                Whitespace,
+                Indented,
+                Word,
+                Whitespace
+            ]
+        );
+        let body = child(&document, 6);
+        assert_eq!(
+            child_kinds(&body),
+            vec![
+                Whitespace,
+                Word,
+                Whitespace,
+                Word,
+                Punctuation,
+                Whitespace,
+                Indented,
+                Whitespace,
+                Word,
+                Whitespace
+            ]
+        );
+        assert_eq!(
+            InMemoryNode::deep_literal(&body),
+            "    if a:\n        b\n\n    c\n"
+        );
+        assert_eq!(
+            InMemoryNode::deep_literal(&child(&body, 6)),
+            "        b\n\n"
+        );
+
+        // The lines within a block are only grouped if they are indented further than the
+        // block's first line, and closing the block closes them too
+        let root = InMemoryNode::<SyntaxKind>::new_from_parsed(
+            "void f() {\n  if (a)\n    b();\n  c(\n      d);\n}\n",
+        );
+        let block = child(&child(&root, 0), 5);
+        assert_eq!(
+            child_kinds(&block),
+            vec![
+                Open,
+                Whitespace,
+                Word,
+                Whitespace,
+                Block,
+                Whitespace,
+                Indented,
+                Whitespace,
+                Word,
+                Block,
+                Punctuation,
+                Whitespace,
+                Close
+            ]
+        );
+        assert_eq!(InMemoryNode::deep_literal(&child(&block, 6)), "    b();\n");
+        assert_eq!(
+            child_kinds(&child(&block, 9)),
+            vec![Open, Whitespace, Word, Close]
+        );
+    }
+
+    #[test]
+    fn unbalanced_brackets_are_kept() {
+        use SyntaxKind::*;
+        let root = InMemoryNode::<SyntaxKind>::new_from_parsed("} ({]");
+        let document = child(&root, 0);
+        assert_eq!(child_kinds(&document), vec![Punctuation, Whitespace, Block]);
+        assert_eq!(child_kinds(&child(&document, 2)), vec![Open, Block]);
+        assert_eq!(
+            child_kinds(&child(&child(&document, 2), 1)),
+            vec![Open, Punctuation]
+        );
+    }
+
+    #[test]
+    fn edits_only_reparse_the_enclosing_block() {
+        let root = InMemoryNode::<SyntaxKind>::new_from_parsed("fn a() {\n  b(1);\n}\nfn c() {}\n");
+        let document = child(&root, 0);
+        let first_body = child(&document, 5);
+        let second_body = child(&document, 13);
+        let call = child(&first_body, 3);
+
+        // Add an argument to the call
+        Selection::new_from_cursor_pair(
+            Cursor::new_at_rows_cols(root.clone(), (2, 5)),
+            Cursor::new_at_rows_cols(root.clone(), (2, 6)),
+        )
+        .replace("1, [2]")
+        .unwrap();
+        assert_eq!(
+            InMemoryNode::deep_literal(&root),
+            "fn a() {\n  b(1, [2]);\n}\nfn c() {}\n"
+        );
+
+        use SyntaxKind::*;
+        assert!(Rc::ptr_eq(&child(&document, 5), &first_body));
+        assert!(Rc::ptr_eq(&child(&document, 13), &second_body));
+        assert!(Rc::ptr_eq(&child(&first_body, 3), &call));
+        assert_eq!(
+            child_kinds(&call),
+            vec![Open, Number, Punctuation, Whitespace, Block, Close]
+        );
+    }
+}
@@ -0,0 +1,873 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::node_tree::{
+    diagnostic::{Diagnostic, Severity},
+    node::{InMemoryNode, NodeMetadata, TokenKindTrait},
+    style::HighlightGroup,
+};
+
+/// The kinds of nodes that make up a parsed CSS (or SCSS) stylesheet.
+///
+/// Stylesheets, rules, at-rules, blocks, declarations, and the groups within them contain other
+/// nodes, and everything else is a token with its own literal text. Like `languages::json`,
+/// whitespace and comments are kept in the tree as tokens, so the literal text of a parsed
+/// stylesheet is always exactly what was parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyntaxKind {
+    Stylesheet,
+    Rule,        // A selector, followed by a block, ie, `a:hover { color: red; }`
+    AtRule,      // ie, `@import "a.css";`, or `@media screen { ... }`
+    Selector,    // Everything in a rule before its block
+    Prelude,     // Everything in an at-rule between its name and its block or semicolon
+    Block,       // A `{ ... }` block of declarations and nested rules
+    Declaration, // A `property: value;` pair within a block
+    Value,       // Everything in a declaration between its colon and its semicolon
+    Function,    // ie, `rgba(0, 0, 0, 0.5)`, or `:not(.a)`
+    Group,       // A `(...)` or `[...]` group that isn't part of a function call
+
+    Property, // The name at the start of a declaration
+    FunctionName,
+    AtKeyword, // ie, `@media`
+    Ident,
+    Class,         // ie, `.foo`
+    Hash,          // ie, `#foo` in a selector, or `#fff` in a value
+    Variable,      // A SCSS variable, ie, `$foo`
+    Interpolation, // A SCSS interpolation, ie, `#{$foo}`
+    String,
+    Url, // An unquoted url, ie, `url(a.png)`
+    Number,
+    Dimension,  // ie, `10px`
+    Percentage, // ie, `50%`
+    Important,  // ie, `!important`, or a SCSS flag like `!default`
+
+    LCurly,
+    RCurly,
+    LParen,
+    RParen,
+    LBrack,
+    RBrack,
+    Colon,
+    Semicolon,
+    Comma,
+    Delim, // Any other single char, ie, the `>` in `a > b`, or the `&` in `&:hover`
+    Whitespace,
+    Comment,
+}
+
+impl SyntaxKind {
+    fn is_trivia(&self) -> bool {
+        matches!(self, SyntaxKind::Whitespace | SyntaxKind::Comment)
+    }
+
+    /// When called, returns true if this kind of token ends a selector, prelude, or value.
+    fn is_terminator(&self) -> bool {
+        matches!(
+            self,
+            SyntaxKind::LCurly | SyntaxKind::RCurly | SyntaxKind::Semicolon
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: SyntaxKind,
+    text: String,
+    start: usize, // In chars from the start of the text being parsed
+}
+
+impl Token {
+    fn end(&self) -> usize {
+        self.start + self.text.chars().count()
+    }
+}
+
+fn is_name_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_' || c == '\\' || !c.is_ascii()
+}
+
+fn is_name_char(c: char) -> bool {
+    is_name_start(c) || c.is_ascii_digit() || c == '-'
+}
+
+/// When called, splits `literal` up into tokens. Every char in `literal` ends up in exactly one
+/// token, and anything that is left unterminated (strings, comments, etc) is reported as a
+/// diagnostic.
+fn tokenize(literal: &str, diagnostics: &mut Vec<Diagnostic>) -> Vec<Token> {
+    let chars = literal.chars().collect::<Vec<char>>();
+    let mut tokens = vec![];
+    let mut index = 0;
+
+    // When called, returns the index just past the name (ie, an identifier) that starts at `index`.
+    let name_end = |mut index: usize| {
+        while index < chars.len() && is_name_char(chars[index]) {
+            // Skip over the char that is escaped by a backslash, ie, the `:` in `.sm\:flex`
+            index += if chars[index] == '\\' { 2 } else { 1 };
+        }
+        index.min(chars.len())
+    };
+    let starts_name = |index: usize| match (chars.get(index), chars.get(index + 1)) {
+        (Some('-'), Some(c)) => is_name_start(*c) || *c == '-',
+        (Some(c), _) => is_name_start(*c),
+        _ => false,
+    };
+
+    while index < chars.len() {
+        let start = index;
+        let c = chars[index];
+        let next = chars.get(index + 1).copied();
+        index += 1;
+
+        let kind = match c {
+            '{' => SyntaxKind::LCurly,
+            '}' => SyntaxKind::RCurly,
+            '(' => SyntaxKind::LParen,
+            ')' => SyntaxKind::RParen,
+            '[' => SyntaxKind::LBrack,
+            ']' => SyntaxKind::RBrack,
+            ':' => SyntaxKind::Colon,
+            ';' => SyntaxKind::Semicolon,
+            ',' => SyntaxKind::Comma,
+            // NOTE: a byte order mark is treated as whitespace so that it doesn't show up as a delim
+            c if c.is_whitespace() || c == '\u{feff}' => {
+                while index < chars.len()
+                    && (chars[index].is_whitespace() || chars[index] == '\u{feff}')
+                {
+                    index += 1;
+                }
+                SyntaxKind::Whitespace
+            }
+            '/' if next == Some('*') => {
+                index += 1;
+                match (index..chars.len()).find(|index| chars[*index..].starts_with(&['*', '/'])) {
+                    Some(end) => index = end + 2,
+                    None => {
+                        index = chars.len();
+                        diagnostics.push(Diagnostic::new(
+                            start..index,
+                            Severity::Error,
+                            "unterminated comment",
+                        ));
+                    }
+                };
+                SyntaxKind::Comment
+            }
+            // SCSS line comments
+            '/' if next == Some('/') => {
+                while index < chars.len() && chars[index] != '\n' && chars[index] != '\r' {
+                    index += 1;
+                }
+                SyntaxKind::Comment
+            }
+            '"' | '\'' => {
+                let mut is_escaped = false;
+                let mut is_terminated = false;
+                while index < chars.len() && chars[index] != '\n' && chars[index] != '\r' {
+                    let current = chars[index];
+                    index += 1;
+                    if is_escaped {
+                        is_escaped = false;
+                    } else if current == '\\' {
+                        is_escaped = true;
+                    } else if current == c {
+                        is_terminated = true;
+                        break;
+                    }
+                }
+                if !is_terminated {
+                    diagnostics.push(Diagnostic::new(
+                        start..index,
+                        Severity::Error,
+                        "unterminated string",
+                    ));
+                }
+                SyntaxKind::String
+            }
+            c if c.is_ascii_digit()
+                || (c == '.' && next.is_some_and(|next| next.is_ascii_digit()))
+                || (matches!(c, '+' | '-')
+                    && (next.is_some_and(|next| next.is_ascii_digit())
+                        || (next == Some('.')
+                            && chars.get(index + 1).is_some_and(|c| c.is_ascii_digit())))) =>
+            {
+                while index < chars.len() && (chars[index].is_ascii_digit() || chars[index] == '.')
+                {
+                    index += 1;
+                }
+                if chars.get(index) == Some(&'%') {
+                    index += 1;
+                    SyntaxKind::Percentage
+                } else if starts_name(index) {
+                    index = name_end(index);
+                    SyntaxKind::Dimension
+                } else {
+                    SyntaxKind::Number
+                }
+            }
+            '@' if starts_name(index) => {
+                index = name_end(index);
+                SyntaxKind::AtKeyword
+            }
+            '$' if starts_name(index) => {
+                index = name_end(index);
+                SyntaxKind::Variable
+            }
+            '.' if starts_name(index) => {
+                index = name_end(index);
+                SyntaxKind::Class
+            }
+            '!' if (index..chars.len())
+                .find(|index| !chars[*index].is_whitespace())
+                .is_some_and(starts_name) =>
+            {
+                while chars[index].is_whitespace() {
+                    index += 1;
+                }
+                index = name_end(index);
+                SyntaxKind::Important
+            }
+            '#' if next == Some('{') => {
+                match (index..chars.len()).find(|index| chars[*index] == '}') {
+                    Some(end) => index = end + 1,
+                    None => {
+                        index = chars.len();
+                        diagnostics.push(Diagnostic::new(
+                            start..index,
+                            Severity::Error,
+                            "unterminated interpolation",
+                        ));
+                    }
+                };
+                SyntaxKind::Interpolation
+            }
+            '#' if next.is_some_and(is_name_char) => {
+                index = name_end(index);
+                SyntaxKind::Hash
+            }
+            _ if starts_name(start) => {
+                index = name_end(start);
+                let name = chars[start..index].iter().collect::<String>();
+
+                // An unquoted url is a single token, since it can contain chars like `//` that
+                // would otherwise be tokenized as something else
+                let argument_start =
+                    (index + 1..chars.len()).find(|index| !chars[*index].is_whitespace());
+                let is_unquoted_url = name.eq_ignore_ascii_case("url")
+                    && chars.get(index) == Some(&'(')
+                    && argument_start
+                        .is_some_and(|start| !matches!(chars[start], '"' | '\'' | ')'));
+                if is_unquoted_url {
+                    match (index..chars.len()).find(|index| chars[*index] == ')') {
+                        Some(end) => index = end + 1,
+                        None => {
+                            index = chars.len();
+                            diagnostics.push(Diagnostic::new(
+                                start..index,
+                                Severity::Error,
+                                "unterminated url",
+                            ));
+                        }
+                    };
+                    SyntaxKind::Url
+                } else {
+                    SyntaxKind::Ident
+                }
+            }
+            _ => SyntaxKind::Delim,
+        };
+
+        tokens.push(Token {
+            kind,
+            text: chars[start..index].iter().collect(),
+            start,
+        });
+    }
+    tokens
+}
+
+/// A recursive descent parser that turns a list of tokens into a token tree. Whether a statement
+/// within a block is a nested rule or a declaration is decided by looking ahead for a `{`. A
+/// token that isn't allowed where it is found (ie, a stray `}` in a stylesheet) is kept where it
+/// is with a diagnostic, and a missing `{` or `}` is reported where it should have been.
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Parser {
+    fn new(literal: &str) -> Self {
+        let mut diagnostics = vec![];
+        let tokens = tokenize(literal, &mut diagnostics);
+        Self {
+            tokens,
+            position: 0,
+            diagnostics,
+        }
+    }
+
+    fn peek(&self) -> Option<SyntaxKind> {
+        self.tokens.get(self.position).map(|token| token.kind)
+    }
+
+    /// When called, returns the kind of the next token that isn't whitespace or a comment.
+    fn peek_past_trivia(&self) -> Option<SyntaxKind> {
+        self.tokens[self.position..]
+            .iter()
+            .map(|token| token.kind)
+            .find(|kind| !kind.is_trivia())
+    }
+
+    /// When called, returns the char offset that the next token starts at.
+    fn offset(&self) -> usize {
+        match self.tokens.get(self.position) {
+            Some(token) => token.start,
+            None => self.tokens.last().map(Token::end).unwrap_or(0),
+        }
+    }
+
+    fn error(&mut self, range: std::ops::Range<usize>, message: &str) {
+        self.diagnostics
+            .push(Diagnostic::new(range, Severity::Error, message));
+    }
+
+    /// When called, consumes the next token, and appends it to `parent` as a node of kind `kind`.
+    fn bump_as(&mut self, parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>, kind: SyntaxKind) {
+        let literal = Some(self.tokens[self.position].text.clone());
+        self.position += 1;
+        InMemoryNode::append_child(
+            parent,
+            InMemoryNode::new_with_metadata(NodeMetadata::AstNode { kind, literal }),
+        );
+    }
+
+    fn bump(&mut self, parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>) {
+        let kind = self.tokens[self.position].kind;
+        self.bump_as(parent, kind);
+    }
+
+    /// When called, consumes the next token, reporting it as being somewhere it isn't allowed.
+    fn bump_unexpected(&mut self, parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>) {
+        let token = self.tokens[self.position].clone();
+        self.error(
+            token.start..token.end(),
+            &format!("unexpected `{}`", token.text),
+        );
+        self.bump(parent);
+    }
+
+    fn eat_trivia(&mut self, parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>) {
+        while self.peek().is_some_and(|kind| kind.is_trivia()) {
+            self.bump(parent);
+        }
+    }
+
+    /// When called, returns true if the next statement in a block is a rule (or nested rule) and
+    /// not a declaration - ie, a `{` comes before the next `;` or `}`.
+    fn is_rule_next(&self) -> bool {
+        let mut depth = 0usize;
+        for token in &self.tokens[self.position..] {
+            match token.kind {
+                SyntaxKind::LParen | SyntaxKind::LBrack => depth += 1,
+                SyntaxKind::RParen | SyntaxKind::RBrack => depth = depth.saturating_sub(1),
+                SyntaxKind::LCurly if depth == 0 => return true,
+                SyntaxKind::RCurly | SyntaxKind::Semicolon if depth == 0 => return false,
+                _ => {}
+            }
+        }
+        false
+    }
+
+    /// When called, parses the statements within a stylesheet or block, up until (but not
+    /// including) the `}` that closes it.
+    ///
+    /// NOTE: nodes are appended to their parent before their own children are parsed, so that
+    /// each node is given a fractional index relative to the nodes that come before it.
+    fn parse_statements(
+        &mut self,
+        parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>,
+        is_top_level: bool,
+    ) {
+        loop {
+            self.eat_trivia(parent);
+            match self.peek() {
+                None => break,
+                Some(SyntaxKind::RCurly) if !is_top_level => break,
+                Some(SyntaxKind::RCurly) => self.bump_unexpected(parent),
+                // An empty statement, ie, the second `;` in `a: b;;`
+                Some(SyntaxKind::Semicolon) => self.bump(parent),
+                Some(SyntaxKind::AtKeyword) => self.parse_at_rule(parent),
+                Some(_) if self.is_rule_next() => self.parse_rule(parent),
+                Some(_) => self.parse_declaration(parent),
+            };
+        }
+    }
+
+    /// When called, parses a rule, starting at its selector.
+    fn parse_rule(&mut self, parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>) {
+        let rule = InMemoryNode::append_child(parent, new_node(SyntaxKind::Rule));
+        self.parse_rule_body(&rule, SyntaxKind::Rule, false);
+    }
+
+    /// When called, parses an at-rule, starting at its name.
+    fn parse_at_rule(&mut self, parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>) {
+        let at_rule = InMemoryNode::append_child(parent, new_node(SyntaxKind::AtRule));
+        self.bump(&at_rule);
+        self.eat_trivia(&at_rule);
+        self.parse_rule_body(&at_rule, SyntaxKind::AtRule, false);
+    }
+
+    /// When called, parses the rest of a rule or at-rule - its selector or prelude, and then its
+    /// block (or, for an at-rule, its semicolon). If `is_fragment` is set, the rule's block is
+    /// allowed to be missing, since it might not be part of the text being reparsed.
+    fn parse_rule_body(
+        &mut self,
+        rule: &Rc<RefCell<InMemoryNode<SyntaxKind>>>,
+        kind: SyntaxKind,
+        is_fragment: bool,
+    ) {
+        if self.peek().is_some_and(|kind| !kind.is_terminator()) {
+            let prelude_kind = match kind {
+                SyntaxKind::Rule => SyntaxKind::Selector,
+                _ => SyntaxKind::Prelude,
+            };
+            self.parse_components(rule, prelude_kind);
+        }
+
+        match self.peek_past_trivia() {
+            Some(SyntaxKind::LCurly) => {
+                self.eat_trivia(rule);
+                self.parse_block(rule);
+            }
+            Some(SyntaxKind::Semicolon) if kind == SyntaxKind::AtRule => {
+                self.eat_trivia(rule);
+                self.bump(rule);
+            }
+            None if is_fragment => {}
+            _ if kind == SyntaxKind::Rule => {
+                let offset = self.offset();
+                self.error(offset..offset, "expected `{`");
+            }
+            // The last at-rule in a block or stylesheet doesn't need a semicolon
+            _ => {}
+        };
+    }
+
+    /// When called, parses a `{ ... }` block, starting at its opening curly brace.
+    fn parse_block(&mut self, parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>) {
+        let block = InMemoryNode::append_child(parent, new_node(SyntaxKind::Block));
+        self.bump(&block);
+        self.parse_statements(&block, false);
+        if self.peek() == Some(SyntaxKind::RCurly) {
+            self.bump(&block);
+        } else {
+            let offset = self.offset();
+            self.error(offset..offset, "expected `}`");
+        }
+    }
+
+    /// When called, parses a `property: value;` declaration.
+    fn parse_declaration(&mut self, parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>) {
+        let declaration = InMemoryNode::append_child(parent, new_node(SyntaxKind::Declaration));
+
+        // The property is usually a single identifier, but can also be made up of a few tokens,
+        // ie, the IE hack `*zoom`, or the SCSS `#{$side}-width`
+        while let Some(next) = self.peek_past_trivia() {
+            if next == SyntaxKind::Colon || next.is_terminator() {
+                break;
+            }
+            self.eat_trivia(&declaration);
+            match next {
+                SyntaxKind::Ident => self.bump_as(&declaration, SyntaxKind::Property),
+                _ => self.parse_component(&declaration),
+            };
+        }
+
+        if self.peek_past_trivia() == Some(SyntaxKind::Colon) {
+            self.eat_trivia(&declaration);
+            self.bump(&declaration);
+            self.eat_trivia(&declaration);
+            if self.peek().is_some_and(|kind| !kind.is_terminator()) {
+                self.parse_components(&declaration, SyntaxKind::Value);
+            } else {
+                let offset = self.offset();
+                self.error(offset..offset, "expected a value");
+            }
+        } else {
+            let offset = self.offset();
+            self.error(offset..offset, "expected `:`");
+        }
+
+        if self.peek_past_trivia() == Some(SyntaxKind::Semicolon) {
+            self.eat_trivia(&declaration);
+            self.bump(&declaration);
+        }
+    }
+
+    /// When called, parses a selector, prelude, or value into a new node of kind `kind`, up until
+    /// (but not including) the `{`, `}`, or `;` that ends it. Whitespace at the end is left out.
+    fn parse_components(
+        &mut self,
+        parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>,
+        kind: SyntaxKind,
+    ) {
+        let node = InMemoryNode::append_child(parent, new_node(kind));
+        while self
+            .peek_past_trivia()
+            .is_some_and(|kind| !kind.is_terminator())
+        {
+            self.eat_trivia(&node);
+            self.parse_component(&node);
+        }
+    }
+
+    /// When called, parses a single token, or a function or group along with everything in it.
+    fn parse_component(&mut self, parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>) {
+        let next_kind = self.tokens.get(self.position + 1).map(|token| token.kind);
+        match self.peek() {
+            Some(SyntaxKind::Ident) if next_kind == Some(SyntaxKind::LParen) => {
+                let function = InMemoryNode::append_child(parent, new_node(SyntaxKind::Function));
+                self.bump_as(&function, SyntaxKind::FunctionName);
+                self.parse_group_body(&function, SyntaxKind::RParen, ")");
+            }
+            Some(SyntaxKind::LParen) => {
+                let group = InMemoryNode::append_child(parent, new_node(SyntaxKind::Group));
+                self.parse_group_body(&group, SyntaxKind::RParen, ")");
+            }
+            Some(SyntaxKind::LBrack) => {
+                let group = InMemoryNode::append_child(parent, new_node(SyntaxKind::Group));
+                self.parse_group_body(&group, SyntaxKind::RBrack, "]");
+            }
+            Some(SyntaxKind::RParen | SyntaxKind::RBrack) => self.bump_unexpected(parent),
+            Some(_) => self.bump(parent),
+            None => {}
+        };
+    }
+
+    /// When called, parses a group or the arguments of a function, starting at its opening
+    /// bracket.
+    fn parse_group_body(
+        &mut self,
+        group: &Rc<RefCell<InMemoryNode<SyntaxKind>>>,
+        closing_kind: SyntaxKind,
+        closing_text: &str,
+    ) {
+        self.bump(group);
+        loop {
+            self.eat_trivia(group);
+            match self.peek() {
+                Some(kind) if kind == closing_kind => {
+                    self.bump(group);
+                    break;
+                }
+                Some(kind) if !kind.is_terminator() => self.parse_component(group),
+                _ => {
+                    let offset = self.offset();
+                    self.error(offset..offset, &format!("expected `{closing_text}`"));
+                    break;
+                }
+            };
+        }
+    }
+
+    /// When called, parses a whole stylesheet.
+    fn parse_stylesheet(&mut self) -> Rc<RefCell<InMemoryNode<SyntaxKind>>> {
+        let stylesheet = new_node(SyntaxKind::Stylesheet);
+        self.parse_statements(&stylesheet, true);
+        stylesheet
+    }
+
+    /// When called, parses text that was taken out of the middle of a rule or at-rule - ie, its
+    /// selector or prelude, its block, or both.
+    fn parse_rule_fragment(&mut self, kind: SyntaxKind) -> Rc<RefCell<InMemoryNode<SyntaxKind>>> {
+        let rule = new_node(kind);
+        self.eat_trivia(&rule);
+        if kind == SyntaxKind::AtRule && self.peek() == Some(SyntaxKind::AtKeyword) {
+            self.bump(&rule);
+            self.eat_trivia(&rule);
+        }
+        self.parse_rule_body(&rule, kind, true);
+
+        // An edit that ends right at the end of the rule can pull in the tokens after it, ie,
+        // the `}` that closes the block the rule is nested within
+        loop {
+            self.parse_statements(&rule, false);
+            if self.peek().is_none() {
+                break;
+            }
+            self.bump(&rule);
+        }
+        rule
+    }
+}
+
+fn new_node(kind: SyntaxKind) -> Rc<RefCell<InMemoryNode<SyntaxKind>>> {
+    InMemoryNode::new_with_metadata(NodeMetadata::AstNode {
+        kind,
+        literal: None,
+    })
+}
+
+impl TokenKindTrait for SyntaxKind {
+    type ParseOptions = ();
+
+    fn language_name() -> &'static str {
+        "css"
+    }
+
+    fn highlight_group(ancestry: std::vec::IntoIter<SyntaxKind>) -> Option<HighlightGroup> {
+        let ancestry = ancestry.as_slice();
+        let is_in_selector = ancestry.contains(&SyntaxKind::Selector);
+        match ancestry.first()? {
+            SyntaxKind::Property => Some(HighlightGroup::Property),
+            SyntaxKind::FunctionName => Some(HighlightGroup::FunctionName),
+            SyntaxKind::AtKeyword | SyntaxKind::Important => Some(HighlightGroup::Keyword),
+            SyntaxKind::Variable | SyntaxKind::Interpolation => Some(HighlightGroup::VariableDecl),
+            SyntaxKind::String | SyntaxKind::Url => Some(HighlightGroup::String),
+            SyntaxKind::Number | SyntaxKind::Dimension | SyntaxKind::Percentage => {
+                Some(HighlightGroup::Number)
+            }
+            SyntaxKind::Comment => Some(HighlightGroup::Comment),
+            SyntaxKind::Ident | SyntaxKind::Class if is_in_selector => Some(HighlightGroup::Type),
+            SyntaxKind::Hash if is_in_selector => Some(HighlightGroup::Type),
+            SyntaxKind::Ident | SyntaxKind::Hash => Some(HighlightGroup::Constant),
+            SyntaxKind::LCurly
+            | SyntaxKind::RCurly
+            | SyntaxKind::LParen
+            | SyntaxKind::RParen
+            | SyntaxKind::LBrack
+            | SyntaxKind::RBrack
+            | SyntaxKind::Colon
+            | SyntaxKind::Semicolon
+            | SyntaxKind::Comma => Some(HighlightGroup::Punctuation),
+            _ => None,
+        }
+    }
+
+    fn is_reparsable(&self) -> bool {
+        matches!(
+            self,
+            SyntaxKind::Stylesheet | SyntaxKind::Rule | SyntaxKind::AtRule
+        )
+    }
+
+    // Blocks start on the same row as their rule's selector, so folding a block leaves the
+    // selector shown
+    fn is_foldable(&self) -> bool {
+        matches!(self, SyntaxKind::Block | SyntaxKind::Comment)
+    }
+
+    fn parse(
+        literal: &str,
+        parent: Option<Rc<RefCell<InMemoryNode<Self>>>>,
+    ) -> Rc<RefCell<InMemoryNode<Self>>> {
+        let parent_kind = parent.and_then(|parent| match parent.borrow().metadata {
+            NodeMetadata::AstNode { kind, .. } => Some(kind),
+            _ => None,
+        });
+
+        // When reparsing part of a rule, parse the text as the contents of that kind of rule. The
+        // result is wrapped in a node of the same kind as the parent, so that the wrapper's
+        // children take the place of the text that was reparsed.
+        let mut parser = Parser::new(literal);
+        let node = match parent_kind {
+            Some(kind @ (SyntaxKind::Rule | SyntaxKind::AtRule)) => {
+                parser.parse_rule_fragment(kind)
+            }
+            _ => parser.parse_stylesheet(),
+        };
+
+        for diagnostic in parser.diagnostics {
+            InMemoryNode::attach_diagnostic(&node, diagnostic);
+        }
+        node
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SyntaxKind;
+    use crate::{
+        languages::test_utils::{assert_kept_with_diagnostics, child, child_kinds},
+        node_tree::{
+            cursor::{Cursor, Selection},
+            node::InMemoryNode,
+        },
+    };
+    use std::rc::Rc;
+
+    #[test]
+    fn parses_rules_declarations_and_at_rules() {
+        use SyntaxKind::*;
+        let root = InMemoryNode::<SyntaxKind>::new_from_parsed(
+            "@import url(//a.css);\na > .b:not(#c) {\n  color: rgba(0, 0, 0, 50%) !important;\n  margin: 0 auto\n}\n",
+        );
+        assert!(InMemoryNode::diagnostics(&root).is_empty());
+
+        let stylesheet = child(&root, 0);
+        assert_eq!(
+            child_kinds(&stylesheet),
+            vec![AtRule, Whitespace, Rule, Whitespace]
+        );
+        assert_eq!(
+            child_kinds(&child(&stylesheet, 0)),
+            vec![AtKeyword, Whitespace, Prelude, Semicolon]
+        );
+        assert_eq!(child_kinds(&child(&child(&stylesheet, 0), 2)), vec![Url]);
+
+        let rule = child(&stylesheet, 2);
+        assert_eq!(child_kinds(&rule), vec![Selector, Whitespace, Block]);
+        assert_eq!(
+            child_kinds(&child(&rule, 0)),
+            vec![Ident, Whitespace, Delim, Whitespace, Class, Colon, Function]
+        );
+        let block = child(&rule, 2);
+        assert_eq!(
+            child_kinds(&block),
+            vec![
+                LCurly,
+                Whitespace,
+                Declaration,
+                Whitespace,
+                Declaration,
+                Whitespace,
+                RCurly
+            ]
+        );
+        let declaration = child(&block, 2);
+        assert_eq!(
+            child_kinds(&declaration),
+            vec![Property, Colon, Whitespace, Value, Semicolon]
+        );
+        assert_eq!(
+            child_kinds(&child(&declaration, 3)),
+            vec![Function, Whitespace, Important]
+        );
+        assert_eq!(
+            child_kinds(&child(&child(&declaration, 3), 0)),
+            vec![
+                FunctionName,
+                LParen,
+                Number,
+                Comma,
+                Whitespace,
+                Number,
+                Comma,
+                Whitespace,
+                Number,
+                Comma,
+                Whitespace,
+                Percentage,
+                RParen
+            ]
+        );
+        assert_eq!(
+            child_kinds(&child(&block, 4)),
+            vec![Property, Colon, Whitespace, Value]
+        );
+    }
+
+    #[test]
+    fn parses_nested_scss_rules() {
+        use SyntaxKind::*;
+        let root = InMemoryNode::<SyntaxKind>::new_from_parsed(
+            "$gap: 4px !default;\n.a {\n  // comment\n  &:hover { margin-#{$side}: $gap; }\n  @include b(1);\n}",
+        );
+        assert!(InMemoryNode::diagnostics(&root).is_empty());
+
+        let stylesheet = child(&root, 0);
+        assert_eq!(
+            child_kinds(&stylesheet),
+            vec![Declaration, Whitespace, Rule]
+        );
+        assert_eq!(
+            child_kinds(&child(&stylesheet, 0)),
+            vec![Variable, Colon, Whitespace, Value, Semicolon]
+        );
+        let block = child(&child(&stylesheet, 2), 2);
+        assert_eq!(
+            child_kinds(&block),
+            vec![
+                LCurly, Whitespace, Comment, Whitespace, Rule, Whitespace, AtRule, Whitespace,
+                RCurly
+            ]
+        );
+        let nested_block = child(&child(&block, 4), 2);
+        assert_eq!(
+            child_kinds(&child(&nested_block, 2)),
+            vec![Property, Interpolation, Colon, Whitespace, Value, Semicolon]
+        );
+    }
+
+    #[test]
+    fn malformed_stylesheets_are_kept_with_diagnostics() {
+        let documents = [
+            ("a { color: red;", vec!["expected `}`"]),
+            ("a { color red; }", vec!["expected `:`"]),
+            ("a { color: ; }", vec!["expected a value"]),
+            ("a { b: c(1; }", vec!["expected `)`"]),
+            ("a { b: \"c; }", vec!["unterminated string", "expected `}`"]),
+            ("} a {}", vec!["unexpected `}`"]),
+            ("/* a {}", vec!["unterminated comment"]),
+        ];
+        assert_kept_with_diagnostics::<SyntaxKind>(&documents);
+    }
+
+    #[test]
+    fn edits_only_reparse_the_enclosing_rule() {
+        let root = InMemoryNode::<SyntaxKind>::new_from_parsed(
+            "a {\n  color: red;\n}\n@media print {\n  b { margin: 0; }\n}\n",
+        );
+        let stylesheet = child(&root, 0);
+        let first_rule = child(&stylesheet, 0);
+        let media = child(&stylesheet, 2);
+        let nested_rule = child(&child(&media, 4), 2);
+
+        // Change a value in the first rule
+        Selection::new_from_cursor_pair(
+            Cursor::new_at_rows_cols(root.clone(), (2, 10)),
+            Cursor::new_at_rows_cols(root.clone(), (2, 13)),
+        )
+        .replace("blue; top: 1px")
+        .unwrap();
+        assert_eq!(
+            InMemoryNode::deep_literal(&root),
+            "a {\n  color: blue; top: 1px;\n}\n@media print {\n  b { margin: 0; }\n}\n"
+        );
+        assert!(InMemoryNode::diagnostics(&root).is_empty());
+
+        use SyntaxKind::*;
+        assert!(Rc::ptr_eq(&child(&stylesheet, 0), &first_rule));
+        assert!(Rc::ptr_eq(&child(&stylesheet, 2), &media));
+        assert_eq!(
+            child_kinds(&child(&first_rule, 2)),
+            vec![
+                LCurly,
+                Whitespace,
+                Declaration,
+                Whitespace,
+                Declaration,
+                Whitespace,
+                RCurly
+            ]
+        );
+
+        // Change the selector of the rule nested in the at-rule
+        Selection::new_from_cursor_pair(
+            Cursor::new_at_rows_cols(root.clone(), (5, 3)),
+            Cursor::new_at_rows_cols(root.clone(), (5, 5)),
+        )
+        .replace("b.c, d ")
+        .unwrap();
+        assert_eq!(
+            InMemoryNode::deep_literal(&root),
+            "a {\n  color: blue; top: 1px;\n}\n@media print {\n  b.c, d { margin: 0; }\n}\n"
+        );
+        assert!(InMemoryNode::diagnostics(&root).is_empty());
+        assert!(Rc::ptr_eq(&child(&stylesheet, 2), &media));
+        assert!(Rc::ptr_eq(&child(&child(&media, 4), 2), &nested_rule));
+        assert_eq!(
+            child_kinds(&child(&nested_rule, 0)),
+            vec![Ident, Class, Comma, Whitespace, Ident]
+        );
+    }
+}
@@ -0,0 +1,10 @@
+import * as React from "react";
+
+interface ButtonProps {
+  label: string;
+  onClick?: () => void;
+}
+
+export function Button({ label, onClick }: ButtonProps) {
+  return <button className="button" onClick={onClick}>{label}</button>;
+}
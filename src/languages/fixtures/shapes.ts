@@ -0,0 +1,49 @@
+import { EventEmitter } from "events";
+import type { Logger } from "./logger";
+
+/** A point on a two dimensional plane. */
+export interface Point<T = number> {
+  readonly x: T;
+  readonly y: T;
+  label?: string;
+}
+
+export type Shape =
+  | { kind: "circle"; center: Point; radius: number }
+  | { kind: "rect"; origin: Point; size: [number, number] };
+
+export enum Unit {
+  Pixels = "px",
+  Points = "pt",
+}
+
+export abstract class Canvas<S extends Shape> extends EventEmitter {
+  private shapes: S[] = [];
+  protected constructor(private readonly logger: Logger, public unit: Unit = Unit.Pixels) {
+    super();
+  }
+
+  abstract render(shape: S): void;
+
+  add(shape: S): this {
+    this.shapes.push(shape);
+    this.logger.info(`added a ${shape.kind}`);
+    return this;
+  }
+
+  area(): number {
+    return this.shapes.reduce((total: number, shape) => total + areaOf(shape), 0);
+  }
+}
+
+export function areaOf(shape: Shape): number {
+  switch (shape.kind) {
+    case "circle":
+      return Math.PI * shape.radius ** 2;
+    case "rect":
+      return shape.size[0] * shape.size[1];
+  }
+}
+
+export const origin = <Point>{ x: 0, y: 0 };
+export default Canvas;
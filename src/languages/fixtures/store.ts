@@ -0,0 +1,36 @@
+// A tiny typed key value store, with CRLF line endings and unicode
+declare global {
+  interface Window { __store?: Store<unknown>; }
+}
+
+namespace Keys {
+  export const prefix = "störe:";
+}
+
+type Listener<V> = (value: V | undefined, key: string) => void;
+
+export class Store<V> {
+  #values = new Map<string, V>();
+  private listeners: Array<Listener<V>> = [];
+
+  get<K extends string>(key: K): V | undefined {
+    return this.#values.get(Keys.prefix + key);
+  }
+
+  set(key: string, value: V): void {
+    this.#values.set(Keys.prefix + key, value);
+    for (const listener of this.listeners) listener(value, key);
+  }
+
+  subscribe(listener: Listener<V>): () => void {
+    this.listeners.push(listener);
+    return () => {
+      this.listeners = this.listeners.filter((l) => l !== listener);
+    };
+  }
+}
+
+export const emoji = new Store<string>();
+emoji.set("crab", "🦀");
+const maybe = emoji.get("crab")!;
+const length = (maybe as string).length ?? 0;
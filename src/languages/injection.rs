@@ -0,0 +1,597 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    languages::{css, typescript},
+    node_tree::{
+        node::{InMemoryNode, NodeMetadata, TokenKindTrait},
+        style::HighlightGroup,
+    },
+};
+
+/// TypeScript, with CSS injected into styled-components (and other `css` tagged) templates.
+pub type TypeScriptWithCss = Injected<typescript::SyntaxKind, css::SyntaxKind>;
+
+/// The kinds of nodes in a document of the `Host` language, where nodes that the host says contain
+/// the `Guest` language (see `TokenKindTrait::injected_language`) have been parsed with the guest
+/// language, ie, CSS within the `styled.div`...`` templates of a TypeScript file.
+///
+/// Every node in an `InMemoryNode` tree has the same token kind, so (like the `TypeScript` nodes in
+/// `languages::markdown`) this wraps the kinds of both languages into one. The guest's nodes are
+/// children of an `Injection` node, which is the host's node that the guest's text was in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Injected<Host, Guest> {
+    Host(Host),
+    Injection(Host),
+    Guest(Guest),
+}
+
+fn kind_of<TokenKind: TokenKindTrait>(
+    node: &Rc<RefCell<InMemoryNode<TokenKind>>>,
+) -> Option<TokenKind> {
+    match &node.borrow().metadata {
+        NodeMetadata::AstNode { kind, .. } => Some(kind.clone()),
+        _ => None,
+    }
+}
+
+/// When called, copies the subtree under `node` (along with its diagnostics) into a tree of
+/// another token kind, appending it to `parent` if given. `convert` maps the metadata of each node
+/// in the subtree into the metadata of its copy.
+///
+/// NOTE: nodes are appended to their parent before their own children are copied, so that each
+/// node is given a fractional index relative to the nodes that come before it.
+pub fn copy_subtree<From: TokenKindTrait, To: TokenKindTrait>(
+    parent: Option<&Rc<RefCell<InMemoryNode<To>>>>,
+    node: &Rc<RefCell<InMemoryNode<From>>>,
+    convert: &impl Fn(&NodeMetadata<From>) -> NodeMetadata<To>,
+) -> Rc<RefCell<InMemoryNode<To>>> {
+    let copy = InMemoryNode::new_with_metadata(convert(&node.borrow().metadata));
+    let copy = match parent {
+        Some(parent) => InMemoryNode::append_child(parent, copy),
+        None => copy,
+    };
+    for child in node.borrow().children.iter() {
+        copy_subtree(Some(&copy), child, convert);
+    }
+
+    // NOTE: appending a child clears the diagnostics of its ancestors, so copy them over last
+    copy.borrow_mut().diagnostics = node.borrow().diagnostics.clone();
+    copy
+}
+
+impl<Host: TokenKindTrait, Guest: TokenKindTrait> Injected<Host, Guest> {
+    /// When called, parses `literal` on its own with the guest language, and copies the result
+    /// into a subtree of guest nodes, appending it to `parent` if given.
+    fn parse_guest(
+        parent: Option<&Rc<RefCell<InMemoryNode<Self>>>>,
+        literal: &str,
+        guest_parent: Option<Rc<RefCell<InMemoryNode<Guest>>>>,
+    ) -> Rc<RefCell<InMemoryNode<Self>>> {
+        let guest_parent = guest_parent.unwrap_or_else(|| {
+            InMemoryNode::new_with_metadata(NodeMetadata::Root(Default::default()))
+        });
+        let node = Guest::parse(literal, Some(guest_parent));
+        copy_subtree(parent, &node, &|metadata| match metadata {
+            NodeMetadata::AstNode { kind, literal } => NodeMetadata::AstNode {
+                kind: Injected::Guest(kind.clone()),
+                literal: literal.clone(),
+            },
+            NodeMetadata::Literal(literal) => NodeMetadata::Literal(literal.clone()),
+            NodeMetadata::Fragment => NodeMetadata::Fragment,
+            NodeMetadata::Empty | NodeMetadata::Root(_) => NodeMetadata::Empty,
+        })
+    }
+
+    /// When called, copies a subtree of host nodes into a subtree of injected nodes, appending it
+    /// to `parent` if given. The text of each node that the host says contains the guest language
+    /// is parsed with the guest language, and the result takes the place of its children.
+    fn inject(
+        parent: Option<&Rc<RefCell<InMemoryNode<Self>>>>,
+        node: &Rc<RefCell<InMemoryNode<Host>>>,
+    ) -> Rc<RefCell<InMemoryNode<Self>>> {
+        let is_injection = Host::injected_language(node) == Some(Guest::language_name());
+        let metadata = match &node.borrow().metadata {
+            NodeMetadata::AstNode { kind, .. } if is_injection => NodeMetadata::AstNode {
+                kind: Injected::Injection(kind.clone()),
+                literal: None,
+            },
+            NodeMetadata::AstNode { kind, literal } => NodeMetadata::AstNode {
+                kind: Injected::Host(kind.clone()),
+                literal: literal.clone(),
+            },
+            NodeMetadata::Root(options) => NodeMetadata::Root(options.clone()),
+            NodeMetadata::Literal(literal) => NodeMetadata::Literal(literal.clone()),
+            NodeMetadata::Fragment => NodeMetadata::Fragment,
+            NodeMetadata::Empty => NodeMetadata::Empty,
+        };
+
+        let injected = InMemoryNode::new_with_metadata(metadata);
+        let injected = match parent {
+            Some(parent) => InMemoryNode::append_child(parent, injected),
+            None => injected,
+        };
+        if is_injection {
+            Self::parse_guest(Some(&injected), &InMemoryNode::deep_literal(node), None);
+        } else {
+            for child in node.borrow().children.iter() {
+                Self::inject(Some(&injected), child);
+            }
+        }
+        injected.borrow_mut().diagnostics = node.borrow().diagnostics.clone();
+        injected
+    }
+
+    /// When called, copies a subtree of injected nodes back into a subtree of host nodes, so that
+    /// the host language can reparse within it. Each injection node is copied as a single host
+    /// token containing all of its text, like it was before the guest language was injected.
+    ///
+    /// `node` can be within a tree of any token kind (see `TokenKindTrait::key_path_segments`),
+    /// where `unwrap` returns the injected kind that each wrapped kind is, if any.
+    fn extract_host<Wrapper: TokenKindTrait>(
+        parent: &Rc<RefCell<InMemoryNode<Host>>>,
+        node: &Rc<RefCell<InMemoryNode<Wrapper>>>,
+        unwrap: &dyn Fn(&Wrapper) -> Option<Self>,
+    ) -> Rc<RefCell<InMemoryNode<Host>>> {
+        let kind = match &node.borrow().metadata {
+            NodeMetadata::AstNode { kind, .. } => unwrap(kind),
+            _ => None,
+        };
+        let metadata = match (&node.borrow().metadata, &kind) {
+            (NodeMetadata::AstNode { .. }, Some(Injected::Injection(kind))) => {
+                NodeMetadata::AstNode {
+                    kind: kind.clone(),
+                    literal: Some(InMemoryNode::deep_literal(node)),
+                }
+            }
+            (NodeMetadata::AstNode { literal, .. }, Some(Injected::Host(kind))) => {
+                NodeMetadata::AstNode {
+                    kind: kind.clone(),
+                    literal: literal.clone(),
+                }
+            }
+            (NodeMetadata::Literal(literal), _) => NodeMetadata::Literal(literal.clone()),
+            (NodeMetadata::Fragment, _) => NodeMetadata::Fragment,
+            _ => NodeMetadata::Empty,
+        };
+
+        let host = InMemoryNode::append_child(parent, InMemoryNode::new_with_metadata(metadata));
+        if !matches!(kind, Some(Injected::Injection(_))) {
+            for child in node.borrow().children.iter() {
+                Self::extract_host(&host, child, unwrap);
+            }
+        }
+        host
+    }
+
+    /// When called with a node within injected text, returns true if the host would still inject
+    /// the guest language into the text of the injection node it is within, ie, not once a `${}`
+    /// has been typed into a `css`...`` template. The host is asked with a copy of the host nodes
+    /// around the injection, since the host's nodes are never kept in the injected tree.
+    fn is_still_injected<Wrapper: TokenKindTrait>(
+        node: &Rc<RefCell<InMemoryNode<Wrapper>>>,
+        unwrap: &dyn Fn(&Wrapper) -> Option<Self>,
+    ) -> bool {
+        let kind_of = |node: &Rc<RefCell<InMemoryNode<Wrapper>>>| match &node.borrow().metadata {
+            NodeMetadata::AstNode { kind, .. } => unwrap(kind),
+            _ => None,
+        };
+        let mut injection = node.clone();
+        while !matches!(kind_of(&injection), Some(Injected::Injection(_))) {
+            let parent = injection
+                .borrow()
+                .parent
+                .as_ref()
+                .and_then(|parent| parent.upgrade());
+            let Some(parent) = parent else {
+                return false;
+            };
+            injection = parent;
+        }
+        let (parent, child_index) = {
+            let injection = injection.borrow();
+            (
+                injection
+                    .parent
+                    .as_ref()
+                    .and_then(|parent| parent.upgrade()),
+                injection.child_index,
+            )
+        };
+        let (Some(parent), Some(child_index)) = (parent, child_index) else {
+            return false;
+        };
+
+        let root = InMemoryNode::new_with_metadata(NodeMetadata::Root(Default::default()));
+        let host = Self::extract_host(&root, &parent, unwrap);
+        let host_injection = host.borrow().children.get(child_index).cloned();
+        host_injection.is_some_and(|host_injection| {
+            Host::injected_language(&host_injection) == Some(Guest::language_name())
+        })
+    }
+}
+
+impl<Host: TokenKindTrait, Guest: TokenKindTrait> TokenKindTrait for Injected<Host, Guest> {
+    type ParseOptions = Host::ParseOptions;
+
+    fn language_name() -> &'static str {
+        Host::language_name()
+    }
+
+    fn highlight_group(ancestry: std::vec::IntoIter<Self>) -> Option<HighlightGroup> {
+        let ancestry = ancestry.collect::<Vec<_>>();
+
+        // Guest nodes are highlighted by the guest language
+        let guest_ancestry = ancestry
+            .iter()
+            .map_while(|kind| match kind {
+                Injected::Guest(kind) => Some(kind.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if !guest_ancestry.is_empty() {
+            return Guest::highlight_group(guest_ancestry.into_iter());
+        }
+
+        let host_ancestry = ancestry
+            .into_iter()
+            .filter_map(|kind| match kind {
+                Injected::Host(kind) | Injected::Injection(kind) => Some(kind),
+                Injected::Guest(_) => None,
+            })
+            .collect::<Vec<_>>();
+        Host::highlight_group(host_ancestry.into_iter())
+    }
+
+    fn kind_name(&self) -> String {
+        match self {
+            Injected::Host(kind) | Injected::Injection(kind) => kind.kind_name(),
+            Injected::Guest(kind) => kind.kind_name(),
+        }
+    }
+
+    fn field_name(parent: &Self, children: &[Self], index: usize) -> Option<&'static str> {
+        match parent {
+            Injected::Host(parent) | Injected::Injection(parent) => {
+                let children = children
+                    .iter()
+                    .map(|kind| match kind {
+                        Injected::Host(kind) | Injected::Injection(kind) => Some(kind.clone()),
+                        Injected::Guest(_) => None,
+                    })
+                    .collect::<Option<Vec<Host>>>()?;
+                Host::field_name(parent, &children, index)
+            }
+            Injected::Guest(parent) => {
+                let children = children
+                    .iter()
+                    .map(|kind| match kind {
+                        Injected::Guest(kind) => Some(kind.clone()),
+                        _ => None,
+                    })
+                    .collect::<Option<Vec<Guest>>>()?;
+                Guest::field_name(parent, &children, index)
+            }
+        }
+    }
+
+    // The symbols in a document are found with the host's query, so injected text (ie, CSS) never
+    // adds any symbols of its own
+    fn outline_query(parse_options: &Self::ParseOptions) -> Option<&'static str> {
+        Host::outline_query(parse_options)
+    }
+
+    // Injection nodes are reparsable, so that an edit to injected text never reparses any of the
+    // host's nodes around it
+    fn is_reparsable(&self) -> bool {
+        match self {
+            Injected::Host(kind) => kind.is_reparsable(),
+            Injected::Injection(_) => true,
+            Injected::Guest(kind) => kind.is_reparsable(),
+        }
+    }
+
+    // Injected text is only reparsed by the guest while the host would still inject the guest
+    // into it, otherwise the host's nodes around it are reparsed by the host
+    fn is_reparsable_node<Wrapper: TokenKindTrait>(
+        node: &Rc<RefCell<InMemoryNode<Wrapper>>>,
+        unwrap: &dyn Fn(&Wrapper) -> Option<Self>,
+    ) -> bool {
+        let kind = match &node.borrow().metadata {
+            NodeMetadata::AstNode { kind, .. } => unwrap(kind),
+            _ => None,
+        };
+        match kind {
+            Some(Injected::Host(_)) => Host::is_reparsable_node(node, &|kind| match unwrap(kind) {
+                Some(Injected::Host(kind)) => Some(kind),
+                _ => None,
+            }),
+            Some(Injected::Injection(_)) => Self::is_still_injected(node, unwrap),
+            Some(Injected::Guest(_)) => {
+                Guest::is_reparsable_node(node, &|kind| match unwrap(kind) {
+                    Some(Injected::Guest(kind)) => Some(kind),
+                    _ => None,
+                }) && Self::is_still_injected(node, unwrap)
+            }
+            None => false,
+        }
+    }
+
+    fn is_foldable(&self) -> bool {
+        match self {
+            Injected::Host(kind) | Injected::Injection(kind) => kind.is_foldable(),
+            Injected::Guest(kind) => kind.is_foldable(),
+        }
+    }
+
+    fn parse(
+        literal: &str,
+        parent: Option<Rc<RefCell<InMemoryNode<Self>>>>,
+    ) -> Rc<RefCell<InMemoryNode<Self>>> {
+        let Some(parent) = parent else {
+            return Self::inject(None, &Host::parse(literal, None));
+        };
+
+        match kind_of(&parent) {
+            // Reparsing within guest nodes, so parse with the guest language, within a copy of
+            // the guest nodes being reparsed within
+            Some(Injected::Guest(_)) => {
+                let root = InMemoryNode::new_with_metadata(NodeMetadata::Root(Default::default()));
+                let guest_parent = copy_subtree(Some(&root), &parent, &|metadata| match metadata {
+                    NodeMetadata::AstNode {
+                        kind: Injected::Guest(kind),
+                        literal,
+                    } => NodeMetadata::AstNode {
+                        kind: kind.clone(),
+                        literal: literal.clone(),
+                    },
+                    NodeMetadata::Literal(literal) => NodeMetadata::Literal(literal.clone()),
+                    _ => NodeMetadata::Empty,
+                });
+                Self::parse_guest(None, literal, Some(guest_parent))
+            }
+
+            // Reparsing all of the text within an injection node
+            Some(Injected::Injection(kind)) => {
+                let injection = InMemoryNode::new_with_metadata(NodeMetadata::AstNode {
+                    kind: Injected::Injection(kind),
+                    literal: None,
+                });
+                Self::parse_guest(Some(&injection), literal, None);
+                injection
+            }
+
+            // Reparsing within host nodes, so parse with the host language, within a copy of
+            // the host nodes being reparsed within
+            _ => {
+                let root = InMemoryNode::new_with_metadata(NodeMetadata::Root(
+                    InMemoryNode::parse_options(&parent),
+                ));
+                let host_parent = match parent.borrow().metadata {
+                    NodeMetadata::Root(_) => root,
+                    _ => Self::extract_host(&root, &parent, &|kind| Some(kind.clone())),
+                };
+                Self::inject(None, &Host::parse(literal, Some(host_parent)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Injected, TypeScriptWithCss};
+    use crate::{
+        languages::{css, typescript},
+        node_tree::{
+            cursor::{Cursor, Selection},
+            node::{InMemoryNode, NodeMetadata, TokenKindTrait},
+            style::HighlightGroup,
+        },
+    };
+    use std::{cell::RefCell, rc::Rc};
+
+    /// When called, returns every node in the subtree under `node` with the given kind, in
+    /// document order.
+    fn find_all(
+        node: &Rc<RefCell<InMemoryNode<TypeScriptWithCss>>>,
+        kind: TypeScriptWithCss,
+    ) -> Vec<Rc<RefCell<InMemoryNode<TypeScriptWithCss>>>> {
+        let mut found = vec![];
+        if matches!(&node.borrow().metadata, NodeMetadata::AstNode { kind: node_kind, .. } if *node_kind == kind)
+        {
+            found.push(node.clone());
+        }
+        for child in node.borrow().children.iter() {
+            found.extend(find_all(child, kind));
+        }
+        found
+    }
+
+    const STYLED_COMPONENTS: &str = r#"const Button = styled.button`
+  color: red;
+  &:hover { color: blue; }
+`;
+const label = `color: red;`;
+const Title = styled(Button)`font-size: ${size}px;`;
+"#;
+
+    #[test]
+    fn css_is_injected_into_tagged_templates() {
+        let root = InMemoryNode::<TypeScriptWithCss>::new_from_parsed(STYLED_COMPONENTS);
+        assert_eq!(InMemoryNode::deep_literal(&root), STYLED_COMPONENTS);
+        assert!(InMemoryNode::diagnostics(&root).is_empty());
+
+        // Only the template without interpolations that is tagged with `styled` is injected
+        let injections = find_all(
+            &root,
+            Injected::Injection(typescript::SyntaxKind::TEMPLATE_CHUNK),
+        );
+        assert_eq!(injections.len(), 1);
+        assert_eq!(
+            InMemoryNode::deep_literal(&injections[0]),
+            "\n  color: red;\n  &:hover { color: blue; }\n"
+        );
+        assert_eq!(
+            find_all(&injections[0], Injected::Guest(css::SyntaxKind::Rule)).len(),
+            1
+        );
+        assert_eq!(
+            find_all(
+                &root,
+                Injected::Host(typescript::SyntaxKind::TEMPLATE_CHUNK)
+            )
+            .len(),
+            3
+        );
+
+        // Each language highlights its own nodes
+        assert_eq!(
+            TypeScriptWithCss::highlight_group(
+                vec![
+                    Injected::Guest(css::SyntaxKind::Property),
+                    Injected::Guest(css::SyntaxKind::Declaration),
+                    Injected::Guest(css::SyntaxKind::Stylesheet),
+                    Injected::Injection(typescript::SyntaxKind::TEMPLATE_CHUNK),
+                    Injected::Host(typescript::SyntaxKind::TEMPLATE),
+                ]
+                .into_iter()
+            ),
+            Some(HighlightGroup::Property)
+        );
+        assert_eq!(
+            TypeScriptWithCss::highlight_group(
+                vec![
+                    Injected::Host(typescript::SyntaxKind::CONST_KW),
+                    Injected::Host(typescript::SyntaxKind::VAR_DECL),
+                ]
+                .into_iter()
+            ),
+            Some(HighlightGroup::Keyword)
+        );
+    }
+
+    #[test]
+    fn injected_diagnostics_are_relative_to_the_document() {
+        let literal = "let a = 1;\nconst b = css`a { color: red;`;";
+        let root = InMemoryNode::<TypeScriptWithCss>::new_from_parsed(literal);
+        assert_eq!(InMemoryNode::deep_literal(&root), literal);
+
+        let diagnostics = InMemoryNode::diagnostics(&root);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].range.start,
+            "let a = 1;\nconst b = css`a { color: red;".len()
+        );
+    }
+
+    #[test]
+    fn edits_within_injected_text_are_reparsed_by_the_guest() {
+        let root = InMemoryNode::<TypeScriptWithCss>::new_from_parsed(STYLED_COMPONENTS);
+        let injection = find_all(
+            &root,
+            Injected::Injection(typescript::SyntaxKind::TEMPLATE_CHUNK),
+        )[0]
+        .clone();
+        let template = find_all(&root, Injected::Host(typescript::SyntaxKind::TEMPLATE))[0].clone();
+
+        // `color: red;` => `color: red; margin: 0;`
+        Selection::new_from_cursor_pair(
+            Cursor::new_at_rows_cols(root.clone(), (2, 13)),
+            Cursor::new_at_rows_cols(root.clone(), (2, 14)),
+        )
+        .replace("; margin: 0;")
+        .unwrap();
+        assert_eq!(
+            InMemoryNode::deep_literal(&root),
+            STYLED_COMPONENTS.replace("color: red;\n  &", "color: red; margin: 0;\n  &")
+        );
+        assert!(InMemoryNode::diagnostics(&root).is_empty());
+
+        // The host's nodes were left alone, and the new declaration was parsed as CSS
+        assert!(Rc::ptr_eq(
+            &find_all(&root, Injected::Host(typescript::SyntaxKind::TEMPLATE))[0],
+            &template
+        ));
+        assert!(Rc::ptr_eq(
+            &find_all(
+                &root,
+                Injected::Injection(typescript::SyntaxKind::TEMPLATE_CHUNK)
+            )[0],
+            &injection
+        ));
+        assert_eq!(
+            find_all(&injection, Injected::Guest(css::SyntaxKind::Declaration)).len(),
+            3
+        );
+
+        // Edits to the host are reparsed by the host, which injects the guest again
+        Selection::new_from_cursor_pair(
+            Cursor::new_at_rows_cols(root.clone(), (5, 15)),
+            Cursor::new_at_rows_cols(root.clone(), (5, 16)),
+        )
+        .replace("css`")
+        .unwrap();
+        assert!(InMemoryNode::deep_literal(&root).contains("const label = css`color: red;`;"));
+        assert!(InMemoryNode::diagnostics(&root).is_empty());
+        assert_eq!(
+            find_all(
+                &root,
+                Injected::Injection(typescript::SyntaxKind::TEMPLATE_CHUNK)
+            )
+            .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn only_css_tags_are_injected() {
+        let literal = concat!(
+            "const a = notcss`color: red;`;\n",
+            "const b = mycss.x`color: red;`;\n",
+            "const c = css.escape()`color: red;`;\n",
+            "const d = styled`color: red;`;\n",
+            "const e = styled.div.attrs({ role: 'button' })`color: red;`;\n",
+            "const f = keyframes`from { opacity: 0; }`;\n",
+        );
+        let root = InMemoryNode::<TypeScriptWithCss>::new_from_parsed(literal);
+        assert_eq!(InMemoryNode::deep_literal(&root), literal);
+        let injections = find_all(
+            &root,
+            Injected::Injection(typescript::SyntaxKind::TEMPLATE_CHUNK),
+        );
+        assert_eq!(
+            injections
+                .iter()
+                .map(InMemoryNode::deep_literal)
+                .collect::<Vec<_>>(),
+            vec!["color: red;", "from { opacity: 0; }"]
+        );
+        assert_eq!(Cursor::new(injections[0].clone()).to_rows_cols(), (5, 48));
+    }
+
+    #[test]
+    fn injections_are_removed_once_templates_are_interpolated() {
+        let literal = "const a = css`color: red;`;\n";
+        let root = InMemoryNode::<TypeScriptWithCss>::new_from_parsed(literal);
+        let injections = || {
+            find_all(
+                &root,
+                Injected::Injection(typescript::SyntaxKind::TEMPLATE_CHUNK),
+            )
+        };
+        assert_eq!(injections().len(), 1);
+
+        // `red` => `${color}`
+        Selection::new_from_cursor_pair(
+            Cursor::new_at_rows_cols(root.clone(), (1, 22)),
+            Cursor::new_at_rows_cols(root.clone(), (1, 25)),
+        )
+        .replace("${color}")
+        .unwrap();
+        assert_eq!(
+            InMemoryNode::deep_literal(&root),
+            "const a = css`color: ${color};`;\n"
+        );
+        assert!(injections().is_empty());
+        assert!(find_all(&root, Injected::Guest(css::SyntaxKind::Declaration)).is_empty());
+    }
+}
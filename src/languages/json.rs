@@ -0,0 +1,768 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::node_tree::{
+    diagnostic::{Diagnostic, Severity},
+    node::{InMemoryNode, NodeMetadata, TokenKindTrait},
+    style::HighlightGroup,
+};
+
+/// The kinds of nodes that make up a parsed JSON document.
+///
+/// Documents, objects, arrays, and members contain other nodes, and everything else is a token
+/// with its own literal text. Whitespace is kept in the tree as tokens, so the literal text of a
+/// parsed document is always exactly what was parsed - even if it isn't valid JSON.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyntaxKind {
+    Document,
+    Object,
+    Array,
+    Member, // A `"key": value` pair within an object
+
+    Key, // The string at the start of a member
+    String,
+    Number,
+    True,
+    False,
+    Null,
+
+    LCurly,
+    RCurly,
+    LBrack,
+    RBrack,
+    Colon,
+    Comma,
+    Whitespace,
+    Error, // Text that isn't part of any JSON token
+}
+
+impl SyntaxKind {
+    fn is_value_start(&self) -> bool {
+        matches!(
+            self,
+            SyntaxKind::LCurly
+                | SyntaxKind::LBrack
+                | SyntaxKind::String
+                | SyntaxKind::Number
+                | SyntaxKind::True
+                | SyntaxKind::False
+                | SyntaxKind::Null
+                | SyntaxKind::Error
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: SyntaxKind,
+    text: String,
+    start: usize, // In chars from the start of the text being parsed
+}
+
+impl Token {
+    fn end(&self) -> usize {
+        self.start + self.text.chars().count()
+    }
+}
+
+/// When called, splits `literal` up into tokens. Text that isn't valid JSON is still turned into
+/// tokens (along with a diagnostic), so that every char in `literal` ends up in exactly one token.
+fn tokenize(literal: &str, diagnostics: &mut Vec<Diagnostic>) -> Vec<Token> {
+    let chars = literal.chars().collect::<Vec<char>>();
+    let mut tokens = vec![];
+    let mut index = 0;
+    while index < chars.len() {
+        let start = index;
+        let c = chars[index];
+        index += 1;
+
+        let kind = match c {
+            '{' => SyntaxKind::LCurly,
+            '}' => SyntaxKind::RCurly,
+            '[' => SyntaxKind::LBrack,
+            ']' => SyntaxKind::RBrack,
+            ':' => SyntaxKind::Colon,
+            ',' => SyntaxKind::Comma,
+            // NOTE: a byte order mark is treated as whitespace so that it doesn't show up as an error
+            ' ' | '\t' | '\n' | '\r' | '\u{feff}' => {
+                while index < chars.len()
+                    && matches!(chars[index], ' ' | '\t' | '\n' | '\r' | '\u{feff}')
+                {
+                    index += 1;
+                }
+                SyntaxKind::Whitespace
+            }
+            '"' => {
+                let mut is_escaped = false;
+                let mut is_terminated = false;
+                while index < chars.len() && chars[index] != '\n' && chars[index] != '\r' {
+                    let c = chars[index];
+                    index += 1;
+                    if is_escaped {
+                        is_escaped = false;
+                    } else if c == '\\' {
+                        is_escaped = true;
+                    } else if c == '"' {
+                        is_terminated = true;
+                        break;
+                    }
+                }
+                if !is_terminated {
+                    diagnostics.push(Diagnostic::new(
+                        start..index,
+                        Severity::Error,
+                        "unterminated string",
+                    ));
+                }
+                SyntaxKind::String
+            }
+            '-' | '0'..='9' => {
+                while index < chars.len()
+                    && matches!(chars[index], '0'..='9' | '.' | 'e' | 'E' | '+' | '-')
+                {
+                    index += 1;
+                }
+                let text = chars[start..index].iter().collect::<String>();
+                if !is_valid_number(&text) {
+                    diagnostics.push(Diagnostic::new(
+                        start..index,
+                        Severity::Error,
+                        &format!("invalid number `{text}`"),
+                    ));
+                }
+                SyntaxKind::Number
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                while index < chars.len() && (chars[index].is_alphanumeric() || chars[index] == '_')
+                {
+                    index += 1;
+                }
+                match chars[start..index].iter().collect::<String>().as_str() {
+                    "true" => SyntaxKind::True,
+                    "false" => SyntaxKind::False,
+                    "null" => SyntaxKind::Null,
+                    word => {
+                        diagnostics.push(Diagnostic::new(
+                            start..index,
+                            Severity::Error,
+                            &format!("expected a value, but found `{word}` instead"),
+                        ));
+                        SyntaxKind::Error
+                    }
+                }
+            }
+            c => {
+                diagnostics.push(Diagnostic::new(
+                    start..index,
+                    Severity::Error,
+                    &format!("unexpected character `{c}`"),
+                ));
+                SyntaxKind::Error
+            }
+        };
+
+        tokens.push(Token {
+            kind,
+            text: chars[start..index].iter().collect(),
+            start,
+        });
+    }
+    tokens
+}
+
+/// When called, returns true if `text` is a number that is allowed by the JSON spec - an optional
+/// minus, an integer part with no leading zeros, and then an optional fraction and exponent.
+fn is_valid_number(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    let mut index = 0;
+    let digits = |index: &mut usize| {
+        let start = *index;
+        while *index < bytes.len() && bytes[*index].is_ascii_digit() {
+            *index += 1;
+        }
+        *index - start
+    };
+
+    if bytes.get(index) == Some(&b'-') {
+        index += 1;
+    }
+    let integer_start = index;
+    match digits(&mut index) {
+        0 => return false,
+        1 => {}
+        _ if bytes[integer_start] == b'0' => return false,
+        _ => {}
+    };
+    if bytes.get(index) == Some(&b'.') {
+        index += 1;
+        if digits(&mut index) == 0 {
+            return false;
+        }
+    }
+    if matches!(bytes.get(index), Some(b'e' | b'E')) {
+        index += 1;
+        if matches!(bytes.get(index), Some(b'+' | b'-')) {
+            index += 1;
+        }
+        if digits(&mut index) == 0 {
+            return false;
+        }
+    }
+    index == bytes.len()
+}
+
+/// A recursive descent parser that turns a list of tokens into a token tree. When it runs into
+/// something it doesn't expect, it records a diagnostic and keeps going, so that every token ends
+/// up somewhere in the tree.
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    diagnostics: Vec<Diagnostic>,
+
+    // The kinds of the objects / arrays that are currently being parsed, innermost last. This is
+    // used to figure out if a closing bracket belongs to an outer value that is missing its own
+    // closing bracket.
+    open: Vec<SyntaxKind>,
+}
+
+impl Parser {
+    fn new(literal: &str) -> Self {
+        let mut diagnostics = vec![];
+        let tokens = tokenize(literal, &mut diagnostics);
+        Self {
+            tokens,
+            position: 0,
+            diagnostics,
+            open: vec![],
+        }
+    }
+
+    fn peek(&self) -> Option<SyntaxKind> {
+        self.tokens.get(self.position).map(|token| token.kind)
+    }
+
+    /// When called, returns the kind of the next token that isn't whitespace.
+    fn peek_past_whitespace(&self) -> Option<SyntaxKind> {
+        self.tokens[self.position..]
+            .iter()
+            .map(|token| token.kind)
+            .find(|kind| *kind != SyntaxKind::Whitespace)
+    }
+
+    /// When called, returns the char offset that the next token starts at.
+    fn offset(&self) -> usize {
+        match self.tokens.get(self.position) {
+            Some(token) => token.start,
+            None => self.tokens.last().map(Token::end).unwrap_or(0),
+        }
+    }
+
+    fn error(&mut self, range: std::ops::Range<usize>, message: &str) {
+        self.diagnostics
+            .push(Diagnostic::new(range, Severity::Error, message));
+    }
+
+    /// When called, consumes the next token, and appends it to `parent` as a node of kind `kind`.
+    fn bump_as(&mut self, parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>, kind: SyntaxKind) {
+        let literal = Some(self.tokens[self.position].text.clone());
+        self.position += 1;
+        InMemoryNode::append_child(
+            parent,
+            InMemoryNode::new_with_metadata(NodeMetadata::AstNode { kind, literal }),
+        );
+    }
+
+    fn bump(&mut self, parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>) {
+        let kind = self.tokens[self.position].kind;
+        self.bump_as(parent, kind);
+    }
+
+    /// When called, consumes the next token, reporting it as being somewhere it isn't allowed.
+    fn bump_unexpected(&mut self, parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>) {
+        let token = self.tokens[self.position].clone();
+        if token.kind != SyntaxKind::Error {
+            // Error tokens already had a diagnostic reported when they were tokenized
+            self.error(
+                token.start..token.end(),
+                &format!("unexpected `{}`", token.text),
+            );
+        }
+        self.bump(parent);
+    }
+
+    fn eat_whitespace(&mut self, parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>) {
+        while self.peek() == Some(SyntaxKind::Whitespace) {
+            self.bump(parent);
+        }
+    }
+
+    /// When called, returns true if `kind` closes an object or array that an inner value is
+    /// nested within, ie, the `}` in `{"a": [1, 2}`.
+    fn closes_outer_value(&self, kind: SyntaxKind) -> bool {
+        match kind {
+            SyntaxKind::RCurly => self.open.contains(&SyntaxKind::Object),
+            SyntaxKind::RBrack => self.open.contains(&SyntaxKind::Array),
+            _ => false,
+        }
+    }
+
+    /// When called, parses the next value and appends it to `parent`. Returns false if the next
+    /// token can't start a value.
+    ///
+    /// NOTE: nodes are appended to their parent before their own children are parsed, so that
+    /// each node is given a fractional index relative to the nodes that come before it.
+    fn parse_value(&mut self, parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>) -> bool {
+        match self.peek() {
+            Some(SyntaxKind::LCurly) => self.parse_container(parent, SyntaxKind::Object),
+            Some(SyntaxKind::LBrack) => self.parse_container(parent, SyntaxKind::Array),
+            Some(kind) if kind.is_value_start() => self.bump(parent),
+            _ => return false,
+        };
+        true
+    }
+
+    /// When called, parses an object or an array, starting at its opening bracket.
+    fn parse_container(
+        &mut self,
+        parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>,
+        kind: SyntaxKind,
+    ) {
+        let (closing_kind, closing_text) = match kind {
+            SyntaxKind::Object => (SyntaxKind::RCurly, "}"),
+            _ => (SyntaxKind::RBrack, "]"),
+        };
+
+        let container = InMemoryNode::append_child(parent, new_node(kind));
+        self.bump(&container);
+        self.open.push(kind);
+        self.parse_container_body(&container, kind);
+        self.open.pop();
+
+        if self.peek() == Some(closing_kind) {
+            self.bump(&container);
+        } else {
+            let offset = self.offset();
+            self.error(offset..offset, &format!("expected `{closing_text}`"));
+        }
+    }
+
+    /// When called, parses the members of an object or the elements of an array, up until (but
+    /// not including) the bracket that closes it.
+    fn parse_container_body(
+        &mut self,
+        container: &Rc<RefCell<InMemoryNode<SyntaxKind>>>,
+        kind: SyntaxKind,
+    ) {
+        let closing_kind = match kind {
+            SyntaxKind::Object => SyntaxKind::RCurly,
+            _ => SyntaxKind::RBrack,
+        };
+        let noun = match kind {
+            SyntaxKind::Object => "a member",
+            _ => "a value",
+        };
+
+        // The comma after the last member or element, if it hasn't been followed by another yet
+        let mut trailing_comma = None;
+        let mut is_empty = true;
+        loop {
+            self.eat_whitespace(container);
+            let Some(next) = self.peek() else {
+                break;
+            };
+            if next == closing_kind || self.closes_outer_value(next) {
+                break;
+            }
+
+            if next == SyntaxKind::Comma {
+                let token = self.tokens[self.position].clone();
+                if is_empty || trailing_comma.is_some() {
+                    self.error(
+                        token.start..token.end(),
+                        &format!("expected {noun}, but found `,` instead"),
+                    );
+                }
+                trailing_comma = Some(token);
+                self.bump(container);
+                continue;
+            }
+
+            if !is_empty && trailing_comma.is_none() {
+                let offset = self.offset();
+                self.error(offset..offset, "expected `,`");
+            }
+
+            match kind {
+                SyntaxKind::Object if next != SyntaxKind::RBrack => self.parse_member(container),
+                SyntaxKind::Object => self.bump_unexpected(container),
+                _ => {
+                    if !self.parse_value(container) {
+                        self.bump_unexpected(container);
+                    }
+                }
+            };
+            is_empty = false;
+            trailing_comma = None;
+        }
+
+        if let Some(comma) = trailing_comma {
+            if !is_empty {
+                self.error(
+                    comma.start..comma.end(),
+                    "trailing commas are not allowed in JSON",
+                );
+            }
+        }
+    }
+
+    /// When called, parses a `"key": value` member of an object.
+    fn parse_member(&mut self, parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>) {
+        let member = InMemoryNode::append_child(parent, new_node(SyntaxKind::Member));
+
+        match self.peek() {
+            Some(SyntaxKind::String) => self.bump_as(&member, SyntaxKind::Key),
+            Some(SyntaxKind::Colon) => {
+                let offset = self.offset();
+                self.error(offset..offset, "expected a property name");
+            }
+            _ => {
+                let start = self.offset();
+                if !self.parse_value(&member) {
+                    self.bump(&member);
+                }
+                let end = self.tokens[self.position - 1].end();
+                self.error(
+                    start..end,
+                    "expected a property name, which must be a string in JSON",
+                );
+            }
+        };
+
+        match self.peek_past_whitespace() {
+            Some(SyntaxKind::Colon) => {
+                self.eat_whitespace(&member);
+                self.bump(&member);
+                self.eat_whitespace(&member);
+            }
+            // A value after the key that isn't another string (which is probably the key of the next
+            // member) still belongs to this member, ie, `{"a" 1}`
+            Some(next) if next.is_value_start() && next != SyntaxKind::String => {
+                let offset = self.offset();
+                self.error(offset..offset, "expected `:`");
+                self.eat_whitespace(&member);
+            }
+            _ => {
+                let offset = self.offset();
+                self.error(offset..offset, "expected `:`");
+                return;
+            }
+        };
+
+        if !self.parse_value(&member) {
+            let offset = self.offset();
+            self.error(offset..offset, "expected a value");
+        }
+    }
+
+    /// When called, parses a whole JSON document - a single value, surrounded by whitespace.
+    fn parse_document(&mut self) -> Rc<RefCell<InMemoryNode<SyntaxKind>>> {
+        let document = new_node(SyntaxKind::Document);
+        self.eat_whitespace(&document);
+        if !self.parse_value(&document) && self.peek().is_some() {
+            let offset = self.offset();
+            self.error(offset..offset, "expected a value");
+        }
+
+        self.parse_rest(&document);
+        document
+    }
+
+    /// When called, parses the text that is left over after the value at the top of a document.
+    fn parse_rest(&mut self, document: &Rc<RefCell<InMemoryNode<SyntaxKind>>>) {
+        loop {
+            self.eat_whitespace(document);
+            if self.peek().is_none() {
+                break;
+            }
+
+            let start = self.offset();
+            if self.parse_value(document) {
+                let end = self.tokens[self.position - 1].end();
+                self.error(start..end, "expected the end of the document");
+            } else {
+                self.bump_unexpected(document);
+            }
+        }
+    }
+
+    /// When called, parses text that was taken out of the middle of an object or array - ie, a
+    /// few of its members, possibly along with its opening or closing bracket.
+    fn parse_container_fragment(
+        &mut self,
+        kind: SyntaxKind,
+    ) -> Rc<RefCell<InMemoryNode<SyntaxKind>>> {
+        let (opening_kind, closing_kind) = match kind {
+            SyntaxKind::Object => (SyntaxKind::LCurly, SyntaxKind::RCurly),
+            _ => (SyntaxKind::LBrack, SyntaxKind::RBrack),
+        };
+
+        let container = new_node(kind);
+        self.eat_whitespace(&container);
+        if self.peek() == Some(opening_kind) {
+            self.bump(&container);
+        }
+        let mut is_closed = false;
+        loop {
+            self.parse_container_body(&container, kind);
+            match self.peek() {
+                None => break,
+                Some(next) if next == closing_kind && !is_closed => {
+                    self.bump(&container);
+                    is_closed = true;
+                }
+                // An edit that ends right at the end of the container can pull in the token after
+                // it, ie, the comma in `[1, 2],`. These are fine to keep, since they would be
+                // valid in the container's parent.
+                Some(SyntaxKind::Comma | SyntaxKind::RCurly | SyntaxKind::RBrack) if is_closed => {
+                    self.bump(&container);
+                }
+                Some(_) => self.bump_unexpected(&container),
+            };
+        }
+        container
+    }
+
+    /// When called, parses text that was taken out of the middle of a member - ie, just its value,
+    /// or its colon and value.
+    fn parse_member_fragment(&mut self) -> Rc<RefCell<InMemoryNode<SyntaxKind>>> {
+        let member = new_node(SyntaxKind::Member);
+        loop {
+            self.eat_whitespace(&member);
+            let Some(next) = self.peek() else {
+                break;
+            };
+
+            // A string is only the member's key if it is followed by the member's colon
+            if next == SyntaxKind::String {
+                let after = self.tokens[self.position + 1..]
+                    .iter()
+                    .find(|token| token.kind != SyntaxKind::Whitespace);
+                if after.is_some_and(|token| token.kind == SyntaxKind::Colon) {
+                    self.bump_as(&member, SyntaxKind::Key);
+                    continue;
+                }
+            }
+
+            // NOTE: a fragment of a member can end with the comma or closing bracket that comes
+            // after the member in its object, since these can be part of the same edit
+            if !self.parse_value(&member) {
+                self.bump(&member);
+            }
+        }
+        member
+    }
+}
+
+fn new_node(kind: SyntaxKind) -> Rc<RefCell<InMemoryNode<SyntaxKind>>> {
+    InMemoryNode::new_with_metadata(NodeMetadata::AstNode {
+        kind,
+        literal: None,
+    })
+}
+
+impl TokenKindTrait for SyntaxKind {
+    type ParseOptions = ();
+
+    fn language_name() -> &'static str {
+        "json"
+    }
+
+    fn highlight_group(ancestry: std::vec::IntoIter<SyntaxKind>) -> Option<HighlightGroup> {
+        match ancestry.as_slice().first()? {
+            SyntaxKind::Key => Some(HighlightGroup::Property),
+            SyntaxKind::String => Some(HighlightGroup::String),
+            SyntaxKind::Number => Some(HighlightGroup::Number),
+            SyntaxKind::True | SyntaxKind::False | SyntaxKind::Null => {
+                Some(HighlightGroup::Constant)
+            }
+            SyntaxKind::LCurly
+            | SyntaxKind::RCurly
+            | SyntaxKind::LBrack
+            | SyntaxKind::RBrack
+            | SyntaxKind::Colon
+            | SyntaxKind::Comma => Some(HighlightGroup::Punctuation),
+            SyntaxKind::Document
+            | SyntaxKind::Object
+            | SyntaxKind::Array
+            | SyntaxKind::Member
+            | SyntaxKind::Whitespace
+            | SyntaxKind::Error => None,
+        }
+    }
+
+    fn is_reparsable(&self) -> bool {
+        matches!(
+            self,
+            SyntaxKind::Document | SyntaxKind::Object | SyntaxKind::Array | SyntaxKind::Member
+        )
+    }
+
+    fn is_foldable(&self) -> bool {
+        matches!(self, SyntaxKind::Object | SyntaxKind::Array)
+    }
+
+    fn parse(
+        literal: &str,
+        parent: Option<Rc<RefCell<InMemoryNode<Self>>>>,
+    ) -> Rc<RefCell<InMemoryNode<Self>>> {
+        let parent_kind = parent.and_then(|parent| match parent.borrow().metadata {
+            NodeMetadata::AstNode { kind, .. } => Some(kind),
+            _ => None,
+        });
+
+        // When reparsing part of an object, array, or member, parse the text as the contents of
+        // that kind of node. The result is wrapped in a node of the same kind as the parent, so
+        // that the wrapper's children take the place of the text that was reparsed.
+        let mut parser = Parser::new(literal);
+        let node = match parent_kind {
+            Some(kind @ (SyntaxKind::Object | SyntaxKind::Array)) => {
+                parser.parse_container_fragment(kind)
+            }
+            Some(SyntaxKind::Member) => parser.parse_member_fragment(),
+            _ => parser.parse_document(),
+        };
+
+        for diagnostic in parser.diagnostics {
+            InMemoryNode::attach_diagnostic(&node, diagnostic);
+        }
+        node
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SyntaxKind;
+    use crate::{
+        languages::test_utils::{assert_kept_with_diagnostics, child, child_kinds},
+        node_tree::{
+            cursor::{Cursor, Selection},
+            node::InMemoryNode,
+        },
+    };
+    use std::rc::Rc;
+
+    #[test]
+    fn parses_objects_arrays_and_members() {
+        let root = InMemoryNode::<SyntaxKind>::new_from_parsed(
+            "{\"a\": [1, -2.5e3], \"b\": {\"c\": null}, \"d\": true}\n",
+        );
+        assert!(InMemoryNode::diagnostics(&root).is_empty());
+
+        let document = child(&root, 0);
+        assert_eq!(
+            child_kinds(&document),
+            vec![SyntaxKind::Object, SyntaxKind::Whitespace]
+        );
+        let object = child(&document, 0);
+        use SyntaxKind::*;
+        assert_eq!(
+            child_kinds(&object),
+            vec![LCurly, Member, Comma, Whitespace, Member, Comma, Whitespace, Member, RCurly]
+        );
+        let member = child(&object, 1);
+        assert_eq!(child_kinds(&member), vec![Key, Colon, Whitespace, Array]);
+        assert_eq!(
+            child_kinds(&child(&member, 3)),
+            vec![LBrack, Number, Comma, Whitespace, Number, RBrack]
+        );
+        assert_eq!(
+            child_kinds(&child(&child(&object, 4), 3)),
+            vec![LCurly, Member, RCurly]
+        );
+    }
+
+    #[test]
+    fn malformed_documents_are_kept_with_diagnostics() {
+        let documents = [
+            (
+                "{\"a\": 1,}",
+                vec!["trailing commas are not allowed in JSON"],
+            ),
+            ("{\"a\" 1}", vec!["expected `:`"]),
+            ("{\"a\" \"b\": 1}", vec!["expected `:`", "expected `,`"]),
+            (
+                "{a: 1}",
+                vec![
+                    "expected a value, but found `a` instead",
+                    "expected a property name, which must be a string in JSON",
+                ],
+            ),
+            ("[1 2]", vec!["expected `,`"]),
+            (
+                "[1, 01, 2.]",
+                vec!["invalid number `01`", "invalid number `2.`"],
+            ),
+            ("{\"a\": [1, 2}", vec!["expected `]`"]),
+            ("[\"abc\n]", vec!["unterminated string"]),
+            ("{\"a\": }", vec!["expected a value"]),
+            ("1 2", vec!["expected the end of the document"]),
+            ("[1]]", vec!["unexpected `]`"]),
+            ("{\"a\": 1", vec!["expected `}`"]),
+        ];
+        assert_kept_with_diagnostics::<SyntaxKind>(&documents);
+    }
+
+    #[test]
+    fn edits_only_reparse_the_enclosing_value() {
+        let root = InMemoryNode::<SyntaxKind>::new_from_parsed(
+            "{\n  \"a\": [1, 2],\n  \"b\": {\"c\": \"xyz\"}\n}\n",
+        );
+        let object = child(&child(&root, 0), 0);
+        let first_member = child(&object, 2);
+        let second_member = child(&object, 5);
+        let array = child(&first_member, 3);
+
+        // Add an element to the array
+        Selection::new_from_cursor_pair(
+            Cursor::new_at_rows_cols(root.clone(), (2, 12)),
+            Cursor::new_at_rows_cols(root.clone(), (2, 13)),
+        )
+        .replace("2, \"x\"")
+        .unwrap();
+        assert_eq!(
+            InMemoryNode::deep_literal(&root),
+            "{\n  \"a\": [1, 2, \"x\"],\n  \"b\": {\"c\": \"xyz\"}\n}\n"
+        );
+        assert!(InMemoryNode::diagnostics(&root).is_empty());
+
+        // The array was reparsed in place, and the rest of the document was left alone
+        use SyntaxKind::*;
+        assert!(Rc::ptr_eq(&child(&object, 2), &first_member));
+        assert!(Rc::ptr_eq(&child(&object, 5), &second_member));
+        assert!(Rc::ptr_eq(&child(&first_member, 3), &array));
+        assert_eq!(
+            child_kinds(&array),
+            vec![LBrack, Number, Comma, Whitespace, Number, Comma, Whitespace, String, RBrack]
+        );
+
+        // Change the value of a member within a nested object
+        let nested_object = child(&second_member, 3);
+        let nested_member = child(&nested_object, 1);
+        Selection::new_from_cursor_pair(
+            Cursor::new_at_rows_cols(root.clone(), (3, 12)),
+            Cursor::new_at_rows_cols(root.clone(), (3, 16)),
+        )
+        .replace(": \"ab")
+        .unwrap();
+        assert_eq!(
+            InMemoryNode::deep_literal(&root),
+            "{\n  \"a\": [1, 2, \"x\"],\n  \"b\": {\"c\": \"abyz\"}\n}\n"
+        );
+        assert!(InMemoryNode::diagnostics(&root).is_empty());
+        assert!(Rc::ptr_eq(&child(&object, 5), &second_member));
+        assert!(Rc::ptr_eq(&child(&nested_object, 1), &nested_member));
+        assert_eq!(
+            child_kinds(&nested_member),
+            vec![Key, Colon, Whitespace, String]
+        );
+    }
+}
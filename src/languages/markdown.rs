@@ -0,0 +1,810 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    languages::{
+        injection::copy_subtree,
+        typescript::{self, ParseMode},
+    },
+    node_tree::{
+        node::{InMemoryNode, NodeMetadata, TokenKindTrait},
+        style::HighlightGroup,
+    },
+};
+
+/// The kinds of nodes that make up a parsed markdown document.
+///
+/// Block level nodes (headings, paragraphs, lists, etc) each contain whole lines of the document,
+/// including their line endings. Code within fences tagged as typescript or javascript is parsed
+/// with the typescript language, and its nodes are stored in the tree as `TypeScript` nodes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SyntaxKind {
+    Document,
+    Section, // A heading, and everything after it up until the next heading of the same level or higher
+    Heading,
+    HeadingMarker, // The `#`s at the start of a heading, and the whitespace after them
+    Paragraph,
+    List,
+    ListItem,
+    ListMarker, // The `-`, `*`, `+`, or `1.` at the start of a list item, and the whitespace after it
+    BlockQuote,
+    QuoteMarker,
+    CodeFence,
+    FenceMarker,
+    InfoString, // The language name after the opening fence marker, ie, `ts`
+    Code,       // Code within a fence that is in a language that isn't parsed
+
+    Emphasis,
+    Strong,
+    EmphasisMarker,
+    Link,
+    LinkMarker,
+    LinkDestination,
+    CodeSpan,
+    Text,
+
+    Indent,
+    Newline,
+    BlankLine,
+
+    TypeScript(typescript::SyntaxKind),
+}
+
+/// A line of the document, split into its content and its line ending (which is empty on the last
+/// line of a document that doesn't end with a newline).
+#[derive(Debug, Clone, Copy)]
+struct Line<'a> {
+    content: &'a str,
+    ending: &'a str,
+}
+
+impl<'a> Line<'a> {
+    fn split(literal: &'a str) -> Vec<Self> {
+        literal
+            .split_inclusive('\n')
+            .map(|line| {
+                let content = line.trim_end_matches('\n').trim_end_matches('\r');
+                Line {
+                    content,
+                    ending: &line[content.len()..],
+                }
+            })
+            .collect()
+    }
+
+    fn is_blank(&self) -> bool {
+        self.content.trim().is_empty()
+    }
+
+    fn indent(&self) -> &'a str {
+        let trimmed = self.content.trim_start_matches([' ', '\t']);
+        &self.content[..self.content.len() - trimmed.len()]
+    }
+
+    /// When called, returns the level of the heading that this line starts, if any.
+    fn heading_level(&self) -> Option<usize> {
+        let level = self.content.chars().take_while(|c| *c == '#').count();
+        let rest = &self.content[level..];
+        if (1..=6).contains(&level) && (rest.is_empty() || rest.starts_with([' ', '\t'])) {
+            Some(level)
+        } else {
+            None
+        }
+    }
+
+    /// When called, returns the fence marker at the start of this line, if it is a code fence.
+    fn fence_marker(&self) -> Option<&'a str> {
+        let content = self.content.trim_start_matches(' ');
+        let fence_char = content.chars().next().filter(|c| *c == '`' || *c == '~')?;
+        let length = content.chars().take_while(|c| *c == fence_char).count();
+        if length >= 3 {
+            Some(&content[..length])
+        } else {
+            None
+        }
+    }
+
+    fn is_block_quote(&self) -> bool {
+        self.content.starts_with('>')
+    }
+
+    /// When called, returns the indent and marker (including the whitespace after it) of the list
+    /// item that this line starts, if any.
+    fn list_marker(&self) -> Option<(&'a str, &'a str)> {
+        let indent = self.indent();
+        let rest = &self.content[indent.len()..];
+        let marker_length = if rest.starts_with(['-', '*', '+']) {
+            1
+        } else {
+            let digits = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+            if digits == 0 || !rest[digits..].starts_with(['.', ')']) {
+                return None;
+            }
+            digits + 1
+        };
+
+        let after_marker = &rest[marker_length..];
+        let whitespace = after_marker.len() - after_marker.trim_start_matches([' ', '\t']).len();
+        if whitespace == 0 && !after_marker.is_empty() {
+            return None;
+        }
+        Some((indent, &rest[..marker_length + whitespace]))
+    }
+
+    /// When called, returns true if this line starts a block other than a paragraph, and so can't
+    /// be a continuation of the paragraph (or list item) before it.
+    fn starts_block(&self) -> bool {
+        self.heading_level().is_some()
+            || self.fence_marker().is_some()
+            || self.is_block_quote()
+            || self.list_marker().is_some()
+    }
+}
+
+fn append_node(
+    parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>,
+    kind: SyntaxKind,
+) -> Rc<RefCell<InMemoryNode<SyntaxKind>>> {
+    InMemoryNode::append_child(
+        parent,
+        InMemoryNode::new_with_metadata(NodeMetadata::AstNode {
+            kind,
+            literal: None,
+        }),
+    )
+}
+
+fn append_token(parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>, kind: SyntaxKind, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    InMemoryNode::append_child(
+        parent,
+        InMemoryNode::new_with_metadata(NodeMetadata::AstNode {
+            kind,
+            literal: Some(text.into()),
+        }),
+    );
+}
+
+/// When called, parses `text` (which is all on one line) for links, emphasis, and code spans, and
+/// appends the result to `parent`.
+fn parse_inline(parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>, text: &str) {
+    let chars = text.chars().collect::<Vec<char>>();
+    let substring = |start: usize, end: usize| chars[start..end].iter().collect::<String>();
+    let find = |needle: &[char], from: usize| {
+        (from..chars.len()).find(|index| chars[*index..].starts_with(needle))
+    };
+
+    let mut text_start = 0;
+    let mut index = 0;
+    while index < chars.len() {
+        let c = chars[index];
+        let run = chars[index..].iter().take_while(|n| **n == c).count();
+
+        // Each of these is the index just after the inline element that starts at `index`
+        let end = match c {
+            '`' => {
+                let marker = &chars[index..index + run];
+                let close = (index + run..chars.len()).find(|close| {
+                    chars[*close..].starts_with(marker)
+                        && chars.get(close + run) != Some(&'`')
+                        && chars[*close - 1] != '`'
+                });
+                close.map(|close| {
+                    append_token(parent, SyntaxKind::Text, &substring(text_start, index));
+                    append_token(parent, SyntaxKind::CodeSpan, &substring(index, close + run));
+                    close + run
+                })
+            }
+            '*' | '_' => {
+                let marker_length = run.min(2);
+                let marker = &chars[index..index + marker_length];
+                let content_start = index + marker_length;
+                // Underscores within a word (ie, `snake_case_names`) aren't emphasis
+                let is_within_word = |index: Option<usize>| {
+                    c == '_'
+                        && index
+                            .and_then(|index| chars.get(index))
+                            .is_some_and(|c| c.is_alphanumeric())
+                };
+                let is_opening = chars
+                    .get(content_start)
+                    .is_some_and(|next| !next.is_whitespace())
+                    && !is_within_word(index.checked_sub(1));
+                let close = (content_start + 1..chars.len()).find(|close| {
+                    chars[*close..].starts_with(marker)
+                        && !chars[*close - 1].is_whitespace()
+                        && !is_within_word(Some(close + marker_length))
+                });
+                close.filter(|_| is_opening).map(|close| {
+                    append_token(parent, SyntaxKind::Text, &substring(text_start, index));
+                    let kind = if marker_length == 2 {
+                        SyntaxKind::Strong
+                    } else {
+                        SyntaxKind::Emphasis
+                    };
+                    let emphasis = append_node(parent, kind);
+                    let marker = marker.iter().collect::<String>();
+                    append_token(&emphasis, SyntaxKind::EmphasisMarker, &marker);
+                    parse_inline(&emphasis, &substring(content_start, close));
+                    append_token(&emphasis, SyntaxKind::EmphasisMarker, &marker);
+                    close + marker_length
+                })
+            }
+            '[' => find(&[']', '('], index + 1).and_then(|text_end| {
+                let destination_end = find(&[')'], text_end + 2)?;
+                append_token(parent, SyntaxKind::Text, &substring(text_start, index));
+                let link = append_node(parent, SyntaxKind::Link);
+                append_token(&link, SyntaxKind::LinkMarker, "[");
+                parse_inline(&link, &substring(index + 1, text_end));
+                append_token(&link, SyntaxKind::LinkMarker, "](");
+                append_token(
+                    &link,
+                    SyntaxKind::LinkDestination,
+                    &substring(text_end + 2, destination_end),
+                );
+                append_token(&link, SyntaxKind::LinkMarker, ")");
+                Some(destination_end + 1)
+            }),
+            _ => None,
+        };
+
+        match end {
+            Some(end) => {
+                index = end;
+                text_start = end;
+            }
+            // Skip over the whole run, so that ie, the second `*` of a `**` that isn't closed
+            // isn't treated as the start of an emphasis
+            None => index += run,
+        }
+    }
+    append_token(
+        parent,
+        SyntaxKind::Text,
+        &substring(text_start, chars.len()),
+    );
+}
+
+/// When called, returns the parse mode to parse code in a fence with the given info string with,
+/// or None if the code in the fence shouldn't be parsed.
+fn fence_parse_mode(info_string: &str) -> Option<ParseMode> {
+    match info_string.split_whitespace().next()? {
+        "ts" | "typescript" => Some(ParseMode::TypeScript),
+        "tsx" => Some(ParseMode::Tsx),
+        // NOTE: javascript snippets in documentation often use `import`, so parse them as modules
+        "js" | "javascript" | "jsx" | "mjs" => Some(ParseMode::Module),
+        _ => None,
+    }
+}
+
+/// When called, converts the metadata of a node from the typescript language into the metadata
+/// of the same node embedded in a markdown document.
+fn embed_metadata(metadata: &NodeMetadata<typescript::SyntaxKind>) -> NodeMetadata<SyntaxKind> {
+    match metadata {
+        NodeMetadata::Empty | NodeMetadata::Root(_) => NodeMetadata::Empty,
+        NodeMetadata::Literal(literal) => NodeMetadata::Literal(literal.clone()),
+        NodeMetadata::Fragment => NodeMetadata::Fragment,
+        NodeMetadata::AstNode { kind, literal } => NodeMetadata::AstNode {
+            kind: SyntaxKind::TypeScript(*kind),
+            literal: literal.clone(),
+        },
+    }
+}
+
+/// When called, parses `code` with the typescript language. If `parent_kind` is set, then `code` is
+/// being reparsed within a node of that kind.
+fn parse_typescript(
+    code: &str,
+    mode: ParseMode,
+    parent_kind: Option<typescript::SyntaxKind>,
+) -> Rc<RefCell<InMemoryNode<typescript::SyntaxKind>>> {
+    // The typescript parser finds the parse mode (and the kind of node being reparsed within)
+    // from the parent it is given, so build a stand in for the parent in a tree of its own
+    let root = InMemoryNode::new_with_metadata(NodeMetadata::Root(mode));
+    let parent = match parent_kind {
+        Some(kind) => InMemoryNode::append_child(
+            &root,
+            InMemoryNode::new_with_metadata(NodeMetadata::AstNode {
+                kind,
+                literal: None,
+            }),
+        ),
+        None => root,
+    };
+    typescript::SyntaxKind::parse(code, Some(parent))
+}
+
+/// A parser that turns the lines of a markdown document into a token tree, one block at a time.
+struct Parser<'a> {
+    lines: Vec<Line<'a>>,
+    position: usize,
+
+    // The sections that the next block is nested within, along with the level of the heading at
+    // the start of each, innermost last
+    sections: Vec<(Rc<RefCell<InMemoryNode<SyntaxKind>>>, usize)>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(literal: &'a str) -> Self {
+        Self {
+            lines: Line::split(literal),
+            position: 0,
+            sections: vec![],
+        }
+    }
+
+    fn append_line_ending(&self, parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>, line: Line) {
+        append_token(parent, SyntaxKind::Newline, line.ending);
+    }
+
+    fn parse_document(&mut self, document: &Rc<RefCell<InMemoryNode<SyntaxKind>>>) {
+        while let Some(line) = self.lines.get(self.position).copied() {
+            let parent = match line.heading_level() {
+                Some(level) => {
+                    // A heading closes any sections that are at its level or deeper
+                    while self
+                        .sections
+                        .last()
+                        .is_some_and(|(_, section_level)| *section_level >= level)
+                    {
+                        self.sections.pop();
+                    }
+                    let parent = self
+                        .sections
+                        .last()
+                        .map(|(section, _)| section.clone())
+                        .unwrap_or_else(|| document.clone());
+                    let section = append_node(&parent, SyntaxKind::Section);
+                    self.sections.push((section.clone(), level));
+                    section
+                }
+                None => self
+                    .sections
+                    .last()
+                    .map(|(section, _)| section.clone())
+                    .unwrap_or_else(|| document.clone()),
+            };
+            self.parse_block(&parent);
+        }
+    }
+
+    /// When called, parses the block that starts at the current line, and appends it to `parent`.
+    fn parse_block(&mut self, parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>) {
+        let line = self.lines[self.position];
+        if line.is_blank() {
+            append_token(
+                parent,
+                SyntaxKind::BlankLine,
+                &format!("{}{}", line.content, line.ending),
+            );
+            self.position += 1;
+        } else if let Some(level) = line.heading_level() {
+            let heading = append_node(parent, SyntaxKind::Heading);
+            let content = &line.content[level..];
+            let marker_length = level + content.len() - content.trim_start().len();
+            append_token(
+                &heading,
+                SyntaxKind::HeadingMarker,
+                &line.content[..marker_length],
+            );
+            parse_inline(&heading, &line.content[marker_length..]);
+            self.append_line_ending(&heading, line);
+            self.position += 1;
+        } else if let Some(marker) = line.fence_marker() {
+            self.parse_code_fence(parent, marker);
+        } else if line.is_block_quote() {
+            let block_quote = append_node(parent, SyntaxKind::BlockQuote);
+            while let Some(line) = self.lines.get(self.position).copied() {
+                if !line.is_block_quote() {
+                    break;
+                }
+                let content = &line.content[1..];
+                let marker_length = 1 + content.len() - content.trim_start().len();
+                append_token(
+                    &block_quote,
+                    SyntaxKind::QuoteMarker,
+                    &line.content[..marker_length],
+                );
+                parse_inline(&block_quote, &line.content[marker_length..]);
+                self.append_line_ending(&block_quote, line);
+                self.position += 1;
+            }
+        } else if let Some((indent, _)) = line.list_marker() {
+            self.parse_list(parent, indent.len());
+        } else {
+            let paragraph = append_node(parent, SyntaxKind::Paragraph);
+            let start = self.position;
+            while let Some(line) = self.lines.get(self.position).copied() {
+                if line.is_blank() || (line.starts_block() && self.position > start) {
+                    break;
+                }
+                parse_inline(&paragraph, line.content);
+                self.append_line_ending(&paragraph, line);
+                self.position += 1;
+            }
+        }
+    }
+
+    fn parse_code_fence(&mut self, parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>, marker: &str) {
+        let line = self.lines[self.position];
+        self.position += 1;
+
+        let code_fence = append_node(parent, SyntaxKind::CodeFence);
+        let marker_end = line.content.find(marker).unwrap_or(0) + marker.len();
+        append_token(
+            &code_fence,
+            SyntaxKind::FenceMarker,
+            &line.content[..marker_end],
+        );
+        let info_string = &line.content[marker_end..];
+        append_token(&code_fence, SyntaxKind::InfoString, info_string);
+        self.append_line_ending(&code_fence, line);
+
+        // The fence is closed by a line with at least as many of the same fence characters, or
+        // the end of the document
+        let fence_char = marker.chars().next().unwrap_or('`');
+        let closing_line = self.lines[self.position..].iter().position(|line| {
+            let content = line.content.trim();
+            content.len() >= marker.len() && content.chars().all(|c| c == fence_char)
+        });
+        let code_end = closing_line
+            .map(|index| self.position + index)
+            .unwrap_or(self.lines.len());
+
+        let code = self.lines[self.position..code_end]
+            .iter()
+            .map(|line| format!("{}{}", line.content, line.ending))
+            .collect::<String>();
+        if !code.is_empty() {
+            match fence_parse_mode(info_string) {
+                Some(mode) => {
+                    copy_subtree(
+                        Some(&code_fence),
+                        &parse_typescript(&code, mode, None),
+                        &embed_metadata,
+                    );
+                }
+                None => append_token(&code_fence, SyntaxKind::Code, &code),
+            };
+        }
+        self.position = code_end;
+
+        if let Some(line) = self.lines.get(self.position).copied() {
+            append_token(&code_fence, SyntaxKind::FenceMarker, line.content);
+            self.append_line_ending(&code_fence, line);
+            self.position += 1;
+        }
+    }
+
+    /// When called, parses a list whose items are indented by `indent`. Items that are indented
+    /// further are parsed as a nested list within the item before them.
+    fn parse_list(&mut self, parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>, indent: usize) {
+        let list = append_node(parent, SyntaxKind::List);
+        let mut item: Option<Rc<RefCell<InMemoryNode<SyntaxKind>>>> = None;
+        while let Some(line) = self.lines.get(self.position).copied() {
+            if line.is_blank() {
+                // Blank lines between items are part of the list
+                let next_marker = self.lines[self.position..]
+                    .iter()
+                    .find(|line| !line.is_blank())
+                    .and_then(|line| line.list_marker());
+                if next_marker.is_none_or(|(next_indent, _)| next_indent.len() < indent) {
+                    break;
+                }
+                append_token(
+                    &list,
+                    SyntaxKind::BlankLine,
+                    &format!("{}{}", line.content, line.ending),
+                );
+                self.position += 1;
+                continue;
+            }
+
+            match (line.list_marker(), &item) {
+                (Some((line_indent, _)), _) if line_indent.len() < indent => break,
+                (Some((line_indent, _)), Some(item)) if line_indent.len() > indent => {
+                    self.parse_list(item, line_indent.len());
+                }
+                (Some((line_indent, marker)), _) => {
+                    let new_item = append_node(&list, SyntaxKind::ListItem);
+                    append_token(&new_item, SyntaxKind::Indent, line_indent);
+                    append_token(&new_item, SyntaxKind::ListMarker, marker);
+                    parse_inline(&new_item, &line.content[line_indent.len() + marker.len()..]);
+                    self.append_line_ending(&new_item, line);
+                    self.position += 1;
+                    item = Some(new_item);
+                }
+                // A line that doesn't start a new block continues the item before it
+                (None, Some(item)) if !line.starts_block() => {
+                    let line_indent = line.indent();
+                    append_token(item, SyntaxKind::Indent, line_indent);
+                    parse_inline(item, &line.content[line_indent.len()..]);
+                    self.append_line_ending(item, line);
+                    self.position += 1;
+                }
+                _ => break,
+            }
+        }
+    }
+}
+
+impl TokenKindTrait for SyntaxKind {
+    type ParseOptions = ();
+
+    fn language_name() -> &'static str {
+        "markdown"
+    }
+
+    fn kind_name(&self) -> String {
+        match self {
+            SyntaxKind::TypeScript(kind) => kind.kind_name(),
+            kind => format!("{kind:?}"),
+        }
+    }
+
+    // Embedded typescript has the same fields as it does in a typescript file
+    fn field_name(parent: &Self, children: &[Self], index: usize) -> Option<&'static str> {
+        let SyntaxKind::TypeScript(parent) = parent else {
+            return None;
+        };
+        let children = children
+            .iter()
+            .map(|kind| match kind {
+                SyntaxKind::TypeScript(kind) => Some(*kind),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+        typescript::SyntaxKind::field_name(parent, &children, index)
+    }
+
+    fn highlight_group(ancestry: std::vec::IntoIter<SyntaxKind>) -> Option<HighlightGroup> {
+        let ancestry = ancestry.collect::<Vec<_>>();
+
+        // Embedded typescript is highlighted by the typescript language
+        let typescript_ancestry = ancestry
+            .iter()
+            .map_while(|kind| match kind {
+                SyntaxKind::TypeScript(kind) => Some(*kind),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if !typescript_ancestry.is_empty() {
+            return typescript::SyntaxKind::highlight_group(typescript_ancestry.into_iter());
+        }
+
+        match ancestry.first()? {
+            SyntaxKind::HeadingMarker
+            | SyntaxKind::ListMarker
+            | SyntaxKind::QuoteMarker
+            | SyntaxKind::FenceMarker
+            | SyntaxKind::EmphasisMarker
+            | SyntaxKind::LinkMarker => Some(HighlightGroup::Punctuation),
+            SyntaxKind::InfoString => Some(HighlightGroup::Type),
+            SyntaxKind::Code | SyntaxKind::CodeSpan => Some(HighlightGroup::String),
+            SyntaxKind::LinkDestination => Some(HighlightGroup::String),
+            SyntaxKind::Text => {
+                // Text is highlighted based on the innermost node it is within that is styled
+                ancestry[1..].iter().find_map(|kind| match kind {
+                    SyntaxKind::Heading => Some(HighlightGroup::Keyword),
+                    SyntaxKind::Link => Some(HighlightGroup::Property),
+                    SyntaxKind::Emphasis | SyntaxKind::Strong => Some(HighlightGroup::Constant),
+                    SyntaxKind::BlockQuote => Some(HighlightGroup::Comment),
+                    _ => None,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    // The structure of a markdown document depends on the lines around each block (ie, a heading
+    // closes every section of a lower level after it), so only the document as a whole is
+    // reparsed. Embedded code is reparsed by its own language.
+    fn is_reparsable(&self) -> bool {
+        match self {
+            SyntaxKind::Document => true,
+            SyntaxKind::TypeScript(kind) => kind.is_reparsable(),
+            _ => false,
+        }
+    }
+
+    fn is_foldable(&self) -> bool {
+        match self {
+            SyntaxKind::Section
+            | SyntaxKind::List
+            | SyntaxKind::ListItem
+            | SyntaxKind::BlockQuote
+            | SyntaxKind::CodeFence => true,
+            SyntaxKind::TypeScript(kind) => kind.is_foldable(),
+            _ => false,
+        }
+    }
+
+    fn parse(
+        literal: &str,
+        parent: Option<Rc<RefCell<InMemoryNode<Self>>>>,
+    ) -> Rc<RefCell<InMemoryNode<Self>>> {
+        let parent_kind = parent
+            .as_ref()
+            .and_then(|parent| match parent.borrow().metadata {
+                NodeMetadata::AstNode { kind, .. } => Some(kind),
+                _ => None,
+            });
+
+        if let (Some(parent), Some(SyntaxKind::TypeScript(kind))) = (parent, parent_kind) {
+            // Reparsing code within a fence, so parse with the mode picked by the fence's info
+            // string
+            let mode = code_fence_info_string(&parent)
+                .and_then(|info_string| fence_parse_mode(&info_string))
+                .unwrap_or_default();
+            return copy_subtree(
+                None,
+                &parse_typescript(literal, mode, Some(kind)),
+                &embed_metadata,
+            );
+        }
+
+        let document = InMemoryNode::new_with_metadata(NodeMetadata::AstNode {
+            kind: SyntaxKind::Document,
+            literal: None,
+        });
+        Parser::new(literal).parse_document(&document);
+        document
+    }
+}
+
+/// When called, returns the info string of the code fence that `node` is within.
+fn code_fence_info_string(node: &Rc<RefCell<InMemoryNode<SyntaxKind>>>) -> Option<String> {
+    let mut pointer = node.clone();
+    loop {
+        let parent = pointer.borrow().parent.as_ref().and_then(|n| n.upgrade())?;
+        pointer = parent;
+        if matches!(
+            pointer.borrow().metadata,
+            NodeMetadata::AstNode {
+                kind: SyntaxKind::CodeFence,
+                ..
+            }
+        ) {
+            break;
+        }
+    }
+
+    let code_fence = pointer.borrow();
+    code_fence
+        .children
+        .iter()
+        .find_map(|child| match &child.borrow().metadata {
+            NodeMetadata::AstNode {
+                kind: SyntaxKind::InfoString,
+                literal,
+            } => literal.clone(),
+            _ => None,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SyntaxKind;
+    use crate::{
+        languages::{
+            test_utils::{child, child_kinds},
+            typescript,
+        },
+        node_tree::{
+            cursor::{Cursor, Selection},
+            node::{InMemoryNode, TokenKindTrait},
+            style::HighlightGroup,
+        },
+    };
+    use std::rc::Rc;
+
+    const DOCUMENT: &str = concat!(
+        "Some *intro* text, with a [link](https://example.com).\n",
+        "\n",
+        "# Title\n",
+        "\n",
+        "## Install\n",
+        "\n",
+        "- one `snake_case`\n",
+        "  continued\n",
+        "  - nested **bold**\n",
+        "- two\n",
+        "\n",
+        "> quoted\n",
+        "\n",
+        "```ts\n",
+        "let a: number = 1;\n",
+        "```\n",
+        "\n",
+        "# Other\n",
+        "~~~\n",
+        "plain\n",
+    );
+
+    #[test]
+    fn parses_blocks_into_sections() {
+        let root = InMemoryNode::<SyntaxKind>::new_from_parsed(DOCUMENT);
+        assert_eq!(InMemoryNode::deep_literal(&root), DOCUMENT);
+        assert!(InMemoryNode::diagnostics(&root).is_empty());
+
+        use SyntaxKind::*;
+        let document = child(&root, 0);
+        assert_eq!(
+            child_kinds(&document),
+            vec![Paragraph, BlankLine, Section, Section]
+        );
+        assert_eq!(
+            child_kinds(&child(&document, 0)),
+            vec![Text, Emphasis, Text, Link, Text, Newline]
+        );
+
+        // `## Install` is nested within `# Title`, and the unclosed fence runs to the end of the
+        // document
+        let title = child(&document, 2);
+        assert_eq!(child_kinds(&title), vec![Heading, BlankLine, Section]);
+        let install = child(&title, 2);
+        assert_eq!(
+            child_kinds(&install),
+            vec![Heading, BlankLine, List, BlankLine, BlockQuote, BlankLine, CodeFence, BlankLine]
+        );
+        assert_eq!(child_kinds(&child(&document, 3)), vec![Heading, CodeFence]);
+
+        let list = child(&install, 2);
+        assert_eq!(child_kinds(&list), vec![ListItem, ListItem]);
+        assert_eq!(
+            child_kinds(&child(&list, 0)),
+            vec![ListMarker, Text, CodeSpan, Newline, Indent, Text, Newline, List]
+        );
+
+        let code_fence = child(&install, 6);
+        assert_eq!(
+            child_kinds(&code_fence),
+            vec![
+                FenceMarker,
+                InfoString,
+                Newline,
+                TypeScript(typescript::SyntaxKind::MODULE),
+                FenceMarker,
+                Newline,
+            ]
+        );
+    }
+
+    #[test]
+    fn typescript_in_fences_is_highlighted_and_reparsed() {
+        let literal = "# Example\n\n```ts\nlet a = 1;\nfoo(a);\n```\n\nAfter *text*\n";
+        let root = InMemoryNode::<SyntaxKind>::new_from_parsed(literal);
+        let highlight_group_at = |position| {
+            let node = Cursor::new_at_rows_cols(root.clone(), position).node;
+            SyntaxKind::highlight_group(InMemoryNode::kind_ancestry(&node).into_iter())
+        };
+        assert_eq!(highlight_group_at((1, 3)), Some(HighlightGroup::Keyword));
+        assert_eq!(highlight_group_at((4, 1)), Some(HighlightGroup::Keyword));
+        assert_eq!(highlight_group_at((4, 9)), Some(HighlightGroup::Number));
+        assert_eq!(highlight_group_at((8, 8)), Some(HighlightGroup::Constant));
+
+        // Break the code in the fence
+        let paragraph = Cursor::new_at_rows_cols(root.clone(), (8, 2)).node;
+        Selection::new_from_cursor_pair(
+            Cursor::new_at_rows_cols(root.clone(), (4, 7)),
+            Cursor::new_at_rows_cols(root.clone(), (4, 10)),
+        )
+        .replace("= ")
+        .unwrap();
+        assert_eq!(
+            InMemoryNode::deep_literal(&root),
+            "# Example\n\n```ts\nlet a = ;\nfoo(a);\n```\n\nAfter *text*\n"
+        );
+
+        // Only the code was reparsed, and its errors are reported in the right place
+        assert!(Rc::ptr_eq(
+            &Cursor::new_at_rows_cols(root.clone(), (8, 2)).node,
+            &paragraph
+        ));
+        let diagnostics = InMemoryNode::diagnostics(&root);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].range, 25..26);
+        assert_eq!(highlight_group_at((4, 1)), Some(HighlightGroup::Keyword));
+    }
+}
@@ -1,2 +1,278 @@
+pub mod brackets;
+pub mod css;
+pub mod injection;
+pub mod json;
+pub mod markdown;
 pub mod raw;
+pub mod registry;
+#[cfg(test)]
+mod test_utils;
+pub mod toml;
 pub mod typescript;
+pub mod yaml;
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        languages::{self, typescript::ParseMode},
+        node_tree::node::{InMemoryNode, ParseOptionsTrait, TokenKindTrait},
+    };
+    use std::path::Path;
+
+    /// Statements that the generated documents are assembled from. These have been picked to
+    /// exercise the parts of a document that are easy to lose when parsing - multibyte characters,
+    /// comments, and syntax that doesn't parse.
+    const STATEMENTS: &[&str] = &[
+        "let foo = \"héllo wörld\";",
+        "const bar = [1, 2.5, '🦀'];",
+        "function baz(a, b) { return a + b; }",
+        "foo.bar(`a ${b} c`);",
+        "// 日本語 comment",
+        "/* block\ncomment */",
+        "if (foo == 1) { bar(); } else { baz(); }",
+        "let = ;",
+        "}",
+    ];
+
+    /// Whitespace that is placed between each statement, including both kinds of line endings.
+    const SEPARATORS: &[&str] = &["", " ", "\t", "\n", "\r\n", "\n\n", "\r\n\r\n", "  \n  "];
+
+    /// A small xorshift pseudo random number generator, so that the generated documents are the
+    /// same on every test run.
+    struct Rng(u64);
+    impl Rng {
+        fn next(&mut self, max: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % max as u64) as usize
+        }
+    }
+
+    fn generate_document(rng: &mut Rng) -> String {
+        let mut document = String::new();
+        if rng.next(4) == 0 {
+            document.push('\u{feff}');
+        }
+        for _ in 0..rng.next(12) {
+            document.push_str(STATEMENTS[rng.next(STATEMENTS.len())]);
+            document.push_str(SEPARATORS[rng.next(SEPARATORS.len())]);
+        }
+        document
+    }
+
+    fn assert_round_trip<TokenKind: TokenKindTrait>(literal: &str) {
+        assert_round_trip_with_options::<TokenKind>(literal, Default::default());
+    }
+
+    fn assert_round_trip_with_options<TokenKind: TokenKindTrait>(
+        literal: &str,
+        options: TokenKind::ParseOptions,
+    ) {
+        let root = InMemoryNode::<TokenKind>::new_from_parsed_with_options(literal, options);
+        assert_eq!(
+            InMemoryNode::deep_literal(&root).as_bytes(),
+            literal.as_bytes(),
+            "parse -> deep_literal did not reproduce {literal:?}"
+        );
+    }
+
+    #[test]
+    fn round_trip_is_byte_exact() {
+        let documents = [
+            "",
+            "\n",
+            "let foo = 1;",
+            "let foo = 1;\r\nlet bar = 2;\r\n",
+            "\u{feff}let foo = 1;\r\nlet bar = 2;",
+            "let foo = \"héllo wörld\";\n// 日本語\n",
+            // NOTE: this input causes rslint_parser to panic
+            "[}\\[1/* block */.  leté",
+        ];
+        for document in documents {
+            assert_round_trip::<languages::typescript::SyntaxKind>(document);
+            assert_round_trip::<languages::injection::TypeScriptWithCss>(document);
+            assert_round_trip::<languages::raw::SyntaxKind>(document);
+            assert_round_trip::<languages::registry::AnySyntaxKind>(document);
+            assert_round_trip::<languages::json::SyntaxKind>(document);
+            assert_round_trip::<languages::markdown::SyntaxKind>(document);
+            assert_round_trip::<languages::brackets::SyntaxKind>(document);
+            assert_round_trip::<languages::css::SyntaxKind>(document);
+            assert_round_trip::<languages::toml::SyntaxKind>(document);
+            assert_round_trip::<languages::yaml::SyntaxKind>(document);
+        }
+
+        let mut rng = Rng(0x2545f4914f6cdd1d);
+        for _ in 0..250 {
+            let document = generate_document(&mut rng);
+            assert_round_trip::<languages::typescript::SyntaxKind>(&document);
+            assert_round_trip::<languages::injection::TypeScriptWithCss>(&document);
+            assert_round_trip::<languages::raw::SyntaxKind>(&document);
+            assert_round_trip::<languages::json::SyntaxKind>(&document);
+            assert_round_trip::<languages::markdown::SyntaxKind>(&document);
+            assert_round_trip::<languages::brackets::SyntaxKind>(&document);
+            assert_round_trip::<languages::css::SyntaxKind>(&document);
+            assert_round_trip::<languages::toml::SyntaxKind>(&document);
+            assert_round_trip::<languages::yaml::SyntaxKind>(&document);
+        }
+    }
+
+    #[test]
+    fn json_round_trip_is_byte_exact() {
+        let documents = [
+            "{}",
+            "\u{feff}{\"a\": 1}\r\n",
+            "{\n  \"héllo\": \"wörld 🦀\",\n  \"list\": [1, -2.5e+3, true, false, null]\n}\n",
+            "[[[]], {\"a\": {\"b\": [{}]}}]",
+            "{\"escaped \\\" quote\": \"\\u00e9\"}",
+            // Malformed documents
+            "{\"a\": [1, 2}",
+            "{\"a\" 1, b: 'c',}",
+            "]]}} {{[[",
+            "\"unterminated\n[1 2 3]",
+            "1 2 3",
+        ];
+        for document in documents {
+            assert_round_trip::<languages::json::SyntaxKind>(document);
+            assert_round_trip::<languages::markdown::SyntaxKind>(document);
+        }
+    }
+
+    /// Real world TypeScript files, using syntax that a plain javascript parser can't handle -
+    /// imports and exports, type annotations, interfaces, generics, enums, namespaces, etc.
+    const TYPESCRIPT_FILES: &[(&str, &str)] = &[
+        ("shapes.ts", include_str!("fixtures/shapes.ts")),
+        ("store.ts", include_str!("fixtures/store.ts")),
+        ("button.tsx", include_str!("fixtures/button.tsx")),
+    ];
+
+    #[test]
+    fn typescript_files_round_trip_in_each_parse_mode() {
+        for (name, literal) in TYPESCRIPT_FILES {
+            let mode = ParseMode::from_path(Path::new(name));
+            for mode in [
+                mode,
+                ParseMode::Script,
+                ParseMode::Module,
+                ParseMode::TypeScript,
+                ParseMode::Tsx,
+            ] {
+                assert_round_trip_with_options::<languages::typescript::SyntaxKind>(literal, mode);
+            }
+        }
+    }
+
+    #[test]
+    fn typescript_files_parse_without_errors() {
+        let diagnostic_count = |literal: &str, mode: ParseMode| {
+            let root =
+                InMemoryNode::<languages::typescript::SyntaxKind>::new_from_parsed_with_options(
+                    literal, mode,
+                );
+            InMemoryNode::diagnostics(&root).len()
+        };
+
+        for (name, literal) in TYPESCRIPT_FILES {
+            let mode = ParseMode::from_path(Path::new(name));
+            if mode == ParseMode::Tsx {
+                // JSX isn't supported by rslint_parser, but the rest of the file still parses
+                continue;
+            }
+            assert_eq!(mode, ParseMode::TypeScript);
+            assert_eq!(
+                diagnostic_count(literal, mode),
+                0,
+                "{name} has parse errors"
+            );
+            assert!(diagnostic_count(literal, ParseMode::Script) > 0);
+        }
+
+        assert_eq!(diagnostic_count("import a from 'a';", ParseMode::Module), 0);
+        assert!(diagnostic_count("import a from 'a';", ParseMode::Script) > 0);
+    }
+
+    #[test]
+    fn markdown_round_trip_is_byte_exact() {
+        let documents = [
+            "# Title\r\n\r\nSome *text* with **bold**, `code`, and [a link](url).\r\n",
+            "\u{feff}## Héllo 🦀\n- one\n  - two\n    3. three\n\n- four",
+            "> quote\n>no space\n\n```js\nimport a from 'a';\n```\n",
+            "```ts\nlet = ;\n",
+            "~~~python\nprint('hi')\n~~~\n\n```\n\n```",
+            "*unclosed **emphasis [link](",
+            "######no heading\n####### also not\n#\n",
+        ];
+        for document in documents {
+            assert_round_trip::<languages::markdown::SyntaxKind>(document);
+        }
+    }
+
+    #[test]
+    fn brackets_round_trip_is_byte_exact() {
+        let documents = [
+            "#include <stdio.h>\r\n\r\nint main(void) {\r\n  printf(\"%d\\n\", 1);\r\n}\r\n",
+            "\u{feff}#ifdef DEBUG\n#  define LOG 1\n#elif X\n#else\n/* héllo */\n#endif\n",
+            "fn foo<'a>(x: &'a str) -> char { 'b' }",
+            "#!/bin/sh\n# don't\necho \"$(ls [a-z]*)\" 'single' # 🦀\n",
+            "package main\n\nfunc main() { fmt.Println(\"unterminated) }\n",
+            "]]}} {{[[ (]) /* unclosed",
+            "#endif #else",
+        ];
+        for document in documents {
+            assert_round_trip::<languages::brackets::SyntaxKind>(document);
+        }
+    }
+
+    #[test]
+    fn css_round_trip_is_byte_exact() {
+        let documents = [
+            "\u{feff}a{color:red}\r\n",
+            "@charset \"utf-8\";\n@media (min-width: 10em) and print {\n  .héllo::after { content: '🦀'; }\n}\n",
+            "a[href^='http'] > b ~ c + d { background: url( a b.png ) no-repeat, -1.5e3px .5% }",
+            "$map: (a: 1, b: (c: 2));\n.x { &-y { #{$p}: 1 !global } }",
+            ".sm\\:flex { --custom: { a }; }",
+            // Malformed stylesheets
+            "a { b: c(1; } } {{ ]) /* unclosed",
+            "@media { a { b",
+            "\"unterminated\n'also\\",
+            "#{unclosed",
+        ];
+        for document in documents {
+            assert_round_trip::<languages::css::SyntaxKind>(document);
+        }
+    }
+
+    #[test]
+    fn toml_round_trip_is_byte_exact() {
+        let documents = [
+            "\u{feff}title = \"héllo 🦀\"\r\n\r\n[package]\r\nname = 'a'\r\n",
+            "[dependencies]\nfoo = { version = \"1.0\", features = [\"a\", \"b\"] }\n\n[[bin]]\nname = \"x\"",
+            "multi = \"\"\"\nline\n\"\"\"\nliteral = '''\nraw\n'''\n# comment",
+            "dates = [1979-05-27T07:32:00Z, 1979-05-27, 07:32:00]\nnum = [+1_000, 0xff, 6.02e23, inf]",
+            // Malformed documents
+            "a = [1, 2\n[b]",
+            "a = {b = 1\n[c",
+            "= 1\nb =\n\"unterminated\n[[]]",
+        ];
+        for document in documents {
+            assert_round_trip::<languages::toml::SyntaxKind>(document);
+        }
+    }
+
+    #[test]
+    fn yaml_round_trip_is_byte_exact() {
+        let documents = [
+            "\u{feff}name: héllo 🦀\r\nlist:\r\n  - a\r\n  - b: 1\r\n    c: 2\r\n",
+            "%YAML 1.2\n---\nanchor: &a { x: 1, y: [1, 2] }\nalias: *a\n...\n--- second\n",
+            "text: |\n  line one\n\n  # not a comment\nfolded: >-\n  a\n  b\n\n# trailing\n",
+            "key: plain\n  continued # comment\nquoted: \"multi\n  line\"\nsingle: 'it''s'",
+            // Malformed documents
+            "a: [1, 2\nb: {c: d",
+            "a: 1\n   b: 2\nc\n- d\n  \"unterminated\n",
+            ": : :\n- - -\n[}]{",
+        ];
+        for document in documents {
+            assert_round_trip::<languages::yaml::SyntaxKind>(document);
+        }
+    }
+}
@@ -1,7 +1,9 @@
-use colored::ColoredString;
 use std::{cell::RefCell, rc::Rc};
 
-use crate::node_tree::node::{InMemoryNode, TokenKindTrait};
+use crate::node_tree::{
+    node::{InMemoryNode, TokenKindTrait},
+    style::HighlightGroup,
+};
 
 /// The size of characters from the literal stored in each ast node.
 const RAW_LITERAL_NODE_CHUNK_SIZE_CHARS: usize = 32;
@@ -12,11 +14,14 @@ const RAW_LITERAL_NODE_CHUNK_SIZE_CHARS: usize = 32;
 pub enum SyntaxKind {}
 
 impl TokenKindTrait for SyntaxKind {
-    fn apply_debug_syntax_color(
-        text: String,
-        _ancestry: std::vec::IntoIter<SyntaxKind>,
-    ) -> ColoredString {
-        text.into()
+    type ParseOptions = ();
+
+    fn language_name() -> &'static str {
+        "raw"
+    }
+
+    fn highlight_group(_ancestry: std::vec::IntoIter<SyntaxKind>) -> Option<HighlightGroup> {
+        None
     }
 
     // Any node can be reparsed, since a reparse is effectively a noop at the moment
@@ -0,0 +1,805 @@
+use std::{cell::RefCell, path::Path, rc::Rc, sync::OnceLock};
+
+use crate::{
+    languages::{
+        brackets, css,
+        injection::{copy_subtree, TypeScriptWithCss},
+        json, markdown, raw, toml,
+        typescript::ParseMode,
+        yaml,
+    },
+    node_tree::{
+        node::{InMemoryNode, NodeMetadata, ParseOptionsTrait, TokenKindTrait},
+        style::HighlightGroup,
+    },
+};
+
+// Like vim's `modelines` option, only this many lines at the start and end of a file are checked
+// for a modeline
+const MODELINES: usize = 5;
+
+/// When called, defines AnySyntaxKind, with a variant wrapping the token kind of each language,
+/// and implements `Variant` for each of those token kinds.
+macro_rules! any_syntax_kind {
+    ($(#[$meta:meta])* $($variant:ident($kind:ty)),* $(,)?) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq)]
+        pub enum AnySyntaxKind {
+            $($variant($kind)),*
+        }
+
+        $(
+            impl Variant for $kind {
+                fn wrap(self) -> AnySyntaxKind {
+                    AnySyntaxKind::$variant(self)
+                }
+                fn unwrap(kind: AnySyntaxKind) -> Option<Self> {
+                    match kind {
+                        AnySyntaxKind::$variant(kind) => Some(kind),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+/// When called, matches `$kind` (an AnySyntaxKind) against `$inner` (a pattern for the kind it
+/// wraps), and evaluates `$body` with `$V` as the token kind of the wrapped kind's language.
+macro_rules! match_variant {
+    ($kind:expr, $inner:pat, $V:ident => $body:expr) => {
+        match $kind {
+            AnySyntaxKind::TypeScript($inner) => {
+                type $V = TypeScriptWithCss;
+                $body
+            }
+            AnySyntaxKind::Json($inner) => {
+                type $V = json::SyntaxKind;
+                $body
+            }
+            AnySyntaxKind::Markdown($inner) => {
+                type $V = markdown::SyntaxKind;
+                $body
+            }
+            AnySyntaxKind::Css($inner) => {
+                type $V = css::SyntaxKind;
+                $body
+            }
+            AnySyntaxKind::Toml($inner) => {
+                type $V = toml::SyntaxKind;
+                $body
+            }
+            AnySyntaxKind::Yaml($inner) => {
+                type $V = yaml::SyntaxKind;
+                $body
+            }
+            AnySyntaxKind::Brackets($inner) => {
+                type $V = brackets::SyntaxKind;
+                $body
+            }
+            AnySyntaxKind::Raw($inner) => {
+                type $V = raw::SyntaxKind;
+                $body
+            }
+        }
+    };
+}
+
+/// When called, matches `$filetype` and evaluates `$body` with `$V` as the token kind of the
+/// filetype's language, and `$options` bound to the options it is parsed with.
+macro_rules! match_filetype {
+    ($filetype:expr, $options:pat, $V:ident => $body:expr) => {
+        match $filetype {
+            Filetype::TypeScript($options) => {
+                type $V = TypeScriptWithCss;
+                $body
+            }
+            Filetype::Json => {
+                type $V = json::SyntaxKind;
+                let $options = ();
+                $body
+            }
+            Filetype::Markdown => {
+                type $V = markdown::SyntaxKind;
+                let $options = ();
+                $body
+            }
+            Filetype::Css => {
+                type $V = css::SyntaxKind;
+                let $options = ();
+                $body
+            }
+            Filetype::Toml => {
+                type $V = toml::SyntaxKind;
+                let $options = ();
+                $body
+            }
+            Filetype::Yaml => {
+                type $V = yaml::SyntaxKind;
+                let $options = ();
+                $body
+            }
+            Filetype::Brackets => {
+                type $V = brackets::SyntaxKind;
+                let $options = ();
+                $body
+            }
+            Filetype::Raw => {
+                type $V = raw::SyntaxKind;
+                let $options = ();
+                $body
+            }
+        }
+    };
+}
+
+/// The language that a document is parsed as, ie `json`. TypeScript documents are also parsed in
+/// one of a few modes, picked from their extension (see `ParseMode`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filetype {
+    TypeScript(ParseMode),
+    Json,
+    Markdown,
+    Css,
+    Toml,
+    Yaml,
+    Brackets,
+    Raw,
+}
+
+impl Default for Filetype {
+    fn default() -> Self {
+        Filetype::TypeScript(ParseMode::default())
+    }
+}
+
+impl Filetype {
+    /// When called, returns the name of the language, as returned by its
+    /// `TokenKindTrait::language_name`.
+    pub fn language_name(&self) -> &'static str {
+        match_filetype!(*self, _, V => V::language_name())
+    }
+}
+
+impl ParseOptionsTrait for Filetype {
+    /// When called, picks the language from the file's name. Files that aren't in any known
+    /// language are parsed with `languages::brackets`, which gives most source code some
+    /// structure without knowing its grammar.
+    fn from_path(path: &Path) -> Self {
+        LanguageRegistry::builtin()
+            .detect(Some(path), "")
+            .unwrap_or(Filetype::Brackets)
+    }
+
+    fn detect(path: &Path, literal: &str) -> Self {
+        LanguageRegistry::builtin()
+            .detect(Some(path), literal)
+            .unwrap_or(Filetype::Brackets)
+    }
+
+    fn from_filetype(filetype: &str) -> Option<Self> {
+        LanguageRegistry::builtin()
+            .find(filetype)
+            .map(|language| language.filetype)
+    }
+
+    fn filetype(&self) -> Option<&'static str> {
+        Some(self.language_name())
+    }
+
+    // Only TypeScript has parse modes, so setting one switches the document to TypeScript
+    fn from_name(name: &str) -> Option<Self> {
+        ParseMode::from_name(name).map(Filetype::TypeScript)
+    }
+
+    fn name(&self) -> String {
+        match self {
+            Filetype::TypeScript(mode) => mode.name(),
+            _ => ().name(),
+        }
+    }
+}
+
+/// A Language that documents can be detected as being written in, along with how to detect it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Language {
+    pub name: &'static str,
+    pub filetype: Filetype,
+
+    // Other names the language can be picked with, ie, in `:set filetype=js` or a modeline
+    pub aliases: &'static [&'static str],
+
+    // Files with these extensions (or with exactly these names) are written in the language
+    pub extensions: &'static [&'static str],
+    pub file_names: &'static [&'static str],
+
+    // Files starting with a shebang that runs one of these programs are written in the language,
+    // ie, `node` for `#!/usr/bin/env node`
+    pub interpreters: &'static [&'static str],
+}
+
+/// A LanguageRegistry maps the files opened in the editor to the language they should be parsed
+/// as, from (in order of precedence) a modeline like `vim: set ft=json:`, the file's extension or
+/// name, or a shebang on its first line.
+#[derive(Debug, Clone)]
+pub struct LanguageRegistry {
+    languages: Vec<Language>,
+}
+
+impl Default for LanguageRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LanguageRegistry {
+    /// When called, creates a registry containing all of the languages built in to the editor.
+    pub fn new() -> Self {
+        Self {
+            languages: vec![
+                Language {
+                    name: "raw",
+                    filetype: Filetype::Raw,
+                    aliases: &["text"],
+                    extensions: &["txt"],
+                    file_names: &[],
+                    interpreters: &[],
+                },
+                Language {
+                    name: "brackets",
+                    filetype: Filetype::Brackets,
+                    aliases: &[
+                        "c", "cpp", "go", "rust", "java", "sh", "bash", "zsh", "python", "make",
+                    ],
+                    extensions: &[
+                        "c", "h", "cc", "cpp", "cxx", "hpp", "hh", "go", "rs", "java", "kt",
+                        "scala", "swift", "cs", "php", "sh", "bash", "zsh", "zig", "proto", "py",
+                        "pyi", "mk",
+                    ],
+                    file_names: &["Makefile", "makefile", "GNUmakefile"],
+                    interpreters: &["sh", "bash", "zsh", "dash", "ksh", "php", "python", "make"],
+                },
+                Language {
+                    name: "typescript",
+                    filetype: Filetype::TypeScript(ParseMode::TypeScript),
+                    aliases: &[
+                        "ts",
+                        "tsx",
+                        "typescriptreact",
+                        "javascript",
+                        "js",
+                        "jsx",
+                        "javascriptreact",
+                    ],
+                    extensions: &["ts", "mts", "cts", "tsx", "js", "cjs", "mjs", "jsx"],
+                    file_names: &[],
+                    interpreters: &["node", "nodejs", "deno", "bun", "ts-node", "tsx"],
+                },
+                Language {
+                    name: "json",
+                    filetype: Filetype::Json,
+                    aliases: &["jsonc"],
+                    extensions: &["json", "jsonc", "webmanifest"],
+                    file_names: &[".babelrc"],
+                    interpreters: &[],
+                },
+                Language {
+                    name: "markdown",
+                    filetype: Filetype::Markdown,
+                    aliases: &["md"],
+                    extensions: &["md", "markdown", "mkd"],
+                    file_names: &[],
+                    interpreters: &[],
+                },
+                Language {
+                    name: "css",
+                    filetype: Filetype::Css,
+                    aliases: &["scss"],
+                    extensions: &["css", "scss"],
+                    file_names: &[],
+                    interpreters: &[],
+                },
+                Language {
+                    name: "toml",
+                    filetype: Filetype::Toml,
+                    aliases: &[],
+                    extensions: &["toml"],
+                    file_names: &["Cargo.lock", "Pipfile"],
+                    interpreters: &[],
+                },
+                Language {
+                    name: "yaml",
+                    filetype: Filetype::Yaml,
+                    aliases: &["yml"],
+                    extensions: &["yaml", "yml"],
+                    file_names: &[],
+                    interpreters: &[],
+                },
+            ],
+        }
+    }
+
+    /// When called, returns the registry of all of the languages built in to the editor (see
+    /// `new`), which is only created the first time it is needed, rather than each time a file's
+    /// language is detected.
+    pub fn builtin() -> &'static Self {
+        static BUILTIN: OnceLock<LanguageRegistry> = OnceLock::new();
+        BUILTIN.get_or_init(Self::new)
+    }
+
+    /// When called, returns the language with the given name or alias, ie, `js`.
+    pub fn find(&self, name: &str) -> Option<&Language> {
+        self.languages
+            .iter()
+            .rev()
+            .find(|language| language.name == name || language.aliases.contains(&name))
+    }
+
+    /// When called, picks the language that a document should be parsed as, from its contents and
+    /// the path it was read from. Returns None if the document isn't in any known language.
+    pub fn detect(&self, path: Option<&Path>, literal: &str) -> Option<Filetype> {
+        if let Some(language) = modeline_filetype(literal).and_then(|name| self.find(name)) {
+            return Some(language.filetype);
+        }
+
+        if let Some(path) = path {
+            let file_name = path.file_name().and_then(|name| name.to_str());
+            let extension = path
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .map(|extension| extension.to_lowercase());
+            let language = self.languages.iter().rev().find(|language| {
+                file_name.is_some_and(|name| language.file_names.contains(&name))
+                    || extension
+                        .as_deref()
+                        .is_some_and(|extension| language.extensions.contains(&extension))
+            });
+            if let Some(language) = language {
+                return Some(match language.filetype {
+                    // Javascript files are parsed as scripts or modules depending on their
+                    // extension, too
+                    Filetype::TypeScript(_) => Filetype::TypeScript(ParseMode::from_path(path)),
+                    filetype => filetype,
+                });
+            }
+        }
+
+        let interpreter = shebang_interpreter(literal)?;
+        // Versioned interpreters (ie, `bash5`) are detected the same as unversioned ones
+        let unversioned = interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+        self.languages
+            .iter()
+            .rev()
+            .find(|language| {
+                language.interpreters.contains(&interpreter)
+                    || language.interpreters.contains(&unversioned)
+            })
+            .map(|language| language.filetype)
+    }
+}
+
+/// When called, returns the name of the program that the shebang on the first line of `literal`
+/// runs, ie, `node` for both `#!/usr/local/bin/node` and `#!/usr/bin/env -S node --flag`.
+fn shebang_interpreter(literal: &str) -> Option<&str> {
+    let line = literal
+        .trim_start_matches('\u{feff}')
+        .lines()
+        .next()?
+        .strip_prefix("#!")?;
+    let mut words = line.split_whitespace();
+    let program = words.next()?.rsplit('/').next()?;
+    if program != "env" {
+        return Some(program);
+    }
+    // Skip over env's own flags and environment variables
+    words.find(|word| !word.starts_with('-') && !word.contains('='))
+}
+
+/// When called, returns the filetype set by a vim modeline in the first or last few lines of
+/// `literal`, ie, `json` for `// vim: set ft=json:` or `# vim: filetype=json`.
+fn modeline_filetype(literal: &str) -> Option<&str> {
+    let lines = literal.lines().collect::<Vec<&str>>();
+    let head = MODELINES.min(lines.len());
+    let tail = lines.len().saturating_sub(MODELINES).max(head);
+    lines[..head]
+        .iter()
+        .chain(lines[tail..].iter())
+        .find_map(|line| parse_modeline(line))
+}
+
+fn parse_modeline(line: &str) -> Option<&str> {
+    let options = ["vim:", "Vim:", "vi:", "ex:"].iter().find_map(|marker| {
+        // Like vim, the marker must be at the start of the line or after whitespace, so that ie
+        // `ex:` doesn't match within `index:`
+        line.match_indices(marker)
+            .find(|(index, _)| *index == 0 || line[..*index].ends_with(char::is_whitespace))
+            .map(|(index, marker)| &line[index + marker.len()..])
+    })?;
+
+    // `vim: set ft=json:` ends at the next `:`, while `vim: ft=json` runs to the end of the line
+    let trimmed = options.trim_start();
+    let options = match trimmed
+        .strip_prefix("set ")
+        .or_else(|| trimmed.strip_prefix("se "))
+    {
+        Some(options) => options.split(':').next().unwrap_or(""),
+        None => options,
+    };
+    options
+        .split(|c: char| c == ':' || c.is_whitespace())
+        .find_map(|option| {
+            option
+                .strip_prefix("ft=")
+                .or_else(|| option.strip_prefix("filetype="))
+        })
+        .filter(|name| !name.is_empty())
+}
+
+any_syntax_kind! {
+    /// The kinds of nodes in a document of any of the languages built in to the editor, so that
+    /// buffers in different languages can be loaded at the same time. The language each document is
+    /// parsed as is picked by the `Filetype` it is parsed with, and can be changed at any time.
+    ///
+    /// Like `languages::injection::Injected`, this wraps the kinds of each language into one, and
+    /// parses documents by copying the nodes each language's parser generates.
+    TypeScript(TypeScriptWithCss),
+    Json(json::SyntaxKind),
+    Markdown(markdown::SyntaxKind),
+    Css(css::SyntaxKind),
+    Toml(toml::SyntaxKind),
+    Yaml(yaml::SyntaxKind),
+    Brackets(brackets::SyntaxKind),
+    Raw(raw::SyntaxKind),
+}
+
+/// The token kind of a language that can be wrapped into an AnySyntaxKind.
+trait Variant: TokenKindTrait {
+    fn wrap(self) -> AnySyntaxKind;
+    fn unwrap(kind: AnySyntaxKind) -> Option<Self>;
+}
+
+/// When called, converts the metadata of a node into the metadata of a node of another token kind.
+/// Nodes with kinds that can't be converted are kept as plain text.
+fn convert_metadata<From: TokenKindTrait, To: TokenKindTrait>(
+    metadata: &NodeMetadata<From>,
+    convert_kind: impl Fn(From) -> Option<To>,
+) -> NodeMetadata<To> {
+    match metadata {
+        NodeMetadata::AstNode { kind, literal } => match convert_kind(kind.clone()) {
+            Some(kind) => NodeMetadata::AstNode {
+                kind,
+                literal: literal.clone(),
+            },
+            None => match literal {
+                Some(literal) => NodeMetadata::Literal(literal.clone()),
+                None => NodeMetadata::Fragment,
+            },
+        },
+        NodeMetadata::Literal(literal) => NodeMetadata::Literal(literal.clone()),
+        NodeMetadata::Fragment => NodeMetadata::Fragment,
+        NodeMetadata::Empty | NodeMetadata::Root(_) => NodeMetadata::Empty,
+    }
+}
+
+impl AnySyntaxKind {
+    /// When called, parses `literal` with the language `V`, within a copy of the nodes being
+    /// reparsed within (if there are any), and copies the result back into a subtree of wrapped
+    /// nodes.
+    fn parse_as<V: Variant>(
+        literal: &str,
+        parent: Option<Rc<RefCell<InMemoryNode<Self>>>>,
+        options: V::ParseOptions,
+    ) -> Rc<RefCell<InMemoryNode<Self>>> {
+        let root = InMemoryNode::new_with_metadata(NodeMetadata::Root(options));
+        let inner_parent = match parent {
+            Some(parent) => Self::copy_ancestry(&root, &parent),
+            None => root,
+        };
+        let node = V::parse(literal, Some(inner_parent));
+        copy_subtree(None, &node, &|metadata| {
+            convert_metadata(metadata, |kind: V| Some(kind.wrap()))
+        })
+    }
+
+    /// When called, copies `node` and its ancestors into the language `V`, under `root` in place
+    /// of the document's own root, and returns the copy of `node`.
+    ///
+    /// Parsers only look at the kinds of the nodes they are parsed within and at the tokens at
+    /// the start of them (ie, a block's opening bracket, or a code fence's info string), so only
+    /// those tokens are copied along with each node, rather than all of its descendants.
+    fn copy_ancestry<V: Variant>(
+        root: &Rc<RefCell<InMemoryNode<V>>>,
+        node: &Rc<RefCell<InMemoryNode<Self>>>,
+    ) -> Rc<RefCell<InMemoryNode<V>>> {
+        if matches!(node.borrow().metadata, NodeMetadata::Root(_)) {
+            return root.clone();
+        }
+        let parent = node
+            .borrow()
+            .parent
+            .as_ref()
+            .and_then(|parent| parent.upgrade());
+        let parent = match parent {
+            Some(parent) => Self::copy_ancestry(root, &parent),
+            None => root.clone(),
+        };
+
+        let convert = |node: &Rc<RefCell<InMemoryNode<Self>>>| {
+            InMemoryNode::new_with_metadata(convert_metadata(&node.borrow().metadata, V::unwrap))
+        };
+        let copy = InMemoryNode::append_child(&parent, convert(node));
+        for token in node
+            .borrow()
+            .children
+            .iter()
+            .take_while(|child| child.borrow().children.is_empty())
+        {
+            InMemoryNode::append_child(&copy, convert(token));
+        }
+        copy
+    }
+
+    fn field_name_as<V: Variant>(
+        parent: V,
+        children: &[Self],
+        index: usize,
+    ) -> Option<&'static str> {
+        let children = children
+            .iter()
+            .map(|kind| V::unwrap(kind.clone()))
+            .collect::<Option<Vec<V>>>()?;
+        V::field_name(&parent, &children, index)
+    }
+
+    fn highlight_group_as<V: Variant>(ancestry: Vec<Self>) -> Option<HighlightGroup> {
+        let ancestry = ancestry
+            .into_iter()
+            .map_while(V::unwrap)
+            .collect::<Vec<V>>();
+        V::highlight_group(ancestry.into_iter())
+    }
+
+    fn is_reparsable_node_as<V: Variant, Wrapper: TokenKindTrait>(
+        node: &Rc<RefCell<InMemoryNode<Wrapper>>>,
+        unwrap: &dyn Fn(&Wrapper) -> Option<Self>,
+    ) -> bool {
+        V::is_reparsable_node(node, &|kind| unwrap(kind).and_then(V::unwrap))
+    }
+
+    fn key_path_segments_as<V: Variant, Wrapper: TokenKindTrait>(
+        node: &Rc<RefCell<InMemoryNode<Wrapper>>>,
+        unwrap: &dyn Fn(&Wrapper) -> Option<Self>,
+    ) -> Vec<String> {
+        V::key_path_segments(node, &|kind| unwrap(kind).and_then(V::unwrap))
+    }
+}
+
+impl TokenKindTrait for AnySyntaxKind {
+    type ParseOptions = Filetype;
+
+    fn language_name() -> &'static str {
+        "any"
+    }
+
+    fn highlight_group(ancestry: std::vec::IntoIter<Self>) -> Option<HighlightGroup> {
+        let ancestry = ancestry.collect::<Vec<_>>();
+        match_variant!(ancestry.first()?, _, V => Self::highlight_group_as::<V>(ancestry))
+    }
+
+    fn kind_name(&self) -> String {
+        match_variant!(self, kind, V => V::kind_name(kind))
+    }
+
+    fn field_name(parent: &Self, children: &[Self], index: usize) -> Option<&'static str> {
+        match_variant!(parent.clone(), kind, V => Self::field_name_as::<V>(kind, children, index))
+    }
+
+    fn is_reparsable(&self) -> bool {
+        match_variant!(self, kind, V => V::is_reparsable(kind))
+    }
+
+    fn is_reparsable_node<Wrapper: TokenKindTrait>(
+        node: &Rc<RefCell<InMemoryNode<Wrapper>>>,
+        unwrap: &dyn Fn(&Wrapper) -> Option<Self>,
+    ) -> bool {
+        let kind = match &node.borrow().metadata {
+            NodeMetadata::AstNode { kind, .. } => unwrap(kind),
+            _ => None,
+        };
+        let Some(kind) = kind else {
+            return false;
+        };
+        match_variant!(kind, _, V => Self::is_reparsable_node_as::<V, _>(node, unwrap))
+    }
+
+    fn is_foldable(&self) -> bool {
+        match_variant!(self, kind, V => V::is_foldable(kind))
+    }
+
+    fn parse(
+        literal: &str,
+        parent: Option<Rc<RefCell<InMemoryNode<Self>>>>,
+    ) -> Rc<RefCell<InMemoryNode<Self>>> {
+        let filetype = parent
+            .as_ref()
+            .map(InMemoryNode::parse_options)
+            .unwrap_or_default();
+        match_filetype!(filetype, options, V => Self::parse_as::<V>(literal, parent, options))
+    }
+
+    fn outline_query(filetype: &Filetype) -> Option<&'static str> {
+        match_filetype!(*filetype, options, V => V::outline_query(&options))
+    }
+
+    fn key_path_segments<Wrapper: TokenKindTrait>(
+        node: &Rc<RefCell<InMemoryNode<Wrapper>>>,
+        unwrap: &dyn Fn(&Wrapper) -> Option<Self>,
+    ) -> Vec<String> {
+        let kind = match &node.borrow().metadata {
+            NodeMetadata::AstNode { kind, .. } => unwrap(kind),
+            _ => None,
+        };
+        let Some(kind) = kind else {
+            return vec![];
+        };
+        match_variant!(kind, _, V => Self::key_path_segments_as::<V, _>(node, unwrap))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AnySyntaxKind, Filetype, LanguageRegistry};
+    use crate::{
+        editor::{buffer::Buffer, buffer_set::BufferSet},
+        languages::{json, typescript::ParseMode},
+        node_tree::{
+            cursor::{Cursor, Selection},
+            node::{InMemoryNode, NodeMetadata, ParseOptionsTrait},
+        },
+    };
+    use std::{cell::RefCell, path::Path, rc::Rc};
+
+    fn count_kind(node: &Rc<RefCell<InMemoryNode<AnySyntaxKind>>>, kind: &AnySyntaxKind) -> usize {
+        let is_kind = matches!(&node.borrow().metadata, NodeMetadata::AstNode { kind: node_kind, .. } if node_kind == kind);
+        let children = node.borrow().children.clone();
+        children.iter().fold(is_kind as usize, |count, child| {
+            count + count_kind(child, kind)
+        })
+    }
+
+    #[test]
+    fn builtin_registry_is_only_created_once() {
+        assert!(std::ptr::eq(
+            LanguageRegistry::builtin(),
+            LanguageRegistry::builtin()
+        ));
+        assert_eq!(
+            LanguageRegistry::builtin()
+                .find("js")
+                .map(|language| language.name),
+            Some("typescript")
+        );
+    }
+
+    #[test]
+    fn detects_filetypes() {
+        let registry = LanguageRegistry::new();
+        let detect = |path: &str, literal: &str| registry.detect(Some(Path::new(path)), literal);
+
+        // From the file's extension or name
+        assert_eq!(detect("a/b.json", ""), Some(Filetype::Json));
+        assert_eq!(detect("README.MD", ""), Some(Filetype::Markdown));
+        assert_eq!(detect("Cargo.lock", ""), Some(Filetype::Toml));
+        assert_eq!(
+            detect("index.js", ""),
+            Some(Filetype::TypeScript(ParseMode::Script))
+        );
+        assert_eq!(detect("Makefile", ""), Some(Filetype::Brackets));
+        assert_eq!(detect("src/main.py", ""), Some(Filetype::Brackets));
+        assert_eq!(detect("rules.mk", ""), Some(Filetype::Brackets));
+
+        // From a shebang, if the extension doesn't say
+        assert_eq!(
+            detect("bin/server", "#!/usr/bin/env -S node --flag\n"),
+            Some(Filetype::TypeScript(ParseMode::TypeScript))
+        );
+        assert_eq!(
+            detect("configure", "#!/bin/bash5\n"),
+            Some(Filetype::Brackets)
+        );
+        assert_eq!(
+            detect("script", "#!/usr/bin/python3\n"),
+            Some(Filetype::Brackets)
+        );
+        assert_eq!(
+            detect("build", "#!/usr/bin/make -f\n"),
+            Some(Filetype::Brackets)
+        );
+        assert_eq!(detect("notes", "#!/usr/bin/unknown\n"), None);
+
+        // From a modeline, which takes precedence over everything else
+        assert_eq!(
+            detect("a.txt", "{\"a\": 1}\n// vim: set ft=json:\n"),
+            Some(Filetype::Json)
+        );
+        assert_eq!(
+            detect("a.js", "# vim: filetype=yml ts=2\na: 1\n"),
+            Some(Filetype::Yaml)
+        );
+        assert_eq!(detect("a.txt", "index: ft=json\n"), Some(Filetype::Raw));
+        assert_eq!(registry.detect(None, "vi:ft=unknown"), None);
+
+        // Files that aren't in any known language still get some structure from their brackets
+        assert_eq!(
+            <Filetype as ParseOptionsTrait>::detect(Path::new("notes"), "a (b)\n"),
+            Filetype::Brackets
+        );
+    }
+
+    #[test]
+    fn buffers_in_different_languages_are_loaded_at_once() {
+        let mut buffers = BufferSet::new(Buffer::<AnySyntaxKind>::new_from_literal("let a = 1;"));
+        buffers.add(Buffer::new_from_literal_with_parse_options(
+            "{\"a\": [1, 2]}",
+            Filetype::Json,
+        ));
+        buffers.add(Buffer::new_from_literal_with_parse_options(
+            "a\nb",
+            Filetype::Raw,
+        ));
+        let languages = (1..=3)
+            .map(|number| buffers.get(number).unwrap().language())
+            .collect::<Vec<&str>>();
+        assert_eq!(languages, vec!["typescript", "json", "raw"]);
+
+        // Edits are reparsed by the buffer's own language
+        let buffer = buffers.get_mut(2).unwrap();
+        let root = buffer.root.clone();
+        Selection::new_from_cursor_pair(
+            Cursor::new_at_rows_cols(root.clone(), (1, 11)),
+            Cursor::new_at_rows_cols(root.clone(), (1, 12)),
+        )
+        .replace("2, {}")
+        .unwrap();
+        assert_eq!(buffer.literal(), "{\"a\": [1, 2, {}]}");
+        assert!(InMemoryNode::diagnostics(&root).is_empty());
+        let object_count = count_kind(&root, &AnySyntaxKind::Json(json::SyntaxKind::Object));
+        assert_eq!(object_count, 2);
+    }
+
+    #[test]
+    fn finds_key_paths() {
+        let mut buffer = Buffer::<AnySyntaxKind>::new_from_literal_with_parse_options(
+            "[package]\nname = \"a\"\n\n[[bin]]\nname = \"b\"\n\n[[bin]]\nname = \"c\"\n",
+            Filetype::Toml,
+        );
+        buffer.jump_to_key_path("bin.1.name").unwrap();
+        assert_eq!(buffer.cursor.to_rows_cols(), (8, 1));
+        let node = buffer.cursor.node.clone();
+        assert_eq!(InMemoryNode::key_path(&node), vec!["bin", "1", "name"]);
+    }
+
+    #[test]
+    fn changing_the_filetype_reparses_the_buffer() {
+        let mut buffer = Buffer::<AnySyntaxKind>::new_from_literal_with_parse_options(
+            "{\"a\": }",
+            Filetype::Raw,
+        );
+        assert_eq!(buffer.language(), "raw");
+        assert!(buffer.diagnostics().is_empty());
+
+        buffer
+            .set_parse_options(Filetype::from_filetype("jsonc").unwrap())
+            .unwrap();
+        assert_eq!(buffer.language(), "json");
+        assert_eq!(buffer.diagnostics().len(), 1);
+        assert_eq!(buffer.literal(), "{\"a\": }");
+
+        // Setting a parse mode switches to TypeScript
+        buffer
+            .set_parse_options(Filetype::from_name("module").unwrap())
+            .unwrap();
+        assert_eq!(buffer.language(), "typescript");
+        assert_eq!(buffer.parse_options().name(), "module");
+    }
+}
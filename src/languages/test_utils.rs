@@ -0,0 +1,40 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::node_tree::node::{InMemoryNode, NodeMetadata, TokenKindTrait};
+
+/// When called, returns the kinds of the AST node children of `node`, in order.
+pub fn child_kinds<TokenKind: TokenKindTrait>(
+    node: &Rc<RefCell<InMemoryNode<TokenKind>>>,
+) -> Vec<TokenKind> {
+    node.borrow()
+        .children
+        .iter()
+        .filter_map(|child| match &child.borrow().metadata {
+            NodeMetadata::AstNode { kind, .. } => Some(kind.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// When called, returns the child of `node` at `index`.
+pub fn child<TokenKind: TokenKindTrait>(
+    node: &Rc<RefCell<InMemoryNode<TokenKind>>>,
+    index: usize,
+) -> Rc<RefCell<InMemoryNode<TokenKind>>> {
+    node.borrow().children[index].clone()
+}
+
+/// When called, parses each of the given malformed documents, and asserts that the text of each
+/// is kept exactly as it was, with the given diagnostic messages pointing out what is wrong with
+/// it.
+pub fn assert_kept_with_diagnostics<TokenKind: TokenKindTrait>(documents: &[(&str, Vec<&str>)]) {
+    for (literal, messages) in documents {
+        let root = InMemoryNode::<TokenKind>::new_from_parsed(literal);
+        assert_eq!(InMemoryNode::deep_literal(&root), *literal);
+        let diagnostics = InMemoryNode::diagnostics(&root)
+            .into_iter()
+            .map(|diagnostic| diagnostic.message)
+            .collect::<Vec<_>>();
+        assert_eq!(diagnostics, *messages, "diagnostics for {literal:?}");
+    }
+}
@@ -0,0 +1,847 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::node_tree::{
+    diagnostic::{Diagnostic, Severity},
+    node::{InMemoryNode, NodeMetadata, TokenKindTrait},
+    style::HighlightGroup,
+    utils::unquote,
+};
+
+/// The kinds of nodes that make up a parsed TOML document.
+///
+/// Documents, tables, headers, keys, key / value pairs, arrays, and inline tables contain other
+/// nodes, and everything else is a token with its own literal text. Like `languages::json`,
+/// whitespace, newlines, and comments are kept in the tree as tokens, so the literal text of a
+/// parsed document is always exactly what was parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyntaxKind {
+    Document,
+    Table,            // A header, followed by every key / value pair up until the next header
+    TableHeader,      // ie, `[dependencies]`
+    ArrayTableHeader, // ie, `[[bin]]`
+    KeyValue,         // ie, `version = "1.0"`
+    Key,              // A bare, quoted, or dotted key, ie, `a."b.c".d`
+    Array,
+    InlineTable, // ie, `{ version = "1.0", features = ["derive"] }`
+
+    BareKey,
+    QuotedKey,
+    String,
+    Number,
+    Boolean,
+    DateTime,
+
+    LBrack, // Also `[[`, at the start of an array table header
+    RBrack, // Also `]]`, at the end of an array table header
+    LCurly,
+    RCurly,
+    Equals,
+    Dot,
+    Comma,
+    Whitespace,
+    Newline,
+    Comment,
+    Error, // Text that isn't part of any TOML token
+}
+
+impl SyntaxKind {
+    fn is_value(&self) -> bool {
+        matches!(
+            self,
+            SyntaxKind::String
+                | SyntaxKind::Number
+                | SyntaxKind::Boolean
+                | SyntaxKind::DateTime
+                | SyntaxKind::Array
+                | SyntaxKind::InlineTable
+                | SyntaxKind::Error
+        )
+    }
+}
+
+fn is_bare_key_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+/// When called, returns the kind of scalar that `word` is, or None if it isn't a valid one.
+fn scalar_kind(word: &str) -> Option<SyntaxKind> {
+    let is_digits = |text: &str| !text.is_empty() && text.chars().all(|c| c.is_ascii_digit());
+    let unsigned = word.trim_start_matches(['+', '-']);
+    let is_prefixed_number = |is_digit: fn(&char) -> bool| {
+        unsigned.len() > 2 && unsigned[2..].chars().all(|c| is_digit(&c) || c == '_')
+    };
+    let is_number = match unsigned.get(..2) {
+        Some("0x") => is_prefixed_number(char::is_ascii_hexdigit),
+        Some("0o") => is_prefixed_number(|c| matches!(c, '0'..='7')),
+        Some("0b") => is_prefixed_number(|c| matches!(c, '0' | '1')),
+        _ => {
+            let unsigned = unsigned.replace('_', "");
+            let (mantissa, exponent) = match unsigned.split_once(['e', 'E']) {
+                Some((mantissa, exponent)) => (mantissa, Some(exponent)),
+                None => (unsigned.as_str(), None),
+            };
+            let is_mantissa = match mantissa.split_once('.') {
+                Some((integer, fraction)) => is_digits(integer) && is_digits(fraction),
+                None => is_digits(mantissa),
+            };
+            matches!(unsigned.as_str(), "inf" | "nan")
+                || (is_mantissa
+                    && exponent
+                        .is_none_or(|exponent| is_digits(exponent.trim_start_matches(['+', '-']))))
+        }
+    };
+
+    // Dates and times, ie, `1979-05-27`, `07:32:00`, or `1979-05-27T07:32:00.999-07:00`
+    let bytes = word.as_bytes();
+    let is_date_time = word.chars().all(|c| {
+        c.is_ascii_digit() || matches!(c, '-' | ':' | '.' | '+' | 'T' | 't' | 'Z' | 'z' | ' ')
+    }) && ((bytes.len() >= 10 && bytes[4] == b'-' && bytes[7] == b'-')
+        || (bytes.len() >= 8 && bytes[2] == b':' && bytes[5] == b':'));
+
+    match word {
+        "true" | "false" => Some(SyntaxKind::Boolean),
+        _ if is_number => Some(SyntaxKind::Number),
+        _ if is_date_time => Some(SyntaxKind::DateTime),
+        _ => None,
+    }
+}
+
+/// A recursive descent parser that turns TOML text into a token tree. TOML is line based, so a
+/// line that can't start a key / value pair, or anything left over on a line after its value, is
+/// kept in the tree as an Error node with a diagnostic, and parsing picks up on the next line.
+///
+/// NOTE: TOML is tokenized differently depending on where in a line the parser is (ie, `1234` is a
+/// bare key before the `=`, but a number after it), so there isn't a separate tokenizer.
+struct Parser {
+    chars: Vec<char>,
+    index: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Parser {
+    fn new(literal: &str) -> Self {
+        Self {
+            chars: literal.chars().collect(),
+            index: 0,
+            diagnostics: vec![],
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.index).copied()
+    }
+
+    fn starts_with(&self, text: &str) -> bool {
+        text.chars()
+            .enumerate()
+            .all(|(offset, c)| self.chars.get(self.index + offset) == Some(&c))
+    }
+
+    fn is_at_line_end(&self) -> bool {
+        matches!(self.peek(), None | Some('\n' | '\r'))
+    }
+
+    fn error(&mut self, range: std::ops::Range<usize>, message: &str) {
+        self.diagnostics
+            .push(Diagnostic::new(range, Severity::Error, message));
+    }
+
+    /// When called, consumes `length` chars, and appends them to `parent` as a node of kind `kind`.
+    fn bump(
+        &mut self,
+        parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>,
+        kind: SyntaxKind,
+        length: usize,
+    ) {
+        let literal = self.chars[self.index..self.index + length].iter().collect();
+        self.index += length;
+        InMemoryNode::append_child(
+            parent,
+            InMemoryNode::new_with_metadata(NodeMetadata::AstNode {
+                kind,
+                literal: Some(literal),
+            }),
+        );
+    }
+
+    /// When called, consumes chars for as long as `predicate` returns true for them, appending
+    /// them to `parent` as a node of kind `kind`.
+    fn bump_while(
+        &mut self,
+        parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>,
+        kind: SyntaxKind,
+        predicate: impl Fn(char) -> bool,
+    ) {
+        let length = self.chars[self.index..]
+            .iter()
+            .take_while(|c| predicate(**c))
+            .count();
+        if length > 0 {
+            self.bump(parent, kind, length);
+        }
+    }
+
+    /// When called, consumes whitespace (but not newlines).
+    fn eat_whitespace(&mut self, parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>) {
+        // NOTE: a byte order mark is treated as whitespace so that it doesn't show up as an error
+        self.bump_while(parent, SyntaxKind::Whitespace, |c| {
+            matches!(c, ' ' | '\t' | '\u{feff}')
+        });
+    }
+
+    /// When called, consumes a single line ending, if there is one.
+    fn eat_newline(&mut self, parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>) -> bool {
+        let length = if self.starts_with("\r\n") {
+            2
+        } else if self.is_at_line_end() && self.peek().is_some() {
+            1
+        } else {
+            return false;
+        };
+        self.bump(parent, SyntaxKind::Newline, length);
+        true
+    }
+
+    fn eat_comment(&mut self, parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>) {
+        if self.peek() == Some('#') {
+            self.bump_while(parent, SyntaxKind::Comment, |c| c != '\n' && c != '\r');
+        }
+    }
+
+    /// When called, consumes whitespace, newlines, and comments.
+    fn eat_trivia(&mut self, parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>) {
+        loop {
+            self.eat_whitespace(parent);
+            self.eat_comment(parent);
+            if !self.eat_newline(parent) {
+                break;
+            }
+        }
+    }
+
+    /// When called, consumes the rest of the line after a header or key / value pair, which
+    /// should only contain whitespace and a comment.
+    fn parse_line_end(&mut self, parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>) {
+        self.eat_whitespace(parent);
+        self.eat_comment(parent);
+
+        // An unclosed array or inline table can end on a later line, after its trailing newline
+        let is_at_line_start = self
+            .index
+            .checked_sub(1)
+            .is_some_and(|index| matches!(self.chars[index], '\n' | '\r'));
+        if !self.is_at_line_end() && !is_at_line_start {
+            let start = self.index;
+            self.bump_while(parent, SyntaxKind::Error, |c| c != '\n' && c != '\r');
+            self.error(start..self.index, "expected a newline");
+        }
+    }
+
+    /// When called, parses the key / value pairs (and, if `is_top_level` is set, the tables) of a
+    /// document or table.
+    ///
+    /// NOTE: nodes are appended to their parent before their own children are parsed, so that
+    /// each node is given a fractional index relative to the nodes that come before it.
+    fn parse_statements(
+        &mut self,
+        parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>,
+        is_top_level: bool,
+    ) {
+        loop {
+            self.eat_trivia(parent);
+            match self.peek() {
+                None => break,
+                Some('[') if is_top_level => self.parse_table(parent),
+                Some('[') => break,
+                // A line that can't start a key / value pair is skipped over as a whole
+                Some(c) if !is_bare_key_char(c) && !matches!(c, '"' | '\'' | '=') => {
+                    let start = self.index;
+                    self.bump_while(parent, SyntaxKind::Error, |c| c != '\n' && c != '\r');
+                    self.error(start..self.index, "expected a key");
+                }
+                Some(_) => {
+                    self.parse_key_value(parent);
+                    self.parse_line_end(parent);
+                }
+            };
+        }
+    }
+
+    /// When called, parses a table, starting at its header.
+    fn parse_table(&mut self, parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>) {
+        let table = InMemoryNode::append_child(parent, new_node(SyntaxKind::Table));
+        self.parse_header(&table);
+        self.parse_statements(&table, false);
+    }
+
+    /// When called, parses a `[table]` or `[[array.table]]` header, and the rest of its line.
+    fn parse_header(&mut self, parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>) {
+        let (kind, opening, closing) = if self.starts_with("[[") {
+            (SyntaxKind::ArrayTableHeader, 2, "]]")
+        } else {
+            (SyntaxKind::TableHeader, 1, "]")
+        };
+        let header = InMemoryNode::append_child(parent, new_node(kind));
+        self.bump(&header, SyntaxKind::LBrack, opening);
+        self.eat_whitespace(&header);
+        self.parse_key(&header);
+        self.eat_whitespace(&header);
+        if self.starts_with(closing) {
+            self.bump(&header, SyntaxKind::RBrack, closing.len());
+        } else {
+            self.error(self.index..self.index, &format!("expected `{closing}`"));
+        }
+        self.parse_line_end(parent);
+    }
+
+    /// When called, parses a bare, quoted, or dotted key.
+    fn parse_key(&mut self, parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>) {
+        let key = InMemoryNode::append_child(parent, new_node(SyntaxKind::Key));
+        loop {
+            match self.peek() {
+                Some('"' | '\'') => self.parse_string(&key, SyntaxKind::QuotedKey),
+                Some(c) if is_bare_key_char(c) => {
+                    self.bump_while(&key, SyntaxKind::BareKey, is_bare_key_char)
+                }
+                _ => {
+                    self.error(self.index..self.index, "expected a key");
+                    break;
+                }
+            };
+
+            // Whitespace around the dots in a dotted key is allowed, but whitespace at the end of
+            // the key belongs to whatever comes after it
+            let whitespace_length = self.chars[self.index..]
+                .iter()
+                .take_while(|c| matches!(c, ' ' | '\t'))
+                .count();
+            if self.chars.get(self.index + whitespace_length) != Some(&'.') {
+                break;
+            }
+            self.eat_whitespace(&key);
+            self.bump(&key, SyntaxKind::Dot, 1);
+            self.eat_whitespace(&key);
+        }
+    }
+
+    /// When called, parses a `key = value` pair.
+    fn parse_key_value(&mut self, parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>) {
+        let key_value = InMemoryNode::append_child(parent, new_node(SyntaxKind::KeyValue));
+        self.parse_key(&key_value);
+        self.eat_whitespace(&key_value);
+        if self.peek() == Some('=') {
+            self.bump(&key_value, SyntaxKind::Equals, 1);
+            self.eat_whitespace(&key_value);
+        } else {
+            self.error(self.index..self.index, "expected `=`");
+        }
+
+        if !self.parse_value(&key_value) {
+            self.error(self.index..self.index, "expected a value");
+        }
+    }
+
+    /// When called, parses a string, which could be a basic (`"`), literal (`'`), or multi-line
+    /// (`"""` or `'''`) string.
+    fn parse_string(&mut self, parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>, kind: SyntaxKind) {
+        let start = self.index;
+        let quote = self.chars[self.index];
+        let is_multiline =
+            kind == SyntaxKind::String && self.starts_with(&quote.to_string().repeat(3));
+        let delimiter_length = if is_multiline { 3 } else { 1 };
+
+        let mut index = self.index + delimiter_length;
+        let mut is_terminated = false;
+        while index < self.chars.len() {
+            let c = self.chars[index];
+            if !is_multiline && (c == '\n' || c == '\r') {
+                break;
+            }
+            index += 1;
+            if c == '\\' && quote == '"' {
+                index += 1;
+            } else if c == quote
+                && (!is_multiline || self.chars[index..].starts_with(&[quote, quote]))
+            {
+                // A multi-line string can end with up to two extra quotes, ie, `"""a""""`
+                if is_multiline {
+                    index += 2;
+                    let closing_end = index;
+                    while index < closing_end + 2 && self.chars.get(index) == Some(&quote) {
+                        index += 1;
+                    }
+                }
+                is_terminated = true;
+                break;
+            }
+        }
+        let index = index.min(self.chars.len());
+
+        self.bump(parent, kind, index - start);
+        if !is_terminated {
+            self.error(start..self.index, "unterminated string");
+        }
+    }
+
+    /// When called, parses the next value and appends it to `parent`. Returns false if there is
+    /// no value next.
+    fn parse_value(&mut self, parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>) -> bool {
+        match self.peek() {
+            Some('"' | '\'') => self.parse_string(parent, SyntaxKind::String),
+            Some('[') => self.parse_container(parent, SyntaxKind::Array),
+            Some('{') => self.parse_container(parent, SyntaxKind::InlineTable),
+            _ => {
+                let start = self.index;
+                let mut length = self.chars[self.index..]
+                    .iter()
+                    .take_while(|c| !matches!(c, ' ' | '\t' | '\n' | '\r' | ',' | ']' | '}' | '#'))
+                    .count();
+                if length == 0 {
+                    return false;
+                }
+
+                // A date can be separated from its time by a space, ie, `1979-05-27 07:32:00`
+                let word = self.chars[start..start + length].iter().collect::<String>();
+                if scalar_kind(&word) == Some(SyntaxKind::DateTime)
+                    && self.chars.get(start + length) == Some(&' ')
+                    && self.chars.get(start + length + 3) == Some(&':')
+                {
+                    length += 1 + self.chars[start + length + 1..]
+                        .iter()
+                        .take_while(|c| {
+                            !matches!(c, ' ' | '\t' | '\n' | '\r' | ',' | ']' | '}' | '#')
+                        })
+                        .count();
+                }
+
+                let word = self.chars[start..start + length].iter().collect::<String>();
+                let kind = scalar_kind(&word).unwrap_or(SyntaxKind::Error);
+                self.bump(parent, kind, length);
+                if kind == SyntaxKind::Error {
+                    self.error(start..self.index, &format!("invalid value `{word}`"));
+                }
+            }
+        };
+        true
+    }
+
+    /// When called, parses an array or inline table, starting at its opening bracket.
+    fn parse_container(
+        &mut self,
+        parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>,
+        kind: SyntaxKind,
+    ) {
+        let (closing, closing_kind) = match kind {
+            SyntaxKind::Array => (']', SyntaxKind::RBrack),
+            _ => ('}', SyntaxKind::RCurly),
+        };
+        let container = InMemoryNode::append_child(parent, new_node(kind));
+        let opening_kind = match kind {
+            SyntaxKind::Array => SyntaxKind::LBrack,
+            _ => SyntaxKind::LCurly,
+        };
+        self.bump(&container, opening_kind, 1);
+
+        loop {
+            self.eat_trivia(&container);
+            match self.peek() {
+                Some(c) if c == closing => {
+                    self.bump(&container, closing_kind, 1);
+                    break;
+                }
+                Some(',') => self.bump(&container, SyntaxKind::Comma, 1),
+                // A table header on its own line means the container was never closed
+                Some('[') if kind == SyntaxKind::InlineTable => {
+                    self.error(self.index..self.index, &format!("expected `{closing}`"));
+                    break;
+                }
+                Some(_) if kind == SyntaxKind::InlineTable => self.parse_key_value(&container),
+                Some(_) => {
+                    if !self.parse_value(&container) {
+                        let start = self.index;
+                        self.bump(&container, SyntaxKind::Error, 1);
+                        let text = self.chars[start];
+                        self.error(start..self.index, &format!("unexpected `{text}`"));
+                    }
+                }
+                None => {
+                    self.error(self.index..self.index, &format!("expected `{closing}`"));
+                    break;
+                }
+            };
+        }
+    }
+
+    /// When called, parses a whole TOML document.
+    fn parse_document(&mut self) -> Rc<RefCell<InMemoryNode<SyntaxKind>>> {
+        let document = new_node(SyntaxKind::Document);
+        self.parse_statements(&document, true);
+        document
+    }
+
+    /// When called, parses text that was taken out of a table - ie, its header, a few of its key /
+    /// value pairs, or both.
+    fn parse_table_fragment(&mut self) -> Rc<RefCell<InMemoryNode<SyntaxKind>>> {
+        let table = new_node(SyntaxKind::Table);
+        self.eat_trivia(&table);
+        if self.peek() == Some('[') {
+            self.parse_header(&table);
+        }
+        loop {
+            self.parse_statements(&table, false);
+            if self.peek().is_none() {
+                break;
+            }
+            // A header after the first one was added as part of the edit, so start a new table
+            self.parse_table(&table);
+        }
+        table
+    }
+}
+
+fn new_node(kind: SyntaxKind) -> Rc<RefCell<InMemoryNode<SyntaxKind>>> {
+    InMemoryNode::new_with_metadata(NodeMetadata::AstNode {
+        kind,
+        literal: None,
+    })
+}
+
+fn kind_of(node: &Rc<RefCell<InMemoryNode<SyntaxKind>>>) -> Option<SyntaxKind> {
+    match node.borrow().metadata {
+        NodeMetadata::AstNode { kind, .. } => Some(kind),
+        _ => None,
+    }
+}
+
+/// When called, returns the kind of `node` within a tree of wrapped kinds (see
+/// `TokenKindTrait::key_path_segments`).
+fn unwrapped_kind_of<Wrapper: TokenKindTrait>(
+    node: &Rc<RefCell<InMemoryNode<Wrapper>>>,
+    unwrap: &dyn Fn(&Wrapper) -> Option<SyntaxKind>,
+) -> Option<SyntaxKind> {
+    match &node.borrow().metadata {
+        NodeMetadata::AstNode { kind, .. } => unwrap(kind),
+        _ => None,
+    }
+}
+
+/// When called, returns the segments of the first `Key` node within `node`, ie, `["a", "b.c"]`
+/// for the key `a."b.c"`.
+fn key_segments<Wrapper: TokenKindTrait>(
+    node: &Rc<RefCell<InMemoryNode<Wrapper>>>,
+    unwrap: &dyn Fn(&Wrapper) -> Option<SyntaxKind>,
+) -> Vec<String> {
+    let children = node.borrow().children.clone();
+    let Some(key) = children
+        .iter()
+        .find(|child| unwrapped_kind_of(child, unwrap) == Some(SyntaxKind::Key))
+    else {
+        return vec![];
+    };
+    let segments = key
+        .borrow()
+        .children
+        .iter()
+        .filter(|child| {
+            matches!(
+                unwrapped_kind_of(child, unwrap),
+                Some(SyntaxKind::BareKey | SyntaxKind::QuotedKey)
+            )
+        })
+        .map(|child| unquote(&InMemoryNode::literal(child)))
+        .collect();
+    segments
+}
+
+impl TokenKindTrait for SyntaxKind {
+    type ParseOptions = ();
+
+    fn language_name() -> &'static str {
+        "toml"
+    }
+
+    fn highlight_group(ancestry: std::vec::IntoIter<SyntaxKind>) -> Option<HighlightGroup> {
+        let ancestry = ancestry.as_slice();
+        let is_in_header = ancestry
+            .iter()
+            .any(|kind| matches!(kind, SyntaxKind::TableHeader | SyntaxKind::ArrayTableHeader));
+        match ancestry.first()? {
+            SyntaxKind::BareKey | SyntaxKind::QuotedKey if is_in_header => {
+                Some(HighlightGroup::Type)
+            }
+            SyntaxKind::BareKey | SyntaxKind::QuotedKey => Some(HighlightGroup::Property),
+            SyntaxKind::String => Some(HighlightGroup::String),
+            SyntaxKind::Number => Some(HighlightGroup::Number),
+            SyntaxKind::Boolean | SyntaxKind::DateTime => Some(HighlightGroup::Constant),
+            SyntaxKind::Comment => Some(HighlightGroup::Comment),
+            SyntaxKind::LBrack
+            | SyntaxKind::RBrack
+            | SyntaxKind::LCurly
+            | SyntaxKind::RCurly
+            | SyntaxKind::Equals
+            | SyntaxKind::Dot
+            | SyntaxKind::Comma => Some(HighlightGroup::Punctuation),
+            _ => None,
+        }
+    }
+
+    fn is_reparsable(&self) -> bool {
+        matches!(self, SyntaxKind::Document | SyntaxKind::Table)
+    }
+
+    fn is_foldable(&self) -> bool {
+        matches!(
+            self,
+            SyntaxKind::Table | SyntaxKind::Array | SyntaxKind::InlineTable
+        )
+    }
+
+    fn parse(
+        literal: &str,
+        parent: Option<Rc<RefCell<InMemoryNode<Self>>>>,
+    ) -> Rc<RefCell<InMemoryNode<Self>>> {
+        // When reparsing part of a table, parse the text as the contents of a table. The result is
+        // wrapped in a table node, so that the wrapper's children take the place of the text that
+        // was reparsed.
+        let mut parser = Parser::new(literal);
+        let node = match parent.as_ref().and_then(kind_of) {
+            Some(SyntaxKind::Table) => parser.parse_table_fragment(),
+            _ => parser.parse_document(),
+        };
+
+        for diagnostic in parser.diagnostics {
+            InMemoryNode::attach_diagnostic(&node, diagnostic);
+        }
+        node
+    }
+
+    fn key_path_segments<Wrapper: TokenKindTrait>(
+        node: &Rc<RefCell<InMemoryNode<Wrapper>>>,
+        unwrap: &dyn Fn(&Wrapper) -> Option<Self>,
+    ) -> Vec<String> {
+        let kind_of = |node: &Rc<RefCell<InMemoryNode<Wrapper>>>| unwrapped_kind_of(node, unwrap);
+        let parent = node
+            .borrow()
+            .parent
+            .as_ref()
+            .and_then(|parent| parent.upgrade());
+        match kind_of(node) {
+            // Table headers are absolute, so a table's key path is just its header's key. Each table
+            // in an array of tables is also given its index in the array, ie, `bin.0`.
+            Some(SyntaxKind::Table) => {
+                let header = node.borrow().children.first().cloned();
+                let Some(header) = header else {
+                    return vec![];
+                };
+                let mut segments = key_segments(&header, unwrap);
+                if kind_of(&header) == Some(SyntaxKind::ArrayTableHeader) {
+                    let siblings = parent.map(|parent| parent.borrow().children.clone());
+                    let index = siblings
+                        .unwrap_or_default()
+                        .iter()
+                        .take_while(|sibling| !Rc::ptr_eq(sibling, node))
+                        .filter(|sibling| {
+                            let header = sibling.borrow().children.first().cloned();
+                            header.is_some_and(|header| {
+                                kind_of(&header) == Some(SyntaxKind::ArrayTableHeader)
+                                    && key_segments(&header, unwrap) == segments
+                            })
+                        })
+                        .count();
+                    segments.push(index.to_string());
+                }
+                segments
+            }
+            Some(SyntaxKind::KeyValue) => key_segments(node, unwrap),
+            // Values in an array are keyed by their index, ie, `features.0`
+            Some(kind) if kind.is_value() => {
+                let Some(parent) =
+                    parent.filter(|parent| kind_of(parent) == Some(SyntaxKind::Array))
+                else {
+                    return vec![];
+                };
+                let index = parent
+                    .borrow()
+                    .children
+                    .iter()
+                    .take_while(|sibling| !Rc::ptr_eq(sibling, node))
+                    .filter(|sibling| kind_of(sibling).is_some_and(|kind| kind.is_value()))
+                    .count();
+                vec![index.to_string()]
+            }
+            _ => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SyntaxKind;
+    use crate::{
+        languages::test_utils::{assert_kept_with_diagnostics, child, child_kinds},
+        node_tree::{
+            cursor::{Cursor, Selection},
+            node::InMemoryNode,
+            utils::split_key_path,
+        },
+    };
+    use std::rc::Rc;
+
+    const CARGO_TOML: &str = r#"# A manifest
+[package]
+name = "token-tree"
+edition = 2021
+
+[dependencies]
+colored = "2"
+serde = { version = "1.0", features = ["derive", "rc"] }
+"a.b".c = 1979-05-27 07:32:00
+
+[[bin]]
+name = "a"
+
+[[bin]]
+name = "b"
+"#;
+
+    #[test]
+    fn parses_tables_keys_and_values() {
+        use SyntaxKind::*;
+        let root = InMemoryNode::<SyntaxKind>::new_from_parsed(CARGO_TOML);
+        assert!(InMemoryNode::diagnostics(&root).is_empty());
+
+        let document = child(&root, 0);
+        assert_eq!(
+            child_kinds(&document),
+            vec![Comment, Newline, Table, Table, Table, Table]
+        );
+        let package = child(&document, 2);
+        assert_eq!(
+            child_kinds(&package),
+            vec![
+                TableHeader,
+                Newline,
+                KeyValue,
+                Newline,
+                KeyValue,
+                Newline,
+                Newline
+            ]
+        );
+        assert_eq!(child_kinds(&child(&package, 0)), vec![LBrack, Key, RBrack]);
+        assert_eq!(
+            child_kinds(&child(&package, 4)),
+            vec![Key, Whitespace, Equals, Whitespace, Number]
+        );
+
+        let dependencies = child(&document, 3);
+        let serde = child(&dependencies, 4);
+        let inline_table = child(&serde, 4);
+        assert_eq!(
+            child_kinds(&inline_table),
+            vec![LCurly, Whitespace, KeyValue, Comma, Whitespace, KeyValue, Whitespace, RCurly]
+        );
+        assert_eq!(
+            child_kinds(&child(&child(&inline_table, 5), 4)),
+            vec![LBrack, String, Comma, Whitespace, String, RBrack]
+        );
+        let dotted = child(&dependencies, 6);
+        assert_eq!(
+            child_kinds(&child(&dotted, 0)),
+            vec![QuotedKey, Dot, BareKey]
+        );
+        assert_eq!(child_kinds(&dotted)[4], DateTime);
+
+        assert_eq!(
+            child_kinds(&child(&child(&document, 4), 0)),
+            vec![LBrack, Key, RBrack]
+        );
+    }
+
+    #[test]
+    fn malformed_documents_are_kept_with_diagnostics() {
+        let documents = [
+            ("a 1", vec!["expected `=`"]),
+            ("a =\nb = 2", vec!["expected a value"]),
+            ("a = tru", vec!["invalid value `tru`"]),
+            ("a = \"b", vec!["unterminated string"]),
+            ("a = \"\"\"b\nc", vec!["unterminated string"]),
+            ("a = [1, 2\n[b]", vec!["invalid value `b`", "expected `]`"]),
+            ("a = {b = 1\n[c]", vec!["expected `}`"]),
+            ("[a\nb = 1", vec!["expected `]`"]),
+            ("a = 1 b", vec!["expected a newline"]),
+            ("= 1", vec!["expected a key"]),
+            ("}\n}", vec!["expected a key", "expected a key"]),
+        ];
+        assert_kept_with_diagnostics::<SyntaxKind>(&documents);
+    }
+
+    #[test]
+    fn finds_nodes_by_key_path() {
+        let root = InMemoryNode::<SyntaxKind>::new_from_parsed(CARGO_TOML);
+        let find = |path: &str| {
+            InMemoryNode::find_key_path(&root, &split_key_path(path)).map(|node| {
+                (
+                    Cursor::new(node.clone()).to_rows_cols(),
+                    InMemoryNode::key_path(&node).join("."),
+                )
+            })
+        };
+
+        assert_eq!(find("package"), Some(((2, 1), "package".into())));
+        assert_eq!(
+            find("package.edition"),
+            Some(((4, 1), "package.edition".into()))
+        );
+        assert_eq!(
+            find("dependencies.serde.version"),
+            Some(((8, 11), "dependencies.serde.version".into()))
+        );
+        assert_eq!(
+            find("dependencies.serde.features.1"),
+            Some(((8, 50), "dependencies.serde.features.1".into()))
+        );
+        assert_eq!(
+            find("dependencies.\"a.b\".c"),
+            Some(((9, 1), "dependencies.a.b.c".into()))
+        );
+        assert_eq!(find("bin.1.name"), Some(((15, 1), "bin.1.name".into())));
+        assert_eq!(find("dependencies.missing"), None);
+        assert_eq!(find("package.name.x"), None);
+    }
+
+    #[test]
+    fn edits_only_reparse_the_enclosing_table() {
+        let root = InMemoryNode::<SyntaxKind>::new_from_parsed(CARGO_TOML);
+        let document = child(&root, 0);
+        let package = child(&document, 2);
+        let dependencies = child(&document, 3);
+
+        Selection::new_from_cursor_pair(
+            Cursor::new_at_rows_cols(root.clone(), (4, 11)),
+            Cursor::new_at_rows_cols(root.clone(), (4, 15)),
+        )
+        .replace("2018\nversion = \"0.1.0\"")
+        .unwrap();
+        assert!(InMemoryNode::diagnostics(&root).is_empty());
+        assert!(Rc::ptr_eq(&child(&document, 2), &package));
+        assert!(Rc::ptr_eq(&child(&document, 3), &dependencies));
+
+        use SyntaxKind::*;
+        assert_eq!(
+            child_kinds(&package),
+            vec![
+                TableHeader,
+                Newline,
+                KeyValue,
+                Newline,
+                KeyValue,
+                Newline,
+                KeyValue,
+                Newline,
+                Newline
+            ]
+        );
+        assert!(InMemoryNode::find_key_path(&root, &split_key_path("package.version")).is_some());
+    }
+}
@@ -1,11 +1,21 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{
+    cell::{Cell, RefCell},
+    path::Path,
+    rc::Rc,
+    sync::Once,
+};
 
-use colored::{ColoredString, Colorize};
+use rslint_errors::Severity as RslintSeverity;
 use rslint_parser::{
-    parse_text, NodeOrToken, SyntaxKind as SyntaxKindGlobal, SyntaxNode, WalkEvent,
+    parse_with_syntax, FileKind, NodeOrToken, ParserError, Syntax, SyntaxKind as SyntaxKindGlobal,
+    SyntaxNode, WalkEvent,
 };
 
-use crate::node_tree::node::{InMemoryNode, NodeMetadata, TokenKindTrait};
+use crate::node_tree::{
+    diagnostic::{Diagnostic, Severity},
+    node::{InMemoryNode, NodeMetadata, ParseOptionsTrait, TokenKindTrait},
+    style::HighlightGroup,
+};
 
 /// rslint_parser::SyntaxKind is an enum from rslint_parser (javascript parser) which contains all
 /// output tokens. It has been re-exported from rslint_parser::SyntaxKind here.
@@ -352,22 +362,25 @@ pub fn convert_rslint_syntaxnode_to_inmemorynode(
                     literal: Some(pointer_literal),
                 } = parent_metadata
                 {
+                    // NOTE: `child_literal.len()` is a byte length, so slice by bytes rather than
+                    // skipping chars - otherwise any multibyte character (ie, a BOM or non ascii
+                    // string literal) would cause too much of the parent literal to be dropped.
                     let new_literal = if pointer_literal.starts_with(&child_literal) {
-                        pointer_literal
-                            .chars()
-                            .skip(child_literal.len())
-                            .collect::<String>()
+                        pointer_literal[child_literal.len()..].to_string()
                     } else {
                         pointer_literal
                     };
-                    pointer.borrow_mut().metadata = NodeMetadata::AstNode {
-                        kind,
-                        literal: if new_literal.is_empty() {
-                            None
-                        } else {
-                            Some(new_literal)
+                    InMemoryNode::set_metadata(
+                        &pointer,
+                        NodeMetadata::AstNode {
+                            kind,
+                            literal: if new_literal.is_empty() {
+                                None
+                            } else {
+                                Some(new_literal)
+                            },
                         },
-                    };
+                    );
                 }
 
                 pointer = InMemoryNode::append_child(&pointer, child);
@@ -394,346 +407,555 @@ pub fn convert_rslint_syntaxnode_to_inmemorynode(
     }
 }
 
+/// The ParseMode picks which syntax a document is parsed with.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ParseMode {
+    Script, // Plain javascript, where `import` / `export` aren't allowed
+    Module, // A javascript ES module
+    #[default]
+    TypeScript,
+    // TypeScript with JSX. Note that rslint_parser can't parse JSX, so the JSX in these files is
+    // reported as errors.
+    Tsx,
+}
+
+impl ParseMode {
+    fn syntax(&self) -> Syntax {
+        match self {
+            ParseMode::Script => Syntax::new(FileKind::Script),
+            ParseMode::Module => Syntax::new(FileKind::Module),
+            ParseMode::TypeScript | ParseMode::Tsx => Syntax::new(FileKind::TypeScript),
+        }
+    }
+}
+
+impl ParseOptionsTrait for ParseMode {
+    /// When called, picks the parse mode from the file's extension. Files with other extensions
+    /// are parsed as TypeScript, since it is a superset of javascript.
+    fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("js" | "cjs") => ParseMode::Script,
+            Some("mjs" | "jsx") => ParseMode::Module,
+            Some("tsx") => ParseMode::Tsx,
+            _ => ParseMode::TypeScript,
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "script" => Some(ParseMode::Script),
+            "module" => Some(ParseMode::Module),
+            "typescript" => Some(ParseMode::TypeScript),
+            "tsx" => Some(ParseMode::Tsx),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> String {
+        match self {
+            ParseMode::Script => "script",
+            ParseMode::Module => "module",
+            ParseMode::TypeScript => "typescript",
+            ParseMode::Tsx => "tsx",
+        }
+        .into()
+    }
+}
+
+/// When called, parses `literal` with rslint_parser, returning the untyped root syntax node along
+/// with any errors the parser ran into.
+///
+/// rslint_parser panics on some malformed inputs ("The parser seems to be recursing forever"), and
+/// since documents get reparsed while they are being edited, that would take down the whole
+/// editor. So catch these panics and return None instead.
+///
+/// The panic hook is global to the process, so rather than swapping it out around each parse
+/// (which races with other threads parsing at the same time), a hook is installed once which only
+/// keeps quiet about panics raised on a thread while it is parsing.
+fn parse_text_without_panicking(
+    literal: &str,
+    mode: ParseMode,
+) -> Option<(SyntaxNode, Vec<ParserError>)> {
+    SILENCE_PARSER_PANICS.call_once(|| {
+        let previous_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            if !IS_PARSING.with(Cell::get) {
+                previous_hook(info);
+            }
+        }));
+    });
+
+    IS_PARSING.with(|is_parsing| is_parsing.set(true));
+    let result = std::panic::catch_unwind(|| {
+        let parse = parse_with_syntax(literal, 0, mode.syntax());
+        (parse.syntax(), parse.errors().to_vec())
+    });
+    IS_PARSING.with(|is_parsing| is_parsing.set(false));
+
+    result.ok()
+}
+
+static SILENCE_PARSER_PANICS: Once = Once::new();
+
+thread_local! {
+    // Set while rslint_parser is running on this thread, so the panics it raises aren't printed
+    static IS_PARSING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// When called, converts an error reported by rslint_parser while parsing `literal` into a
+/// Diagnostic. rslint_parser reports ranges in bytes, but Diagnostics are in chars.
+fn convert_parser_error_to_diagnostic(literal: &str, error: &ParserError) -> Diagnostic {
+    let severity = match error.severity {
+        RslintSeverity::Bug | RslintSeverity::Error => Severity::Error,
+        RslintSeverity::Warning => Severity::Warning,
+        RslintSeverity::Note => Severity::Info,
+        RslintSeverity::Help => Severity::Hint,
+    };
+
+    let byte_offset_to_chars = |byte_offset: usize| {
+        literal
+            .char_indices()
+            .take_while(|(index, _)| *index < byte_offset)
+            .count()
+    };
+    let range = match &error.primary {
+        Some(primary) => {
+            byte_offset_to_chars(primary.span.range.start)
+                ..byte_offset_to_chars(primary.span.range.end)
+        }
+        None => 0..0,
+    };
+
+    Diagnostic::new(range, severity, &error.title)
+}
+
+/// The symbols shown in the outline of a TypeScript / JavaScript document (see
+/// `TokenKindTrait::outline_query`).
+const OUTLINE_QUERY: &str = r#"
+(FN_DECL name: (NAME) @name) @definition.function
+(CLASS_DECL name: (NAME) @name) @definition.class
+(METHOD name: (_) @name) @definition.method
+(GETTER name: (_) @name) @definition.method
+(SETTER name: (_) @name) @definition.method
+(CONSTRUCTOR (NAME) @name) @definition.method
+(CLASS_PROP (NAME) @name) @definition.property
+(PRIVATE_PROP (PRIVATE_NAME) @name) @definition.property
+(TS_INTERFACE_DECL name: (NAME) @name) @definition.interface
+(TS_TYPE_ALIAS_DECL name: (NAME) @name) @definition.type
+(TS_ENUM name: (NAME) @name) @definition.enum
+(TS_NAMESPACE_DECL (NAME) @name) @definition.namespace
+(TS_MODULE_DECL (NAME) @name) @definition.namespace
+
+; Destructured variables are named after the whole pattern, ie `{ a, b }`
+(DECLARATOR pattern: (SINGLE_PATTERN (NAME) @name)) @definition.variable
+(DECLARATOR pattern: (OBJECT_PATTERN) @name) @definition.variable
+(DECLARATOR pattern: (ARRAY_PATTERN) @name) @definition.variable
+
+; Exports that don't declare anything of their own, ie `export { a, b as c }`
+(EXPORT_NAMED (SPECIFIER)+ @name) @definition.export
+(EXPORT_WILDCARD (LITERAL) @name) @definition.export
+(EXPORT_DEFAULT_EXPR "default" @name) @definition.export
+
+(EXPORT_DECL) @export
+(EXPORT_DEFAULT_DECL) @export
+"#;
+
 impl TokenKindTrait for SyntaxKind {
-    fn apply_debug_syntax_color(
-        text: String,
-        mut ancestry: std::vec::IntoIter<SyntaxKind>,
-    ) -> ColoredString {
-        let (Some(kind), Some(parent_kind)) = (ancestry.next(), ancestry.next()) else {
-            return text.into();
-        };
+    type ParseOptions = ParseMode;
 
-        // Some bespoke syntax highlighting rules:
-        if parent_kind == SyntaxKind::VAR_DECL && kind == SyntaxKind::IDENT {
-            return ColoredString::from(text).bold().red();
-        };
-        if parent_kind == SyntaxKind::FN_DECL && kind == SyntaxKind::FUNCTION_KW {
-            return ColoredString::from(text).bold().blue();
-        };
-        if parent_kind == SyntaxKind::LITERAL && kind == SyntaxKind::STRING {
-            return ColoredString::from(text).green();
-        };
-        if parent_kind == SyntaxKind::LITERAL && kind == SyntaxKind::NUMBER {
-            return ColoredString::from(text).purple();
-        };
-        if kind == SyntaxKind::COMMENT {
-            return ColoredString::from(text).bright_black();
+    fn language_name() -> &'static str {
+        "typescript"
+    }
+
+    fn highlight_group(ancestry: std::vec::IntoIter<SyntaxKind>) -> Option<HighlightGroup> {
+        let ancestry = ancestry.collect::<Vec<_>>();
+        let kind = *ancestry.first()?;
+        let parent_kind = ancestry.get(1).copied();
+
+        // Some bespoke syntax highlighting rules, for tokens whose group depends on where they are
+        // in the tree:
+        match (kind, parent_kind) {
+            // `let` is parsed as an identifier, not a keyword
+            (SyntaxKind::IDENT, Some(SyntaxKind::VAR_DECL)) => {
+                return Some(HighlightGroup::Keyword);
+            }
+            (
+                SyntaxKind::TRUE_KW | SyntaxKind::FALSE_KW | SyntaxKind::NULL_KW,
+                Some(SyntaxKind::LITERAL),
+            ) => {
+                return Some(HighlightGroup::Constant);
+            }
+            (SyntaxKind::IDENT, Some(SyntaxKind::NAME)) => match ancestry[2..] {
+                [SyntaxKind::FN_DECL | SyntaxKind::FN_EXPR | SyntaxKind::METHOD, ..] => {
+                    return Some(HighlightGroup::FunctionName);
+                }
+                [SyntaxKind::CLASS_DECL
+                | SyntaxKind::TS_INTERFACE_DECL
+                | SyntaxKind::TS_TYPE_ALIAS_DECL, ..] => {
+                    return Some(HighlightGroup::Type);
+                }
+                [SyntaxKind::SINGLE_PATTERN, SyntaxKind::DECLARATOR, ..] => {
+                    return Some(HighlightGroup::VariableDecl);
+                }
+                [SyntaxKind::DOT_EXPR, ..] => return Some(HighlightGroup::Property),
+                _ => {}
+            },
+            (
+                SyntaxKind::IDENT,
+                Some(
+                    SyntaxKind::TS_ANY
+                    | SyntaxKind::TS_UNKNOWN
+                    | SyntaxKind::TS_NUMBER
+                    | SyntaxKind::TS_OBJECT
+                    | SyntaxKind::TS_BOOLEAN
+                    | SyntaxKind::TS_BIGINT
+                    | SyntaxKind::TS_STRING
+                    | SyntaxKind::TS_SYMBOL
+                    | SyntaxKind::TS_VOID
+                    | SyntaxKind::TS_UNDEFINED
+                    | SyntaxKind::TS_NULL
+                    | SyntaxKind::TS_NEVER
+                    | SyntaxKind::TS_TYPE_NAME,
+                ),
+            ) => return Some(HighlightGroup::Type),
+            _ => {}
         };
 
         match kind {
-            SyntaxKind::TOMBSTONE => text.into(),
-            SyntaxKind::EOF => text.into(),
-            SyntaxKind::SEMICOLON => text.into(),
-            SyntaxKind::COMMA => text.into(),
-            SyntaxKind::L_PAREN => text.into(),
-            SyntaxKind::R_PAREN => text.into(),
-            SyntaxKind::L_CURLY => text.into(),
-            SyntaxKind::R_CURLY => text.into(),
-            SyntaxKind::L_BRACK => text.into(),
-            SyntaxKind::R_BRACK => text.into(),
-            SyntaxKind::L_ANGLE => text.into(),
-            SyntaxKind::R_ANGLE => text.into(),
-            SyntaxKind::TILDE => text.into(),
-            SyntaxKind::QUESTION => text.into(),
-            SyntaxKind::QUESTION2 => text.into(),
-            SyntaxKind::QUESTIONDOT => text.into(),
-            SyntaxKind::AMP => text.into(),
-            SyntaxKind::PIPE => text.into(),
-            SyntaxKind::PLUS => text.into(),
-            SyntaxKind::PLUS2 => text.into(),
-            SyntaxKind::STAR => text.into(),
-            SyntaxKind::STAR2 => text.into(),
-            SyntaxKind::SLASH => text.into(),
-            SyntaxKind::CARET => text.into(),
-            SyntaxKind::PERCENT => text.into(),
-            SyntaxKind::DOT => text.into(),
-            SyntaxKind::DOT2 => text.into(),
-            SyntaxKind::COLON => text.into(),
-            SyntaxKind::EQ => text.into(),
-            SyntaxKind::EQ2 => text.into(),
-            SyntaxKind::EQ3 => text.into(),
-            SyntaxKind::FAT_ARROW => text.into(),
-            SyntaxKind::BANG => text.into(),
-            SyntaxKind::NEQ => text.into(),
-            SyntaxKind::NEQ2 => text.into(),
-            SyntaxKind::MINUS => text.into(),
-            SyntaxKind::MINUS2 => text.into(),
-            SyntaxKind::LTEQ => text.into(),
-            SyntaxKind::GTEQ => text.into(),
-            SyntaxKind::PLUSEQ => text.into(),
-            SyntaxKind::MINUSEQ => text.into(),
-            SyntaxKind::PIPEEQ => text.into(),
-            SyntaxKind::AMPEQ => text.into(),
-            SyntaxKind::CARETEQ => text.into(),
-            SyntaxKind::SLASHEQ => text.into(),
-            SyntaxKind::STAREQ => text.into(),
-            SyntaxKind::PERCENTEQ => text.into(),
-            SyntaxKind::AMP2 => text.into(),
-            SyntaxKind::PIPE2 => text.into(),
-            SyntaxKind::SHL => text.into(),
-            SyntaxKind::SHR => text.into(),
-            SyntaxKind::USHR => text.into(),
-            SyntaxKind::SHLEQ => text.into(),
-            SyntaxKind::SHREQ => text.into(),
-            SyntaxKind::USHREQ => text.into(),
-            SyntaxKind::AMP2EQ => text.into(),
-            SyntaxKind::PIPE2EQ => text.into(),
-            SyntaxKind::STAR2EQ => text.into(),
-            SyntaxKind::QUESTION2EQ => text.into(),
-            SyntaxKind::AT => text.into(),
-            SyntaxKind::AWAIT_KW => text.into(),
-            SyntaxKind::BREAK_KW => text.into(),
-            SyntaxKind::CASE_KW => text.into(),
-            SyntaxKind::CATCH_KW => text.into(),
-            SyntaxKind::CLASS_KW => text.into(),
-            SyntaxKind::CONST_KW => text.into(),
-            SyntaxKind::CONTINUE_KW => text.into(),
-            SyntaxKind::DEBUGGER_KW => text.into(),
-            SyntaxKind::DEFAULT_KW => text.into(),
-            SyntaxKind::DELETE_KW => text.into(),
-            SyntaxKind::DO_KW => text.into(),
-            SyntaxKind::ELSE_KW => text.into(),
-            SyntaxKind::ENUM_KW => text.into(),
-            SyntaxKind::EXPORT_KW => text.into(),
-            SyntaxKind::EXTENDS_KW => text.into(),
-            SyntaxKind::FALSE_KW => text.into(),
-            SyntaxKind::FINALLY_KW => text.into(),
-            SyntaxKind::FOR_KW => text.into(),
-            SyntaxKind::FUNCTION_KW => text.into(),
-            SyntaxKind::IF_KW => text.into(),
-            SyntaxKind::IN_KW => text.into(),
-            SyntaxKind::INSTANCEOF_KW => text.into(),
-            SyntaxKind::INTERFACE_KW => text.into(),
-            SyntaxKind::IMPORT_KW => text.into(),
-            SyntaxKind::IMPLEMENTS_KW => text.into(),
-            SyntaxKind::NEW_KW => text.into(),
-            SyntaxKind::NULL_KW => text.into(),
-            SyntaxKind::PACKAGE_KW => text.into(),
-            SyntaxKind::PRIVATE_KW => text.into(),
-            SyntaxKind::PROTECTED_KW => text.into(),
-            SyntaxKind::PUBLIC_KW => text.into(),
-            SyntaxKind::RETURN_KW => text.into(),
-            SyntaxKind::SUPER_KW => text.into(),
-            SyntaxKind::SWITCH_KW => text.into(),
-            SyntaxKind::THIS_KW => text.into(),
-            SyntaxKind::THROW_KW => text.into(),
-            SyntaxKind::TRY_KW => text.into(),
-            SyntaxKind::TRUE_KW => text.into(),
-            SyntaxKind::TYPEOF_KW => text.into(),
-            SyntaxKind::VAR_KW => text.into(),
-            SyntaxKind::VOID_KW => text.into(),
-            SyntaxKind::WHILE_KW => text.into(),
-            SyntaxKind::WITH_KW => text.into(),
-            SyntaxKind::YIELD_KW => text.into(),
-            SyntaxKind::READONLY_KW => text.into(),
-            SyntaxKind::KEYOF_KW => text.into(),
-            SyntaxKind::UNIQUE_KW => text.into(),
-            SyntaxKind::DECLARE_KW => text.into(),
-            SyntaxKind::ABSTRACT_KW => text.into(),
-            SyntaxKind::STATIC_KW => text.into(),
-            SyntaxKind::ASYNC_KW => text.into(),
-            SyntaxKind::TYPE_KW => text.into(),
-            SyntaxKind::FROM_KW => text.into(),
-            SyntaxKind::AS_KW => text.into(),
-            SyntaxKind::REQUIRE_KW => text.into(),
-            SyntaxKind::NAMESPACE_KW => text.into(),
-            SyntaxKind::ASSERT_KW => text.into(),
-            SyntaxKind::MODULE_KW => text.into(),
-            SyntaxKind::GLOBAL_KW => text.into(),
-            SyntaxKind::INFER_KW => text.into(),
-            SyntaxKind::GET_KW => text.into(),
-            SyntaxKind::SET_KW => text.into(),
-            SyntaxKind::NUMBER => text.into(),
-            SyntaxKind::STRING => text.into(),
-            SyntaxKind::REGEX => text.into(),
-            SyntaxKind::HASH => text.into(),
-            SyntaxKind::TEMPLATE_CHUNK => text.into(),
-            SyntaxKind::DOLLARCURLY => text.into(),
-            SyntaxKind::BACKTICK => text.into(),
-            SyntaxKind::ERROR_TOKEN => text.into(),
-            SyntaxKind::IDENT => text.into(),
-            SyntaxKind::WHITESPACE => text.into(),
-            SyntaxKind::COMMENT => text.into(),
-            SyntaxKind::SHEBANG => text.into(),
-            SyntaxKind::SCRIPT => text.into(),
-            SyntaxKind::MODULE => text.into(),
-            SyntaxKind::ERROR => text.into(),
-            SyntaxKind::BLOCK_STMT => text.into(),
-            SyntaxKind::VAR_DECL => text.into(),
-            SyntaxKind::DECLARATOR => text.into(),
-            SyntaxKind::EMPTY_STMT => text.into(),
-            SyntaxKind::EXPR_STMT => text.into(),
-            SyntaxKind::IF_STMT => text.into(),
-            SyntaxKind::DO_WHILE_STMT => text.into(),
-            SyntaxKind::WHILE_STMT => text.into(),
-            SyntaxKind::FOR_STMT => text.into(),
-            SyntaxKind::FOR_IN_STMT => text.into(),
-            SyntaxKind::CONTINUE_STMT => text.into(),
-            SyntaxKind::BREAK_STMT => text.into(),
-            SyntaxKind::RETURN_STMT => text.into(),
-            SyntaxKind::WITH_STMT => text.into(),
-            SyntaxKind::SWITCH_STMT => text.into(),
-            SyntaxKind::CASE_CLAUSE => text.into(),
-            SyntaxKind::DEFAULT_CLAUSE => text.into(),
-            SyntaxKind::LABELLED_STMT => text.into(),
-            SyntaxKind::THROW_STMT => text.into(),
-            SyntaxKind::TRY_STMT => text.into(),
-            SyntaxKind::CATCH_CLAUSE => text.into(),
-            SyntaxKind::FINALIZER => text.into(),
-            SyntaxKind::DEBUGGER_STMT => text.into(),
-            SyntaxKind::FN_DECL => text.into(),
-            SyntaxKind::NAME => text.into(),
-            SyntaxKind::NAME_REF => text.into(),
-            SyntaxKind::PARAMETER_LIST => text.into(),
-            SyntaxKind::THIS_EXPR => text.into(),
-            SyntaxKind::ARRAY_EXPR => text.into(),
-            SyntaxKind::OBJECT_EXPR => text.into(),
-            SyntaxKind::LITERAL_PROP => text.into(),
-            SyntaxKind::GETTER => text.into(),
-            SyntaxKind::SETTER => text.into(),
-            SyntaxKind::GROUPING_EXPR => text.into(),
-            SyntaxKind::NEW_EXPR => text.into(),
-            SyntaxKind::FN_EXPR => text.into(),
-            SyntaxKind::BRACKET_EXPR => text.into(),
-            SyntaxKind::DOT_EXPR => text.into(),
-            SyntaxKind::CALL_EXPR => text.into(),
-            SyntaxKind::UNARY_EXPR => text.into(),
-            SyntaxKind::BIN_EXPR => text.into(),
-            SyntaxKind::COND_EXPR => text.into(),
-            SyntaxKind::ASSIGN_EXPR => text.into(),
-            SyntaxKind::SEQUENCE_EXPR => text.into(),
-            SyntaxKind::ARG_LIST => text.into(),
-            SyntaxKind::LITERAL => text.into(),
-            SyntaxKind::TEMPLATE => text.into(),
-            SyntaxKind::TEMPLATE_ELEMENT => text.into(),
-            SyntaxKind::CONDITION => text.into(),
-            SyntaxKind::SPREAD_ELEMENT => text.into(),
-            SyntaxKind::SUPER_CALL => text.into(),
-            SyntaxKind::IMPORT_CALL => text.into(),
-            SyntaxKind::NEW_TARGET => text.into(),
-            SyntaxKind::IMPORT_META => text.into(),
-            SyntaxKind::IDENT_PROP => text.into(),
-            SyntaxKind::SPREAD_PROP => text.into(),
-            SyntaxKind::INITIALIZED_PROP => text.into(),
-            SyntaxKind::OBJECT_PATTERN => text.into(),
-            SyntaxKind::ARRAY_PATTERN => text.into(),
-            SyntaxKind::ASSIGN_PATTERN => text.into(),
-            SyntaxKind::REST_PATTERN => text.into(),
-            SyntaxKind::KEY_VALUE_PATTERN => text.into(),
-            SyntaxKind::COMPUTED_PROPERTY_NAME => text.into(),
-            SyntaxKind::FOR_OF_STMT => text.into(),
-            SyntaxKind::SINGLE_PATTERN => text.into(),
-            SyntaxKind::ARROW_EXPR => text.into(),
-            SyntaxKind::YIELD_EXPR => text.into(),
-            SyntaxKind::CLASS_DECL => text.into(),
-            SyntaxKind::CLASS_EXPR => text.into(),
-            SyntaxKind::CLASS_BODY => text.into(),
-            SyntaxKind::METHOD => text.into(),
-            SyntaxKind::IMPORT_DECL => text.into(),
-            SyntaxKind::EXPORT_DECL => text.into(),
-            SyntaxKind::EXPORT_NAMED => text.into(),
-            SyntaxKind::EXPORT_DEFAULT_DECL => text.into(),
-            SyntaxKind::EXPORT_DEFAULT_EXPR => text.into(),
-            SyntaxKind::EXPORT_WILDCARD => text.into(),
-            SyntaxKind::WILDCARD_IMPORT => text.into(),
-            SyntaxKind::NAMED_IMPORTS => text.into(),
-            SyntaxKind::SPECIFIER => text.into(),
-            SyntaxKind::AWAIT_EXPR => text.into(),
-            SyntaxKind::FOR_STMT_TEST => text.into(),
-            SyntaxKind::FOR_STMT_UPDATE => text.into(),
-            SyntaxKind::FOR_STMT_INIT => text.into(),
-            SyntaxKind::PRIVATE_NAME => text.into(),
-            SyntaxKind::CLASS_PROP => text.into(),
-            SyntaxKind::PRIVATE_PROP => text.into(),
-            SyntaxKind::CONSTRUCTOR => text.into(),
-            SyntaxKind::CONSTRUCTOR_PARAMETERS => text.into(),
-            SyntaxKind::PRIVATE_PROP_ACCESS => text.into(),
-            SyntaxKind::IMPORT_STRING_SPECIFIER => text.into(),
-            SyntaxKind::EXPR_PATTERN => text.into(),
-            SyntaxKind::TS_ANY => text.into(),
-            SyntaxKind::TS_UNKNOWN => text.into(),
-            SyntaxKind::TS_NUMBER => text.into(),
-            SyntaxKind::TS_OBJECT => text.into(),
-            SyntaxKind::TS_BOOLEAN => text.into(),
-            SyntaxKind::TS_BIGINT => text.into(),
-            SyntaxKind::TS_STRING => text.into(),
-            SyntaxKind::TS_SYMBOL => text.into(),
-            SyntaxKind::TS_VOID => text.into(),
-            SyntaxKind::TS_UNDEFINED => text.into(),
-            SyntaxKind::TS_NULL => text.into(),
-            SyntaxKind::TS_NEVER => text.into(),
-            SyntaxKind::TS_THIS => text.into(),
-            SyntaxKind::TS_LITERAL => text.into(),
-            SyntaxKind::TS_PREDICATE => text.into(),
-            SyntaxKind::TS_TUPLE => text.into(),
-            SyntaxKind::TS_TUPLE_ELEMENT => text.into(),
-            SyntaxKind::TS_PAREN => text.into(),
-            SyntaxKind::TS_TYPE_REF => text.into(),
-            SyntaxKind::TS_QUALIFIED_PATH => text.into(),
-            SyntaxKind::TS_TYPE_NAME => text.into(),
-            SyntaxKind::TS_TEMPLATE => text.into(),
-            SyntaxKind::TS_TEMPLATE_ELEMENT => text.into(),
-            SyntaxKind::TS_MAPPED_TYPE => text.into(),
-            SyntaxKind::TS_MAPPED_TYPE_PARAM => text.into(),
-            SyntaxKind::TS_MAPPED_TYPE_READONLY => text.into(),
-            SyntaxKind::TS_TYPE_QUERY => text.into(),
-            SyntaxKind::TS_TYPE_QUERY_EXPR => text.into(),
-            SyntaxKind::TS_IMPORT => text.into(),
-            SyntaxKind::TS_TYPE_ARGS => text.into(),
-            SyntaxKind::TS_ARRAY => text.into(),
-            SyntaxKind::TS_INDEXED_ARRAY => text.into(),
-            SyntaxKind::TS_TYPE_OPERATOR => text.into(),
-            SyntaxKind::TS_INTERSECTION => text.into(),
-            SyntaxKind::TS_UNION => text.into(),
-            SyntaxKind::TS_TYPE_PARAMS => text.into(),
-            SyntaxKind::TS_FN_TYPE => text.into(),
-            SyntaxKind::TS_CONSTRUCTOR_TYPE => text.into(),
-            SyntaxKind::TS_EXTENDS => text.into(),
-            SyntaxKind::TS_CONDITIONAL_TYPE => text.into(),
-            SyntaxKind::TS_CONSTRAINT => text.into(),
-            SyntaxKind::TS_DEFAULT => text.into(),
-            SyntaxKind::TS_TYPE_PARAM => text.into(),
-            SyntaxKind::TS_NON_NULL => text.into(),
-            SyntaxKind::TS_ASSERTION => text.into(),
-            SyntaxKind::TS_CONST_ASSERTION => text.into(),
-            SyntaxKind::TS_ENUM => text.into(),
-            SyntaxKind::TS_ENUM_MEMBER => text.into(),
-            SyntaxKind::TS_TYPE_ALIAS_DECL => text.into(),
-            SyntaxKind::TS_NAMESPACE_DECL => text.into(),
-            SyntaxKind::TS_MODULE_BLOCK => text.into(),
-            SyntaxKind::TS_MODULE_DECL => text.into(),
-            SyntaxKind::TS_CONSTRUCTOR_PARAM => text.into(),
-            SyntaxKind::TS_CALL_SIGNATURE_DECL => text.into(),
-            SyntaxKind::TS_CONSTRUCT_SIGNATURE_DECL => text.into(),
-            SyntaxKind::TS_INDEX_SIGNATURE => text.into(),
-            SyntaxKind::TS_METHOD_SIGNATURE => text.into(),
-            SyntaxKind::TS_PROPERTY_SIGNATURE => text.into(),
-            SyntaxKind::TS_INTERFACE_DECL => text.into(),
-            SyntaxKind::TS_ACCESSIBILITY => text.into(),
-            SyntaxKind::TS_OBJECT_TYPE => text.into(),
-            SyntaxKind::TS_EXPR_WITH_TYPE_ARGS => text.into(),
-            SyntaxKind::TS_IMPORT_EQUALS_DECL => text.into(),
-            SyntaxKind::TS_MODULE_REF => text.into(),
-            SyntaxKind::TS_EXTERNAL_MODULE_REF => text.into(),
-            SyntaxKind::TS_EXPORT_ASSIGNMENT => text.into(),
-            SyntaxKind::TS_NAMESPACE_EXPORT_DECL => text.into(),
-            SyntaxKind::TS_DECORATOR => text.into(),
-            SyntaxKind::TS_INFER => text.into(),
-            SyntaxKind::__LAST => text.into(),
+            SyntaxKind::TOMBSTONE => None,
+            SyntaxKind::EOF => None,
+            SyntaxKind::SEMICOLON => Some(HighlightGroup::Punctuation),
+            SyntaxKind::COMMA => Some(HighlightGroup::Punctuation),
+            SyntaxKind::L_PAREN => Some(HighlightGroup::Punctuation),
+            SyntaxKind::R_PAREN => Some(HighlightGroup::Punctuation),
+            SyntaxKind::L_CURLY => Some(HighlightGroup::Punctuation),
+            SyntaxKind::R_CURLY => Some(HighlightGroup::Punctuation),
+            SyntaxKind::L_BRACK => Some(HighlightGroup::Punctuation),
+            SyntaxKind::R_BRACK => Some(HighlightGroup::Punctuation),
+            SyntaxKind::L_ANGLE => Some(HighlightGroup::Punctuation),
+            SyntaxKind::R_ANGLE => Some(HighlightGroup::Punctuation),
+            SyntaxKind::TILDE => Some(HighlightGroup::Punctuation),
+            SyntaxKind::QUESTION => Some(HighlightGroup::Punctuation),
+            SyntaxKind::QUESTION2 => Some(HighlightGroup::Punctuation),
+            SyntaxKind::QUESTIONDOT => Some(HighlightGroup::Punctuation),
+            SyntaxKind::AMP => Some(HighlightGroup::Punctuation),
+            SyntaxKind::PIPE => Some(HighlightGroup::Punctuation),
+            SyntaxKind::PLUS => Some(HighlightGroup::Punctuation),
+            SyntaxKind::PLUS2 => Some(HighlightGroup::Punctuation),
+            SyntaxKind::STAR => Some(HighlightGroup::Punctuation),
+            SyntaxKind::STAR2 => Some(HighlightGroup::Punctuation),
+            SyntaxKind::SLASH => Some(HighlightGroup::Punctuation),
+            SyntaxKind::CARET => Some(HighlightGroup::Punctuation),
+            SyntaxKind::PERCENT => Some(HighlightGroup::Punctuation),
+            SyntaxKind::DOT => Some(HighlightGroup::Punctuation),
+            SyntaxKind::DOT2 => Some(HighlightGroup::Punctuation),
+            SyntaxKind::COLON => Some(HighlightGroup::Punctuation),
+            SyntaxKind::EQ => Some(HighlightGroup::Punctuation),
+            SyntaxKind::EQ2 => Some(HighlightGroup::Punctuation),
+            SyntaxKind::EQ3 => Some(HighlightGroup::Punctuation),
+            SyntaxKind::FAT_ARROW => Some(HighlightGroup::Punctuation),
+            SyntaxKind::BANG => Some(HighlightGroup::Punctuation),
+            SyntaxKind::NEQ => Some(HighlightGroup::Punctuation),
+            SyntaxKind::NEQ2 => Some(HighlightGroup::Punctuation),
+            SyntaxKind::MINUS => Some(HighlightGroup::Punctuation),
+            SyntaxKind::MINUS2 => Some(HighlightGroup::Punctuation),
+            SyntaxKind::LTEQ => Some(HighlightGroup::Punctuation),
+            SyntaxKind::GTEQ => Some(HighlightGroup::Punctuation),
+            SyntaxKind::PLUSEQ => Some(HighlightGroup::Punctuation),
+            SyntaxKind::MINUSEQ => Some(HighlightGroup::Punctuation),
+            SyntaxKind::PIPEEQ => Some(HighlightGroup::Punctuation),
+            SyntaxKind::AMPEQ => Some(HighlightGroup::Punctuation),
+            SyntaxKind::CARETEQ => Some(HighlightGroup::Punctuation),
+            SyntaxKind::SLASHEQ => Some(HighlightGroup::Punctuation),
+            SyntaxKind::STAREQ => Some(HighlightGroup::Punctuation),
+            SyntaxKind::PERCENTEQ => Some(HighlightGroup::Punctuation),
+            SyntaxKind::AMP2 => Some(HighlightGroup::Punctuation),
+            SyntaxKind::PIPE2 => Some(HighlightGroup::Punctuation),
+            SyntaxKind::SHL => Some(HighlightGroup::Punctuation),
+            SyntaxKind::SHR => Some(HighlightGroup::Punctuation),
+            SyntaxKind::USHR => Some(HighlightGroup::Punctuation),
+            SyntaxKind::SHLEQ => Some(HighlightGroup::Punctuation),
+            SyntaxKind::SHREQ => Some(HighlightGroup::Punctuation),
+            SyntaxKind::USHREQ => Some(HighlightGroup::Punctuation),
+            SyntaxKind::AMP2EQ => Some(HighlightGroup::Punctuation),
+            SyntaxKind::PIPE2EQ => Some(HighlightGroup::Punctuation),
+            SyntaxKind::STAR2EQ => Some(HighlightGroup::Punctuation),
+            SyntaxKind::QUESTION2EQ => Some(HighlightGroup::Punctuation),
+            SyntaxKind::AT => Some(HighlightGroup::Punctuation),
+            SyntaxKind::AWAIT_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::BREAK_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::CASE_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::CATCH_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::CLASS_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::CONST_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::CONTINUE_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::DEBUGGER_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::DEFAULT_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::DELETE_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::DO_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::ELSE_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::ENUM_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::EXPORT_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::EXTENDS_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::FALSE_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::FINALLY_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::FOR_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::FUNCTION_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::IF_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::IN_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::INSTANCEOF_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::INTERFACE_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::IMPORT_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::IMPLEMENTS_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::NEW_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::NULL_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::PACKAGE_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::PRIVATE_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::PROTECTED_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::PUBLIC_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::RETURN_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::SUPER_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::SWITCH_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::THIS_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::THROW_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::TRY_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::TRUE_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::TYPEOF_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::VAR_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::VOID_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::WHILE_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::WITH_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::YIELD_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::READONLY_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::KEYOF_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::UNIQUE_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::DECLARE_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::ABSTRACT_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::STATIC_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::ASYNC_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::TYPE_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::FROM_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::AS_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::REQUIRE_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::NAMESPACE_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::ASSERT_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::MODULE_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::GLOBAL_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::INFER_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::GET_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::SET_KW => Some(HighlightGroup::Keyword),
+            SyntaxKind::NUMBER => Some(HighlightGroup::Number),
+            SyntaxKind::STRING => Some(HighlightGroup::String),
+            SyntaxKind::REGEX => Some(HighlightGroup::String),
+            SyntaxKind::HASH => None,
+            SyntaxKind::TEMPLATE_CHUNK => Some(HighlightGroup::String),
+            SyntaxKind::DOLLARCURLY => None,
+            SyntaxKind::BACKTICK => Some(HighlightGroup::String),
+            SyntaxKind::ERROR_TOKEN => None,
+            SyntaxKind::IDENT => None,
+            SyntaxKind::WHITESPACE => None,
+            SyntaxKind::COMMENT => Some(HighlightGroup::Comment),
+            SyntaxKind::SHEBANG => None,
+            SyntaxKind::SCRIPT => None,
+            SyntaxKind::MODULE => None,
+            SyntaxKind::ERROR => None,
+            SyntaxKind::BLOCK_STMT => None,
+            SyntaxKind::VAR_DECL => None,
+            SyntaxKind::DECLARATOR => None,
+            SyntaxKind::EMPTY_STMT => None,
+            SyntaxKind::EXPR_STMT => None,
+            SyntaxKind::IF_STMT => None,
+            SyntaxKind::DO_WHILE_STMT => None,
+            SyntaxKind::WHILE_STMT => None,
+            SyntaxKind::FOR_STMT => None,
+            SyntaxKind::FOR_IN_STMT => None,
+            SyntaxKind::CONTINUE_STMT => None,
+            SyntaxKind::BREAK_STMT => None,
+            SyntaxKind::RETURN_STMT => None,
+            SyntaxKind::WITH_STMT => None,
+            SyntaxKind::SWITCH_STMT => None,
+            SyntaxKind::CASE_CLAUSE => None,
+            SyntaxKind::DEFAULT_CLAUSE => None,
+            SyntaxKind::LABELLED_STMT => None,
+            SyntaxKind::THROW_STMT => None,
+            SyntaxKind::TRY_STMT => None,
+            SyntaxKind::CATCH_CLAUSE => None,
+            SyntaxKind::FINALIZER => None,
+            SyntaxKind::DEBUGGER_STMT => None,
+            SyntaxKind::FN_DECL => None,
+            SyntaxKind::NAME => None,
+            SyntaxKind::NAME_REF => None,
+            SyntaxKind::PARAMETER_LIST => None,
+            SyntaxKind::THIS_EXPR => None,
+            SyntaxKind::ARRAY_EXPR => None,
+            SyntaxKind::OBJECT_EXPR => None,
+            SyntaxKind::LITERAL_PROP => None,
+            SyntaxKind::GETTER => None,
+            SyntaxKind::SETTER => None,
+            SyntaxKind::GROUPING_EXPR => None,
+            SyntaxKind::NEW_EXPR => None,
+            SyntaxKind::FN_EXPR => None,
+            SyntaxKind::BRACKET_EXPR => None,
+            SyntaxKind::DOT_EXPR => None,
+            SyntaxKind::CALL_EXPR => None,
+            SyntaxKind::UNARY_EXPR => None,
+            SyntaxKind::BIN_EXPR => None,
+            SyntaxKind::COND_EXPR => None,
+            SyntaxKind::ASSIGN_EXPR => None,
+            SyntaxKind::SEQUENCE_EXPR => None,
+            SyntaxKind::ARG_LIST => None,
+            SyntaxKind::LITERAL => None,
+            SyntaxKind::TEMPLATE => None,
+            SyntaxKind::TEMPLATE_ELEMENT => None,
+            SyntaxKind::CONDITION => None,
+            SyntaxKind::SPREAD_ELEMENT => None,
+            SyntaxKind::SUPER_CALL => None,
+            SyntaxKind::IMPORT_CALL => None,
+            SyntaxKind::NEW_TARGET => None,
+            SyntaxKind::IMPORT_META => None,
+            SyntaxKind::IDENT_PROP => None,
+            SyntaxKind::SPREAD_PROP => None,
+            SyntaxKind::INITIALIZED_PROP => None,
+            SyntaxKind::OBJECT_PATTERN => None,
+            SyntaxKind::ARRAY_PATTERN => None,
+            SyntaxKind::ASSIGN_PATTERN => None,
+            SyntaxKind::REST_PATTERN => None,
+            SyntaxKind::KEY_VALUE_PATTERN => None,
+            SyntaxKind::COMPUTED_PROPERTY_NAME => None,
+            SyntaxKind::FOR_OF_STMT => None,
+            SyntaxKind::SINGLE_PATTERN => None,
+            SyntaxKind::ARROW_EXPR => None,
+            SyntaxKind::YIELD_EXPR => None,
+            SyntaxKind::CLASS_DECL => None,
+            SyntaxKind::CLASS_EXPR => None,
+            SyntaxKind::CLASS_BODY => None,
+            SyntaxKind::METHOD => None,
+            SyntaxKind::IMPORT_DECL => None,
+            SyntaxKind::EXPORT_DECL => None,
+            SyntaxKind::EXPORT_NAMED => None,
+            SyntaxKind::EXPORT_DEFAULT_DECL => None,
+            SyntaxKind::EXPORT_DEFAULT_EXPR => None,
+            SyntaxKind::EXPORT_WILDCARD => None,
+            SyntaxKind::WILDCARD_IMPORT => None,
+            SyntaxKind::NAMED_IMPORTS => None,
+            SyntaxKind::SPECIFIER => None,
+            SyntaxKind::AWAIT_EXPR => None,
+            SyntaxKind::FOR_STMT_TEST => None,
+            SyntaxKind::FOR_STMT_UPDATE => None,
+            SyntaxKind::FOR_STMT_INIT => None,
+            SyntaxKind::PRIVATE_NAME => None,
+            SyntaxKind::CLASS_PROP => None,
+            SyntaxKind::PRIVATE_PROP => None,
+            SyntaxKind::CONSTRUCTOR => None,
+            SyntaxKind::CONSTRUCTOR_PARAMETERS => None,
+            SyntaxKind::PRIVATE_PROP_ACCESS => None,
+            SyntaxKind::IMPORT_STRING_SPECIFIER => None,
+            SyntaxKind::EXPR_PATTERN => None,
+            SyntaxKind::TS_ANY => None,
+            SyntaxKind::TS_UNKNOWN => None,
+            SyntaxKind::TS_NUMBER => None,
+            SyntaxKind::TS_OBJECT => None,
+            SyntaxKind::TS_BOOLEAN => None,
+            SyntaxKind::TS_BIGINT => None,
+            SyntaxKind::TS_STRING => None,
+            SyntaxKind::TS_SYMBOL => None,
+            SyntaxKind::TS_VOID => None,
+            SyntaxKind::TS_UNDEFINED => None,
+            SyntaxKind::TS_NULL => None,
+            SyntaxKind::TS_NEVER => None,
+            SyntaxKind::TS_THIS => None,
+            SyntaxKind::TS_LITERAL => None,
+            SyntaxKind::TS_PREDICATE => None,
+            SyntaxKind::TS_TUPLE => None,
+            SyntaxKind::TS_TUPLE_ELEMENT => None,
+            SyntaxKind::TS_PAREN => None,
+            SyntaxKind::TS_TYPE_REF => None,
+            SyntaxKind::TS_QUALIFIED_PATH => None,
+            SyntaxKind::TS_TYPE_NAME => None,
+            SyntaxKind::TS_TEMPLATE => None,
+            SyntaxKind::TS_TEMPLATE_ELEMENT => None,
+            SyntaxKind::TS_MAPPED_TYPE => None,
+            SyntaxKind::TS_MAPPED_TYPE_PARAM => None,
+            SyntaxKind::TS_MAPPED_TYPE_READONLY => None,
+            SyntaxKind::TS_TYPE_QUERY => None,
+            SyntaxKind::TS_TYPE_QUERY_EXPR => None,
+            SyntaxKind::TS_IMPORT => None,
+            SyntaxKind::TS_TYPE_ARGS => None,
+            SyntaxKind::TS_ARRAY => None,
+            SyntaxKind::TS_INDEXED_ARRAY => None,
+            SyntaxKind::TS_TYPE_OPERATOR => None,
+            SyntaxKind::TS_INTERSECTION => None,
+            SyntaxKind::TS_UNION => None,
+            SyntaxKind::TS_TYPE_PARAMS => None,
+            SyntaxKind::TS_FN_TYPE => None,
+            SyntaxKind::TS_CONSTRUCTOR_TYPE => None,
+            SyntaxKind::TS_EXTENDS => None,
+            SyntaxKind::TS_CONDITIONAL_TYPE => None,
+            SyntaxKind::TS_CONSTRAINT => None,
+            SyntaxKind::TS_DEFAULT => None,
+            SyntaxKind::TS_TYPE_PARAM => None,
+            SyntaxKind::TS_NON_NULL => None,
+            SyntaxKind::TS_ASSERTION => None,
+            SyntaxKind::TS_CONST_ASSERTION => None,
+            SyntaxKind::TS_ENUM => None,
+            SyntaxKind::TS_ENUM_MEMBER => None,
+            SyntaxKind::TS_TYPE_ALIAS_DECL => None,
+            SyntaxKind::TS_NAMESPACE_DECL => None,
+            SyntaxKind::TS_MODULE_BLOCK => None,
+            SyntaxKind::TS_MODULE_DECL => None,
+            SyntaxKind::TS_CONSTRUCTOR_PARAM => None,
+            SyntaxKind::TS_CALL_SIGNATURE_DECL => None,
+            SyntaxKind::TS_CONSTRUCT_SIGNATURE_DECL => None,
+            SyntaxKind::TS_INDEX_SIGNATURE => None,
+            SyntaxKind::TS_METHOD_SIGNATURE => None,
+            SyntaxKind::TS_PROPERTY_SIGNATURE => None,
+            SyntaxKind::TS_INTERFACE_DECL => None,
+            SyntaxKind::TS_ACCESSIBILITY => None,
+            SyntaxKind::TS_OBJECT_TYPE => None,
+            SyntaxKind::TS_EXPR_WITH_TYPE_ARGS => None,
+            SyntaxKind::TS_IMPORT_EQUALS_DECL => None,
+            SyntaxKind::TS_MODULE_REF => None,
+            SyntaxKind::TS_EXTERNAL_MODULE_REF => None,
+            SyntaxKind::TS_EXPORT_ASSIGNMENT => None,
+            SyntaxKind::TS_NAMESPACE_EXPORT_DECL => None,
+            SyntaxKind::TS_DECORATOR => None,
+            SyntaxKind::TS_INFER => None,
+            SyntaxKind::__LAST => None,
         }
     }
 
     fn is_reparsable(&self) -> bool {
-        matches!(self, SyntaxKind::SCRIPT | SyntaxKind::EXPR_STMT)
+        matches!(
+            self,
+            SyntaxKind::SCRIPT | SyntaxKind::MODULE | SyntaxKind::EXPR_STMT
+        )
     }
 
     fn parse(
         literal: &str,
         parent: Option<Rc<RefCell<InMemoryNode<Self>>>>,
     ) -> Rc<RefCell<InMemoryNode<Self>>> {
-        let parse = parse_text(literal, 0);
-        // The untyped syntax node of `foo.bar[2]`, the root node is `Script`.
-        let untyped_expr_node = parse.syntax();
+        // Parse with the same mode as the rest of the document
+        let mode = parent
+            .as_ref()
+            .map(InMemoryNode::parse_options)
+            .unwrap_or_default();
+
+        // The untyped syntax node of `foo.bar[2]`, the root node is `Script` (or `Module`).
+        let Some((untyped_expr_node, errors)) = parse_text_without_panicking(literal, mode) else {
+            // The parser couldn't make sense of the text at all, so keep it around unparsed so that
+            // no part of the document is lost
+            let node = InMemoryNode::new_from_literal(literal);
+            InMemoryNode::attach_diagnostic(
+                &node,
+                Diagnostic::new(
+                    0..literal.chars().count(),
+                    Severity::Error,
+                    "Unable to parse this text",
+                ),
+            );
+            return node;
+        };
 
         let root = convert_rslint_syntaxnode_to_inmemorynode(untyped_expr_node);
 
@@ -749,7 +971,7 @@ impl TokenKindTrait for SyntaxKind {
         //       - D
         //       - E
         //       - F
-        if let Some(NodeMetadata::AstNode {
+        let node = if let Some(NodeMetadata::AstNode {
             kind: parent_kind, ..
         }) = parent.as_ref().map(|n| n.borrow().metadata.clone())
         {
@@ -775,7 +997,8 @@ impl TokenKindTrait for SyntaxKind {
                     //
                     // So, bail out of this optimization, and just return root. The newly generated
                     // AST is signifigantly enough different where this path is not viable.
-                    return root;
+                    pointer = root.clone();
+                    break;
                 };
                 pointer = first_child;
             }
@@ -783,6 +1006,202 @@ impl TokenKindTrait for SyntaxKind {
             pointer
         } else {
             root
+        };
+
+        // Every node skipped over above has no text of its own, so the errors' ranges are still
+        // relative to the start of `node`
+        for error in errors.iter() {
+            InMemoryNode::attach_diagnostic(
+                &node,
+                convert_parser_error_to_diagnostic(literal, error),
+            );
         }
+
+        node
+    }
+
+    /// Fields are named after the accessors of rslint_parser's typed AST, ie, the `NAME` in a
+    /// `FN_DECL` is its `name`, and its `BLOCK_STMT` is its `body`.
+    fn field_name(parent: &Self, children: &[Self], index: usize) -> Option<&'static str> {
+        let kind = *children.get(index)?;
+        let previous = children[..index]
+            .iter()
+            .rev()
+            .find(|kind| !matches!(kind, SyntaxKind::WHITESPACE | SyntaxKind::COMMENT))
+            .copied();
+        let is_first = previous.is_none();
+        match (parent, kind) {
+            (
+                SyntaxKind::FN_DECL
+                | SyntaxKind::FN_EXPR
+                | SyntaxKind::METHOD
+                | SyntaxKind::GETTER
+                | SyntaxKind::SETTER
+                | SyntaxKind::CLASS_DECL
+                | SyntaxKind::CLASS_EXPR
+                | SyntaxKind::TS_INTERFACE_DECL
+                | SyntaxKind::TS_TYPE_ALIAS_DECL
+                | SyntaxKind::TS_ENUM
+                | SyntaxKind::TS_PROPERTY_SIGNATURE
+                | SyntaxKind::TS_METHOD_SIGNATURE,
+                SyntaxKind::NAME,
+            ) => Some("name"),
+            (
+                SyntaxKind::FN_DECL
+                | SyntaxKind::FN_EXPR
+                | SyntaxKind::METHOD
+                | SyntaxKind::GETTER
+                | SyntaxKind::SETTER
+                | SyntaxKind::CONSTRUCTOR
+                | SyntaxKind::ARROW_EXPR
+                | SyntaxKind::TS_METHOD_SIGNATURE,
+                SyntaxKind::PARAMETER_LIST,
+            ) => Some("parameters"),
+            (
+                SyntaxKind::FN_DECL
+                | SyntaxKind::FN_EXPR
+                | SyntaxKind::METHOD
+                | SyntaxKind::GETTER
+                | SyntaxKind::SETTER
+                | SyntaxKind::CONSTRUCTOR,
+                SyntaxKind::BLOCK_STMT,
+            ) => Some("body"),
+            (SyntaxKind::CLASS_DECL | SyntaxKind::CLASS_EXPR, SyntaxKind::CLASS_BODY) => {
+                Some("body")
+            }
+            (SyntaxKind::CLASS_DECL | SyntaxKind::CLASS_EXPR, _)
+                if previous == Some(SyntaxKind::EXTENDS_KW) =>
+            {
+                Some("parent")
+            }
+            (SyntaxKind::ARROW_EXPR, _) if previous == Some(SyntaxKind::FAT_ARROW) => Some("body"),
+            (SyntaxKind::DECLARATOR, _) if is_first => Some("pattern"),
+            (SyntaxKind::DECLARATOR, _) if previous == Some(SyntaxKind::EQ) => Some("value"),
+            (SyntaxKind::CALL_EXPR | SyntaxKind::NEW_EXPR, SyntaxKind::ARG_LIST) => {
+                Some("arguments")
+            }
+            (SyntaxKind::CALL_EXPR, _) if is_first => Some("callee"),
+            (SyntaxKind::NEW_EXPR, _) if previous == Some(SyntaxKind::NEW_KW) => Some("callee"),
+            (SyntaxKind::DOT_EXPR, _) if is_first => Some("object"),
+            (SyntaxKind::DOT_EXPR, SyntaxKind::NAME) => Some("prop"),
+            (
+                SyntaxKind::IF_STMT | SyntaxKind::WHILE_STMT | SyntaxKind::DO_WHILE_STMT,
+                SyntaxKind::CONDITION,
+            ) => Some("condition"),
+            (SyntaxKind::IF_STMT, _) if previous == Some(SyntaxKind::CONDITION) => Some("cons"),
+            (SyntaxKind::IF_STMT, _) if previous == Some(SyntaxKind::ELSE_KW) => Some("alt"),
+            (SyntaxKind::WHILE_STMT, _) if previous == Some(SyntaxKind::CONDITION) => Some("body"),
+            (SyntaxKind::RETURN_STMT, SyntaxKind::SEMICOLON) => None,
+            (SyntaxKind::RETURN_STMT, _) if previous == Some(SyntaxKind::RETURN_KW) => {
+                Some("value")
+            }
+            _ => None,
+        }
+    }
+
+    fn outline_query(_parse_options: &ParseMode) -> Option<&'static str> {
+        Some(OUTLINE_QUERY)
+    }
+
+    // Block statements include the bodies of functions, methods, and arrow functions, and
+    // comments are only folded when they are `/* */` comments spanning more than one row
+    fn is_foldable(&self) -> bool {
+        matches!(
+            self,
+            SyntaxKind::BLOCK_STMT
+                | SyntaxKind::OBJECT_EXPR
+                | SyntaxKind::CLASS_BODY
+                | SyntaxKind::TS_MODULE_BLOCK
+                | SyntaxKind::COMMENT
+        )
+    }
+
+    /// Templates tagged with `css` / `styled.div` / `styled(Button)` (styled-components,
+    /// emotion, etc) contain CSS. See `is_css_template_tag` for the tags that are recognized.
+    ///
+    /// NOTE: only templates without any `${}` interpolations are injected, since the text in
+    /// between interpolations usually isn't valid in the other language on its own.
+    fn injected_language(node: &Rc<RefCell<InMemoryNode<Self>>>) -> Option<&'static str> {
+        let kind_of = |node: &Rc<RefCell<InMemoryNode<Self>>>| match node.borrow().metadata {
+            NodeMetadata::AstNode { kind, .. } => Some(kind),
+            _ => None,
+        };
+        if kind_of(node) != Some(SyntaxKind::TEMPLATE_CHUNK) {
+            return None;
+        }
+        let template = node
+            .borrow()
+            .parent
+            .as_ref()
+            .and_then(|parent| parent.upgrade())?;
+        let children = template.borrow().children.clone();
+        if kind_of(&template) != Some(SyntaxKind::TEMPLATE)
+            || children
+                .iter()
+                .any(|child| kind_of(child) == Some(SyntaxKind::TEMPLATE_ELEMENT))
+            || !is_template_chunk(&InMemoryNode::deep_literal(node))
+        {
+            return None;
+        }
+
+        // The tag comes before the opening backtick, ie, `styled.div` or `styled(Button)`
+        let tag = children
+            .first()
+            .filter(|tag| kind_of(tag) != Some(SyntaxKind::BACKTICK))
+            .map(InMemoryNode::deep_literal)?;
+        is_css_template_tag(tag.trim()).then_some("css")
+    }
+}
+
+/// When called, returns true if `text` could be all of the text of a template without any `${}`
+/// interpolations, ie, it has no `${` or backticks in it that aren't escaped with a `\`. Chunks of
+/// injected text are edited without the template around them being reparsed, so they might not be.
+fn is_template_chunk(text: &str) -> bool {
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '`' => return false,
+            '$' if chars.peek() == Some(&'{') => return false,
+            _ => {}
+        }
+    }
+    true
+}
+
+/// When called, returns true if `tag` is one of the template tags that CSS-in-JS libraries use
+/// for templates containing CSS, ie, `css`, `keyframes`, `styled.div`, `styled(Button)`, or
+/// `styled.div.attrs({ role: "button" })`. Other uses of those names (ie, `css.escape()` or
+/// `mycss`) aren't, since they are more likely to be the project's own functions.
+fn is_css_template_tag(tag: &str) -> bool {
+    if matches!(
+        tag,
+        "css" | "createGlobalStyle" | "keyframes" | "injectGlobal"
+    ) {
+        return true;
+    }
+    let Some(styled) = tag.strip_prefix("styled") else {
+        return false;
+    };
+
+    // Options can be passed along with `.attrs(..)` / `.withConfig(..)`, and props can be typed
+    // with `<Props>` in TypeScript
+    let styled = [".attrs(", ".withConfig("]
+        .iter()
+        .filter_map(|method| styled.find(method))
+        .min()
+        .filter(|_| styled.ends_with(')'))
+        .map_or(styled, |index| &styled[..index]);
+    let styled = match styled.find('<') {
+        Some(index) if styled.ends_with('>') => &styled[..index],
+        _ => styled,
+    };
+
+    if let Some(element) = styled.strip_prefix('.') {
+        !element.is_empty() && element.chars().all(|c| c.is_ascii_alphanumeric())
+    } else {
+        styled.starts_with('(') && styled.ends_with(')')
     }
 }
@@ -0,0 +1,1212 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::node_tree::{
+    diagnostic::{Diagnostic, Severity},
+    node::{InMemoryNode, NodeMetadata, TokenKindTrait},
+    style::HighlightGroup,
+    utils::unquote,
+};
+
+/// The kinds of nodes that make up a parsed YAML stream.
+///
+/// Streams, documents, mappings, sequences, their entries and items, flow collections, and block
+/// scalars contain other nodes, and everything else is a token with its own literal text. Like
+/// `languages::json`, whitespace, newlines, and comments are kept in the tree as tokens, so the
+/// literal text of a parsed stream is always exactly what was parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyntaxKind {
+    Stream,
+    Document,
+    Mapping,      // A block mapping, ie, `a: 1\nb: 2`
+    MappingEntry, // A `key: value` pair, in either a block or flow mapping
+    Sequence,     // A block sequence, ie, `- a\n- b`
+    SequenceItem, // A `- value` item in a block sequence
+    FlowMapping,  // ie, `{ a: 1, b: 2 }`
+    FlowSequence, // ie, `[a, b]`
+    BlockScalar,  // A `|` or `>` header, followed by the indented lines of text under it
+
+    Key,
+    PlainScalar, // An unquoted string, ie, the `ubuntu-latest` in `runs-on: ubuntu-latest`
+    String,      // A single or double quoted string
+    Number,
+    Boolean,
+    Null,
+    BlockScalarHeader, // ie, `|`, `>-`, or `|+2`
+    BlockScalarText,
+    Anchor, // ie, `&default`
+    Alias,  // ie, `*default`
+    Tag,    // ie, `!!str`
+    DocumentStart,
+    DocumentEnd,
+    Directive, // ie, `%YAML 1.2`
+
+    Colon,
+    Dash,
+    Comma,
+    LBrack,
+    RBrack,
+    LCurly,
+    RCurly,
+    Whitespace,
+    Newline,
+    Comment,
+    Error, // Text that isn't part of any YAML token
+}
+
+impl SyntaxKind {
+    fn is_value(&self) -> bool {
+        matches!(
+            self,
+            SyntaxKind::PlainScalar
+                | SyntaxKind::String
+                | SyntaxKind::Number
+                | SyntaxKind::Boolean
+                | SyntaxKind::Null
+                | SyntaxKind::Alias
+                | SyntaxKind::FlowMapping
+                | SyntaxKind::FlowSequence
+                | SyntaxKind::MappingEntry
+        )
+    }
+}
+
+/// When called, returns the kind of plain (unquoted) scalar that `text` is, following the YAML
+/// 1.2 core schema.
+fn plain_scalar_kind(text: &str) -> SyntaxKind {
+    let is_digits = |text: &str| !text.is_empty() && text.chars().all(|c| c.is_ascii_digit());
+    let unsigned = text.trim_start_matches(['+', '-']);
+    let (mantissa, exponent) = match unsigned.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, Some(exponent)),
+        None => (unsigned, None),
+    };
+    let is_mantissa = match mantissa.split_once('.') {
+        Some((integer, fraction)) => {
+            (is_digits(integer) || integer.is_empty())
+                && (is_digits(fraction) || fraction.is_empty())
+                && mantissa != "."
+        }
+        None => is_digits(mantissa),
+    };
+    let is_number = (is_mantissa
+        && exponent.is_none_or(|exponent| is_digits(exponent.trim_start_matches(['+', '-']))))
+        || text
+            .strip_prefix("0x")
+            .or_else(|| text.strip_prefix("0o"))
+            .is_some_and(|digits| {
+                !digits.is_empty() && digits.chars().all(|c| c.is_ascii_hexdigit())
+            })
+        || matches!(
+            unsigned,
+            ".inf" | ".Inf" | ".INF" | ".nan" | ".NaN" | ".NAN"
+        );
+
+    match text {
+        "null" | "Null" | "NULL" | "~" => SyntaxKind::Null,
+        "true" | "True" | "TRUE" | "false" | "False" | "FALSE" => SyntaxKind::Boolean,
+        _ if is_number => SyntaxKind::Number,
+        _ => SyntaxKind::PlainScalar,
+    }
+}
+
+/// The position of a line within the text being parsed, in chars.
+#[derive(Debug, Clone, Copy)]
+struct Line {
+    start: usize,
+    content_start: usize, // After the spaces that the line is indented with
+    end: usize,           // Before the line ending
+}
+
+impl Line {
+    fn indent(&self) -> usize {
+        self.content_start - self.start
+    }
+}
+
+/// An indentation sensitive recursive descent parser that turns YAML text into a token tree. The
+/// text is split into lines up front, and block collections are parsed by comparing the
+/// indentation of each line with the column that the collection started at. Text that can't be
+/// parsed, ie, an unterminated string or a stray `:` in a flow collection, is kept in the tree as
+/// an Error or String node with a diagnostic.
+struct Parser {
+    chars: Vec<char>,
+    lines: Vec<Line>,
+    index: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Parser {
+    fn new(literal: &str) -> Self {
+        let chars = literal.chars().collect::<Vec<char>>();
+        let mut lines = vec![];
+        let mut start = 0;
+        while start <= chars.len() {
+            let end = (start..chars.len())
+                .find(|index| matches!(chars[*index], '\n' | '\r'))
+                .unwrap_or(chars.len());
+            // NOTE: a byte order mark is treated as indentation so that it doesn't show up as an error
+            let content_start = (start..end)
+                .find(|index| !matches!(chars[*index], ' ' | '\u{feff}'))
+                .unwrap_or(end);
+            lines.push(Line {
+                start,
+                content_start,
+                end,
+            });
+
+            start = end
+                + if chars[end..].starts_with(&['\r', '\n']) {
+                    2
+                } else {
+                    1
+                };
+        }
+
+        Self {
+            chars,
+            lines,
+            index: 0,
+            diagnostics: vec![],
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.index).copied()
+    }
+
+    fn peek_at(&self, index: usize) -> Option<char> {
+        self.chars.get(index).copied()
+    }
+
+    fn error(&mut self, range: std::ops::Range<usize>, message: &str) {
+        self.diagnostics
+            .push(Diagnostic::new(range, Severity::Error, message));
+    }
+
+    /// When called, returns the line that the char at `index` is on.
+    fn line(&self, index: usize) -> Line {
+        let line_index = self.lines.partition_point(|line| line.start <= index);
+        self.lines[line_index.saturating_sub(1)]
+    }
+
+    fn column(&self, index: usize) -> usize {
+        index - self.line(index).start
+    }
+
+    /// When called, returns the end of the text on the current line that isn't a comment or
+    /// trailing whitespace.
+    fn content_end(&self) -> usize {
+        let line = self.line(self.index);
+        let mut end = line.end;
+        if let Some(comment) = (self.index..line.end).find(|index| {
+            self.chars[*index] == '#'
+                && (*index == line.start || matches!(self.chars[*index - 1], ' ' | '\t'))
+        }) {
+            end = comment;
+        }
+        while end > self.index && matches!(self.chars[end - 1], ' ' | '\t') {
+            end -= 1;
+        }
+        end
+    }
+
+    fn has_content_on_line(&self) -> bool {
+        self.content_end() > self.index
+    }
+
+    /// When called, returns the next line at or after the current position that has something
+    /// other than whitespace or a comment on it.
+    fn next_content_line(&self) -> Option<Line> {
+        self.lines
+            .iter()
+            .find(|line| {
+                line.content_start >= self.index
+                    && line.content_start < line.end
+                    && self.chars[line.content_start] != '#'
+            })
+            .copied()
+    }
+
+    fn is_document_marker(&self, line: &Line, marker: &str) -> bool {
+        let text = marker.chars().collect::<Vec<char>>();
+        line.indent() == 0
+            && self.chars[line.start..].starts_with(&text)
+            && self
+                .peek_at(line.start + 3)
+                .is_none_or(|c| c.is_whitespace())
+    }
+
+    fn is_marker_line(&self, line: &Line) -> bool {
+        self.is_document_marker(line, "---") || self.is_document_marker(line, "...")
+    }
+
+    /// When called, returns true if there is a `- ` that starts a block sequence item at `index`.
+    fn is_sequence_item(&self, index: usize) -> bool {
+        self.peek_at(index) == Some('-')
+            && self
+                .peek_at(index + 1)
+                .is_none_or(|c| matches!(c, ' ' | '\t' | '\n' | '\r'))
+    }
+
+    /// When called, returns the length of the key of the block mapping entry that starts at
+    /// `index`, or None if there isn't one there.
+    fn mapping_key_length(&self, index: usize) -> Option<usize> {
+        let line = self.line(index);
+        let is_value_indicator = |index: usize| {
+            self.peek_at(index) == Some(':')
+                && self
+                    .peek_at(index + 1)
+                    .is_none_or(|c| matches!(c, ' ' | '\t' | '\n' | '\r'))
+        };
+
+        match self.peek_at(index)? {
+            quote @ ('"' | '\'') => {
+                let end = (index + 1..line.end).find(|end| {
+                    self.chars[*end] == quote && (quote == '\'' || self.chars[*end - 1] != '\\')
+                })? + 1;
+                let colon =
+                    (end..line.end).find(|index| !matches!(self.chars[*index], ' ' | '\t'))?;
+                is_value_indicator(colon).then_some(end - index)
+            }
+            '[' | '{' | '#' | '|' | '>' | '*' | '&' | '!' | '?' | '%' | '@' | '`' => None,
+            _ if self.is_sequence_item(index) => None,
+            _ => {
+                let colon = (index..line.end)
+                    .take_while(|index| {
+                        !(self.chars[*index] == '#' && matches!(self.chars[*index - 1], ' ' | '\t'))
+                    })
+                    .find(|index| is_value_indicator(*index))?;
+                let mut end = colon;
+                while end > index && matches!(self.chars[end - 1], ' ' | '\t') {
+                    end -= 1;
+                }
+                Some(end - index)
+            }
+        }
+    }
+
+    /// When called, consumes `length` chars, and appends them to `parent` as a node of kind `kind`.
+    fn bump(
+        &mut self,
+        parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>,
+        kind: SyntaxKind,
+        length: usize,
+    ) {
+        let literal = self.chars[self.index..self.index + length].iter().collect();
+        self.index += length;
+        InMemoryNode::append_child(
+            parent,
+            InMemoryNode::new_with_metadata(NodeMetadata::AstNode {
+                kind,
+                literal: Some(literal),
+            }),
+        );
+    }
+
+    fn bump_until(
+        &mut self,
+        parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>,
+        kind: SyntaxKind,
+        end: usize,
+    ) {
+        if end > self.index {
+            self.bump(parent, kind, end - self.index);
+        }
+    }
+
+    /// When called, consumes whitespace, newlines, and comments up until `end`.
+    fn eat_trivia_until(&mut self, parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>, end: usize) {
+        while self.index < end {
+            let line_end = self.line(self.index).end;
+            match self.chars[self.index] {
+                '\r' if self.peek_at(self.index + 1) == Some('\n') => {
+                    self.bump(parent, SyntaxKind::Newline, 2)
+                }
+                '\r' | '\n' => self.bump(parent, SyntaxKind::Newline, 1),
+                '#' => self.bump_until(parent, SyntaxKind::Comment, line_end.min(end)),
+                ' ' | '\t' | '\u{feff}' => {
+                    let length = self.chars[self.index..end]
+                        .iter()
+                        .take_while(|c| matches!(c, ' ' | '\t' | '\u{feff}'))
+                        .count();
+                    self.bump(parent, SyntaxKind::Whitespace, length);
+                }
+                _ => self.bump(parent, SyntaxKind::Error, 1),
+            };
+        }
+    }
+
+    /// When called, consumes the rest of the current line (but not its line ending), which
+    /// should only contain whitespace and a comment.
+    fn eat_line_rest(&mut self, parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>) {
+        let content_end = self.content_end();
+        if content_end > self.index {
+            let whitespace_length = self.chars[self.index..content_end]
+                .iter()
+                .take_while(|c| matches!(c, ' ' | '\t'))
+                .count();
+            self.bump(parent, SyntaxKind::Whitespace, whitespace_length);
+            let start = self.index;
+            self.bump_until(parent, SyntaxKind::Error, content_end);
+            self.error(start..self.index, "expected the end of the line");
+        }
+        let line_end = self.line(self.index).end;
+        self.eat_trivia_until(parent, line_end);
+    }
+
+    /// When called, consumes everything up until the first char on `line`.
+    fn eat_trivia_until_line(
+        &mut self,
+        parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>,
+        line: &Line,
+    ) {
+        self.eat_line_rest(parent);
+        self.eat_trivia_until(parent, line.content_start);
+    }
+
+    /// When called, parses a whole YAML stream, made up of one or more documents.
+    fn parse_stream(&mut self) -> Rc<RefCell<InMemoryNode<SyntaxKind>>> {
+        let stream = new_node(SyntaxKind::Stream);
+        loop {
+            let document = InMemoryNode::append_child(&stream, new_node(SyntaxKind::Document));
+            self.parse_document(&document);
+            if self.index >= self.chars.len() {
+                break;
+            }
+        }
+        stream
+    }
+
+    /// When called, parses a single document, stopping at the `---` that starts the next one.
+    ///
+    /// NOTE: nodes are appended to their parent before their own children are parsed, so that
+    /// each node is given a fractional index relative to the nodes that come before it.
+    fn parse_document(&mut self, document: &Rc<RefCell<InMemoryNode<SyntaxKind>>>) {
+        let mut has_started = false;
+        let mut has_content = false;
+        loop {
+            let Some(line) = self.next_content_line() else {
+                let end = self.chars.len();
+                self.eat_trivia_until(document, end);
+                return;
+            };
+            if has_started && self.is_document_marker(&line, "---") {
+                // Leave the rest of this line for the next document
+                let line_end = self.line(self.index).end;
+                if self.index < line_end {
+                    self.eat_line_rest(document);
+                }
+                self.eat_trivia_until(document, line.start);
+                return;
+            }
+            self.eat_trivia_until(document, line.content_start);
+
+            // Directives come before the `---` that starts the document that they apply to
+            if self.chars[self.index] == '%' && !has_started {
+                self.bump_until(document, SyntaxKind::Directive, line.end);
+                continue;
+            }
+            has_started = true;
+
+            if self.is_document_marker(&line, "---") {
+                self.bump(document, SyntaxKind::DocumentStart, 3);
+                let whitespace_length = self.chars[self.index..line.end]
+                    .iter()
+                    .take_while(|c| matches!(c, ' ' | '\t'))
+                    .count();
+                if self
+                    .peek_at(self.index + whitespace_length)
+                    .is_some_and(|c| c != '#')
+                    && self.index + whitespace_length < line.end
+                {
+                    self.bump(document, SyntaxKind::Whitespace, whitespace_length);
+                    // NOTE: the value of a tagged document (ie, `--- !tag`) can start on the
+                    // next line, without being indented
+                    self.parse_inline_value(document, 0);
+                    has_content = true;
+                }
+            } else if self.is_document_marker(&line, "...") {
+                self.bump(document, SyntaxKind::DocumentEnd, 3);
+                self.eat_line_rest(document);
+                let end = self
+                    .next_content_line()
+                    .map(|line| line.start)
+                    .unwrap_or(self.chars.len());
+                self.eat_trivia_until(document, end);
+                return;
+            } else {
+                let start = self.index;
+                let column = line.indent();
+                self.parse_block_node(document, column, 1);
+                if has_content {
+                    let message = if column > 0 {
+                        "bad indentation"
+                    } else {
+                        "expected the end of the document"
+                    };
+                    self.error(start..self.index, message);
+                }
+                has_content = true;
+            }
+        }
+    }
+
+    /// When called, parses the block mapping, block sequence, or value that starts at the current
+    /// char, which is at `column`. Any lines of a multi-line value must be indented by at least
+    /// `min_indent`.
+    fn parse_block_node(
+        &mut self,
+        parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>,
+        column: usize,
+        min_indent: usize,
+    ) {
+        if self.is_sequence_item(self.index) {
+            self.parse_sequence(parent, column);
+        } else if self.mapping_key_length(self.index).is_some() {
+            self.parse_mapping(parent, column);
+        } else {
+            self.parse_inline_value(parent, min_indent);
+        }
+    }
+
+    /// When called, returns the next line if it continues the block mapping or sequence at
+    /// `column`, ie, if it has another entry or item at the same indentation.
+    fn next_line_continuing(&self, column: usize, kind: SyntaxKind) -> Option<Line> {
+        let line = self.next_content_line()?;
+        let is_continuing = line.indent() == column
+            && !self.is_marker_line(&line)
+            && match kind {
+                SyntaxKind::Sequence => self.is_sequence_item(line.content_start),
+                _ => self.mapping_key_length(line.content_start).is_some(),
+            };
+        is_continuing.then_some(line)
+    }
+
+    /// When called, parses a block sequence, starting at the `-` of its first item.
+    fn parse_sequence(&mut self, parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>, column: usize) {
+        let sequence = InMemoryNode::append_child(parent, new_node(SyntaxKind::Sequence));
+        loop {
+            let item = InMemoryNode::append_child(&sequence, new_node(SyntaxKind::SequenceItem));
+            self.bump(&item, SyntaxKind::Dash, 1);
+            self.parse_nested_value(&item, column, SyntaxKind::Sequence);
+
+            let Some(line) = self.next_line_continuing(column, SyntaxKind::Sequence) else {
+                break;
+            };
+            self.eat_line_rest(&item);
+            self.eat_trivia_until(&sequence, line.content_start);
+        }
+    }
+
+    /// When called, parses a block mapping, starting at the key of its first entry.
+    fn parse_mapping(&mut self, parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>, column: usize) {
+        let mapping = InMemoryNode::append_child(parent, new_node(SyntaxKind::Mapping));
+        loop {
+            let entry = InMemoryNode::append_child(&mapping, new_node(SyntaxKind::MappingEntry));
+            let key_length = self.mapping_key_length(self.index).unwrap_or(0);
+            self.bump(&entry, SyntaxKind::Key, key_length);
+            let colon = (self.index..self.chars.len())
+                .find(|index| self.chars[*index] == ':')
+                .unwrap_or(self.index);
+            self.bump_until(&entry, SyntaxKind::Whitespace, colon);
+            self.bump(&entry, SyntaxKind::Colon, 1);
+            self.parse_nested_value(&entry, column, SyntaxKind::Mapping);
+
+            let Some(line) = self.next_line_continuing(column, SyntaxKind::Mapping) else {
+                break;
+            };
+            self.eat_line_rest(&entry);
+            self.eat_trivia_until(&mapping, line.content_start);
+        }
+    }
+
+    /// When called, parses the value of a mapping entry or sequence item at `column`, which can
+    /// either be on the same line, or indented on the lines after it.
+    fn parse_nested_value(
+        &mut self,
+        parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>,
+        column: usize,
+        kind: SyntaxKind,
+    ) {
+        let whitespace_length = self.chars[self.index..]
+            .iter()
+            .take_while(|c| matches!(c, ' ' | '\t'))
+            .count();
+        self.index += whitespace_length;
+        let has_content = self.has_content_on_line();
+        self.index -= whitespace_length;
+
+        if has_content {
+            self.bump(parent, SyntaxKind::Whitespace, whitespace_length);
+            let value_column = self.column(self.index);
+            match kind {
+                // A sequence item can contain a compact mapping or sequence, ie, `- a: 1`
+                SyntaxKind::Sequence => self.parse_block_node(parent, value_column, column + 1),
+                _ => self.parse_inline_value(parent, column + 1),
+            };
+            return;
+        }
+
+        // Like a sequence item, the value of a mapping entry can be a sequence at the same
+        // indentation as the entry's key
+        let Some(line) = self.next_content_line() else {
+            return;
+        };
+        let is_nested = !self.is_marker_line(&line)
+            && (line.indent() > column
+                || (kind == SyntaxKind::Mapping
+                    && line.indent() == column
+                    && self.is_sequence_item(line.content_start)));
+        if is_nested {
+            self.eat_trivia_until_line(parent, &line);
+            self.parse_block_node(parent, line.indent(), column + 1);
+        }
+    }
+
+    /// When called, parses a value that starts at the current char, and the rest of its line.
+    /// Any lines of a multi-line value must be indented by at least `min_indent`.
+    fn parse_inline_value(
+        &mut self,
+        parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>,
+        min_indent: usize,
+    ) {
+        // Anchors and tags come before the value that they apply to
+        while let Some(kind @ (SyntaxKind::Anchor | SyntaxKind::Tag)) = match self.peek() {
+            Some('&') => Some(SyntaxKind::Anchor),
+            Some('!') => Some(SyntaxKind::Tag),
+            _ => None,
+        } {
+            let length = self.chars[self.index..]
+                .iter()
+                .take_while(|c| !c.is_whitespace())
+                .count();
+            self.bump(parent, kind, length);
+            let whitespace_length = self.chars[self.index..]
+                .iter()
+                .take_while(|c| matches!(c, ' ' | '\t'))
+                .count();
+            self.bump_until(
+                parent,
+                SyntaxKind::Whitespace,
+                self.index + whitespace_length,
+            );
+
+            if !self.has_content_on_line() {
+                // The value is on the lines after the anchor or tag
+                let Some(line) = self.next_content_line() else {
+                    return;
+                };
+                if line.indent() >= min_indent && !self.is_marker_line(&line) {
+                    self.eat_trivia_until_line(parent, &line);
+                    self.parse_block_node(parent, line.indent(), min_indent);
+                }
+                return;
+            }
+        }
+
+        match self.peek() {
+            Some('[') => self.parse_flow_collection(parent, SyntaxKind::FlowSequence),
+            Some('{') => self.parse_flow_collection(parent, SyntaxKind::FlowMapping),
+            Some('|' | '>') => {
+                self.parse_block_scalar(parent, min_indent);
+                return;
+            }
+            Some('"' | '\'') => self.parse_quoted_scalar(parent, min_indent),
+            Some('*') => {
+                let length = self.content_end() - self.index;
+                self.bump(parent, SyntaxKind::Alias, length);
+            }
+            _ => {
+                let end = self.content_end();
+                let text = self.chars[self.index..end].iter().collect::<String>();
+                self.bump_until(parent, plain_scalar_kind(&text), end);
+
+                // A plain scalar can continue onto the lines after it, as long as they are
+                // indented, and there isn't a comment in between
+                while let Some(line) = self.next_content_line() {
+                    let is_continuation = line.indent() >= min_indent
+                        && !self.is_marker_line(&line)
+                        && !self.is_sequence_item(line.content_start)
+                        && self.mapping_key_length(line.content_start).is_none()
+                        && !self.chars[self.index..line.start].contains(&'#');
+                    if !is_continuation {
+                        break;
+                    }
+                    self.eat_trivia_until(parent, line.content_start);
+                    let end = self.content_end();
+                    self.bump_until(parent, SyntaxKind::PlainScalar, end);
+                }
+            }
+        };
+        self.eat_line_rest(parent);
+    }
+
+    /// When called, parses a single or double quoted string, which can span multiple lines.
+    fn parse_quoted_scalar(
+        &mut self,
+        parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>,
+        min_indent: usize,
+    ) {
+        let start = self.index;
+        let quote = self.chars[start];
+        let mut index = start + 1;
+        let end = loop {
+            match self.peek_at(index) {
+                Some('\\') if quote == '"' => index += 2,
+                // In single quoted strings, a quote is escaped by doubling it, ie, `'it''s'`
+                Some('\'') if quote == '\'' && self.peek_at(index + 1) == Some('\'') => index += 2,
+                Some(c) if c == quote => break Some(index + 1),
+                // The lines of a multi-line string have to be indented
+                Some('\n' | '\r') => {
+                    let line = self.line(self.line(index).end + 1);
+                    let is_continued = line.content_start == line.end
+                        || (line.indent() >= min_indent && !self.is_marker_line(&line));
+                    if !is_continued {
+                        break None;
+                    }
+                    index += 1;
+                }
+                Some(_) => index += 1,
+                None => break None,
+            }
+        };
+
+        match end {
+            Some(end) => self.bump_until(parent, SyntaxKind::String, end.min(self.chars.len())),
+            None => {
+                let end = self.line(start).end;
+                self.bump_until(parent, SyntaxKind::String, end);
+                self.error(start..end, "unterminated string");
+            }
+        };
+    }
+
+    /// When called, parses a block scalar, starting at its `|` or `>` header.
+    fn parse_block_scalar(
+        &mut self,
+        parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>,
+        min_indent: usize,
+    ) {
+        let block_scalar = InMemoryNode::append_child(parent, new_node(SyntaxKind::BlockScalar));
+        let header_length = 1 + self.chars[self.index + 1..]
+            .iter()
+            .take_while(|c| matches!(c, '-' | '+' | '0'..='9'))
+            .count();
+        self.bump(&block_scalar, SyntaxKind::BlockScalarHeader, header_length);
+        self.eat_line_rest(&block_scalar);
+
+        // The text is every line after the header that is either blank, or indented enough -
+        // except for any blank lines at the end, which are left for the block scalar's parent
+        let header_line = self.line(self.index);
+        let text_end = self
+            .lines
+            .iter()
+            .skip_while(|line| line.start <= header_line.start)
+            .take_while(|line| {
+                line.content_start == line.end
+                    || (line.indent() >= min_indent.max(1) && !self.is_marker_line(line))
+            })
+            .filter(|line| line.content_start < line.end)
+            .last()
+            .map(|line| line.end);
+        if let Some(text_end) = text_end {
+            let text_start = self.line(header_line.end + 1).start;
+            self.eat_trivia_until(&block_scalar, text_start);
+            self.bump_until(&block_scalar, SyntaxKind::BlockScalarText, text_end);
+        }
+    }
+
+    /// When called, parses a flow sequence or flow mapping, starting at its opening bracket.
+    fn parse_flow_collection(
+        &mut self,
+        parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>,
+        kind: SyntaxKind,
+    ) {
+        let (opening_kind, closing, closing_kind) = match kind {
+            SyntaxKind::FlowSequence => (SyntaxKind::LBrack, ']', SyntaxKind::RBrack),
+            _ => (SyntaxKind::LCurly, '}', SyntaxKind::RCurly),
+        };
+        let collection = InMemoryNode::append_child(parent, new_node(kind));
+        self.bump(&collection, opening_kind, 1);
+
+        loop {
+            let trivia_end = self.chars[self.index..]
+                .iter()
+                .enumerate()
+                .scan(false, |is_comment, (offset, c)| {
+                    *is_comment = (*is_comment || *c == '#') && !matches!(c, '\n' | '\r');
+                    (*is_comment || c.is_whitespace()).then_some(offset)
+                })
+                .last()
+                .map(|offset| self.index + offset + 1)
+                .unwrap_or(self.index);
+            self.eat_trivia_until(&collection, trivia_end);
+
+            match self.peek() {
+                Some(c) if c == closing => {
+                    self.bump(&collection, closing_kind, 1);
+                    break;
+                }
+                Some(',') => self.bump(&collection, SyntaxKind::Comma, 1),
+                None | Some(']' | '}') => {
+                    self.error(self.index..self.index, &format!("expected `{closing}`"));
+                    break;
+                }
+                Some(_) => self.parse_flow_entry(&collection, kind),
+            };
+        }
+    }
+
+    /// When called, returns the end of the plain scalar in a flow collection that starts at the
+    /// current char.
+    fn flow_scalar_end(&self) -> usize {
+        let mut end = self.index;
+        while let Some(c) = self.peek_at(end) {
+            let is_value_indicator = c == ':'
+                && self
+                    .peek_at(end + 1)
+                    .is_none_or(|c| c.is_whitespace() || matches!(c, ',' | ']' | '}'));
+            let is_comment = c == '#' && end > 0 && self.chars[end - 1].is_whitespace();
+            if matches!(c, ',' | '[' | ']' | '{' | '}' | '\n' | '\r')
+                || is_value_indicator
+                || is_comment
+            {
+                break;
+            }
+            end += 1;
+        }
+        while end > self.index && matches!(self.chars[end - 1], ' ' | '\t') {
+            end -= 1;
+        }
+        end
+    }
+
+    /// When called, parses an entry in a flow collection - either a value, or a `key: value` pair.
+    fn parse_flow_entry(
+        &mut self,
+        collection: &Rc<RefCell<InMemoryNode<SyntaxKind>>>,
+        kind: SyntaxKind,
+    ) {
+        // Figure out where the entry's key would end, to see if it is followed by a colon
+        let key_end = match self.peek() {
+            Some(quote @ ('"' | '\'')) => (self.index + 1..self.chars.len())
+                .find(|index| self.chars[*index] == quote && self.chars[*index - 1] != '\\')
+                .map(|index| index + 1)
+                .unwrap_or(self.index),
+            Some('[' | '{') => self.index,
+            _ => self.flow_scalar_end(),
+        };
+        let colon =
+            (key_end..self.chars.len()).find(|index| !matches!(self.chars[*index], ' ' | '\t'));
+        let is_entry = key_end > self.index && colon.is_some_and(|colon| self.chars[colon] == ':');
+
+        if !is_entry && kind == SyntaxKind::FlowSequence {
+            self.parse_flow_value(collection);
+            return;
+        }
+
+        let entry = InMemoryNode::append_child(collection, new_node(SyntaxKind::MappingEntry));
+        if is_entry {
+            self.bump_until(&entry, SyntaxKind::Key, key_end);
+            self.bump_until(&entry, SyntaxKind::Whitespace, colon.unwrap_or(self.index));
+            self.bump(&entry, SyntaxKind::Colon, 1);
+            let whitespace_length = self.chars[self.index..]
+                .iter()
+                .take_while(|c| matches!(c, ' ' | '\t'))
+                .count();
+            self.bump_until(
+                &entry,
+                SyntaxKind::Whitespace,
+                self.index + whitespace_length,
+            );
+            if !matches!(self.peek(), Some(',' | ']' | '}')) {
+                self.parse_flow_value(&entry);
+            }
+        } else {
+            // A key without a value, ie, the `a` in `{ a, b: 1 }`
+            self.parse_flow_value(&entry);
+        }
+    }
+
+    /// When called, parses a value in a flow collection.
+    fn parse_flow_value(&mut self, parent: &Rc<RefCell<InMemoryNode<SyntaxKind>>>) {
+        match self.peek() {
+            Some('[') => self.parse_flow_collection(parent, SyntaxKind::FlowSequence),
+            Some('{') => self.parse_flow_collection(parent, SyntaxKind::FlowMapping),
+            Some('"' | '\'') => self.parse_quoted_scalar(parent, 0),
+            Some(c @ ('*' | '&' | '!')) => {
+                let length = self.chars[self.index..]
+                    .iter()
+                    .take_while(|c| !c.is_whitespace() && !matches!(c, ',' | ']' | '}'))
+                    .count();
+                let kind = match c {
+                    '*' => SyntaxKind::Alias,
+                    '&' => SyntaxKind::Anchor,
+                    _ => SyntaxKind::Tag,
+                };
+                self.bump(parent, kind, length);
+            }
+            _ => {
+                let end = self.flow_scalar_end();
+                if end == self.index {
+                    // ie, a stray `:` in a flow collection
+                    let start = self.index;
+                    self.bump(parent, SyntaxKind::Error, 1);
+                    self.error(
+                        start..self.index,
+                        &format!("unexpected `{}`", self.chars[start]),
+                    );
+                    return;
+                }
+                let text = self.chars[self.index..end].iter().collect::<String>();
+                self.bump_until(parent, plain_scalar_kind(&text), end);
+            }
+        };
+    }
+}
+
+fn new_node(kind: SyntaxKind) -> Rc<RefCell<InMemoryNode<SyntaxKind>>> {
+    InMemoryNode::new_with_metadata(NodeMetadata::AstNode {
+        kind,
+        literal: None,
+    })
+}
+
+/// When called, returns the kind of `node` within a tree of wrapped kinds (see
+/// `TokenKindTrait::key_path_segments`).
+fn unwrapped_kind_of<Wrapper: TokenKindTrait>(
+    node: &Rc<RefCell<InMemoryNode<Wrapper>>>,
+    unwrap: &dyn Fn(&Wrapper) -> Option<SyntaxKind>,
+) -> Option<SyntaxKind> {
+    match &node.borrow().metadata {
+        NodeMetadata::AstNode { kind, .. } => unwrap(kind),
+        _ => None,
+    }
+}
+
+impl TokenKindTrait for SyntaxKind {
+    type ParseOptions = ();
+
+    fn language_name() -> &'static str {
+        "yaml"
+    }
+
+    fn highlight_group(ancestry: std::vec::IntoIter<SyntaxKind>) -> Option<HighlightGroup> {
+        match ancestry.as_slice().first()? {
+            SyntaxKind::Key => Some(HighlightGroup::Property),
+            SyntaxKind::PlainScalar | SyntaxKind::String | SyntaxKind::BlockScalarText => {
+                Some(HighlightGroup::String)
+            }
+            SyntaxKind::Number => Some(HighlightGroup::Number),
+            SyntaxKind::Boolean | SyntaxKind::Null => Some(HighlightGroup::Constant),
+            SyntaxKind::Anchor | SyntaxKind::Alias => Some(HighlightGroup::VariableDecl),
+            SyntaxKind::Tag => Some(HighlightGroup::Type),
+            SyntaxKind::BlockScalarHeader
+            | SyntaxKind::DocumentStart
+            | SyntaxKind::DocumentEnd
+            | SyntaxKind::Directive => Some(HighlightGroup::Keyword),
+            SyntaxKind::Comment => Some(HighlightGroup::Comment),
+            SyntaxKind::Colon
+            | SyntaxKind::Dash
+            | SyntaxKind::Comma
+            | SyntaxKind::LBrack
+            | SyntaxKind::RBrack
+            | SyntaxKind::LCurly
+            | SyntaxKind::RCurly => Some(HighlightGroup::Punctuation),
+            _ => None,
+        }
+    }
+
+    /// NOTE: the structure of a YAML document depends on the indentation of each line relative to
+    /// the lines around it, so a part of a document can't be reparsed on its own. Instead, the
+    /// whole stream is reparsed after each edit.
+    fn is_reparsable(&self) -> bool {
+        false
+    }
+
+    // A nested mapping or sequence starts on the row after its key, so it is folded along with
+    // its entry, which leaves the key shown
+    fn is_foldable(&self) -> bool {
+        matches!(
+            self,
+            SyntaxKind::MappingEntry
+                | SyntaxKind::SequenceItem
+                | SyntaxKind::FlowMapping
+                | SyntaxKind::FlowSequence
+        )
+    }
+
+    fn parse(
+        literal: &str,
+        _parent: Option<Rc<RefCell<InMemoryNode<Self>>>>,
+    ) -> Rc<RefCell<InMemoryNode<Self>>> {
+        let mut parser = Parser::new(literal);
+        let node = parser.parse_stream();
+        for diagnostic in parser.diagnostics {
+            InMemoryNode::attach_diagnostic(&node, diagnostic);
+        }
+        node
+    }
+
+    fn key_path_segments<Wrapper: TokenKindTrait>(
+        node: &Rc<RefCell<InMemoryNode<Wrapper>>>,
+        unwrap: &dyn Fn(&Wrapper) -> Option<Self>,
+    ) -> Vec<String> {
+        let kind_of = |node: &Rc<RefCell<InMemoryNode<Wrapper>>>| unwrapped_kind_of(node, unwrap);
+        let parent = node
+            .borrow()
+            .parent
+            .as_ref()
+            .and_then(|parent| parent.upgrade());
+        let index_in_parent = |is_counted: fn(SyntaxKind) -> bool| {
+            let siblings = parent
+                .as_ref()
+                .map(|parent| parent.borrow().children.clone())
+                .unwrap_or_default();
+            siblings
+                .iter()
+                .take_while(|sibling| !Rc::ptr_eq(sibling, node))
+                .filter(|sibling| kind_of(sibling).is_some_and(is_counted))
+                .count()
+        };
+
+        match kind_of(node) {
+            Some(SyntaxKind::MappingEntry) => {
+                let key = node
+                    .borrow()
+                    .children
+                    .iter()
+                    .find(|child| kind_of(child) == Some(SyntaxKind::Key))
+                    .map(|key| unquote(&InMemoryNode::literal(key)));
+                key.into_iter().collect()
+            }
+            // Items in a sequence are keyed by their index, ie, `steps.0`
+            Some(SyntaxKind::SequenceItem) => {
+                vec![index_in_parent(|kind| kind == SyntaxKind::SequenceItem).to_string()]
+            }
+            Some(kind)
+                if kind.is_value()
+                    && parent.as_ref().and_then(kind_of) == Some(SyntaxKind::FlowSequence) =>
+            {
+                vec![index_in_parent(|kind| kind.is_value()).to_string()]
+            }
+            _ => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SyntaxKind;
+    use crate::{
+        languages::test_utils::{assert_kept_with_diagnostics, child, child_kinds},
+        node_tree::{
+            cursor::{Cursor, Selection},
+            node::InMemoryNode,
+            utils::split_key_path,
+        },
+    };
+
+    const WORKFLOW_YAML: &str = r#"# A workflow
+name: CI
+on: [push, pull_request]
+jobs:
+  test:
+    runs-on: &os ubuntu-latest
+    steps:
+      - uses: actions/checkout@v4
+      - name: "Run tests"
+        run: |
+          cargo build
+          cargo test
+        timeout: 10
+    env: { RUST_LOG: debug, "A.B": ~ }
+"#;
+
+    #[test]
+    fn parses_mappings_sequences_and_scalars() {
+        use SyntaxKind::*;
+        let root = InMemoryNode::<SyntaxKind>::new_from_parsed(WORKFLOW_YAML);
+        assert!(InMemoryNode::diagnostics(&root).is_empty());
+
+        let stream = child(&root, 0);
+        assert_eq!(child_kinds(&stream), vec![Document]);
+        let document = child(&stream, 0);
+        assert_eq!(
+            child_kinds(&document),
+            vec![Comment, Newline, Mapping, Newline]
+        );
+        let mapping = child(&document, 2);
+        assert_eq!(
+            child_kinds(&mapping),
+            vec![MappingEntry, Newline, MappingEntry, Newline, MappingEntry]
+        );
+        assert_eq!(
+            child_kinds(&child(&mapping, 0)),
+            vec![Key, Colon, Whitespace, PlainScalar]
+        );
+        assert_eq!(
+            child_kinds(&child(&child(&mapping, 2), 3)),
+            vec![LBrack, PlainScalar, Comma, Whitespace, PlainScalar, RBrack]
+        );
+
+        let jobs = child(&child(&mapping, 4), 4);
+        let test = child(&child(&jobs, 0), 4);
+        assert_eq!(
+            child_kinds(&test),
+            vec![
+                MappingEntry,
+                Newline,
+                Whitespace,
+                MappingEntry,
+                Newline,
+                Whitespace,
+                MappingEntry
+            ]
+        );
+        assert_eq!(
+            child_kinds(&child(&test, 0)),
+            vec![Key, Colon, Whitespace, Anchor, Whitespace, PlainScalar]
+        );
+        let steps = child(&child(&test, 3), 4);
+        assert_eq!(
+            child_kinds(&steps),
+            vec![SequenceItem, Newline, Whitespace, SequenceItem]
+        );
+        let step = child(&child(&steps, 3), 2);
+        assert_eq!(
+            child_kinds(&step),
+            vec![
+                MappingEntry,
+                Newline,
+                Whitespace,
+                MappingEntry,
+                Newline,
+                Whitespace,
+                MappingEntry
+            ]
+        );
+        assert_eq!(child_kinds(&child(&step, 0))[3], String);
+        assert_eq!(
+            child_kinds(&child(&child(&step, 3), 3)),
+            vec![BlockScalarHeader, Newline, BlockScalarText]
+        );
+        assert_eq!(child_kinds(&child(&step, 6))[3], Number);
+
+        let env = child(&child(&test, 6), 3);
+        assert_eq!(
+            child_kinds(&env),
+            vec![
+                LCurly,
+                Whitespace,
+                MappingEntry,
+                Comma,
+                Whitespace,
+                MappingEntry,
+                Whitespace,
+                RCurly
+            ]
+        );
+        assert_eq!(child_kinds(&child(&env, 5))[3], Null);
+    }
+
+    #[test]
+    fn parses_multiple_documents() {
+        use SyntaxKind::*;
+        let literal = "%YAML 1.2\n---\na: 1\n...\n--- !tag\n- b\n";
+        let root = InMemoryNode::<SyntaxKind>::new_from_parsed(literal);
+        assert!(InMemoryNode::diagnostics(&root).is_empty());
+
+        let stream = child(&root, 0);
+        assert_eq!(child_kinds(&stream), vec![Document, Document]);
+        assert_eq!(
+            child_kinds(&child(&stream, 0)),
+            vec![
+                Directive,
+                Newline,
+                DocumentStart,
+                Newline,
+                Mapping,
+                Newline,
+                DocumentEnd,
+                Newline
+            ]
+        );
+        assert_eq!(
+            child_kinds(&child(&stream, 1)),
+            vec![DocumentStart, Whitespace, Tag, Newline, Sequence, Newline]
+        );
+    }
+
+    #[test]
+    fn malformed_documents_are_kept_with_diagnostics() {
+        let documents = [
+            ("a: \"b", vec!["unterminated string"]),
+            ("a: [1, 2", vec!["expected `]`"]),
+            (
+                "a: {b: 1]",
+                vec!["expected `}`", "expected the end of the line"],
+            ),
+            ("a: 1\n  b: 2", vec!["bad indentation"]),
+            ("a: 1\n- b", vec!["expected the end of the document"]),
+            ("a: \"b\" c", vec!["expected the end of the line"]),
+        ];
+        assert_kept_with_diagnostics::<SyntaxKind>(&documents);
+    }
+
+    #[test]
+    fn finds_nodes_by_key_path() {
+        let root = InMemoryNode::<SyntaxKind>::new_from_parsed(WORKFLOW_YAML);
+        let find = |path: &str| {
+            InMemoryNode::find_key_path(&root, &split_key_path(path)).map(|node| {
+                (
+                    Cursor::new(node.clone()).to_rows_cols(),
+                    InMemoryNode::key_path(&node).join("."),
+                )
+            })
+        };
+
+        assert_eq!(find("name"), Some(((2, 1), "name".into())));
+        assert_eq!(find("on.1"), Some(((3, 12), "on.1".into())));
+        assert_eq!(
+            find("jobs.test.runs-on"),
+            Some(((6, 5), "jobs.test.runs-on".into()))
+        );
+        assert_eq!(
+            find("jobs.test.steps.1"),
+            Some(((9, 7), "jobs.test.steps.1".into()))
+        );
+        assert_eq!(
+            find("jobs.test.steps.1.run"),
+            Some(((10, 9), "jobs.test.steps.1.run".into()))
+        );
+        assert_eq!(
+            find("jobs.test.env.\"A.B\""),
+            Some(((14, 29), "jobs.test.env.A.B".into()))
+        );
+        assert_eq!(find("jobs.test.steps.2"), None);
+        assert_eq!(find("jobs.missing"), None);
+    }
+
+    #[test]
+    fn edits_reparse_the_whole_stream() {
+        let root = InMemoryNode::<SyntaxKind>::new_from_parsed(WORKFLOW_YAML);
+
+        // Indenting a line moves it into the entry above it
+        Selection::new_from_cursor_pair(
+            Cursor::new_at_rows_cols(root.clone(), (2, 1)),
+            Cursor::new_at_rows_cols(root.clone(), (2, 5)),
+        )
+        .replace("env:\n  name")
+        .unwrap();
+        assert_eq!(
+            InMemoryNode::deep_literal(&root),
+            WORKFLOW_YAML.replace("\nname: CI", "\nenv:\n  name: CI")
+        );
+        assert!(InMemoryNode::diagnostics(&root).is_empty());
+        assert!(InMemoryNode::find_key_path(&root, &split_key_path("env.name")).is_some());
+        assert!(InMemoryNode::find_key_path(&root, &split_key_path("name")).is_none());
+        assert!(
+            InMemoryNode::find_key_path(&root, &split_key_path("jobs.test.steps.0.uses")).is_some()
+        );
+    }
+}
@@ -1,115 +1,38 @@
+mod editor;
 mod languages;
 mod node_tree;
 
-use std::{cell::RefCell, rc::Rc};
-
-use pancurses::{curs_set, echo, endwin, initscr, noecho, resize_term, Input};
-
-use crate::node_tree::{
-    cursor::{Cursor, CursorSeek, Selection},
-    node::{
-        InMemoryNode,
-        NodeMetadata,
-        // NodeSeek,
+use std::path::Path;
+
+use crate::{
+    editor::{buffer::Buffer, buffer_set::BufferSet, tui::Tui},
+    node_tree::{
+        cursor::{Cursor, CursorSeek, Selection},
+        node::{
+            NodeMetadata,
+            // NodeSeek,
+        },
+        theme::{set_current_theme, Theme},
+        utils::Newline,
+        // utils::Inclusivity, fractional_index::VariableSizeFractionalIndex,
+        // fractional_index::FractionalIndex,
     },
-    utils::{Inclusivity, Newline},
-    // utils::Inclusivity, fractional_index::VariableSizeFractionalIndex,
-    // fractional_index::FractionalIndex,
 };
 
-fn interactive(root: Rc<RefCell<InMemoryNode<languages::typescript::SyntaxKind>>>) {
-    let window = initscr();
-    window.printw("Hello Rust");
-    window.keypad(true);
-    noecho();
-
-    window.clear();
-    let (start_y, start_x) = window.get_beg_yx();
-    let (end_y, end_x) = window.get_max_yx();
-    let (width_chars, height_chars) = (end_x - start_x, end_y - start_y);
-
-    // window.draw_box(10, 10);
-
-    let mut cursor = Cursor::new(root.clone());
-    let mut status: String;
-
-    {
-        let mut selection = cursor.selection();
-        selection.secondary = selection
-            .secondary
-            .seek_forwards(CursorSeek::advance_until_start_end());
-        window.mvaddstr(0, 0, selection.literal());
-    }
-
-    loop {
-        let (rows, cols) = cursor.to_rows_cols();
-
-        status = format!("({rows}, {cols}) {:?}", cursor);
-        window.mvaddstr(height_chars - 1, 0, &status);
-
-        window.mv((rows - 1) as i32, (cols - 1) as i32);
-        // dbg!(rows, cols);
-
-        window.refresh();
-        match window.getch() {
-            Some(Input::KeyResize) => {
-                resize_term(0, 0);
-            }
-            Some(Input::Character(x)) if x == 'q' => break,
-
-            Some(Input::Character('l')) => {
-                cursor = cursor.seek_forwards(CursorSeek::AdvanceByCharCount(1));
-            }
-            Some(Input::Character('h')) => {
-                cursor = cursor.seek_backwards(CursorSeek::AdvanceByCharCount(1));
-            }
-            Some(Input::Character('j')) => {
-                cursor = cursor.seek_forwards(CursorSeek::AdvanceByLines(1));
-            }
-            Some(Input::Character('k')) => {
-                cursor = cursor.seek_backwards(CursorSeek::AdvanceByLines(1));
-            }
-            Some(Input::Character('w')) => {
-                cursor = cursor.seek_forwards(CursorSeek::forwards_word(1, false, false));
-            }
-            Some(Input::Character('b')) => {
-                cursor = cursor.seek_backwards(CursorSeek::back_word(1, false, false));
-            }
-
-            Some(Input::Character('%')) => {
-                cursor = cursor.seek_forwards(CursorSeek::advance_until_matching_delimiter(
-                    Inclusivity::Inclusive,
-                ));
-            }
-
-            Some(Input::Character('0')) => {
-                cursor = cursor.seek_backwards(CursorSeek::advance_until_line_start());
-            }
-            Some(Input::Character('^')) => {
-                cursor = cursor.seek_backwards(
-                    CursorSeek::advance_until_line_start_after_leading_whitespace(),
-                );
-            }
-            Some(Input::Character('$')) => {
-                cursor = cursor.seek_forwards(CursorSeek::advance_until_line_end());
-            }
-            Some(Input::Character('G')) => {
-                cursor = cursor.seek_forwards(CursorSeek::advance_until_start_end());
-            }
-            Some(Input::Character('g')) => {
-                cursor = cursor.seek_backwards(CursorSeek::advance_until_start_end());
-            }
-            _ => (),
-        }
+fn main() {
+    // Both the debug printer and the interactive editor render syntax highlighting using the theme
+    // named by THEME, ie `THEME=light` or `THEME=path/to/file.theme`
+    if let Ok(name) = std::env::var("THEME") {
+        set_current_theme(Theme::find(&name).expect("Error loading theme: "));
     }
 
-    echo();
-    endwin();
-}
-
-fn main() {
-    let root = InMemoryNode::<languages::typescript::SyntaxKind>::new_from_parsed(
-        r#"
+    let paths = std::env::args().skip(1).collect::<Vec<String>>();
+    let buffer = if let Some(path) = paths.first() {
+        Buffer::<languages::registry::AnySyntaxKind>::open(Path::new(path))
+            .expect("Error opening file: ")
+    } else {
+        Buffer::<languages::registry::AnySyntaxKind>::new_from_literal(
+            r#"
         let foo = "brew";
         function main() {
             console.log("hello world");
@@ -125,7 +48,15 @@ fn main() {
             }
         }
     "#,
-    );
+        )
+    };
+    let root = buffer.root.clone();
+    let mut buffers = BufferSet::new(buffer);
+    for path in paths.iter().skip(1) {
+        buffers.edit(Path::new(path)).expect("Error opening file: ");
+    }
+    // Like vim, start out showing the first file that was passed
+    buffers.switch_to(1).expect("Error opening file: ");
     // let root = InMemoryNode::<languages::typescript::SyntaxKind>::new_from_parsed(r#"
     //     let foo = "brew";
     //     function main() {
@@ -142,11 +73,11 @@ fn main() {
     // println!("INITIAL: {:?}", Selection::new_across_subtree(&root));
 
     if std::env::var("INTERACTIVE").unwrap_or("".into()) == "true" {
-        interactive(root);
+        Tui::new(buffers).run();
         std::process::exit(0);
     }
 
-    // let foo = mini_js::parse_string(r#"
+    // let foo = InMemoryNode::<languages::brackets::SyntaxKind>::new_from_parsed(r#"
     //     {
     //         foo
     //         {
@@ -1,9 +1,14 @@
 use crate::node_tree::{
     cursor::{cursor_seek::CursorSeekContext, CursorSeek, Selection},
-    node::{InMemoryNode, NodeSeek, TokenKindTrait},
-    utils::{Direction, Inclusivity, NEWLINE},
+    node::{InMemoryNode, NodeSeek, OffsetUnit, TextMetrics, TokenKindTrait},
+    utils::{display_width, Direction, Inclusivity, NEWLINE},
 };
 use std::{cell::RefCell, collections::VecDeque, fmt::Debug, rc::Rc};
+use unicode_segmentation::UnicodeSegmentation;
+
+// The most chars that are looked at to find the bounds of a grapheme cluster. Clusters longer than
+// this (which only really happen with deliberately garbled text) are split up.
+const MAX_GRAPHEME_CHARS: usize = 32;
 
 /// A cursor represents a position in a node tree - ie, a node and an offset in characters from the
 /// start of that node. A cursor can be seeked forwards and backwards through the node tree to get
@@ -56,33 +61,67 @@ impl<TokenKind: TokenKindTrait> Cursor<TokenKind> {
     pub fn new_at(node: Rc<RefCell<InMemoryNode<TokenKind>>>, offset: usize) -> Self {
         Self { node, offset }
     }
+    /// When called, creates a new cursor at the given (one indexed) row and column of the
+    /// document under `root`. Like vim, a column past the end of the row puts the cursor at the
+    /// end of that row (rather than wrapping onto the rows after it), and a row past the end of
+    /// the document puts the cursor at the end of the document.
     pub fn new_at_rows_cols(
         root: Rc<RefCell<InMemoryNode<TokenKind>>>,
         rows_cols: (usize, usize),
     ) -> Self {
         let (rows, cols) = rows_cols;
-        let mut row_counter = 1;
-        let mut col_counter = 1;
+        let line_count = root.borrow().subtree_metrics.newlines + 1;
+
+        // Jump straight to the start of the row using the newline counts stored on each node,
+        // rather than scanning the document from the start to get there.
+        let row_start = match rows.min(line_count) {
+            0 | 1 => Self::new(root.clone()),
+            row => InMemoryNode::node_at_newline(&root, row - 1)
+                .map(|(node, offset)| Self::new_at(node, offset + 1))
+                .unwrap_or_else(|| Self::new(root.clone())),
+        };
+        if rows == 0 || rows > line_count {
+            // The row is past the end of the document, so move to the very end
+            return row_start.seek_forwards_until(|_c, _i| CursorSeek::Continue);
+        }
 
-        let cursor = Self::new(root).seek_forwards_until(|c, _i| {
-            if row_counter == rows {
-                // Before reaching the first newline, count the col chars
-                if col_counter < cols {
-                    col_counter += 1;
-                    CursorSeek::Continue
-                } else {
-                    CursorSeek::Done
-                }
-            } else if c == *NEWLINE {
-                // From that point on count each newline
-                row_counter += 1;
+        let mut col_counter = 1;
+        row_start.seek_forwards_until(|c, _i| {
+            // Count the col chars. If the line is shorter than `cols`, stop at the end of the line
+            // rather than wrapping onto the next one.
+            if col_counter < cols && c != *NEWLINE {
+                col_counter += 1;
                 CursorSeek::Continue
             } else {
-                CursorSeek::Continue
+                CursorSeek::Stop
             }
-        });
+        })
+    }
 
-        cursor
+    /// When called, creates a new cursor `offset` units from the start of the document under
+    /// `root`, where `unit` is the encoding the offset was measured in. If `offset` falls in the
+    /// middle of a character (ie, between the two UTF-16 code units of an emoji), the cursor is
+    /// placed before that character. If `offset` is past the end of the document, the cursor is
+    /// placed at the end.
+    pub fn from_offset(
+        root: Rc<RefCell<InMemoryNode<TokenKind>>>,
+        offset: usize,
+        unit: OffsetUnit,
+    ) -> Self {
+        if let Some((node, node_offset)) = InMemoryNode::node_at_offset(&root, offset, unit) {
+            return Self::new_at(node, node_offset);
+        }
+
+        // Find the last node in the document with any text in it, and put the cursor at its end
+        let mut pointer = InMemoryNode::deep_last_child(&root);
+        while let Some(node) = pointer {
+            let length = InMemoryNode::literal(&node).chars().count();
+            if length > 0 {
+                return Self::new_at(node, length);
+            }
+            pointer = node.borrow().previous.as_ref().and_then(|previous| previous.upgrade());
+        }
+        Self::new(root)
     }
 
     /// When called, create a new Selection out of this cursor.
@@ -93,43 +132,95 @@ impl<TokenKind: TokenKindTrait> Cursor<TokenKind> {
         Selection::new_from_cursor(self.clone())
     }
 
+    /// When called, computes the metrics of all text in the document before the cursor. This uses
+    /// the metrics stored on each node, so it doesn't have to scan the document.
+    fn metrics_before(self: &Self) -> TextMetrics {
+        InMemoryNode::metrics_before(&self.node).concat(TextMetrics::from_literal(
+            &InMemoryNode::literal_substring(&self.node, 0, self.offset),
+        ))
+    }
+
     pub fn to_rows_cols(self: &Self) -> (usize, usize) {
-        let mut row_counter = 1;
-        let mut col_counter = 1;
+        // The cursor's column is the number of chars between it and the last newline before it
+        let metrics = self.metrics_before();
+        (metrics.newlines + 1, metrics.last_line_chars + 1)
+    }
 
-        println!("--------");
-        let _ = self.seek_backwards_until(|c, _i| {
-            if c == *NEWLINE {
-                // From that point on count each newline
-                row_counter += 1;
-            } else if row_counter == 1 {
-                // Before reaching the first newline, count the col chars
-                col_counter += 1;
-            };
-            CursorSeek::Continue
-        });
+    pub fn to_cols(self: &Self) -> usize {
+        self.to_rows_cols().1
+    }
 
-        (row_counter, col_counter)
+    /// When called, returns the distance between the start of the document and the cursor,
+    /// measured in the given `unit`.
+    pub fn to_offset(self: &Self, unit: OffsetUnit) -> usize {
+        self.metrics_before().length(unit)
     }
 
-    pub fn to_cols(self: &Self) -> usize {
-        let mut col_counter = 1;
+    pub fn to_rows(self: &Self) -> usize {
+        self.to_rows_cols().0
+    }
 
+    /// When called, returns the text on the cursor's row before the cursor.
+    fn line_prefix(self: &Self) -> String {
+        let mut prefix = vec![];
         let _ = self.seek_backwards_until(|c, _i| {
             if c == *NEWLINE {
                 CursorSeek::Stop
             } else {
-                // Before reaching the first newline, count the col chars
-                col_counter += 1;
+                prefix.push(c);
                 CursorSeek::Continue
             }
         });
+        prefix.into_iter().rev().collect()
+    }
 
-        col_counter
+    /// When called, returns the (one indexed) column on screen that the cursor is drawn at, taking
+    /// into account wide characters (ie, CJK ideographs and emoji) and tabs expanded to `tabstop`.
+    pub fn to_display_cols(self: &Self, tabstop: usize) -> usize {
+        display_width(&self.line_prefix(), tabstop) + 1
     }
 
-    pub fn to_rows(self: &Self) -> usize {
-        self.to_rows_cols().0
+    /// When called, returns the number of chars in the grapheme cluster just after (or, if
+    /// seeking backwards, just before) the cursor, or 0 if the cursor is at the end (or start) of
+    /// the document.
+    fn grapheme_length(self: &Self, direction: Direction) -> usize {
+        let mut chars = vec![];
+        let _ = self.seek_until(direction, |c, _i| {
+            chars.push(c);
+            if chars.len() < MAX_GRAPHEME_CHARS {
+                CursorSeek::Continue
+            } else {
+                CursorSeek::Done
+            }
+        });
+
+        let text = match direction {
+            Direction::Forwards => chars.into_iter().collect::<String>(),
+            Direction::Backwards => chars.into_iter().rev().collect::<String>(),
+        };
+        let grapheme = match direction {
+            Direction::Forwards => text.graphemes(true).next(),
+            Direction::Backwards => text.graphemes(true).next_back(),
+        };
+        grapheme.map(|grapheme| grapheme.chars().count()).unwrap_or(0)
+    }
+
+    /// When called, moves the cursor by `count` extended grapheme clusters in the given
+    /// `direction`, so that characters made up of multiple chars (ie, an emoji with a skin tone
+    /// modifier, or a letter followed by a combining accent) are moved over all at once.
+    pub fn seek_graphemes(self: &Self, direction: Direction, count: usize) -> Self {
+        let mut cursor = self.clone();
+        for _ in 0..count {
+            let length = cursor.grapheme_length(direction);
+            if length == 0 {
+                break;
+            }
+            cursor = match direction {
+                Direction::Forwards => cursor.seek_forwards(CursorSeek::AdvanceByCharCount(length)),
+                Direction::Backwards => cursor.seek_backwards(CursorSeek::AdvanceByCharCount(length)),
+            };
+        }
+        cursor
     }
 
     /// When called, seeks starting at the cursor position character by character through the node
@@ -192,7 +283,7 @@ impl<TokenKind: TokenKindTrait> Cursor<TokenKind> {
                         Direction::Backwards => {
                             // Seek from the end to the offset from the start
                             let mut iterator = node_literal.chars();
-                            for _ in 0..(node_literal.len() - self.offset) {
+                            for _ in 0..(node_literal.chars().count() - self.offset) {
                                 iterator.next_back();
                             }
                             iterator.collect::<VecDeque<char>>()
@@ -204,7 +295,7 @@ impl<TokenKind: TokenKindTrait> Cursor<TokenKind> {
                     // the right value.
                     new_offset = match direction {
                         Direction::Forwards => 0,
-                        Direction::Backwards => node_literal.len(),
+                        Direction::Backwards => node_literal.chars().count(),
                     };
 
                     node_literal.chars().collect::<VecDeque<char>>()
@@ -431,7 +522,7 @@ impl<TokenKind: TokenKindTrait> Cursor<TokenKind> {
                                     Direction::Backwards => {
                                         // Seek from the end to the offset from the start
                                         let mut iterator = node_literal.chars();
-                                        for _ in 0..(node_literal.len() - new_offset) {
+                                        for _ in 0..(node_literal.chars().count() - new_offset) {
                                             iterator.next_back();
                                         }
                                         iterator.collect::<VecDeque<char>>()
@@ -533,7 +624,7 @@ impl<TokenKind: TokenKindTrait> Cursor<TokenKind> {
                                 Direction::Backwards => {
                                     // Seek from the end to the offset from the start
                                     let mut iterator = node_literal.chars();
-                                    for _ in 0..(node_literal.len() - new_offset) {
+                                    for _ in 0..(node_literal.chars().count() - new_offset) {
                                         iterator.next_back();
                                     }
                                     iterator.collect::<VecDeque<char>>()
@@ -2,10 +2,12 @@ use colored::{ColoredString, Colorize};
 
 use crate::node_tree::{
     cursor::Cursor,
-    node::{InMemoryNode, NodeSeek, TokenKindTrait},
+    node::{InMemoryNode, NodeSeek, OffsetUnit, TokenKindTrait},
+    reparse_scheduler::ReparseScheduler,
+    style::{join_styled_pieces, StyleSpan},
     utils::Inclusivity,
 };
-use std::{cell::RefCell, fmt::Debug, rc::Rc};
+use std::{cell::RefCell, fmt::Debug, ops::Range, rc::Rc};
 
 use super::CursorSeek;
 
@@ -48,7 +50,7 @@ impl<TokenKind: TokenKindTrait> Selection<TokenKind> {
     /// ie: calling this function on the root node would select the entire token tree
     pub fn new_across_subtree(node: &Rc<RefCell<InMemoryNode<TokenKind>>>) -> Self {
         let deep_last_child = InMemoryNode::deep_last_child(node).unwrap_or_else(|| node.clone());
-        let deep_last_child_length = InMemoryNode::literal(&deep_last_child).len();
+        let deep_last_child_length = InMemoryNode::literal(&deep_last_child).chars().count();
         Self {
             primary: Cursor::new(node.clone()),
             secondary: Cursor::new_at(deep_last_child, deep_last_child_length),
@@ -116,6 +118,15 @@ impl<TokenKind: TokenKindTrait> Selection<TokenKind> {
         }
     }
 
+    /// When called, returns the span of the document that the selection covers as a range of
+    /// offsets from the start of the document, measured in the given `unit`. The range always runs
+    /// from the earlier cursor to the later one.
+    pub fn to_range(self: &Self, unit: OffsetUnit) -> Range<usize> {
+        let primary = self.primary.to_offset(unit);
+        let secondary = self.secondary.to_offset(unit);
+        primary.min(secondary)..primary.max(secondary)
+    }
+
     /// When called, computes the underlying literal text that the selection has covered.
     pub fn literal(self: &Self) -> String {
         let colored_result = self.generate_literal(false);
@@ -127,8 +138,38 @@ impl<TokenKind: TokenKindTrait> Selection<TokenKind> {
         self.generate_literal(true)
     }
 
+    /// When called, computes the underlying literal text that the selection has covered, along
+    /// with spans describing the syntax highlighting style each part of the text should be drawn
+    /// with.
+    pub fn literal_spans(self: &Self) -> (String, Vec<StyleSpan>) {
+        join_styled_pieces(
+            self.generate_literal_pieces()
+                .into_iter()
+                .map(|(node, literal)| (literal, InMemoryNode::style(&node))),
+        )
+    }
+
     /// When called, computes the underlying literal text that the selection has covered.
     fn generate_literal(self: &Self, include_terminal_colors: bool) -> ColoredString {
+        let literal = self
+            .generate_literal_pieces()
+            .into_iter()
+            .map(|(node, literal)| {
+                // Apply the proper colors to the string, if required
+                if include_terminal_colors {
+                    format!("{}", InMemoryNode::literal_colored(&node, &literal))
+                } else {
+                    literal
+                }
+            })
+            .collect::<String>();
+        literal.into()
+    }
+
+    /// When called, computes the underlying literal text that the selection has covered, returned
+    /// as a list of each node the selection touches paired with the part of its literal that is
+    /// within the selection.
+    fn generate_literal_pieces(self: &Self) -> Vec<(Rc<RefCell<InMemoryNode<TokenKind>>>, String)> {
         // If the node selection spans within a single node, then take a substring of the common
         // literal value based on the offsets.
         if self.primary.node == self.secondary.node {
@@ -144,12 +185,7 @@ impl<TokenKind: TokenKindTrait> Selection<TokenKind> {
                 literal_length,
             );
 
-            // Apply the proper colors to the string, if required
-            return if include_terminal_colors {
-                InMemoryNode::literal_colored(&self.primary.node, &literal_section)
-            } else {
-                literal_section.into()
-            };
+            return vec![(self.primary.node.clone(), literal_section)];
         };
 
         // If the node selection spans multiple nodes, then:
@@ -168,7 +204,7 @@ impl<TokenKind: TokenKindTrait> Selection<TokenKind> {
         let earlier_suffix = InMemoryNode::literal_substring(
             &earlier_cursor.node,
             earlier_cursor.offset,
-            InMemoryNode::literal(&earlier_cursor.node).len() - earlier_cursor.offset,
+            InMemoryNode::literal(&earlier_cursor.node).chars().count() - earlier_cursor.offset,
         );
 
         // 2. Store the first part of the later node which should be kept
@@ -183,32 +219,29 @@ impl<TokenKind: TokenKindTrait> Selection<TokenKind> {
                 if node == &later_cursor.node {
                     NodeSeek::Stop
                 } else {
-                    let literal = InMemoryNode::literal(node);
-                    let literal_colored = if include_terminal_colors {
-                        InMemoryNode::literal_colored(&node, &literal)
-                    } else {
-                        literal.into()
-                    };
-                    NodeSeek::Continue(literal_colored)
+                    NodeSeek::Continue((node.clone(), InMemoryNode::literal(node)))
                 }
             },
         );
 
         // 4. Combine it all together!
-        let in_between = in_between_node_literals
-            .expect("generate_literal seek_forwards_until failed")
-            .fold::<ColoredString, _>("".into(), |acc, colored_str| {
-                format!("{}{}", acc, colored_str).into()
-            });
-        format!("{earlier_suffix}{in_between}{later_prefix}").into()
+        let mut pieces = vec![(earlier_cursor.node.clone(), earlier_suffix)];
+        pieces.extend(
+            in_between_node_literals.expect("generate_literal seek_forwards_until failed"),
+        );
+        pieces.push((later_cursor.node.clone(), later_prefix));
+        pieces
     }
 
-    /// When called, deletes the character span referred to by the selection.
+    /// When called, deletes the character span referred to by the selection, replacing it with
+    /// `new_literal` if given.
+    ///
+    /// Returns the literal node that the text around the selection was collected into if it needs
+    /// to be reparsed, or None if the change was made within a single node.
     fn splice(
         self: &Self,
         new_literal: Option<String>,
-        perform_reparse: bool,
-    ) -> Result<(), String> {
+    ) -> Result<Option<Rc<RefCell<InMemoryNode<TokenKind>>>>, String> {
         // Find the earlier and later pointers out of self.primary and self.secondary
         let earlier_cursor = &{
             // NOTE: advance earlier_cursor forward, skipping empty nodes at the start of the selection
@@ -237,7 +270,11 @@ impl<TokenKind: TokenKindTrait> Selection<TokenKind> {
             }
             earlier_cursor
         };
-        let later_cursor = if self.primary.node < self.secondary.node {
+        let later_cursor = if self.primary == self.secondary {
+            // An empty selection moves along with the earlier cursor, so that text inserted at
+            // the start of the document goes before the first character
+            earlier_cursor
+        } else if self.primary.node < self.secondary.node {
             &self.secondary
         } else {
             &self.primary
@@ -248,9 +285,11 @@ impl<TokenKind: TokenKindTrait> Selection<TokenKind> {
         // If the node selection spans within a single node, then to delete that data, just update
         // the string literal value on the node
         if earlier_cursor.node == later_cursor.node {
-            if earlier_cursor.offset == later_cursor.offset {
-                // A zero length selection - do nothing!
-                return Ok(());
+            if earlier_cursor.offset == later_cursor.offset
+                && new_literal.as_deref().is_none_or(str::is_empty)
+            {
+                // A zero length selection with nothing to insert - do nothing!
+                return Ok(None);
             };
 
             let new_literal_start_offset = if earlier_cursor.offset < later_cursor.offset {
@@ -268,7 +307,8 @@ impl<TokenKind: TokenKindTrait> Selection<TokenKind> {
             let new_literal_suffix = InMemoryNode::literal_substring(
                 &earlier_cursor.node,
                 new_literal_start_offset + new_literal_length,
-                InMemoryNode::literal(&earlier_cursor.node).len() - new_literal_start_offset,
+                InMemoryNode::literal(&earlier_cursor.node).chars().count()
+                    - new_literal_start_offset,
             );
             let new_literal = format!(
                 "{new_literal_prefix}{}{new_literal_suffix}",
@@ -283,7 +323,9 @@ impl<TokenKind: TokenKindTrait> Selection<TokenKind> {
             // new_literal.len() == 0?
             InMemoryNode::set_literal(&earlier_cursor.node, &new_literal);
 
-            return Ok(());
+            // The edit may have changed what the node's text parses as (ie, deleting a bracket
+            // unbalances the brackets around it), so it needs to be reparsed too
+            return Ok(Some(earlier_cursor.node.clone()));
         };
 
         // If the node selection spans multiple nodes, then:
@@ -296,7 +338,7 @@ impl<TokenKind: TokenKindTrait> Selection<TokenKind> {
         let later_cursor_substring_outside_selection = InMemoryNode::literal_substring(
             &later_cursor.node,
             later_cursor.offset,
-            InMemoryNode::literal(&later_cursor.node).len() - later_cursor.offset,
+            InMemoryNode::literal(&later_cursor.node).chars().count() - later_cursor.offset,
         );
 
         let earlier_node_depth = InMemoryNode::depth(&earlier_cursor.node);
@@ -316,6 +358,13 @@ impl<TokenKind: TokenKindTrait> Selection<TokenKind> {
                     return NodeSeek::Continue(None);
                 }
 
+                if node == &later_cursor.node && later_cursor.offset == 0 {
+                    // The selection ends right at the start of `later_cursor.node`, so none of it
+                    // is part of the edit. Leave it where it is rather than pulling it into the
+                    // literal that is about to be reparsed - ie, so that editing the end of one
+                    // statement doesn't pull in the start of the statement after it.
+                    return NodeSeek::Stop;
+                };
                 if node == &later_cursor.node {
                     // The node that was found was `later_cursor.node`, so use the part of the
                     // later node that is outside the selection.
@@ -339,8 +388,10 @@ impl<TokenKind: TokenKindTrait> Selection<TokenKind> {
                     // keep going
                     NodeSeek::Continue(Some(literal))
                 } else {
-                    // The node was at or above `earlier_cursor.node`, so bail out
-                    NodeSeek::Done(Some(literal))
+                    // The node was at or above `earlier_cursor.node`, so bail out. This node is
+                    // outside of the edit, so leave it where it is rather than pulling it into the
+                    // literal that is about to be reparsed.
+                    NodeSeek::Stop
                 }
             },
         )?;
@@ -363,46 +414,81 @@ impl<TokenKind: TokenKindTrait> Selection<TokenKind> {
         InMemoryNode::set_literal(&earlier_cursor.node, &resulting_literal);
         InMemoryNode::remove_all_children(&earlier_cursor.node);
 
-        // 5. Reparse the newly created literal text node
-        // NOTE: consider making this an async job that can run when free cycles are available
-        if perform_reparse {
-            let child = earlier_cursor.node.borrow();
-            if let (Some(Some(parent)), Some(child_index)) = (
+        Ok(Some(earlier_cursor.node.clone()))
+    }
+
+    /// When called, reparses the literal node that `splice` collected the text around the
+    /// selection into.
+    fn reparse(node: &Rc<RefCell<InMemoryNode<TokenKind>>>) -> Result<(), String> {
+        let (parent, child_index) = {
+            let child = node.borrow();
+            (
                 child.parent.as_ref().map(|n| n.upgrade()),
                 child.child_index,
-            ) {
-                InMemoryNode::reparse_child_at_index(parent, child_index)?;
-            } else {
-                // The node that needs to be reparsed doesn't have a parent!
-                //
-                // This should be impossible, since the ROOT node at the top of the document has no
-                // length, and should therefore never be part of a selection
-                unreachable!("Selection::delete: tried to reparse a node that has no parent ({:?}), this is impossible!", child.metadata);
-            }
+            )
+        };
+        if let (Some(Some(parent)), Some(child_index)) = (parent, child_index) {
+            InMemoryNode::reparse_child_at_index(parent, child_index)?;
+        } else {
+            // The node that needs to be reparsed doesn't have a parent!
+            //
+            // This should be impossible, since the ROOT node at the top of the document has no
+            // length, and should therefore never be part of a selection
+            unreachable!("Selection::delete: tried to reparse a node that has no parent ({:?}), this is impossible!", node.borrow().metadata);
         }
-
         Ok(())
     }
 
     /// When called, deletes the character span referred to by the selection, and reparses the
     /// result
     pub fn delete(&self) -> Result<(), String> {
-        self.splice(None, true)
+        match self.splice(None)? {
+            Some(node) => Self::reparse(&node),
+            None => Ok(()),
+        }
     }
     /// When called, deletes the character span referred to by the selection. NO REPARSE OCCURS.
     pub fn delete_raw(&self) -> Result<(), String> {
-        self.splice(None, false)
+        self.splice(None)?;
+        Ok(())
+    }
+    /// When called, deletes the character span referred to by the selection, and marks the result
+    /// as needing a reparse the next time `scheduler` is flushed.
+    pub fn delete_deferred(
+        &self,
+        scheduler: &mut ReparseScheduler<TokenKind>,
+    ) -> Result<(), String> {
+        if let Some(node) = self.splice(None)? {
+            scheduler.mark_dirty(&node);
+        }
+        Ok(())
     }
 
     /// When called, replaces the character span referred to by the selection with the given
     /// literal, and reparses the result
     pub fn replace(&self, literal: &str) -> Result<(), String> {
-        self.splice(Some(literal.into()), true)
+        match self.splice(Some(literal.into()))? {
+            Some(node) => Self::reparse(&node),
+            None => Ok(()),
+        }
     }
     /// When called, replaces the character span referred to by the selection with the given
     /// literal. NO REPARSE OCCURS.
     pub fn replace_raw(&self, literal: &str) -> Result<(), String> {
-        self.splice(Some(literal.into()), false)
+        self.splice(Some(literal.into()))?;
+        Ok(())
+    }
+    /// When called, replaces the character span referred to by the selection with the given
+    /// literal, and marks the result as needing a reparse the next time `scheduler` is flushed.
+    pub fn replace_deferred(
+        &self,
+        literal: &str,
+        scheduler: &mut ReparseScheduler<TokenKind>,
+    ) -> Result<(), String> {
+        if let Some(node) = self.splice(Some(literal.into()))? {
+            scheduler.mark_dirty(&node);
+        }
+        Ok(())
     }
 }
 
@@ -413,10 +499,11 @@ mod tests {
         languages,
         node_tree::{
             cursor::{Cursor, CursorSeek},
-            node::InMemoryNode,
+            node::{InMemoryNode, NodeSeek, OffsetUnit, TextMetrics, TokenKindTrait},
             utils::{Direction, Inclusivity},
         },
     };
+    use std::{cell::RefCell, rc::Rc};
 
     const LOREM_IPSUM: &'static str = concat!(
         "Lorem ipsum dolor sit amet, consectetur adipiscing elit. Vestibulum dignissim ",
@@ -561,4 +648,233 @@ mod tests {
         assert_eq!(selection.primary.to_rows_cols(), (1, 27));
         assert_eq!(selection.secondary.to_rows_cols(), (1, 54));
     }
+
+    fn assert_positions_match_literal<TokenKind: TokenKindTrait>(
+        root: &Rc<RefCell<InMemoryNode<TokenKind>>>,
+    ) {
+        let literal = InMemoryNode::deep_literal(root);
+        assert_eq!(
+            root.borrow().subtree_metrics,
+            TextMetrics::from_literal(&literal)
+        );
+
+        let (mut row, mut col) = (1, 1);
+        for (offset, c) in literal.chars().chain(std::iter::once(' ')).enumerate() {
+            let cursor = Cursor::from_offset(root.clone(), offset, OffsetUnit::Char);
+            assert_eq!(cursor.to_offset(OffsetUnit::Char), offset);
+            assert_eq!(cursor.to_rows_cols(), (row, col));
+            assert_eq!(cursor.to_cols(), col);
+            assert_eq!(
+                Cursor::new_at_rows_cols(root.clone(), (row, col)).to_offset(OffsetUnit::Char),
+                offset
+            );
+            if c == '\n' {
+                (row, col) = (row + 1, 1);
+            } else {
+                col += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn rows_cols_and_offsets_use_subtree_metrics() {
+        let root = InMemoryNode::<languages::typescript::SyntaxKind>::new_from_parsed(
+            "let a = 1;\nfunction foo() {\n  return 'bär';\n}\n",
+        );
+        assert_positions_match_literal(&root);
+
+        // Past the end of a short line, the cursor stops at the end of that line
+        assert_eq!(
+            Cursor::new_at_rows_cols(root.clone(), (1, 40)).to_rows_cols(),
+            (1, 11)
+        );
+        assert_eq!(
+            Cursor::new_at_rows_cols(root.clone(), (40, 1)).to_offset(OffsetUnit::Char),
+            root.borrow().subtree_metrics.chars
+        );
+
+        // After changing the document, the metrics should be updated to match
+        let root = InMemoryNode::<languages::raw::SyntaxKind>::new_tree_from_literal_in_chunks(
+            "first\nsecond line\n\nfourth",
+            4,
+        );
+        assert_positions_match_literal(&root);
+        let selection = Selection::new_from_cursor_pair(
+            Cursor::new_at_rows_cols(root.clone(), (2, 3)),
+            Cursor::new_at_rows_cols(root.clone(), (3, 1)),
+        );
+        selection.replace("x\ny\nz").unwrap();
+        assert_eq!(
+            InMemoryNode::deep_literal(&root),
+            "first\nsex\ny\nz\nfourth"
+        );
+        assert_positions_match_literal(&root);
+    }
+
+    #[test]
+    fn offsets_in_each_unit() {
+        let literal = "let s = 'héllo 😀';\nlet t = 1;\n";
+        let root = InMemoryNode::<languages::typescript::SyntaxKind>::new_from_parsed(literal);
+
+        for (char_offset, (byte_offset, _c)) in literal.char_indices().enumerate() {
+            let utf16_offset = literal[..byte_offset].encode_utf16().count();
+            let cursor = Cursor::from_offset(root.clone(), char_offset, OffsetUnit::Char);
+            assert_eq!(cursor.to_offset(OffsetUnit::Utf8), byte_offset);
+            assert_eq!(cursor.to_offset(OffsetUnit::Utf16), utf16_offset);
+            assert_eq!(
+                Cursor::from_offset(root.clone(), byte_offset, OffsetUnit::Utf8),
+                cursor
+            );
+            assert_eq!(
+                Cursor::from_offset(root.clone(), utf16_offset, OffsetUnit::Utf16),
+                cursor
+            );
+        }
+
+        // An offset in the middle of a character snaps to the start of that character
+        let emoji = Cursor::from_offset(root.clone(), 15, OffsetUnit::Char);
+        assert_eq!(
+            Cursor::from_offset(root.clone(), 17, OffsetUnit::Utf8),
+            emoji
+        );
+        assert_eq!(
+            Cursor::from_offset(root.clone(), 16, OffsetUnit::Utf16),
+            emoji
+        );
+
+        // Past the end, the cursor is placed at the end of the document
+        let end = Cursor::from_offset(root.clone(), 1000, OffsetUnit::Utf16);
+        assert_eq!(end.to_offset(OffsetUnit::Utf8), literal.len());
+
+        // Ranges run from the earlier cursor to the later cursor, regardless of which is primary
+        let selection = Selection::new_from_cursor_pair(
+            Cursor::from_offset(root.clone(), 17, OffsetUnit::Char),
+            Cursor::from_offset(root.clone(), 8, OffsetUnit::Char),
+        );
+        assert_eq!(selection.literal(), "'héllo 😀'");
+        assert_eq!(selection.to_range(OffsetUnit::Char), 8..17);
+        assert_eq!(selection.to_range(OffsetUnit::Utf8), 8..21);
+        assert_eq!(selection.to_range(OffsetUnit::Utf16), 8..18);
+    }
+
+    #[test]
+    fn replace_keeps_the_node_a_selection_ends_before() {
+        let root = InMemoryNode::<languages::typescript::SyntaxKind>::new_from_parsed(
+            "a(1, 2);\nb(3);\n",
+        );
+        let b = Cursor::new_at_rows_cols(root.clone(), (2, 1)).node;
+        assert_eq!(InMemoryNode::literal(&b), "b");
+
+        // The selection ends at the very start of `b`, so none of `b` is part of the edit, and it
+        // is left where it is rather than being pulled into the text that is reparsed
+        Selection::new_from_cursor_pair(
+            Cursor::new_at_rows_cols(root.clone(), (1, 4)),
+            Cursor::new_at(b.clone(), 0),
+        )
+        .replace(");\n")
+        .unwrap();
+        assert_eq!(InMemoryNode::deep_literal(&root), "a(1);\nb(3);\n");
+        assert!(Rc::ptr_eq(
+            &Cursor::new_at_rows_cols(root.clone(), (2, 1)).node,
+            &b
+        ));
+    }
+
+    #[test]
+    fn seek_by_grapheme_cluster() {
+        let root = InMemoryNode::<languages::raw::SyntaxKind>::new_tree_from_literal_in_chunks(
+            "\t漢👍🏽e\u{301}!\nx",
+            2,
+        );
+
+        // Each step moves over a whole grapheme cluster, even when it spans nodes
+        let mut cursor = Cursor::new(root.clone());
+        let mut positions = vec![];
+        for _ in 0..7 {
+            positions.push((
+                cursor.to_offset(OffsetUnit::Char),
+                cursor.to_display_cols(8),
+            ));
+            cursor = cursor.seek_graphemes(Direction::Forwards, 1);
+        }
+        assert_eq!(
+            positions,
+            vec![(0, 1), (1, 9), (2, 11), (4, 13), (6, 14), (7, 15), (8, 1)]
+        );
+
+        let cursor = cursor.seek_graphemes(Direction::Backwards, 3);
+        assert_eq!(cursor.to_offset(OffsetUnit::Char), 6);
+        assert_eq!(cursor.to_display_cols(4), 10);
+        let cursor = cursor.seek_graphemes(Direction::Backwards, 10);
+        assert_eq!(cursor.to_offset(OffsetUnit::Char), 0);
+    }
+
+    #[test]
+    fn new_at_rows_cols_clamps_to_row() {
+        let root = InMemoryNode::<languages::raw::SyntaxKind>::new_from_parsed("ab\ncdef\n\ng");
+        let offset_at = |rows_cols| {
+            Cursor::new_at_rows_cols(root.clone(), rows_cols).to_offset(OffsetUnit::Char)
+        };
+        assert_eq!(offset_at((1, 1)), 0);
+        assert_eq!(offset_at((2, 3)), 5);
+
+        // Columns past the end of a row stop at the end of that row, rather than wrapping onto the
+        // rows after it
+        assert_eq!(offset_at((1, 5)), 2);
+        assert_eq!(offset_at((2, 100)), 7);
+        assert_eq!(offset_at((3, 2)), 8);
+        assert_eq!(Cursor::new_at_rows_cols(root.clone(), (1, 5)).to_rows_cols(), (1, 3));
+
+        // Rows past the end of the document stop at the end of the document
+        assert_eq!(offset_at((4, 1)), 9);
+        assert_eq!(offset_at((10, 1)), 10);
+    }
+
+    #[test]
+    fn reparse_keeps_unchanged_nodes() {
+        let root = InMemoryNode::<languages::typescript::SyntaxKind>::new_from_parsed(
+            "let a = 1;\nfunction foo() {\n  return 'bar';\n}\nlet c = 3;\n",
+        );
+        let script = root.borrow().children[0].clone();
+        let statements = script.borrow().children.clone();
+        let indexes = statements
+            .iter()
+            .map(|node| node.borrow().index.clone())
+            .collect::<Vec<_>>();
+
+        let selection = Selection::new_from_cursor_pair(
+            Cursor::new_at_rows_cols(root.clone(), (3, 10)),
+            Cursor::new_at_rows_cols(root.clone(), (3, 15)),
+        );
+        selection.replace("'baz' + 1").unwrap();
+        assert_eq!(
+            InMemoryNode::deep_literal(&root),
+            "let a = 1;\nfunction foo() {\n  return 'baz' + 1;\n}\nlet c = 3;\n"
+        );
+
+        // The statements before and after the change are the very same nodes as before, with the
+        // same indexes
+        let new_statements = script.borrow().children.clone();
+        for position in [0, new_statements.len() - 1] {
+            let (old, new) = (&statements[position], &new_statements[position]);
+            assert!(Rc::ptr_eq(old, new));
+            assert_eq!(new.borrow().index, indexes[position]);
+        }
+        assert_eq!(
+            root.borrow().subtree_metrics,
+            TextMetrics::from_literal(&InMemoryNode::deep_literal(&root))
+        );
+
+        // The nodes are still linked together in order
+        assert_eq!(
+            Selection::new_across_subtree(&root).literal(),
+            InMemoryNode::deep_literal(&root)
+        );
+        let nodes = InMemoryNode::seek_forwards_until(&root, Inclusivity::Inclusive, |node, _ct| {
+            NodeSeek::Continue(node.borrow().index.clone())
+        })
+        .unwrap()
+        .collect::<Vec<_>>();
+        assert!(nodes.windows(2).all(|pair| pair[0] < pair[1]));
+    }
 }
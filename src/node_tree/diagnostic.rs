@@ -0,0 +1,75 @@
+use std::ops::Range;
+
+use crate::node_tree::style::HighlightGroup;
+
+/// How serious a diagnostic is. Ordered from most to least severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+    Hint,
+}
+
+impl Severity {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+            Severity::Hint => "hint",
+        }
+    }
+
+    /// When called, returns the text shown in the sign column next to rows with a diagnostic of
+    /// this severity.
+    pub fn sign_text(&self) -> &'static str {
+        match self {
+            Severity::Error => "E",
+            Severity::Warning => "W",
+            Severity::Info => "I",
+            Severity::Hint => "H",
+        }
+    }
+
+    pub fn highlight_group(&self) -> HighlightGroup {
+        match self {
+            Severity::Error => HighlightGroup::DiagnosticError,
+            Severity::Warning => HighlightGroup::DiagnosticWarning,
+            Severity::Info => HighlightGroup::DiagnosticInfo,
+            Severity::Hint => HighlightGroup::DiagnosticHint,
+        }
+    }
+}
+
+/// A Diagnostic is a message about a range of text in a document, ie, a syntax error reported by a
+/// language's parser.
+///
+/// Diagnostics are stored on the nearest node in the token tree that contains all of their text,
+/// and `range` is in chars relative to the start of that node. Once they are collected from the
+/// tree with `InMemoryNode::diagnostics`, `range` is relative to the node they were collected from
+/// instead.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub range: Range<usize>,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn new(range: Range<usize>, severity: Severity, message: &str) -> Self {
+        Self {
+            range,
+            severity,
+            message: message.into(),
+        }
+    }
+
+    /// When called, returns a copy of the diagnostic with its range moved `offset` chars later.
+    pub fn offset_by(&self, offset: usize) -> Self {
+        Self {
+            range: self.range.start + offset..self.range.end + offset,
+            ..self.clone()
+        }
+    }
+}
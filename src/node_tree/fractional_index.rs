@@ -227,11 +227,33 @@ impl VariableSizeFractionalIndex {
         match (previous, next) {
             (None, None) => Self::start(),
             (None, Some(next)) => Self::generate(&Self::start(), &next),
-            (Some(previous), None) => Self::generate(&previous, &Self::end()),
+            (Some(previous), None) => Self::generate_after(&previous),
             (Some(previous), Some(next)) => Self::generate(&previous, &next),
         }
     }
 
+    /// Generates an index after `previous` and before `end()`. Unlike picking the midpoint between
+    /// `previous` and `end()`, which adds a place about every 8 calls, this counts up like an
+    /// odometer in the places that `previous` already has, so appending many nodes one after
+    /// another keeps their indexes short.
+    pub fn generate_after(previous: &Self) -> Self {
+        let mut result = previous.0.clone();
+        for place in result.iter_mut().rev() {
+            if *place < u8::MAX - 1 {
+                *place += 1;
+                return Self(result);
+            }
+            *place = u8::MIN;
+        }
+
+        // Every place is full, so add as many places again (ending in a 1) to make room for as
+        // many appends as were made to fill the current places
+        let mut result = previous.0.clone();
+        result.resize(result.len() * 2 - 1, u8::MIN);
+        result.push(1);
+        Self(result)
+    }
+
     /// Generates a roughly equally distributed sequence of `sequence_length` indexes
     /// between `start` and `end`.
     ///
@@ -247,7 +269,7 @@ impl VariableSizeFractionalIndex {
         sequence_length: usize,
     ) -> impl std::iter::Iterator<Item = Self> {
         let mut sequence = VecDeque::from(vec![start.clone(), end.clone()]);
-        while sequence.len() < sequence_length {
+        while sequence.len() < sequence_length + 2 /* start and end */ {
             // Generate a new element between each existing element
             let mut start_index = 0;
             let mut end_index = 1;
@@ -257,8 +279,8 @@ impl VariableSizeFractionalIndex {
                 let end = &sequence[end_index];
                 let midpoint = Self::generate(start, end);
                 sequence.insert(start_index + 1, midpoint);
-                start_index += 2 /* each element of pair */ + 1 /* newly added element */;
-                end_index += 2 /* each element of pair */ + 1 /* newly added element */;
+                start_index += 1 /* end of pair */ + 1 /* newly added element */;
+                end_index += 1 /* end of pair */ + 1 /* newly added element */;
             }
             // println!("SEQ: {:?}", sequence);
         }
@@ -267,7 +289,7 @@ impl VariableSizeFractionalIndex {
         sequence.pop_front();
         sequence.pop_back();
 
-        sequence.into_iter()
+        sequence.into_iter().take(sequence_length)
     }
 
     /// Given node values for a next and previous that may or may not exist, generate a midpoint
@@ -289,3 +311,31 @@ impl VariableSizeFractionalIndex {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::VariableSizeFractionalIndex;
+
+    #[test]
+    fn generate_after_stays_ordered_and_short() {
+        let mut previous = VariableSizeFractionalIndex::start();
+        for _ in 0..100_000 {
+            let next =
+                VariableSizeFractionalIndex::generate_or_fallback(Some(previous.clone()), None);
+            assert!(previous < next, "{previous:?} < {next:?}");
+            assert!(next < VariableSizeFractionalIndex::end());
+            previous = next;
+        }
+        assert!(previous.0.len() <= 8, "{previous:?}");
+
+        // Full places carry over, and once every place is full the places are doubled
+        let after = |raw: Vec<u8>| {
+            VariableSizeFractionalIndex::generate_after(&VariableSizeFractionalIndex::of(raw)).0
+        };
+        assert_eq!(after(vec![0]), vec![1]);
+        assert_eq!(after(vec![3, 0, 0]), vec![3, 0, 1]);
+        assert_eq!(after(vec![3, 254]), vec![4, 0]);
+        assert_eq!(after(vec![254]), vec![254, 1]);
+        assert_eq!(after(vec![254, 254]), vec![254, 254, 0, 1]);
+    }
+}
@@ -1,5 +1,11 @@
 pub mod cursor;
+pub mod diagnostic;
 pub mod fractional_index;
 pub mod node;
 pub mod node_debug_validators;
+pub mod outline;
+pub mod query;
+pub mod reparse_scheduler;
+pub mod style;
+pub mod theme;
 pub mod utils;
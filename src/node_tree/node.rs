@@ -1,14 +1,18 @@
 use crate::node_tree::{
+    diagnostic::Diagnostic,
     fractional_index::VariableSizeFractionalIndex,
     node_debug_validators::{
         validate_node_next, validate_node_previous, NodeNextValidReason, NodePreviousValidReason,
     },
+    style::{HighlightGroup, Style},
+    theme::current_style,
     utils::{Direction, Inclusivity},
 };
 use colored::{ColoredString, Colorize, CustomColor};
 use std::{
     cell::{RefCell, RefMut},
     fmt::Debug,
+    path::Path,
     rc::{Rc, Weak},
 };
 
@@ -21,6 +25,50 @@ pub enum NodeSeek<Item> {
     Fail(&'static str),               // An error happened while processing, terminate
 }
 
+/// A trait for the options that change how a language parses a document, ie, whether a javascript
+/// file is parsed as a script or as a module. Languages without any such options can use `()`.
+///
+/// A document's parse options are stored on the Root node of its token tree, so that later
+/// reparses of parts of the document are parsed the same way.
+pub trait ParseOptionsTrait: Clone + Debug + Default + PartialEq {
+    /// When called, picks the options to parse the file at `path` with, ie, from its extension.
+    fn from_path(_path: &Path) -> Self {
+        Self::default()
+    }
+
+    /// When called, picks the options to parse the file at `path` with, now that its contents are
+    /// known too, ie, from a `#!/usr/bin/env node` shebang on its first line.
+    fn detect(path: &Path, _literal: &str) -> Self {
+        Self::from_path(path)
+    }
+
+    /// When called, picks the options to parse documents written in the language named `filetype`
+    /// with, ie, `json`. Returns None if documents can't be parsed as that language.
+    fn from_filetype(_filetype: &str) -> Option<Self> {
+        None
+    }
+
+    /// When called, returns the name of the language that documents parsed with these options are
+    /// written in, or None if it is always the token kind's own language (`language_name`).
+    fn filetype(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// When called, parses the options from their name, as returned by `name`.
+    fn from_name(_name: &str) -> Option<Self> {
+        None
+    }
+
+    /// When called, returns the name of the options, as shown to the user.
+    fn name(&self) -> String;
+}
+
+impl ParseOptionsTrait for () {
+    fn name(&self) -> String {
+        "default".into()
+    }
+}
+
 /// A trait that any new language definition must implement, which tells the system how to properly
 /// parse and understand ASTs generated for that language.
 ///
@@ -28,12 +76,32 @@ pub enum NodeSeek<Item> {
 pub trait TokenKindTrait: Clone + Debug + PartialEq {
     // TODO: add logic to handle setting effects
 
+    /// The options that change how documents in this language are parsed.
+    type ParseOptions: ParseOptionsTrait;
+
+    /// When called, returns the name of the language this token kind is used to parse, ie
+    /// `typescript`.
+    fn language_name() -> &'static str;
+
+    /// When called, determine the highlight group (keyword, string, comment, etc) that a token
+    /// belongs to, or None if it shouldn't be highlighted. `token_kind_ancestry` starts with the
+    /// kind of the token itself, followed by the kind of its parent, and so on up to the root.
+    fn highlight_group(token_kind_ancestry: std::vec::IntoIter<Self>) -> Option<HighlightGroup>;
+
+    /// When called, determine the style (colors, bold, italic, etc) that a token should be drawn
+    /// with to properly apply syntax highlighting, using the current theme.
+    fn syntax_style(token_kind_ancestry: std::vec::IntoIter<Self>) -> Style {
+        current_style(Self::highlight_group(token_kind_ancestry))
+    }
+
     /// When called, determine the color the given text should render with when rendered into a
     /// terminal to properly apply syntax highlighting.
     fn apply_debug_syntax_color(
         text: String,
         token_kind_ancestry: std::vec::IntoIter<Self>,
-    ) -> ColoredString;
+    ) -> ColoredString {
+        Self::syntax_style(token_kind_ancestry).apply(text)
+    }
 
     /// When called, should return whether this token is reparsable.
     ///
@@ -47,18 +115,101 @@ pub trait TokenKindTrait: Clone + Debug + PartialEq {
     /// document will always be reparsed (very unperformant, but could sometimes be desired),
     fn is_reparsable(&self) -> bool;
 
-    /// When called, parse the literal specified, returning a new token subtree
+    /// When called, returns true if the subtree under `node` can be reparsed on its own. By
+    /// default this is the case for nodes whose kind `is_reparsable`, but languages can also look
+    /// at the text within the node, ie, injected text is only reparsed on its own while it is
+    /// still injected (see `languages::injection`).
+    ///
+    /// Like `key_path_segments`, `node` can be within a tree of any token kind, where `unwrap`
+    /// returns the kind of this language that each wrapped kind is, if any.
+    fn is_reparsable_node<Wrapper: TokenKindTrait>(
+        node: &Rc<RefCell<InMemoryNode<Wrapper>>>,
+        unwrap: &dyn Fn(&Wrapper) -> Option<Self>,
+    ) -> bool {
+        match &node.borrow().metadata {
+            NodeMetadata::AstNode { kind, .. } => {
+                unwrap(kind).is_some_and(|kind| kind.is_reparsable())
+            }
+            _ => false,
+        }
+    }
+
+    /// When called, parse the literal specified, returning a new token subtree. The options to
+    /// parse with can be found with `InMemoryNode::parse_options(parent)`.
+    ///
+    /// Parsing should never fail: text that doesn't parse should still end up somewhere in the
+    /// subtree (with a diagnostic describing what is wrong with it), so that the literal text of
+    /// the subtree is always exactly the literal that was parsed.
     fn parse(
         literal: &str,
         parent: Option<Rc<RefCell<InMemoryNode<Self>>>>,
     ) -> Rc<RefCell<InMemoryNode<Self>>>;
+
+    /// When called, returns the segments that `node` adds on to the key path of its parent, ie,
+    /// `["dependencies"]` for a `[dependencies]` table in a TOML file, or `["0"]` for the first
+    /// item in a sequence in a YAML file.
+    ///
+    /// `node` can be within a tree of any token kind, so that languages that wrap the kinds of this
+    /// one (see `languages::registry::AnySyntaxKind`) can ask for its key paths without copying
+    /// any nodes. `unwrap` returns the kind of this language that each wrapped kind is, if any.
+    ///
+    /// Languages that don't have keys (the default) never add any segments, so none of their
+    /// nodes can be found with `InMemoryNode::find_key_path`.
+    fn key_path_segments<Wrapper: TokenKindTrait>(
+        _node: &Rc<RefCell<InMemoryNode<Wrapper>>>,
+        _unwrap: &dyn Fn(&Wrapper) -> Option<Self>,
+    ) -> Vec<String> {
+        vec![]
+    }
+
+    /// When called, returns the name of the kind as written in queries (see `node_tree::query`),
+    /// ie, `FN_DECL`. Languages that wrap the kinds of other languages return the wrapped kind's
+    /// name.
+    fn kind_name(&self) -> String {
+        format!("{self:?}")
+    }
+
+    /// When called, returns the name of the field (ie, `name` or `body`) that the child of a node
+    /// of kind `parent` at `index` within `children` (the kinds of all of the node's children) is
+    /// in, if it has one. Queries can match children by field, ie, `(FN_DECL name: (NAME))`.
+    fn field_name(_parent: &Self, _children: &[Self], _index: usize) -> Option<&'static str> {
+        None
+    }
+
+    /// When called, returns the query (see `node_tree::query`) that finds the symbols in a
+    /// document parsed with `parse_options`, ie, its functions and classes. Each pattern captures
+    /// the node that defines a symbol as `@definition.<kind>` and its name as `@name`, and nodes
+    /// captured as `@export` mark the symbols directly within them as exported. See
+    /// `node_tree::outline::Symbol` for the kinds of symbols.
+    ///
+    /// Languages without symbols (the default) return None, so their documents have no outline.
+    fn outline_query(_parse_options: &Self::ParseOptions) -> Option<&'static str> {
+        None
+    }
+
+    /// When called, returns true if nodes of this kind can be folded (see `editor::fold`), ie,
+    /// block statements and object literals. Only nodes that span more than one row are ever
+    /// folded, so kinds that are usually on one row (ie, comments) can be foldable too.
+    fn is_foldable(&self) -> bool {
+        false
+    }
+
+    /// When called, returns the name of the language (as returned by `language_name`) that the
+    /// text of `node` is written in, if it isn't this language, ie, `css` for the text of a
+    /// `styled.div`...`` template in a TypeScript file.
+    ///
+    /// This is only a declaration - the text is parsed with the other language when documents are
+    /// parsed with `languages::injection::Injected`, and kept as is otherwise.
+    fn injected_language(_node: &Rc<RefCell<InMemoryNode<Self>>>) -> Option<&'static str> {
+        None
+    }
 }
 
 #[derive(Clone, PartialEq)]
 pub enum NodeMetadata<TokenKind: TokenKindTrait> {
     Empty,
     Literal(String),
-    Root,
+    Root(TokenKind::ParseOptions),
     Fragment,
     AstNode {
         kind: TokenKind,
@@ -71,7 +222,7 @@ impl<TokenKind: TokenKindTrait> Debug for NodeMetadata<TokenKind> {
         match self {
             Self::Empty => write!(f, "EMPTY"),
             Self::Literal(text) => write!(f, "LITERAL({text})"),
-            Self::Root => write!(f, "ROOT"),
+            Self::Root(_) => write!(f, "ROOT"),
             Self::Fragment => write!(f, "FRAGMENT"),
             Self::AstNode {
                 kind,
@@ -96,6 +247,104 @@ impl<TokenKind: TokenKindTrait> Debug for NodeMetadata<TokenKind> {
     }
 }
 
+/// TextMetrics summarize a piece of text so that the position of a character within a whole
+/// document can be computed without scanning all of the text that comes before it.
+///
+/// Each node stores the metrics of its whole subtree (its own literal followed by the literals of
+/// all of its children, in order), which are kept up to date as the tree is changed. Most metrics
+/// are counts that add up across concatenated text, so when part of a subtree changes, its
+/// ancestors only need the difference applied rather than being recomputed from their children.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TextMetrics {
+    pub chars: usize,
+    pub utf8_bytes: usize,
+    pub utf16_units: usize,
+    pub newlines: usize,
+    pub last_line_chars: usize, // The number of chars after the final newline
+}
+
+impl TextMetrics {
+    /// When called, computes the metrics of the given text.
+    pub fn from_literal(literal: &str) -> Self {
+        let mut metrics = Self {
+            utf8_bytes: literal.len(),
+            ..Self::default()
+        };
+        for c in literal.chars() {
+            metrics.chars += 1;
+            metrics.utf16_units += c.len_utf16();
+            if c == '\n' {
+                metrics.newlines += 1;
+                metrics.last_line_chars = 0;
+            } else {
+                metrics.last_line_chars += 1;
+            }
+        }
+        metrics
+    }
+
+    /// When called, computes the metrics of the text described by `self` immediately followed by
+    /// the text described by `other`.
+    pub fn concat(self, other: Self) -> Self {
+        Self {
+            chars: self.chars + other.chars,
+            utf8_bytes: self.utf8_bytes + other.utf8_bytes,
+            utf16_units: self.utf16_units + other.utf16_units,
+            newlines: self.newlines + other.newlines,
+            last_line_chars: if other.newlines > 0 {
+                other.last_line_chars
+            } else {
+                self.last_line_chars + other.chars
+            },
+        }
+    }
+
+    /// When called, computes the metrics of the text described by `self` once the part of it
+    /// described by `old` has been replaced with text described by `new`. Where the last line
+    /// starts depends on where in the text the replacement happened, so `last_line_chars` can't be
+    /// worked out from the difference alone and is left as `last_line_chars` for the caller to
+    /// fill in.
+    pub fn replace(self, old: Self, new: Self, last_line_chars: usize) -> Self {
+        Self {
+            chars: self.chars - old.chars + new.chars,
+            utf8_bytes: self.utf8_bytes - old.utf8_bytes + new.utf8_bytes,
+            utf16_units: self.utf16_units - old.utf16_units + new.utf16_units,
+            newlines: self.newlines - old.newlines + new.newlines,
+            last_line_chars,
+        }
+    }
+
+    /// When called, returns the length of the text in the given unit.
+    pub fn length(&self, unit: OffsetUnit) -> usize {
+        match unit {
+            OffsetUnit::Char => self.chars,
+            OffsetUnit::Utf8 => self.utf8_bytes,
+            OffsetUnit::Utf16 => self.utf16_units,
+        }
+    }
+}
+
+/// The unit that an absolute offset into a document is measured in. Different external tools
+/// expect different units, ie, LSP servers default to UTF-16 code units while most diff tools
+/// work in UTF-8 bytes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OffsetUnit {
+    Char,  // Unicode scalar values, which is how `Cursor::offset` is measured
+    Utf8,  // UTF-8 bytes
+    Utf16, // UTF-16 code units
+}
+
+impl OffsetUnit {
+    /// When called, returns how many of this unit the given character takes up.
+    pub fn length_of(&self, c: char) -> usize {
+        match self {
+            OffsetUnit::Char => 1,
+            OffsetUnit::Utf8 => c.len_utf8(),
+            OffsetUnit::Utf16 => c.len_utf16(),
+        }
+    }
+}
+
 /// A node is the building block of a node tree, and repreents a node in an AST-like structure.
 /// Nodes are linked both as a tree (ie, parent / children / etc) as well as doubly linked as a
 /// linked list (ie, next / previous) to allow for fast traversal both linearly (ie, for printing
@@ -115,6 +364,22 @@ pub struct InMemoryNode<TokenKind: TokenKindTrait> {
     // Linked list data structure refs:
     pub next: Option<Weak<RefCell<InMemoryNode<TokenKind>>>>,
     pub previous: Option<Weak<RefCell<InMemoryNode<TokenKind>>>>,
+
+    // Aggregate metrics of the literals of this node and all nodes in its subtree:
+    pub subtree_metrics: TextMetrics,
+
+    // The metrics of the text in this node's subtree before each of its first few children (see
+    // `metrics_before_child`). Entries from the first child whose text has changed onwards are
+    // dropped, and are computed again the next time they are needed:
+    pub child_offsets: RefCell<Vec<TextMetrics>>,
+
+    // Diagnostics (ie, parse errors) about text within this node's subtree that aren't within
+    // any one of its children:
+    pub diagnostics: Vec<Diagnostic>,
+
+    // Set when the node is a closed fold (see `TokenKindTrait::is_foldable`), so that only the
+    // first of the rows it spans is shown:
+    pub is_folded: bool,
 }
 
 impl<TokenKind: TokenKindTrait> InMemoryNode<TokenKind> {
@@ -122,7 +387,7 @@ impl<TokenKind: TokenKindTrait> InMemoryNode<TokenKind> {
         Self::new_with_metadata(NodeMetadata::Empty)
     }
     pub fn new_root() -> Rc<RefCell<Self>> {
-        Self::new_with_metadata(NodeMetadata::Root)
+        Self::new_with_metadata(NodeMetadata::Root(TokenKind::ParseOptions::default()))
     }
     pub fn new_fragment() -> Rc<RefCell<Self>> {
         Self::new_with_metadata(NodeMetadata::Fragment)
@@ -131,12 +396,26 @@ impl<TokenKind: TokenKindTrait> InMemoryNode<TokenKind> {
         Self::new_with_metadata(NodeMetadata::Literal(literal.into()))
     }
     pub fn new_from_parsed(literal: &str) -> Rc<RefCell<Self>> {
-        let subtree_root = TokenKind::parse(literal, None);
-        let root = Self::new_root();
+        Self::new_from_parsed_with_options(literal, TokenKind::ParseOptions::default())
+    }
+    pub fn new_from_parsed_with_options(
+        literal: &str,
+        options: TokenKind::ParseOptions,
+    ) -> Rc<RefCell<Self>> {
+        let root = Self::new_with_metadata(NodeMetadata::Root(options));
+        let subtree_root = TokenKind::parse(literal, Some(root.clone()));
         InMemoryNode::append_child(&root, subtree_root);
         root
     }
     pub fn new_with_metadata(metadata: NodeMetadata<TokenKind>) -> Rc<RefCell<Self>> {
+        let subtree_metrics = TextMetrics::from_literal(match &metadata {
+            NodeMetadata::Literal(literal) => literal,
+            NodeMetadata::AstNode {
+                literal: Some(literal),
+                ..
+            } => literal,
+            _ => "",
+        });
         Rc::new(RefCell::new(Self {
             index: VariableSizeFractionalIndex::start(),
             metadata,
@@ -147,6 +426,10 @@ impl<TokenKind: TokenKindTrait> InMemoryNode<TokenKind> {
             last_child: None,
             next: None,
             previous: None,
+            subtree_metrics,
+            child_offsets: RefCell::new(vec![]),
+            diagnostics: vec![],
+            is_folded: false,
         }))
     }
 
@@ -448,7 +731,264 @@ impl<TokenKind: TokenKindTrait> InMemoryNode<TokenKind> {
         Self::set_metadata(node, NodeMetadata::Literal(new_literal.into()));
     }
     pub fn set_metadata(node: &Rc<RefCell<Self>>, new_metadata: NodeMetadata<TokenKind>) {
+        let old_metrics = TextMetrics::from_literal(&Self::literal(node));
         (*node.borrow_mut()).metadata = new_metadata;
+        let new_metrics = TextMetrics::from_literal(&Self::literal(node));
+        Self::update_subtree_metrics(node, None, old_metrics, new_metrics);
+    }
+
+    /// When called, updates the `subtree_metrics` of the given node and each of its ancestors
+    /// after text within the node's subtree described by `old` was replaced with text described
+    /// by `new`. `changed_child_index` is the index of the child that was added, removed or
+    /// swapped out, or None if the node's own literal changed. Only the difference is applied to
+    /// each node, so this is proportional to the depth of the tree rather than to the number of
+    /// children each ancestor has.
+    ///
+    /// Since the text within each of these nodes has changed, their diagnostics may no longer
+    /// apply, so they are cleared.
+    fn update_subtree_metrics(
+        node: &Rc<RefCell<Self>>,
+        changed_child_index: Option<usize>,
+        old: TextMetrics,
+        new: TextMetrics,
+    ) {
+        let mut pointer = Some(node.clone());
+        let mut changed_child_index = changed_child_index;
+        while let Some(node) = pointer {
+            {
+                let last_line_chars = Self::last_line_chars(&node);
+                let mut node_mut = node.borrow_mut();
+                node_mut.subtree_metrics =
+                    node_mut.subtree_metrics.replace(old, new, last_line_chars);
+                node_mut.diagnostics.clear();
+
+                // The text before the changed child is the same as it was, but the text before
+                // each child after it isn't
+                let child_offsets = node_mut.child_offsets.get_mut();
+                match changed_child_index {
+                    Some(index) => child_offsets.truncate(index + 1),
+                    None => child_offsets.clear(),
+                }
+            }
+            changed_child_index = node.borrow().child_index;
+            pointer = node
+                .borrow()
+                .parent
+                .as_ref()
+                .and_then(|parent| parent.upgrade());
+        }
+    }
+
+    /// When called, computes the number of chars after the final newline in the subtree under
+    /// `node`, using the metrics stored on its children. Only the children after the last one
+    /// with a newline in it are looked at.
+    fn last_line_chars(node: &Rc<RefCell<Self>>) -> usize {
+        let mut chars = 0;
+        for child in node.borrow().children.iter().rev() {
+            let child_metrics = child.borrow().subtree_metrics;
+            if child_metrics.newlines > 0 {
+                return chars + child_metrics.last_line_chars;
+            }
+            chars += child_metrics.chars;
+        }
+        TextMetrics::from_literal(&Self::literal(node)).last_line_chars + chars
+    }
+
+    /// When called, computes the metrics of all text in the document that comes before the given
+    /// node's literal. Rather than walking through every node that comes before `node`, this only
+    /// looks at the node's ancestors and the offsets cached on them, so it is proportional to the
+    /// depth of the tree rather than the length of the document.
+    pub fn metrics_before(node: &Rc<RefCell<Self>>) -> TextMetrics {
+        let (parent, child_index) = {
+            let node_ref = node.borrow();
+            (
+                node_ref.parent.as_ref().and_then(|parent| parent.upgrade()),
+                node_ref.child_index.unwrap_or(0),
+            )
+        };
+        let Some(parent) = parent else {
+            return TextMetrics::default();
+        };
+        Self::metrics_before(&parent).concat(Self::metrics_before_child(&parent, child_index))
+    }
+
+    /// When called, computes the metrics of the text in the subtree under `node` that comes
+    /// before its `index`th child, ie, its own literal and the subtrees of the children before
+    /// `index`. These are cached in `child_offsets`, so after the first call, this only has to
+    /// look at the children that changed since the last time it was called.
+    fn metrics_before_child(node: &Rc<RefCell<Self>>, index: usize) -> TextMetrics {
+        let node_ref = node.borrow();
+        let mut child_offsets = node_ref.child_offsets.borrow_mut();
+        if child_offsets.is_empty() {
+            child_offsets.push(TextMetrics::from_literal(&Self::literal(node)));
+        }
+        while child_offsets.len() <= index {
+            let previous = child_offsets.len() - 1;
+            let metrics = child_offsets[previous]
+                .concat(node_ref.children[previous].borrow().subtree_metrics);
+            child_offsets.push(metrics);
+        }
+        child_offsets[index]
+    }
+
+    /// When called, returns the node containing the character `offset` units into the subtree
+    /// under `node`, along with the offset of that character (in chars) within the node's literal.
+    /// If `offset` falls in the middle of a character, the character it falls within is returned.
+    /// Returns None if the subtree is `offset` units long or shorter.
+    pub fn node_at_offset(
+        node: &Rc<RefCell<Self>>,
+        offset: usize,
+        unit: OffsetUnit,
+    ) -> Option<(Rc<RefCell<Self>>, usize)> {
+        let mut offset = offset;
+        for (char_offset, c) in Self::literal(node).chars().enumerate() {
+            let length = unit.length_of(c);
+            if offset < length {
+                return Some((node.clone(), char_offset));
+            }
+            offset -= length;
+        }
+
+        for child in node.borrow().children.iter() {
+            let child_length = child.borrow().subtree_metrics.length(unit);
+            if offset < child_length {
+                return Self::node_at_offset(child, offset, unit);
+            }
+            offset -= child_length;
+        }
+        None
+    }
+
+    /// When called, returns the node containing the `newline_count`th newline in the subtree
+    /// under `node`, along with the offset of that newline within the node's literal. Returns None
+    /// if the subtree has fewer than `newline_count` newlines in it.
+    pub fn node_at_newline(
+        node: &Rc<RefCell<Self>>,
+        newline_count: usize,
+    ) -> Option<(Rc<RefCell<Self>>, usize)> {
+        if newline_count == 0 {
+            return None;
+        }
+
+        let literal = Self::literal(node);
+        let newlines_in_literal = literal.matches('\n').count();
+        if newline_count <= newlines_in_literal {
+            let offset = literal
+                .chars()
+                .enumerate()
+                .filter(|(_, c)| *c == '\n')
+                .nth(newline_count - 1)
+                .map(|(offset, _)| offset)?;
+            return Some((node.clone(), offset));
+        }
+
+        let mut newline_count = newline_count - newlines_in_literal;
+        for child in node.borrow().children.iter() {
+            let child_newlines = child.borrow().subtree_metrics.newlines;
+            if newline_count <= child_newlines {
+                return Self::node_at_newline(child, newline_count);
+            }
+            newline_count -= child_newlines;
+        }
+        None
+    }
+
+    /// When called, returns the node at the top of the token tree containing `node`.
+    pub fn root(node: &Rc<RefCell<Self>>) -> Rc<RefCell<Self>> {
+        let mut pointer = node.clone();
+        loop {
+            let parent = pointer
+                .borrow()
+                .parent
+                .as_ref()
+                .and_then(|parent| parent.upgrade());
+            match parent {
+                Some(parent) => pointer = parent,
+                None => return pointer,
+            }
+        }
+    }
+
+    /// When called, returns the options that the document containing `node` is parsed with, which
+    /// are stored on the Root node at the top of its token tree.
+    pub fn parse_options(node: &Rc<RefCell<Self>>) -> TokenKind::ParseOptions {
+        let mut pointer = node.clone();
+        loop {
+            if let NodeMetadata::Root(options) = &pointer.borrow().metadata {
+                return options.clone();
+            }
+            let Some(parent) = pointer
+                .borrow()
+                .parent
+                .as_ref()
+                .and_then(|parent| parent.upgrade())
+            else {
+                return TokenKind::ParseOptions::default();
+            };
+            pointer = parent;
+        }
+    }
+
+    /// When called, stores `diagnostic` on the nearest node in the subtree under `node` that
+    /// contains all of the diagnostic's text. The diagnostic's range should be in chars relative to
+    /// the start of `node`.
+    pub fn attach_diagnostic(node: &Rc<RefCell<Self>>, diagnostic: Diagnostic) {
+        let mut offset = Self::literal(node).chars().count();
+        let children = node.borrow().children.clone();
+        for child in children.iter() {
+            let child_length = child.borrow().subtree_metrics.chars;
+            let child_range = offset..offset + child_length;
+            if child_range.contains(&diagnostic.range.start)
+                && diagnostic.range.end <= child_range.end
+            {
+                let mut diagnostic = diagnostic;
+                diagnostic.range = diagnostic.range.start - offset..diagnostic.range.end - offset;
+                return Self::attach_diagnostic(child, diagnostic);
+            }
+            offset += child_length;
+        }
+
+        node.borrow_mut().diagnostics.push(diagnostic);
+    }
+
+    /// When called, collects all diagnostics stored in the subtree under `node`, with their ranges
+    /// in chars relative to the start of `node`. Diagnostics are sorted by where they start, with
+    /// the most severe first.
+    pub fn diagnostics(node: &Rc<RefCell<Self>>) -> Vec<Diagnostic> {
+        let mut diagnostics = vec![];
+        Self::collect_diagnostics(node, 0, &mut diagnostics);
+        diagnostics.sort_by_key(|diagnostic| (diagnostic.range.start, diagnostic.severity));
+        diagnostics
+    }
+
+    fn collect_diagnostics(
+        node: &Rc<RefCell<Self>>,
+        offset: usize,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        let node_ref = node.borrow();
+        diagnostics.extend(
+            node_ref
+                .diagnostics
+                .iter()
+                .map(|diagnostic| diagnostic.offset_by(offset)),
+        );
+
+        let mut offset = offset + Self::literal(node).chars().count();
+        for child in node_ref.children.iter() {
+            Self::collect_diagnostics(child, offset, diagnostics);
+            offset += child.borrow().subtree_metrics.chars;
+        }
+    }
+
+    /// When called, replaces the diagnostics stored in the subtree under `node` with those stored
+    /// in `other`, which must be a subtree with the same shape.
+    fn copy_diagnostics(node: &Rc<RefCell<Self>>, other: &Rc<RefCell<Self>>) {
+        node.borrow_mut().diagnostics = other.borrow().diagnostics.clone();
+        let children = node.borrow().children.clone();
+        for (child, other_child) in children.iter().zip(other.borrow().children.iter()) {
+            Self::copy_diagnostics(child, other_child);
+        }
     }
 
     /// When called, recurse through the entire subtree underneath the given node and generate the
@@ -471,26 +1011,93 @@ impl<TokenKind: TokenKindTrait> InMemoryNode<TokenKind> {
         format!("{literal}{child_literals}")
     }
 
-    pub fn literal_colored(node: &Rc<RefCell<Self>>, literal: &str) -> ColoredString {
-        let ancestry = {
-            let mut ancestry = vec![];
+    /// When called, returns the kinds of the given node and each of its ancestors, starting with
+    /// the node itself and ending at the root.
+    pub fn kind_ancestry(node: &Rc<RefCell<Self>>) -> Vec<TokenKind> {
+        let mut ancestry = vec![];
 
-            let mut pointer = node.borrow().clone();
-            loop {
-                if let NodeMetadata::AstNode { kind, .. } = pointer.metadata {
-                    ancestry.push(kind);
-                }
-                if let Some(Some(parent)) = pointer.parent.map(|n| n.upgrade()) {
-                    pointer = parent.borrow().clone();
-                } else {
-                    break;
-                }
+        let mut pointer = node.clone();
+        loop {
+            if let NodeMetadata::AstNode { kind, .. } = &pointer.borrow().metadata {
+                ancestry.push(kind.clone());
             }
+            let parent = pointer.borrow().parent.as_ref().and_then(|n| n.upgrade());
+            if let Some(parent) = parent {
+                pointer = parent;
+            } else {
+                break;
+            }
+        }
+
+        ancestry
+    }
+
+    /// When called, returns the key path of the given node, made up of the segments that it and
+    /// each of its ancestors add on to it (see `TokenKindTrait::key_path_segments`), ie,
+    /// `["dependencies", "foo"]` for a `foo = "1.0"` in the `[dependencies]` table of a TOML file.
+    pub fn key_path(node: &Rc<RefCell<Self>>) -> Vec<String> {
+        let mut segments = vec![];
 
-            ancestry
+        let mut pointer = Some(node.clone());
+        while let Some(node) = pointer {
+            if let NodeMetadata::AstNode { .. } = node.borrow().metadata {
+                segments.splice(
+                    0..0,
+                    TokenKind::key_path_segments(&node, &|kind| Some(kind.clone())),
+                );
+            }
+            pointer = node.borrow().parent.as_ref().and_then(|n| n.upgrade());
+        }
+
+        segments
+    }
+
+    /// When called, returns the first node under `node` whose key path is `path`, or None if there
+    /// isn't one.
+    pub fn find_key_path(node: &Rc<RefCell<Self>>, path: &[String]) -> Option<Rc<RefCell<Self>>> {
+        if path.is_empty() {
+            return None;
+        }
+        Self::find_key_path_under(node, vec![], path)
+    }
+
+    fn find_key_path_under(
+        node: &Rc<RefCell<Self>>,
+        mut key_path: Vec<String>,
+        path: &[String],
+    ) -> Option<Rc<RefCell<Self>>> {
+        let segments = match node.borrow().metadata {
+            NodeMetadata::AstNode { .. } => {
+                TokenKind::key_path_segments(node, &|kind| Some(kind.clone()))
+            }
+            _ => vec![],
         };
+        let adds_segments = !segments.is_empty();
+        key_path.extend(segments);
 
-        TokenKind::apply_debug_syntax_color(literal.into(), ancestry.into_iter())
+        // The key paths of the nodes under this node all start with its own key path, so if its key
+        // path isn't the start of `path`, then there's no point looking any further
+        if !path.starts_with(&key_path) {
+            return None;
+        }
+        if adds_segments && key_path.len() == path.len() {
+            return Some(node.clone());
+        }
+
+        let children = node.borrow().children.clone();
+        children
+            .iter()
+            .find_map(|child| Self::find_key_path_under(child, key_path.clone(), path))
+    }
+
+    /// When called, returns the style the given node should be drawn with to apply syntax
+    /// highlighting.
+    pub fn style(node: &Rc<RefCell<Self>>) -> Style {
+        TokenKind::syntax_style(Self::kind_ancestry(node).into_iter())
+    }
+
+    pub fn literal_colored(node: &Rc<RefCell<Self>>, literal: &str) -> ColoredString {
+        TokenKind::apply_debug_syntax_color(literal.into(), Self::kind_ancestry(node).into_iter())
     }
 
     /// This is called after a node is inserted into the tree to assign it a correct fractional
@@ -532,13 +1139,11 @@ impl<TokenKind: TokenKindTrait> InMemoryNode<TokenKind> {
 
         let mut cursor = node.clone();
         let number_of_nodes = 1 /* the passed node */ + Self::deep_children_length(node);
-        // println!("GENERATE: {:?} .. {:?} {}", before_index, after_index, number_of_nodes);
         for index in VariableSizeFractionalIndex::distributed_sequence_or_fallback(
             before_index,
             after_index,
             number_of_nodes,
         ) {
-            // println!("ASSIGN: {:?} = {:?}", cursor.borrow().metadata, index);
             (*cursor.borrow_mut()).index = index;
 
             let Some(next) = cursor.borrow().next.as_ref().map(|n| n.upgrade()).flatten() else {
@@ -591,55 +1196,63 @@ impl<TokenKind: TokenKindTrait> InMemoryNode<TokenKind> {
     }
 
     pub fn deep_children_length(node: &Rc<RefCell<Self>>) -> usize {
-        if node.borrow().children.is_empty() {
-            0
-        } else {
-            1 + node
-                .borrow()
-                .children
-                .iter()
-                .map(Self::deep_children_length)
-                .sum::<usize>()
-        }
+        node.borrow()
+            .children
+            .iter()
+            .map(|child| 1 + Self::deep_children_length(child))
+            .sum::<usize>()
     }
 
     /// When called, reparses the child at the given index with tke parser associated with each
     /// token in the token tree.
     ///
-    /// Returns the head of the newly reparsed subtree, or an error.
+    /// The new subtree is diffed against the old one, and only nodes that changed are replaced, so
+    /// nodes that didn't change (and any cursors pointing into them) stay in the tree. The node
+    /// that the reparsed child is within is returned.
     pub fn reparse_child_at_index(
         parent: Rc<RefCell<Self>>,
         index: usize,
     ) -> Result<Rc<RefCell<Self>>, String> {
-        println!(
-            "REPARSE_CHILD_AT_INDEX: parent={:?} index={index}",
-            parent.borrow().metadata
-        );
         let mut reparsable_pointer = parent.clone();
         let mut reparsable_pointer_child_index = index;
 
-        // 1. Find the next parsable node walking up the node tree
+        // 1. Find the next parsable node walking up the node tree. If there are no reparsable
+        // nodes, then the walk ends at the Root node at the top of the node tree, and the whole
+        // document is reparsed.
         while match &reparsable_pointer.borrow().metadata {
-            NodeMetadata::AstNode { kind, .. } => !TokenKind::is_reparsable(&kind),
+            NodeMetadata::AstNode { .. } => {
+                !TokenKind::is_reparsable_node(&reparsable_pointer, &|kind| Some(kind.clone()))
+            }
+            NodeMetadata::Root(_) => false, // The top of the document, so reparse the whole thing
             _ => false, // NOTE: consider any non ast node containing nodes as not parsable.
         } {
-            let (Some(child_index), Some(parent)) = (
-                reparsable_pointer.borrow().child_index,
-                reparsable_pointer
+            let Some(pointer_parent) = reparsable_pointer
+                .borrow()
+                .parent
+                .as_ref()
+                .and_then(|n| n.upgrade())
+            else {
+                // We've reached the top of the node tree without finding a reparsable node or a
+                // Root node, so there's nowhere to put the reparsed document
+                return Err(format!("InMemoryNode::reparse_child_at_index: No reparsable node or Root node found above {:?} (originally {index} in parent {:?})", reparsable_pointer.borrow().metadata, parent.borrow().metadata));
+            };
+            let child_index = reparsable_pointer.borrow().child_index.or_else(|| {
+                pointer_parent
                     .borrow()
-                    .parent
-                    .as_ref()
-                    .map(|n| n.upgrade())
-                    .flatten(),
-            ) else {
-                // Hmm, we've reached the top of the node tree and no nodes are parsable. In this
-                // case, the whole doeument needs to be reparsed! :(
-                continue;
+                    .children
+                    .iter()
+                    .position(|child| Rc::ptr_eq(child, &reparsable_pointer))
+            });
+            let Some(child_index) = child_index else {
+                return Err(format!(
+                    "InMemoryNode::reparse_child_at_index: {:?} is not a child of its parent {:?}",
+                    reparsable_pointer.borrow().metadata,
+                    pointer_parent.borrow().metadata
+                ));
             };
             reparsable_pointer_child_index = child_index;
-            reparsable_pointer = parent;
+            reparsable_pointer = pointer_parent;
         }
-        // println!("FOUND NEW: {:?} {}", reparsable_pointer.borrow().metadata, reparsable_pointer_child_index);
 
         // 2. Once a reparsable node has been found, get its contents to reparse ...
         let child_deep_literal = {
@@ -651,17 +1264,146 @@ impl<TokenKind: TokenKindTrait> InMemoryNode<TokenKind> {
             Self::deep_literal(&child)
         };
 
-        // println!("DEEP LITERAL: {child_deep_literal:?}");
-
         // 3. ... and then reparse it!
         let new_child = TokenKind::parse(&child_deep_literal, Some(reparsable_pointer.clone()));
 
-        // 4. Swap out the existing literal node being reparsed with the newly
-        // parser-generated token subtree
-        match Self::swap_child_at_index(&reparsable_pointer, index, new_child.clone()) {
-            Ok(()) => Ok(new_child),
-            Err(err) => Err(err),
+        // 4. Put the newly parser-generated token subtree in place of the existing node being
+        // reparsed, keeping the nodes within it that didn't change. If the parser wrapped the
+        // result in a node of the same kind as the node being reparsed within (ie, a SCRIPT around
+        // a reparsed statement), then it's the wrapper's children that take the old child's place
+        let is_wrapped = Self::is_same_kind(&new_child, &reparsable_pointer);
+        let new_children = if is_wrapped {
+            new_child.borrow().children.clone()
+        } else {
+            vec![new_child.clone()]
+        };
+        Self::reconcile_children(
+            &reparsable_pointer,
+            reparsable_pointer_child_index..reparsable_pointer_child_index + 1,
+            new_children,
+        )?;
+
+        // Diagnostics the parser stored on the wrapper itself belong to the node being
+        // reparsed within instead
+        if is_wrapped {
+            let offset = {
+                let pointer_ref = reparsable_pointer.borrow();
+                pointer_ref.children[..reparsable_pointer_child_index]
+                    .iter()
+                    .fold(
+                        Self::literal(&reparsable_pointer).chars().count(),
+                        |sum, child| sum + child.borrow().subtree_metrics.chars,
+                    )
+            };
+            let wrapper_diagnostics = new_child.borrow().diagnostics.clone();
+            reparsable_pointer.borrow_mut().diagnostics.extend(
+                wrapper_diagnostics
+                    .iter()
+                    .map(|diagnostic| diagnostic.offset_by(offset)),
+            );
+        }
+        Ok(reparsable_pointer)
+    }
+
+    /// When called, returns true if both nodes are the same kind of node, ignoring their literal
+    /// text and children.
+    fn is_same_kind(a: &Rc<RefCell<Self>>, b: &Rc<RefCell<Self>>) -> bool {
+        match (&a.borrow().metadata, &b.borrow().metadata) {
+            (NodeMetadata::AstNode { kind: a, .. }, NodeMetadata::AstNode { kind: b, .. }) => {
+                a == b
+            }
+            (a, b) => std::mem::discriminant(a) == std::mem::discriminant(b),
+        }
+    }
+
+    /// When called, returns true if both nodes have the same kind and literal, and contain
+    /// subtrees that are the same too.
+    fn is_same_subtree(a: &Rc<RefCell<Self>>, b: &Rc<RefCell<Self>>) -> bool {
+        let (a, b) = (a.borrow(), b.borrow());
+        a.subtree_metrics == b.subtree_metrics
+            && a.metadata == b.metadata
+            && a.children.len() == b.children.len()
+            && a.children
+                .iter()
+                .zip(b.children.iter())
+                .all(|(a, b)| Self::is_same_subtree(a, b))
+    }
+
+    /// When called, updates the children of `parent` within `old_range` to match `new_children`,
+    /// reusing as many of the old children as possible:
+    ///
+    /// 1. Children at the start and end of the range that are unchanged are kept as they are.
+    /// 2. The remaining old and new children are paired up in order. If a pair has the same kind
+    ///    and literal, the old child is kept and its children are updated in the same way.
+    ///    Otherwise, the old child is swapped out for the new one.
+    /// 3. Any extra old children are removed, and any extra new children are inserted.
+    fn reconcile_children(
+        parent: &Rc<RefCell<Self>>,
+        old_range: std::ops::Range<usize>,
+        new_children: Vec<Rc<RefCell<Self>>>,
+    ) -> Result<(), String> {
+        let old_children = parent.borrow().children[old_range.clone()].to_vec();
+
+        let prefix_length = old_children
+            .iter()
+            .zip(new_children.iter())
+            .take_while(|(old, new)| Self::is_same_subtree(old, new))
+            .count();
+        let suffix_length = old_children[prefix_length..]
+            .iter()
+            .rev()
+            .zip(new_children[prefix_length..].iter().rev())
+            .take_while(|(old, new)| Self::is_same_subtree(old, new))
+            .count();
+        let old_changed = &old_children[prefix_length..old_children.len() - suffix_length];
+        let new_changed = &new_children[prefix_length..new_children.len() - suffix_length];
+        let start = old_range.start + prefix_length;
+
+        for (offset, (old, new)) in old_changed.iter().zip(new_changed.iter()).enumerate() {
+            if old.borrow().metadata == new.borrow().metadata {
+                let old_length = old.borrow().children.len();
+                let new_grandchildren = new.borrow().children.clone();
+                Self::reconcile_children(old, 0..old_length, new_grandchildren)?;
+            } else {
+                Self::swap_child_at_index(parent, start + offset, new.clone())?;
+            }
+        }
+
+        let paired_length = old_changed.len().min(new_changed.len());
+        for _ in new_changed.len()..old_changed.len() {
+            Self::remove_child_at_index(parent, start + paired_length);
+        }
+        for (offset, new) in new_changed.iter().enumerate().skip(paired_length) {
+            // Subtrees can only be spliced in with swap_child_at_index, so insert an empty node to
+            // swap out
+            Self::insert_child(parent, Self::new_empty(), start + offset);
+            Self::swap_child_at_index(parent, start + offset, new.clone())?;
+        }
+
+        // Changing the children above cleared the diagnostics on every node that was kept, so bring
+        // over the diagnostics the parser reported on the new nodes that took their place. This
+        // has to happen last, since each change clears the diagnostics of `parent` and its
+        // ancestors again.
+        let kept_pairs = old_children[..prefix_length]
+            .iter()
+            .zip(new_children[..prefix_length].iter())
+            .chain(
+                old_children[old_children.len() - suffix_length..]
+                    .iter()
+                    .zip(new_children[new_children.len() - suffix_length..].iter()),
+            );
+        for (old, new) in kept_pairs {
+            Self::copy_diagnostics(old, new);
+        }
+        for (old, new) in old_changed.iter().zip(new_changed.iter()) {
+            // Nodes that were swapped out aren't in the tree anymore, only those recursed into
+            if old.borrow().metadata == new.borrow().metadata {
+                let new_diagnostics = new.borrow().diagnostics.clone();
+                old.borrow_mut().diagnostics = new_diagnostics;
+            }
         }
+
+        Ok(())
     }
 
     /// When called, adds the given `child` to the `parent` at the beginning of its children Vec.
@@ -680,13 +1422,6 @@ impl<TokenKind: TokenKindTrait> InMemoryNode<TokenKind> {
         child: Rc<RefCell<Self>>,
         index: usize,
     ) -> Rc<RefCell<Self>> {
-        // println!(
-        //     "CHILD: {:?} PARENT: {:?} INDEX: {}",
-        //     child.borrow().metadata,
-        //     parent.borrow().metadata,
-        //     index,
-        // );
-
         // NOTE: appending a child is the most common case which has an optimized write path that
         // handles edge cases properly. So if this insert is inserting at the end of the child
         // list, then fall back to this
@@ -757,15 +1492,13 @@ impl<TokenKind: TokenKindTrait> InMemoryNode<TokenKind> {
             }
         }
 
+        let child_metrics = child.borrow().subtree_metrics;
+        Self::update_subtree_metrics(parent, Some(index), TextMetrics::default(), child_metrics);
+
         child
     }
 
     pub fn append_child(parent: &Rc<RefCell<Self>>, child: Rc<RefCell<Self>>) -> Rc<RefCell<Self>> {
-        println!(
-            "CHILD: {:?} PARENT: {:?}",
-            child.borrow().metadata,
-            parent.borrow().metadata
-        );
         {
             let mut child_mut = child.borrow_mut();
 
@@ -797,16 +1530,6 @@ impl<TokenKind: TokenKindTrait> InMemoryNode<TokenKind> {
                         })
                         .flatten()
                 };
-                // println!(
-                //     "a. {:?}.next = {:?}",
-                //     child_mut.metadata,
-                //     child_mut
-                //         .next
-                //         .clone()
-                //         .map(|n| n.upgrade())
-                //         .flatten()
-                //         .map(|n| n.borrow().metadata.clone())
-                // );
             }
 
             // Step N: make the new child's previous either:
@@ -864,11 +1587,6 @@ impl<TokenKind: TokenKindTrait> InMemoryNode<TokenKind> {
                 // Step 3: Update parent.first_child to be child IF this is the first node being
                 // added to this parent
                 (*parent_mut).first_child = Some(Rc::downgrade(&child));
-                // println!(
-                //     "b. {:?}.next = {:?}",
-                //     parent_mut.metadata,
-                //     child.borrow().metadata
-                // );
 
                 // Step 5: Update parent.next to be child IF this is the first node being added
                 // to this parent
@@ -880,11 +1598,6 @@ impl<TokenKind: TokenKindTrait> InMemoryNode<TokenKind> {
                 if let Some(parent_last_child) = parent_last_child.upgrade() {
                     if let Some(foo) = Self::deep_last_child(&parent_last_child) {
                         (*foo.borrow_mut()).next = Some(Rc::downgrade(&child));
-                        // println!(
-                        //     "c. {:?}.next = {:?}",
-                        //     foo.borrow().metadata,
-                        //     child.borrow().metadata
-                        // );
                     }
                 }
             }
@@ -894,17 +1607,6 @@ impl<TokenKind: TokenKindTrait> InMemoryNode<TokenKind> {
                 if let Some(upgraded_last_child) = last_child.upgrade() {
                     let parent_old_last_child = upgraded_last_child.borrow().first_child.clone(); // child.first_child
 
-                    // println!(
-                    //     "d. {:?}.next = {:?}.or({:?})",
-                    //     upgraded_last_child.borrow().metadata,
-                    //     parent_old_last_child
-                    //         .clone()
-                    //         .map(|n| n.upgrade())
-                    //         .flatten()
-                    //         .map(|n| n.borrow().metadata.clone()),
-                    //     child.borrow().metadata,
-                    // );
-
                     // parent.(OLD) last_child
                     // or fall back to the child itself if there are no nodes inside
                     let new_next = parent_old_last_child.or_else(|| Some(Rc::downgrade(&child)));
@@ -919,13 +1621,18 @@ impl<TokenKind: TokenKindTrait> InMemoryNode<TokenKind> {
             (*parent_mut).last_child = Some(Rc::downgrade(&child));
         }
 
+        let (child_index, child_metrics) = {
+            let child_ref = child.borrow();
+            (child_ref.child_index, child_ref.subtree_metrics)
+        };
+        Self::update_subtree_metrics(parent, child_index, TextMetrics::default(), child_metrics);
+
         child
     }
 
     /// Removes a child node from a tree, including the subtree under the child node. Returns the
     /// parent node of the removed node, or None if the node that was removed was at the top level.
     pub fn remove_child_at_index(parent: &Rc<RefCell<Self>>, index: usize) {
-        // println!("REMOVE: {:?} INDEX: {:?}", parent.borrow().metadata, index);
         let (child, previous_child, deep_last_child) = {
             let parent = parent.borrow();
             let child = parent.children.get(index);
@@ -945,7 +1652,6 @@ impl<TokenKind: TokenKindTrait> InMemoryNode<TokenKind> {
         };
 
         // Step 1: child_mut.previous.next = child_mut.next
-        // println!("PREV: {:?}", previous_child.clone().map(|p| p.borrow().metadata.clone()));
         if let Some(previous_child) = previous_child {
             (*previous_child.borrow_mut()).next = Self::deep_last_child(&child)
                 .or_else(|| Some(child.clone()))
@@ -994,6 +1700,9 @@ impl<TokenKind: TokenKindTrait> InMemoryNode<TokenKind> {
                 };
             }
         }
+
+        let child_metrics = child.borrow().subtree_metrics;
+        Self::update_subtree_metrics(parent, Some(index), child_metrics, TextMetrics::default());
     }
 
     /// When called, removes all children from a node in the node tree, relinking nodes properly so
@@ -1011,12 +1720,7 @@ impl<TokenKind: TokenKindTrait> InMemoryNode<TokenKind> {
         index: usize,
         new_child: Rc<RefCell<Self>>,
     ) -> Result<(), String> {
-        println!(
-            "SWAP: {:?} INDEX: {} NEW: {:?}",
-            parent.borrow().metadata,
-            index,
-            new_child.borrow().metadata
-        );
+        let new_metrics = new_child.borrow().subtree_metrics;
 
         let (old_child, old_child_previous, old_child_deep_last_child) = {
             let parent = parent.borrow();
@@ -1038,12 +1742,23 @@ impl<TokenKind: TokenKindTrait> InMemoryNode<TokenKind> {
 
         let new_child_deep_last_child = Self::deep_last_child(&new_child);
 
+        // The node that comes after the old child's whole subtree. Note that if the old child has
+        // children, its own `next` is its first child, so it can't be used as a fallback when the
+        // subtree is at the very end of the document.
+        let old_child_subtree_next = match &old_child_deep_last_child {
+            Some(deep_last_child) => deep_last_child.borrow().next.clone(),
+            None => old_child.borrow().next.clone(),
+        };
+
         {
             let mut new_child_mut = new_child.borrow_mut();
 
             // Step N: Update new_child.parent to the common parent
             (*new_child_mut).parent = Some(Rc::downgrade(parent));
 
+            // Step N: Give new_child the old child's position in the parent's children
+            (*new_child_mut).child_index = Some(index);
+
             // Step N: Relink the old_child.previous's next to point to new_child
             if let Some(old_previous) = old_child_previous.clone() {
                 old_previous.borrow_mut().next = Some(Rc::downgrade(&new_child));
@@ -1051,37 +1766,20 @@ impl<TokenKind: TokenKindTrait> InMemoryNode<TokenKind> {
 
             // Step N: Relink the old_child.next's previous to point to new_child.deep_last_child
             if let Some(new_child_deep_last_child) = new_child_deep_last_child.clone() {
-                (*new_child_deep_last_child.borrow_mut()).next = old_child_deep_last_child
-                    .clone()
-                    .map(|n| n.borrow().next.clone())
-                    .flatten()
-                    .or_else(|| old_child.borrow().next.clone());
+                (*new_child_deep_last_child.borrow_mut()).next = old_child_subtree_next.clone();
             } else {
-                (*new_child_mut).next = old_child_deep_last_child
-                    .clone()
-                    .map(|n| n.borrow().next.clone())
-                    .flatten()
-                    .or_else(|| old_child.borrow().next.clone());
+                (*new_child_mut).next = old_child_subtree_next.clone();
             }
 
             // Step N: Update new_child.next to be old_child.deep_last_child.next
             if new_child_mut.next.is_none() {
-                (*new_child_mut).next =
-                    if let Some(deep_last_child) = old_child_deep_last_child.clone() {
-                        deep_last_child.borrow().next.clone()
-                    } else {
-                        old_child.borrow().next.clone()
-                    };
+                (*new_child_mut).next = old_child_subtree_next.clone();
             }
 
             // Step N: Update the next sibling of old_child to point back to it
             //         ie, old_child.(OLD) deep_last_child,next.previous to new_child (or its deep last child if it has children)
-            if let Some(deep_last_child_next) = old_child_deep_last_child
-                .clone()
-                .map(|n| n.borrow().next.clone())
-                .flatten()
-                .map(|n| n.upgrade())
-                .flatten()
+            if let Some(deep_last_child_next) =
+                old_child_subtree_next.clone().and_then(|n| n.upgrade())
             {
                 (*deep_last_child_next.borrow_mut()).previous = Some(Rc::downgrade(
                     &new_child_deep_last_child.unwrap_or(new_child.clone()),
@@ -1119,6 +1817,9 @@ impl<TokenKind: TokenKindTrait> InMemoryNode<TokenKind> {
             (*parent_mut).children.insert(index, new_child);
         }
 
+        let old_metrics = old_child.borrow().subtree_metrics;
+        Self::update_subtree_metrics(parent, Some(index), old_metrics, new_metrics);
+
         Ok(())
     }
 
@@ -1367,3 +2068,167 @@ impl<TokenKind: TokenKindTrait> PartialOrd for InMemoryNode<TokenKind> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{InMemoryNode, NodeMetadata, TextMetrics, TokenKindTrait};
+    use crate::node_tree::{
+        cursor::{Cursor, Selection},
+        style::HighlightGroup,
+    };
+    use std::{cell::RefCell, rc::Rc};
+
+    /// A language made up of words and the spaces between them, where no node is ever reparsable.
+    #[derive(Debug, Clone, PartialEq)]
+    enum WordKind {
+        Document,
+        Word,
+        Space,
+    }
+
+    impl TokenKindTrait for WordKind {
+        type ParseOptions = ();
+
+        fn language_name() -> &'static str {
+            "words"
+        }
+
+        fn highlight_group(_ancestry: std::vec::IntoIter<Self>) -> Option<HighlightGroup> {
+            None
+        }
+
+        fn is_reparsable(&self) -> bool {
+            false
+        }
+
+        fn parse(
+            literal: &str,
+            _parent: Option<Rc<RefCell<InMemoryNode<Self>>>>,
+        ) -> Rc<RefCell<InMemoryNode<Self>>> {
+            let document = InMemoryNode::new_with_metadata(NodeMetadata::AstNode {
+                kind: WordKind::Document,
+                literal: None,
+            });
+            let mut run = String::new();
+            for c in literal.chars() {
+                if !run.is_empty() && run.ends_with(char::is_whitespace) != c.is_whitespace() {
+                    Self::append_run(&document, &mut run);
+                }
+                run.push(c);
+            }
+            Self::append_run(&document, &mut run);
+            document
+        }
+    }
+
+    impl WordKind {
+        fn append_run(document: &Rc<RefCell<InMemoryNode<Self>>>, run: &mut String) {
+            if run.is_empty() {
+                return;
+            }
+            let kind = if run.ends_with(char::is_whitespace) {
+                WordKind::Space
+            } else {
+                WordKind::Word
+            };
+            let literal = Some(std::mem::take(run));
+            InMemoryNode::append_child(
+                document,
+                InMemoryNode::new_with_metadata(NodeMetadata::AstNode { kind, literal }),
+            );
+        }
+    }
+
+    #[test]
+    fn reparse_without_reparsable_nodes_reparses_whole_document() {
+        let root = InMemoryNode::<WordKind>::new_from_parsed("one two three");
+        Selection::new_from_cursor_pair(
+            Cursor::new_at_rows_cols(root.clone(), (1, 2)),
+            Cursor::new_at_rows_cols(root.clone(), (1, 6)),
+        )
+        .replace("ff")
+        .unwrap();
+        assert_eq!(InMemoryNode::deep_literal(&root), "offwo three");
+
+        // The document under the root node was reparsed as a whole
+        let document = root.borrow().children[0].clone();
+        let words = document
+            .borrow()
+            .children
+            .iter()
+            .map(|child| child.borrow().metadata.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            words,
+            vec![
+                NodeMetadata::AstNode {
+                    kind: WordKind::Word,
+                    literal: Some("offwo".into())
+                },
+                NodeMetadata::AstNode {
+                    kind: WordKind::Space,
+                    literal: Some(" ".into())
+                },
+                NodeMetadata::AstNode {
+                    kind: WordKind::Word,
+                    literal: Some("three".into())
+                },
+            ]
+        );
+
+        // Without a root node, there's nowhere for the reparsed document to go
+        let document = WordKind::parse("one two", None);
+        assert!(InMemoryNode::reparse_child_at_index(document, 0).is_err());
+    }
+
+    #[test]
+    fn subtree_metrics_follow_changes() {
+        let root = InMemoryNode::<WordKind>::new_from_parsed("one two\nthree");
+        let document = root.borrow().children[0].clone();
+        let assert_metrics_match_literals = || {
+            for node in [&root, &document] {
+                assert_eq!(
+                    node.borrow().subtree_metrics,
+                    TextMetrics::from_literal(&InMemoryNode::deep_literal(node))
+                );
+            }
+
+            // The offsets cached for each child should also follow along
+            let mut text_before = InMemoryNode::literal(&document);
+            for child in document.borrow().children.iter() {
+                assert_eq!(
+                    InMemoryNode::metrics_before(child),
+                    TextMetrics::from_literal(&text_before)
+                );
+                text_before += &InMemoryNode::deep_literal(child);
+            }
+        };
+        assert_metrics_match_literals();
+
+        let word = |literal: &str| {
+            InMemoryNode::new_with_metadata(NodeMetadata::AstNode {
+                kind: WordKind::Word,
+                literal: Some(literal.into()),
+            })
+        };
+        InMemoryNode::insert_child(&document, word("zwei\n"), 1);
+        assert_eq!(InMemoryNode::deep_literal(&root), "onezwei\n two\nthree");
+        assert_metrics_match_literals();
+
+        InMemoryNode::swap_child_at_index(&document, 0, word("ünö")).unwrap();
+        assert_metrics_match_literals();
+
+        InMemoryNode::remove_child_at_index(&document, 3);
+        assert_eq!(InMemoryNode::deep_literal(&root), "ünözwei\n \nthree");
+        assert_metrics_match_literals();
+
+        let last = document.borrow().children[4].clone();
+        InMemoryNode::set_literal(&last, "3\n\n");
+        assert_eq!(InMemoryNode::deep_literal(&root), "ünözwei\n \n3\n\n");
+        assert_metrics_match_literals();
+        assert_eq!(
+            Cursor::new_at_rows_cols(root.clone(), (3, 2)).to_rows_cols(),
+            (3, 2)
+        );
+    }
+}
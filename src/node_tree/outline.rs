@@ -0,0 +1,309 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::node_tree::{
+    cursor::Selection,
+    node::{InMemoryNode, TokenKindTrait},
+    query::Query,
+};
+
+/// The kinds of symbols that can be in the outline of a document.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymbolKind {
+    Function,
+    Class,
+    Method,
+    Property,
+    Variable,
+    Interface,
+    Type,
+    Enum,
+    Namespace,
+    Export,
+}
+
+impl SymbolKind {
+    /// When called, returns the kind of the symbols captured as `@definition.<name>` by an outline
+    /// query.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "function" => Some(SymbolKind::Function),
+            "class" => Some(SymbolKind::Class),
+            "method" => Some(SymbolKind::Method),
+            "property" => Some(SymbolKind::Property),
+            "variable" => Some(SymbolKind::Variable),
+            "interface" => Some(SymbolKind::Interface),
+            "type" => Some(SymbolKind::Type),
+            "enum" => Some(SymbolKind::Enum),
+            "namespace" => Some(SymbolKind::Namespace),
+            "export" => Some(SymbolKind::Export),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SymbolKind::Function => "function",
+            SymbolKind::Class => "class",
+            SymbolKind::Method => "method",
+            SymbolKind::Property => "property",
+            SymbolKind::Variable => "variable",
+            SymbolKind::Interface => "interface",
+            SymbolKind::Type => "type",
+            SymbolKind::Enum => "enum",
+            SymbolKind::Namespace => "namespace",
+            SymbolKind::Export => "export",
+        }
+    }
+}
+
+/// A Symbol is something named that is defined in a document - ie, a function, a class, or a
+/// method in a class - along with the symbols defined within it. The selection spans the whole
+/// definition, ie, a function's body as well as its name.
+#[derive(Clone)]
+pub struct Symbol<TokenKind: TokenKindTrait> {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub is_exported: bool, // Always set for `SymbolKind::Export` symbols
+    pub selection: Selection<TokenKind>,
+    pub children: Vec<Symbol<TokenKind>>,
+}
+
+impl<TokenKind: TokenKindTrait> Symbol<TokenKind> {
+    /// When called, finds the symbols in the document under `root` with its language's outline
+    /// query (see `TokenKindTrait::outline_query`). Symbols are in the order they are defined in,
+    /// and symbols defined within another symbol (ie, the methods of a class) are its children.
+    pub fn outline(root: &Rc<RefCell<InMemoryNode<TokenKind>>>) -> Result<Vec<Self>, String> {
+        let Some(source) = TokenKind::outline_query(&InMemoryNode::parse_options(root)) else {
+            return Ok(vec![]);
+        };
+        let query = Query::parse(source)?;
+
+        // Every symbol, along with the index of the symbol that it is within
+        let mut symbols: Vec<(Option<usize>, Option<Self>)> = vec![];
+
+        // The nodes that the next symbol might be within, from the outermost to the innermost,
+        // along with the index of the symbol that each defines (or None for `@export` nodes)
+        let mut enclosing = vec![];
+
+        for query_match in query.matches(root) {
+            let definition = query_match.captures.iter().find_map(|capture| {
+                let kind = capture.name.strip_prefix("definition.")?;
+                Some((kind, capture))
+            });
+            let node = match (definition, query_match.capture("export")) {
+                (Some((_, capture)), _) | (None, Some(capture)) => capture.node.clone(),
+                (None, None) => continue,
+            };
+
+            while let Some((ancestor, _)) = enclosing.last() {
+                if is_within(&node, ancestor) {
+                    break;
+                }
+                enclosing.pop();
+            }
+
+            let Some((kind, capture)) = definition else {
+                enclosing.push((node, None));
+                continue;
+            };
+            let Some(kind) = SymbolKind::from_name(kind) else {
+                return Err(format!(
+                    "Error in outline query: unknown symbol kind @definition.{kind}"
+                ));
+            };
+
+            // Names that span more than one line (ie, destructured variables) are shown on one
+            let name = query_match
+                .captures
+                .iter()
+                .filter(|capture| capture.name == "name")
+                .map(|capture| {
+                    InMemoryNode::deep_literal(&capture.node)
+                        .split_whitespace()
+                        .collect::<Vec<&str>>()
+                        .join(" ")
+                })
+                .collect::<Vec<String>>()
+                .join(", ");
+            let parent = enclosing.iter().rev().find_map(|(_, index)| *index);
+            let is_exported =
+                kind == SymbolKind::Export || matches!(enclosing.last(), Some((_, None)));
+
+            enclosing.push((node, Some(symbols.len())));
+            symbols.push((
+                parent,
+                Some(Self {
+                    name,
+                    kind,
+                    is_exported,
+                    selection: capture.selection.clone(),
+                    children: vec![],
+                }),
+            ));
+        }
+
+        // Move each symbol into the symbol it is within. Symbols come after the symbol they are
+        // within, so going backwards means each symbol has all of its children before it is moved.
+        let mut outline = vec![];
+        for index in (0..symbols.len()).rev() {
+            let (parent, symbol) = &mut symbols[index];
+            let (parent, mut symbol) = (*parent, symbol.take().expect("symbol already moved"));
+            symbol.children.reverse();
+            match parent {
+                Some(parent) => symbols[parent]
+                    .1
+                    .as_mut()
+                    .expect("symbol moved before its children")
+                    .children
+                    .push(symbol),
+                None => outline.push(symbol),
+            }
+        }
+        outline.reverse();
+        Ok(outline)
+    }
+
+    /// When called, returns how the symbol is shown in an outline, ie, `export function foo`.
+    pub fn label(&self) -> String {
+        match (self.kind, self.is_exported) {
+            (SymbolKind::Export, _) => format!("export {}", self.name),
+            (kind, true) => format!("export {} {}", kind.name(), self.name),
+            (kind, false) => format!("{} {}", kind.name(), self.name),
+        }
+    }
+
+    /// When called, returns every symbol in `outline` (and their children, and so on), in the
+    /// order they are defined in, along with how deeply nested each is.
+    pub fn flatten(outline: &[Self]) -> Vec<(usize, &Self)> {
+        let mut symbols = vec![];
+        for symbol in outline.iter() {
+            symbols.push((0, symbol));
+            symbols.extend(
+                Self::flatten(&symbol.children)
+                    .into_iter()
+                    .map(|(depth, child)| (depth + 1, child)),
+            );
+        }
+        symbols
+    }
+}
+
+/// When called, returns true if `ancestor` is one of the nodes above `node` in the token tree.
+fn is_within<TokenKind: TokenKindTrait>(
+    node: &Rc<RefCell<InMemoryNode<TokenKind>>>,
+    ancestor: &Rc<RefCell<InMemoryNode<TokenKind>>>,
+) -> bool {
+    let mut pointer = node
+        .borrow()
+        .parent
+        .as_ref()
+        .and_then(|parent| parent.upgrade());
+    while let Some(parent) = pointer {
+        if Rc::ptr_eq(&parent, ancestor) {
+            return true;
+        }
+        pointer = parent
+            .borrow()
+            .parent
+            .as_ref()
+            .and_then(|parent| parent.upgrade());
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Symbol;
+    use crate::{
+        languages::{json, registry::AnySyntaxKind, typescript},
+        node_tree::node::{InMemoryNode, NodeMetadata, TokenKindTrait},
+    };
+    use std::{cell::RefCell, rc::Rc};
+
+    fn describe<TokenKind: TokenKindTrait>(outline: &[Symbol<TokenKind>]) -> Vec<String> {
+        Symbol::flatten(outline)
+            .into_iter()
+            .map(|(depth, symbol)| {
+                format!(
+                    "{}{} {:?}",
+                    "  ".repeat(depth),
+                    symbol.label(),
+                    symbol.selection.primary.to_rows_cols(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn outlines_typescript() {
+        let root = InMemoryNode::<typescript::SyntaxKind>::new_from_parsed(
+            "import { a } from './a';
+export function foo(b) {
+  const c = 1;
+  function bar() {}
+}
+export default class Baz extends Qux {
+  d = 1;
+  constructor() {}
+  get e() { return 1; }
+}
+const f = 1, { g,
+  h } = i;
+export const j = () => {};
+export interface K { l: string }
+type M = string;
+namespace N { export enum O { P } }
+export { a, f as Q };
+",
+        );
+        assert_eq!(
+            describe(&Symbol::outline(&root).unwrap()),
+            vec![
+                "export function foo (2, 8)",
+                "  variable c (3, 9)",
+                "  function bar (4, 3)",
+                "export class Baz (6, 16)",
+                "  property d (7, 3)",
+                "  method constructor (8, 3)",
+                "  method e (9, 3)",
+                "variable f (11, 7)",
+                "variable { g, h } (11, 14)",
+                "export variable j (13, 14)",
+                "export interface K (14, 8)",
+                "type M (15, 1)",
+                "namespace N (16, 1)",
+                "  export enum O (16, 22)",
+                "export a, f as Q (17, 8)",
+            ]
+        );
+
+        // Languages without an outline query have no symbols
+        let root = InMemoryNode::<json::SyntaxKind>::new_from_parsed("{\"a\": 1}");
+        assert!(Symbol::outline(&root).unwrap().is_empty());
+    }
+
+    #[test]
+    fn outlines_buffers_in_any_language() {
+        let root: Rc<RefCell<InMemoryNode<AnySyntaxKind>>> =
+            InMemoryNode::new_from_parsed("class A { b() { return css`color: red;`; } }\n");
+        assert!(matches!(
+            InMemoryNode::parse_options(&root),
+            crate::languages::registry::Filetype::TypeScript(_)
+        ));
+        assert_eq!(
+            describe(&Symbol::outline(&root).unwrap()),
+            vec!["class A (1, 1)", "  method b (1, 11)"]
+        );
+
+        // The symbol's selection covers the whole definition
+        let outline = Symbol::outline(&root).unwrap();
+        assert_eq!(
+            outline[0].children[0].selection.literal(),
+            "b() { return css`color: red;`; }"
+        );
+        assert!(matches!(
+            &outline[0].selection.primary.node.borrow().metadata,
+            NodeMetadata::AstNode { .. }
+        ));
+    }
+}
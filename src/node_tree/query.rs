@@ -0,0 +1,738 @@
+use std::{cell::RefCell, collections::HashSet, rc::Rc};
+
+use regex::Regex;
+
+use crate::node_tree::{
+    cursor::Selection,
+    node::{InMemoryNode, NodeMetadata, TokenKindTrait},
+};
+
+/// How many times in a row a pattern can match the children of a node, ie, `(DECLARATOR)*`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Quantifier {
+    One,
+    ZeroOrOne,  // `?`
+    ZeroOrMore, // `*`
+    OneOrMore,  // `+`
+}
+
+/// The nodes that a pattern matches.
+#[derive(Debug, Clone, PartialEq)]
+enum Matcher {
+    Any,             // `_` or `(_)`
+    Kind(String),    // `(FN_DECL)`, matched against `TokenKindTrait::kind_name`
+    Literal(String), // `"function"`, a token with exactly this text
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Pattern {
+    matcher: Matcher,
+    field: Option<String>,
+    quantifier: Quantifier,
+    children: Vec<Pattern>,
+    captures: Vec<String>,
+}
+
+impl Pattern {
+    fn capture_names(&self, names: &mut HashSet<String>) {
+        names.extend(self.captures.iter().cloned());
+        for child in self.children.iter() {
+            child.capture_names(names);
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Argument {
+    Capture(String),
+    String(String),
+}
+
+/// A condition on the text of the nodes a pattern captured, ie, `(#eq? @name "foo")`.
+#[derive(Debug, Clone)]
+enum Predicate {
+    Eq {
+        capture: String,
+        value: Argument,
+        is_negated: bool,
+    },
+    Match {
+        capture: String,
+        regex: Regex,
+        is_negated: bool,
+    },
+}
+
+/// A node that a pattern captured with `@name`, along with a selection spanning all of its text.
+#[derive(Clone)]
+pub struct QueryCapture<TokenKind: TokenKindTrait> {
+    pub name: String,
+    pub node: Rc<RefCell<InMemoryNode<TokenKind>>>,
+    pub selection: Selection<TokenKind>,
+}
+
+/// A place in a token tree that one of the patterns in a query matched.
+#[derive(Clone)]
+pub struct QueryMatch<TokenKind: TokenKindTrait> {
+    pub captures: Vec<QueryCapture<TokenKind>>,
+}
+
+impl<TokenKind: TokenKindTrait> QueryMatch<TokenKind> {
+    /// When called, returns the first node captured with the given name, if there is one.
+    pub fn capture(&self, name: &str) -> Option<&QueryCapture<TokenKind>> {
+        self.captures.iter().find(|capture| capture.name == name)
+    }
+}
+
+/// A Query finds nodes in a token tree that match one of its patterns, written as tree-sitter
+/// style S-expressions, ie:
+///
+/// ```text
+/// ; Functions named in camelCase
+/// ((FN_DECL name: (NAME) @name body: (BLOCK_STMT) @body)
+///  (#match? @name "^[a-z]"))
+/// ```
+///
+/// A pattern matches a node of the given kind (or any kind, for `_`), whose children match each
+/// of the child patterns in order - though other children can come between them. Child patterns
+/// can be limited to a field with `field: (KIND)` (see `TokenKindTrait::field_name`), repeated
+/// with `*` / `+` / `?`, and `"text"` matches a token with exactly that text. Nodes are captured
+/// with `@name`, and the text of the captured nodes can be checked with `#eq?`, `#not-eq?`,
+/// `#match?`, and `#not-match?` predicates.
+#[derive(Debug, Clone)]
+pub struct Query {
+    patterns: Vec<(Pattern, Vec<Predicate>)>,
+}
+
+impl Query {
+    /// When called, parses the source of a query, which contains any number of patterns.
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut parser = QueryParser {
+            chars: source.chars().collect(),
+            index: 0,
+        };
+        let mut patterns = vec![];
+        loop {
+            parser.skip_whitespace();
+            if parser.peek().is_none() {
+                break;
+            }
+            patterns.push(parser.top_level_pattern()?);
+        }
+        Ok(Self { patterns })
+    }
+
+    /// When called, returns every place that a pattern in the query matches within the subtree
+    /// under `node`, in document order. If more than one pattern matches the same node, the
+    /// matches are ordered by the patterns' order in the query.
+    pub fn matches<TokenKind: TokenKindTrait>(
+        &self,
+        node: &Rc<RefCell<InMemoryNode<TokenKind>>>,
+    ) -> Vec<QueryMatch<TokenKind>> {
+        let mut matches = vec![];
+        self.collect_matches(node, &mut matches);
+        matches
+    }
+
+    fn collect_matches<TokenKind: TokenKindTrait>(
+        &self,
+        node: &Rc<RefCell<InMemoryNode<TokenKind>>>,
+        matches: &mut Vec<QueryMatch<TokenKind>>,
+    ) {
+        for (pattern, predicates) in self.patterns.iter() {
+            let mut captures = vec![];
+            if !match_node(pattern, node, None, &mut captures) {
+                continue;
+            }
+            if !predicates
+                .iter()
+                .all(|predicate| check_predicate(predicate, &captures))
+            {
+                continue;
+            }
+            matches.push(QueryMatch {
+                captures: captures
+                    .into_iter()
+                    .map(|(name, node)| QueryCapture {
+                        name,
+                        selection: Selection::new_across_subtree(&node),
+                        node,
+                    })
+                    .collect(),
+            });
+        }
+
+        let children = node.borrow().children.clone();
+        for child in children.iter() {
+            self.collect_matches(child, matches);
+        }
+    }
+}
+
+type Captures<TokenKind> = Vec<(String, Rc<RefCell<InMemoryNode<TokenKind>>>)>;
+
+fn kind_of<TokenKind: TokenKindTrait>(
+    node: &Rc<RefCell<InMemoryNode<TokenKind>>>,
+) -> Option<TokenKind> {
+    match &node.borrow().metadata {
+        NodeMetadata::AstNode { kind, .. } => Some(kind.clone()),
+        _ => None,
+    }
+}
+
+/// When called, checks whether `node` (which is in the field `field` of its parent) matches
+/// `pattern`, adding the nodes it captures to `captures` if it does.
+fn match_node<TokenKind: TokenKindTrait>(
+    pattern: &Pattern,
+    node: &Rc<RefCell<InMemoryNode<TokenKind>>>,
+    field: Option<&str>,
+    captures: &mut Captures<TokenKind>,
+) -> bool {
+    let Some(kind) = kind_of(node) else {
+        return false;
+    };
+    let is_match = match &pattern.matcher {
+        Matcher::Any => true,
+        Matcher::Kind(name) => kind.kind_name() == *name,
+        Matcher::Literal(text) => {
+            node.borrow().children.is_empty() && InMemoryNode::literal(node) == *text
+        }
+    };
+    if !is_match || (pattern.field.is_some() && pattern.field.as_deref() != field) {
+        return false;
+    }
+
+    let checkpoint = captures.len();
+    if !pattern.children.is_empty() {
+        let (children, kinds): (Vec<_>, Vec<_>) = node
+            .borrow()
+            .children
+            .iter()
+            .filter_map(|child| kind_of(child).map(|kind| (child.clone(), kind)))
+            .unzip();
+        let siblings = Siblings {
+            parent_kind: &kind,
+            children: &children,
+            kinds: &kinds,
+        };
+        if !match_children(&pattern.children, false, &siblings, 0, captures) {
+            captures.truncate(checkpoint);
+            return false;
+        }
+    }
+
+    // A node's own captures come before the captures of the nodes within it
+    for (index, name) in pattern.captures.iter().enumerate() {
+        captures.insert(checkpoint + index, (name.clone(), node.clone()));
+    }
+    true
+}
+
+/// The children of a node being matched against child patterns, along with their kinds.
+struct Siblings<'a, TokenKind: TokenKindTrait> {
+    parent_kind: &'a TokenKind,
+    children: &'a [Rc<RefCell<InMemoryNode<TokenKind>>>],
+    kinds: &'a [TokenKind],
+}
+
+/// When called, checks whether `patterns` match the children from `start` onwards, in order. Each
+/// pattern is tried against every child in turn, backtracking if the patterns after it can't
+/// match the children that are left. `is_repeat` is set if the first pattern has already matched
+/// at least once, so it can either match again or be skipped.
+fn match_children<TokenKind: TokenKindTrait>(
+    patterns: &[Pattern],
+    is_repeat: bool,
+    siblings: &Siblings<TokenKind>,
+    start: usize,
+    captures: &mut Captures<TokenKind>,
+) -> bool {
+    let Some((pattern, rest)) = patterns.split_first() else {
+        return true;
+    };
+    let is_optional = is_repeat
+        || matches!(
+            pattern.quantifier,
+            Quantifier::ZeroOrOne | Quantifier::ZeroOrMore
+        );
+    let can_repeat = matches!(
+        pattern.quantifier,
+        Quantifier::ZeroOrMore | Quantifier::OneOrMore
+    );
+
+    let checkpoint = captures.len();
+    for index in start..siblings.children.len() {
+        let field = TokenKind::field_name(siblings.parent_kind, siblings.kinds, index);
+        if match_node(pattern, &siblings.children[index], field, captures) {
+            let is_match = if can_repeat {
+                match_children(patterns, true, siblings, index + 1, captures)
+            } else {
+                match_children(rest, false, siblings, index + 1, captures)
+            };
+            if is_match {
+                return true;
+            }
+        }
+        captures.truncate(checkpoint);
+    }
+
+    is_optional && match_children(rest, false, siblings, start, captures)
+}
+
+/// When called, checks a predicate against the text of the nodes a pattern captured. Predicates
+/// about captures that didn't capture anything (ie, because they were in an optional pattern)
+/// always pass.
+fn check_predicate<TokenKind: TokenKindTrait>(
+    predicate: &Predicate,
+    captures: &Captures<TokenKind>,
+) -> bool {
+    let texts_of = |name: &str| {
+        captures
+            .iter()
+            .filter(|(capture, _)| capture == name)
+            .map(|(_, node)| InMemoryNode::deep_literal(node))
+            .collect::<Vec<String>>()
+    };
+    match predicate {
+        Predicate::Eq {
+            capture,
+            value,
+            is_negated,
+        } => {
+            let value = match value {
+                Argument::String(text) => Some(text.clone()),
+                Argument::Capture(name) => texts_of(name).into_iter().next(),
+            };
+            let Some(value) = value else {
+                return true;
+            };
+            texts_of(capture)
+                .iter()
+                .all(|text| (*text == value) != *is_negated)
+        }
+        Predicate::Match {
+            capture,
+            regex,
+            is_negated,
+        } => texts_of(capture)
+            .iter()
+            .all(|text| regex.is_match(text) != *is_negated),
+    }
+}
+
+struct QueryParser {
+    chars: Vec<char>,
+    index: usize,
+}
+
+impl QueryParser {
+    fn error(&self, message: &str) -> String {
+        let before = &self.chars[..self.index.min(self.chars.len())];
+        let line = before.iter().filter(|c| **c == '\n').count() + 1;
+        let col = before.iter().rev().take_while(|c| **c != '\n').count() + 1;
+        format!("Error in query at {line}:{col}: {message}")
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.index).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.index += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected `{c}`")))
+        }
+    }
+
+    /// When called, skips over whitespace and `;` comments.
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if c == ';' {
+                while self.peek().is_some_and(|c| c != '\n') {
+                    self.index += 1;
+                }
+            } else if c.is_whitespace() {
+                self.index += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// When called, reads a node kind, field name, capture name, or predicate name.
+    fn identifier(&mut self) -> String {
+        let mut identifier = String::new();
+        while let Some(c) = self
+            .peek()
+            .filter(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '?' | '!'))
+        {
+            identifier.push(c);
+            self.index += 1;
+        }
+        identifier
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut string = String::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.error("unterminated string")),
+                Some('"') => {
+                    self.index += 1;
+                    return Ok(string);
+                }
+                Some('\\') => {
+                    self.index += 1;
+                    match self.peek() {
+                        Some('n') => string.push('\n'),
+                        Some('t') => string.push('\t'),
+                        Some(c @ ('"' | '\\')) => string.push(c),
+                        // Other escapes are kept as is, so that ie `"\d+"` can be used in regexes
+                        Some(c) => {
+                            string.push('\\');
+                            string.push(c);
+                        }
+                        None => return Err(self.error("unterminated string")),
+                    }
+                    self.index += 1;
+                }
+                Some(c) => {
+                    string.push(c);
+                    self.index += 1;
+                }
+            }
+        }
+    }
+
+    /// When called, parses a pattern at the top level of a query, along with its predicates. A
+    /// pattern can be grouped with its predicates, ie `((NAME) @name (#eq? @name "foo"))`.
+    fn top_level_pattern(&mut self) -> Result<(Pattern, Vec<Predicate>), String> {
+        let mut predicates = vec![];
+        let start = self.index;
+        self.expect('(')?;
+        self.skip_whitespace();
+        let pattern = if self.peek() == Some('(') {
+            let pattern = self.pattern(&mut predicates)?;
+            loop {
+                self.skip_whitespace();
+                if self.eat(')') {
+                    break;
+                }
+                self.expect('(')?;
+                predicates.push(self.predicate()?);
+            }
+            pattern
+        } else {
+            self.index = start;
+            self.pattern(&mut predicates)?
+        };
+
+        // Predicates can only refer to nodes that the pattern captures
+        let mut names = HashSet::new();
+        pattern.capture_names(&mut names);
+        for predicate in predicates.iter() {
+            let (capture, value) = match predicate {
+                Predicate::Eq { capture, value, .. } => (capture, Some(value)),
+                Predicate::Match { capture, .. } => (capture, None),
+            };
+            let other = match value {
+                Some(Argument::Capture(other)) => Some(other),
+                _ => None,
+            };
+            for name in std::iter::once(capture).chain(other) {
+                if !names.contains(name) {
+                    return Err(format!(
+                        "Error in query: capture @{name} is not defined in its pattern"
+                    ));
+                }
+            }
+        }
+        Ok((pattern, predicates))
+    }
+
+    /// When called, parses a pattern, along with its field, quantifier, and captures. Predicates
+    /// found within the pattern are added to `predicates`.
+    fn pattern(&mut self, predicates: &mut Vec<Predicate>) -> Result<Pattern, String> {
+        self.skip_whitespace();
+
+        // `name: (NAME)`
+        let start = self.index;
+        let identifier = self.identifier();
+        self.skip_whitespace();
+        let field = if !identifier.is_empty() && self.eat(':') {
+            self.skip_whitespace();
+            Some(identifier)
+        } else {
+            self.index = start;
+            None
+        };
+
+        let mut children = vec![];
+        let matcher = match self.peek() {
+            Some('(') => {
+                self.index += 1;
+                self.skip_whitespace();
+                let matcher = match self.identifier().as_str() {
+                    "" => return Err(self.error("expected a node kind")),
+                    "_" => Matcher::Any,
+                    kind => Matcher::Kind(kind.into()),
+                };
+                loop {
+                    self.skip_whitespace();
+                    match self.peek() {
+                        Some(')') => {
+                            self.index += 1;
+                            break;
+                        }
+                        Some('(') if self.chars.get(self.index + 1) == Some(&'#') => {
+                            self.index += 1;
+                            predicates.push(self.predicate()?);
+                        }
+                        None => return Err(self.error("expected `)`")),
+                        _ => children.push(self.pattern(predicates)?),
+                    }
+                }
+                matcher
+            }
+            Some('"') => Matcher::Literal(self.string()?),
+            Some('_') => {
+                self.index += 1;
+                Matcher::Any
+            }
+            _ => return Err(self.error("expected a pattern")),
+        };
+
+        let quantifier = match self.peek() {
+            Some('?') => Quantifier::ZeroOrOne,
+            Some('*') => Quantifier::ZeroOrMore,
+            Some('+') => Quantifier::OneOrMore,
+            _ => Quantifier::One,
+        };
+        if quantifier != Quantifier::One {
+            self.index += 1;
+        }
+
+        let mut captures = vec![];
+        loop {
+            self.skip_whitespace();
+            if !self.eat('@') {
+                break;
+            }
+            match self.identifier() {
+                name if name.is_empty() => return Err(self.error("expected a capture name")),
+                name => captures.push(name),
+            }
+        }
+
+        Ok(Pattern {
+            matcher,
+            field,
+            quantifier,
+            children,
+            captures,
+        })
+    }
+
+    /// When called, parses a predicate, ie `#eq? @name "foo")`, after its opening `(`.
+    fn predicate(&mut self) -> Result<Predicate, String> {
+        self.skip_whitespace();
+        if !self.eat('#') {
+            return Err(self.error("expected a predicate"));
+        }
+        let name = self.identifier();
+
+        let mut arguments = vec![];
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some(')') => {
+                    self.index += 1;
+                    break;
+                }
+                Some('@') => {
+                    self.index += 1;
+                    arguments.push(Argument::Capture(self.identifier()));
+                }
+                Some('"') => arguments.push(Argument::String(self.string()?)),
+                _ => return Err(self.error("expected a capture or a string")),
+            }
+        }
+
+        let (capture, value) = match arguments.as_slice() {
+            [Argument::Capture(capture), value] => (capture.clone(), value.clone()),
+            _ => {
+                return Err(self.error(&format!(
+                    "#{name} expects a capture followed by a capture or a string"
+                )))
+            }
+        };
+        match name.as_str() {
+            "eq?" | "not-eq?" => Ok(Predicate::Eq {
+                capture,
+                value,
+                is_negated: name == "not-eq?",
+            }),
+            "match?" | "not-match?" => {
+                let Argument::String(pattern) = value else {
+                    return Err(self.error(&format!("#{name} expects a regex string")));
+                };
+                let regex = Regex::new(&pattern)
+                    .map_err(|err| self.error(&format!("invalid regex {pattern:?}: {err}")))?;
+                Ok(Predicate::Match {
+                    capture,
+                    regex,
+                    is_negated: name == "not-match?",
+                })
+            }
+            _ => Err(self.error(&format!("unknown predicate #{name}"))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Query, QueryCapture};
+    use crate::{
+        languages::{json, typescript},
+        node_tree::node::{InMemoryNode, TokenKindTrait},
+    };
+    use std::{cell::RefCell, rc::Rc};
+
+    const SOURCE: &str = "function foo(a, b) { return a; }
+const x = 1, y = () => 2;
+class Bar extends Baz { qux(c) {} }
+foo.bar(1);
+";
+
+    fn captured<TokenKind: TokenKindTrait>(
+        source: &str,
+        root: &Rc<RefCell<InMemoryNode<TokenKind>>>,
+    ) -> Vec<QueryCapture<TokenKind>> {
+        Query::parse(source)
+            .unwrap()
+            .matches(root)
+            .into_iter()
+            .flat_map(|query_match| query_match.captures)
+            .collect()
+    }
+
+    #[test]
+    fn matches_patterns_with_fields_and_captures() {
+        let root = InMemoryNode::<typescript::SyntaxKind>::new_from_parsed(SOURCE);
+
+        let query = Query::parse(
+            "; Functions and methods, along with their names
+            (FN_DECL name: (NAME) @name body: (BLOCK_STMT) @body) @function
+            (METHOD name: (NAME) @name)
+            (DECLARATOR pattern: (_) @name value: (ARROW_EXPR))",
+        )
+        .unwrap();
+        let matches = query.matches(&root);
+        assert_eq!(
+            matches
+                .iter()
+                .map(|query_match| query_match.capture("name").unwrap().selection.literal())
+                .collect::<Vec<_>>(),
+            vec!["foo", "y", "qux"]
+        );
+
+        // Captures are selections covering all of the text of the captured node
+        let captures = &matches[0].captures;
+        assert_eq!(
+            captures
+                .iter()
+                .map(|capture| capture.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["function", "name", "body"]
+        );
+        assert_eq!(
+            captures[0].selection.literal(),
+            "function foo(a, b) { return a; }"
+        );
+        assert_eq!(captures[2].selection.literal(), "{ return a; }");
+        assert_eq!(captures[2].selection.primary.to_rows_cols(), (1, 20));
+
+        // Fields must match, though other children can come between child patterns
+        assert!(Query::parse("(FN_DECL body: (NAME))")
+            .unwrap()
+            .matches(&root)
+            .is_empty());
+        let parameters = captured(
+            "(PARAMETER_LIST \"(\" (SINGLE_PATTERN)+ @parameter \")\")",
+            &root,
+        );
+        assert_eq!(
+            parameters
+                .iter()
+                .map(|capture| capture.selection.literal())
+                .collect::<Vec<_>>(),
+            vec!["a", "b", "c"]
+        );
+    }
+
+    #[test]
+    fn predicates_filter_matches() {
+        let root = InMemoryNode::<typescript::SyntaxKind>::new_from_parsed(SOURCE);
+        let names = |source: &str| {
+            captured(source, &root)
+                .iter()
+                .map(|capture| capture.selection.literal())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(names("((NAME) @name (#eq? @name \"foo\"))"), vec!["foo"]);
+        assert_eq!(
+            names("((NAME) @name (#match? @name \"^[A-Z]\"))"),
+            vec!["Bar"]
+        );
+        assert_eq!(
+            names("(CALL_EXPR callee: (DOT_EXPR object: (_) @object prop: (_) @prop (#not-eq? @object @prop)))"),
+            vec!["foo", "bar"]
+        );
+        assert!(names("(DOT_EXPR (_) @a (_) @b (#eq? @a @b))").is_empty());
+
+        // The same queries run against trees of any language
+        let root = InMemoryNode::<json::SyntaxKind>::new_from_parsed("{\"a\": 1, \"b\": [2]}");
+        let keys = captured("(Member (Key) @key (Array))", &root);
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].selection.literal(), "\"b\"");
+    }
+
+    #[test]
+    fn reports_invalid_queries() {
+        assert_eq!(
+            Query::parse("(FN_DECL (NAME)").err(),
+            Some("Error in query at 1:16: expected `)`".into())
+        );
+        assert_eq!(
+            Query::parse("(FN_DECL)\n  (#eq? @a \"b\")").err(),
+            Some("Error in query at 2:4: expected a node kind".into())
+        );
+        assert_eq!(
+            Query::parse("((NAME) @a (#eq? @b \"c\"))").err(),
+            Some("Error in query: capture @b is not defined in its pattern".into())
+        );
+        assert_eq!(
+            Query::parse("((NAME) @a (#match? @a \"(\"))")
+                .err()
+                .map(|err| err.starts_with("Error in query at 1:28: invalid regex")),
+            Some(true)
+        );
+        assert!(Query::parse("((NAME) @a (#frobnicate? @a \"c\"))").is_err());
+        assert!(Query::parse("")
+            .unwrap()
+            .matches(&InMemoryNode::<json::SyntaxKind>::new_from_parsed("1"))
+            .is_empty());
+    }
+}
@@ -0,0 +1,169 @@
+use std::{
+    cell::RefCell,
+    rc::{Rc, Weak},
+};
+
+use crate::node_tree::node::{InMemoryNode, TokenKindTrait};
+
+/// A ReparseScheduler keeps track of the regions of a token tree that have been edited but not
+/// yet reparsed, so that reparsing can be put off until a good time - ie, until every edit a
+/// command makes has been made, or until a feature (highlighting, structural motions) needs an up
+/// to date token tree.
+///
+/// Each dirty region is the `NodeMetadata::Literal` node that an edit left its text in. Until it
+/// is reparsed, the region is treated as unparsed text.
+pub struct ReparseScheduler<TokenKind: TokenKindTrait> {
+    dirty: Vec<Weak<RefCell<InMemoryNode<TokenKind>>>>,
+}
+
+impl<TokenKind: TokenKindTrait> Default for ReparseScheduler<TokenKind> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<TokenKind: TokenKindTrait> ReparseScheduler<TokenKind> {
+    pub fn new() -> Self {
+        Self { dirty: vec![] }
+    }
+
+    /// When called, marks the given node as needing to be reparsed the next time the scheduler is
+    /// flushed.
+    pub fn mark_dirty(&mut self, node: &Rc<RefCell<InMemoryNode<TokenKind>>>) {
+        let already_dirty = self.dirty.iter().any(|dirty| {
+            dirty
+                .upgrade()
+                .is_some_and(|dirty| Rc::ptr_eq(&dirty, node))
+        });
+        if !already_dirty {
+            self.dirty.push(Rc::downgrade(node));
+        }
+    }
+
+    /// Returns true if there are dirty regions still waiting to be reparsed.
+    pub fn is_pending(&self) -> bool {
+        self.dirty
+            .iter()
+            .filter_map(|dirty| dirty.upgrade())
+            .any(|dirty| Self::is_attached(&dirty))
+    }
+
+    /// When called, forgets about all dirty regions without reparsing them. This is useful when the
+    /// whole token tree is about to be thrown away.
+    pub fn clear(&mut self) {
+        self.dirty.clear();
+    }
+
+    /// When called, reparses all dirty regions in one batch. Returns the number of reparses that
+    /// were performed.
+    ///
+    /// Regions that are next to each other are often reparsed together, since the reparse of one
+    /// region reparses everything within its nearest reparsable ancestor. Any other dirty regions
+    /// inside that ancestor are swapped out of the tree, and are skipped.
+    pub fn flush(&mut self) -> Result<usize, String> {
+        let mut reparse_count = 0;
+        for dirty in std::mem::take(&mut self.dirty) {
+            let Some(node) = dirty.upgrade() else {
+                // The region was deleted by a later edit
+                continue;
+            };
+            if !Self::is_attached(&node) {
+                continue;
+            }
+
+            let (parent, child_index) = {
+                let node = node.borrow();
+                (
+                    node.parent.as_ref().and_then(|parent| parent.upgrade()),
+                    node.child_index,
+                )
+            };
+            let (Some(parent), Some(child_index)) = (parent, child_index) else {
+                continue;
+            };
+            InMemoryNode::reparse_child_at_index(parent, child_index)?;
+            reparse_count += 1;
+        }
+        Ok(reparse_count)
+    }
+
+    /// When called, returns true if the given node is still part of a token tree - ie, it hasn't
+    /// been removed or swapped out by an edit or reparse since it was marked as dirty.
+    fn is_attached(node: &Rc<RefCell<InMemoryNode<TokenKind>>>) -> bool {
+        let mut pointer = node.clone();
+        loop {
+            let (parent, child_index) = {
+                let pointer = pointer.borrow();
+                (
+                    pointer.parent.as_ref().map(|parent| parent.upgrade()),
+                    pointer.child_index,
+                )
+            };
+            let parent = match parent {
+                // Reached the root of the token tree
+                None => return true,
+                Some(None) => return false,
+                Some(Some(parent)) => parent,
+            };
+
+            let is_child_of_parent = child_index
+                .and_then(|index| parent.borrow().children.get(index).cloned())
+                .is_some_and(|child| Rc::ptr_eq(&child, &pointer));
+            if !is_child_of_parent {
+                return false;
+            }
+            pointer = parent;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ReparseScheduler;
+    use crate::{
+        languages::typescript::SyntaxKind,
+        node_tree::{
+            cursor::{Cursor, Selection},
+            node::{InMemoryNode, NodeMetadata},
+        },
+    };
+    use std::rc::Rc;
+
+    #[test]
+    fn deferred_edits_are_reparsed_on_flush() {
+        let root = InMemoryNode::<SyntaxKind>::new_from_parsed(
+            "let a = 1;\nlet b = 'bar';\nlet c = 3;\n",
+        );
+        let script = root.borrow().children[0].clone();
+        let first_statement = script.borrow().children[0].clone();
+        let mut scheduler = ReparseScheduler::new();
+        assert!(!scheduler.is_pending());
+
+        // Make two edits, neither of which is reparsed right away
+        for (start, end, literal) in [((2, 9), (2, 14), "'baz' + 1"), ((3, 9), (3, 10), "4")] {
+            Selection::new_from_cursor_pair(
+                Cursor::new_at_rows_cols(root.clone(), start),
+                Cursor::new_at_rows_cols(root.clone(), end),
+            )
+            .replace_deferred(literal, &mut scheduler)
+            .unwrap();
+        }
+        let literal = "let a = 1;\nlet b = 'baz' + 1;\nlet c = 4;\n";
+        assert_eq!(InMemoryNode::deep_literal(&root), literal);
+        assert!(scheduler.is_pending());
+        let dirty = Cursor::new_at_rows_cols(root.clone(), (2, 10)).node;
+        assert!(matches!(dirty.borrow().metadata, NodeMetadata::Literal(_)));
+
+        assert!(scheduler.flush().unwrap() > 0);
+        assert!(!scheduler.is_pending());
+        assert_eq!(scheduler.flush().unwrap(), 0);
+
+        // Both edits were reparsed, and the statement that wasn't edited was left alone
+        assert_eq!(InMemoryNode::deep_literal(&root), literal);
+        assert!(Rc::ptr_eq(&script.borrow().children[0], &first_statement));
+        for position in [(2, 10), (3, 9)] {
+            let node = Cursor::new_at_rows_cols(root.clone(), position).node;
+            assert!(matches!(node.borrow().metadata, NodeMetadata::AstNode { .. }));
+        }
+    }
+}
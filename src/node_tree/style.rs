@@ -0,0 +1,451 @@
+use colored::{ColoredString, Colorize};
+use std::ops::Range;
+
+/// A color that text can be drawn in. These are the 16 standard terminal colors, so that they can
+/// be rendered the same way by both the `colored` debug printer and the TUI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Color {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    BrightBlack,
+    BrightRed,
+    BrightGreen,
+    BrightYellow,
+    BrightBlue,
+    BrightMagenta,
+    BrightCyan,
+    BrightWhite,
+}
+
+impl Color {
+    /// When called, parses a color name as written in a theme file, ie `red` or `bright-black`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "black" => Some(Color::Black),
+            "red" => Some(Color::Red),
+            "green" => Some(Color::Green),
+            "yellow" => Some(Color::Yellow),
+            "blue" => Some(Color::Blue),
+            "magenta" => Some(Color::Magenta),
+            "cyan" => Some(Color::Cyan),
+            "white" => Some(Color::White),
+            "bright-black" => Some(Color::BrightBlack),
+            "bright-red" => Some(Color::BrightRed),
+            "bright-green" => Some(Color::BrightGreen),
+            "bright-yellow" => Some(Color::BrightYellow),
+            "bright-blue" => Some(Color::BrightBlue),
+            "bright-magenta" => Some(Color::BrightMagenta),
+            "bright-cyan" => Some(Color::BrightCyan),
+            "bright-white" => Some(Color::BrightWhite),
+            _ => None,
+        }
+    }
+
+    fn to_colored(self) -> colored::Color {
+        match self {
+            Color::Black => colored::Color::Black,
+            Color::Red => colored::Color::Red,
+            Color::Green => colored::Color::Green,
+            Color::Yellow => colored::Color::Yellow,
+            Color::Blue => colored::Color::Blue,
+            Color::Magenta => colored::Color::Magenta,
+            Color::Cyan => colored::Color::Cyan,
+            Color::White => colored::Color::White,
+            Color::BrightBlack => colored::Color::BrightBlack,
+            Color::BrightRed => colored::Color::BrightRed,
+            Color::BrightGreen => colored::Color::BrightGreen,
+            Color::BrightYellow => colored::Color::BrightYellow,
+            Color::BrightBlue => colored::Color::BrightBlue,
+            Color::BrightMagenta => colored::Color::BrightMagenta,
+            Color::BrightCyan => colored::Color::BrightCyan,
+            Color::BrightWhite => colored::Color::BrightWhite,
+        }
+    }
+}
+
+/// A Style describes how a piece of text should look when drawn, independent of what it is being
+/// drawn to. A color of None means the terminal's default color should be used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Style {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+    pub italic: bool,
+    pub reverse: bool,
+    pub underline: bool,
+}
+
+impl Style {
+    pub fn fg(self, color: Color) -> Self {
+        Self {
+            fg: Some(color),
+            ..self
+        }
+    }
+
+    pub fn bg(self, color: Color) -> Self {
+        Self {
+            bg: Some(color),
+            ..self
+        }
+    }
+
+    pub fn bold(self) -> Self {
+        Self { bold: true, ..self }
+    }
+
+    pub fn italic(self) -> Self {
+        Self {
+            italic: true,
+            ..self
+        }
+    }
+
+    pub fn reverse(self) -> Self {
+        Self {
+            reverse: true,
+            ..self
+        }
+    }
+
+    pub fn underline(self) -> Self {
+        Self {
+            underline: true,
+            ..self
+        }
+    }
+
+    /// When called, applies the style to `text` using ANSI escape codes, for printing to a
+    /// terminal in debug output.
+    pub fn apply(&self, text: String) -> ColoredString {
+        let mut result = ColoredString::from(text);
+        if let Some(fg) = self.fg {
+            result = result.color(fg.to_colored());
+        }
+        if let Some(bg) = self.bg {
+            result = result.on_color(bg.to_colored());
+        }
+        if self.bold {
+            result = result.bold();
+        }
+        if self.italic {
+            result = result.italic();
+        }
+        if self.reverse {
+            result = result.reversed();
+        }
+        if self.underline {
+            result = result.underline();
+        }
+        result
+    }
+}
+
+/// A HighlightGroup names a kind of text that can be highlighted, ie, keywords or comments.
+///
+/// Languages map their token kinds to highlight groups, and themes map highlight groups to styles,
+/// so that languages don't need to know about colors and themes don't need to know about languages.
+/// The editor's own interface (status lines, the tab line, etc) is styled with highlight groups
+/// too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HighlightGroup {
+    Keyword,
+    String,
+    Number,
+    Constant,
+    Comment,
+    FunctionName,
+    VariableDecl,
+    Type,
+    Property,
+    Punctuation,
+
+    StatusLine,
+    StatusLineInactive,
+    TabLine,
+    TabLineSelected,
+    NonText,
+    VerticalSplit,
+    LineNumber,
+    CursorLineNumber,
+    SignColumn,
+    FoldColumn,
+    Folded,
+    Mark,
+    DiagnosticError,
+    DiagnosticWarning,
+    DiagnosticInfo,
+    DiagnosticHint,
+}
+
+impl HighlightGroup {
+    pub const ALL: [HighlightGroup; 26] = [
+        HighlightGroup::Keyword,
+        HighlightGroup::String,
+        HighlightGroup::Number,
+        HighlightGroup::Constant,
+        HighlightGroup::Comment,
+        HighlightGroup::FunctionName,
+        HighlightGroup::VariableDecl,
+        HighlightGroup::Type,
+        HighlightGroup::Property,
+        HighlightGroup::Punctuation,
+        HighlightGroup::StatusLine,
+        HighlightGroup::StatusLineInactive,
+        HighlightGroup::TabLine,
+        HighlightGroup::TabLineSelected,
+        HighlightGroup::NonText,
+        HighlightGroup::VerticalSplit,
+        HighlightGroup::LineNumber,
+        HighlightGroup::CursorLineNumber,
+        HighlightGroup::SignColumn,
+        HighlightGroup::FoldColumn,
+        HighlightGroup::Folded,
+        HighlightGroup::Mark,
+        HighlightGroup::DiagnosticError,
+        HighlightGroup::DiagnosticWarning,
+        HighlightGroup::DiagnosticInfo,
+        HighlightGroup::DiagnosticHint,
+    ];
+
+    /// When called, returns the name the highlight group is referred to by in theme files.
+    pub fn name(&self) -> &'static str {
+        match self {
+            HighlightGroup::Keyword => "keyword",
+            HighlightGroup::String => "string",
+            HighlightGroup::Number => "number",
+            HighlightGroup::Constant => "constant",
+            HighlightGroup::Comment => "comment",
+            HighlightGroup::FunctionName => "function-name",
+            HighlightGroup::VariableDecl => "variable-decl",
+            HighlightGroup::Type => "type",
+            HighlightGroup::Property => "property",
+            HighlightGroup::Punctuation => "punctuation",
+            HighlightGroup::StatusLine => "status-line",
+            HighlightGroup::StatusLineInactive => "status-line-inactive",
+            HighlightGroup::TabLine => "tab-line",
+            HighlightGroup::TabLineSelected => "tab-line-selected",
+            HighlightGroup::NonText => "non-text",
+            HighlightGroup::VerticalSplit => "vertical-split",
+            HighlightGroup::LineNumber => "line-number",
+            HighlightGroup::CursorLineNumber => "cursor-line-number",
+            HighlightGroup::SignColumn => "sign-column",
+            HighlightGroup::FoldColumn => "fold-column",
+            HighlightGroup::Folded => "folded",
+            HighlightGroup::Mark => "mark",
+            HighlightGroup::DiagnosticError => "diagnostic-error",
+            HighlightGroup::DiagnosticWarning => "diagnostic-warning",
+            HighlightGroup::DiagnosticInfo => "diagnostic-info",
+            HighlightGroup::DiagnosticHint => "diagnostic-hint",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|group| group.name() == name)
+    }
+}
+
+/// A StyleSpan marks a range of characters within a piece of text that should be drawn in the
+/// given style. Ranges are in chars (not bytes).
+#[derive(Debug, Clone, PartialEq)]
+pub struct StyleSpan {
+    pub range: Range<usize>,
+    pub style: Style,
+}
+
+/// When called with a list of pieces of text and the style each should be drawn in, joins the
+/// pieces together, returning the resulting text and the spans for each piece. Adjacent pieces
+/// with the same style are merged into a single span.
+pub fn join_styled_pieces(
+    pieces: impl IntoIterator<Item = (String, Style)>,
+) -> (String, Vec<StyleSpan>) {
+    let mut text = String::new();
+    let mut spans: Vec<StyleSpan> = vec![];
+    let mut offset = 0;
+    for (piece, style) in pieces {
+        let length = piece.chars().count();
+        if length == 0 {
+            continue;
+        }
+        text.push_str(&piece);
+
+        match spans.last_mut() {
+            Some(last) if last.style == style => last.range.end += length,
+            _ => spans.push(StyleSpan {
+                range: offset..offset + length,
+                style,
+            }),
+        }
+        offset += length;
+    }
+    (text, spans)
+}
+
+/// When called, splits `text` into lines, returning each line along with the spans within it.
+/// Span ranges are adjusted to be relative to the start of each line, and the newline characters
+/// are not included in the output.
+pub fn split_styled_lines(text: &str, spans: &[StyleSpan]) -> Vec<(String, Vec<StyleSpan>)> {
+    let mut lines = vec![];
+    let mut line_start = 0;
+    for line in text.split('\n') {
+        let line_end = line_start + line.chars().count();
+        let line_spans = spans
+            .iter()
+            .filter(|span| span.range.start < line_end && span.range.end > line_start)
+            .map(|span| StyleSpan {
+                range: span.range.start.max(line_start) - line_start
+                    ..span.range.end.min(line_end) - line_start,
+                style: span.style,
+            })
+            .collect();
+        lines.push((line.to_string(), line_spans));
+
+        // Skip over the newline
+        line_start = line_end + 1;
+    }
+    lines
+}
+
+/// When called, returns `spans` with the chars in `range` drawn underlined, ie, to point out the
+/// text that a diagnostic is about. Spans that are only partly within `range` are split up.
+pub fn underline_styled_range(spans: &[StyleSpan], range: Range<usize>) -> Vec<StyleSpan> {
+    let mut result = vec![];
+    for span in spans {
+        let pieces = [
+            (
+                span.range.start..span.range.end.min(range.start),
+                span.style,
+            ),
+            (
+                span.range.start.max(range.start)..span.range.end.min(range.end),
+                span.style.underline(),
+            ),
+            (span.range.start.max(range.end)..span.range.end, span.style),
+        ];
+        for (range, style) in pieces {
+            if range.start < range.end {
+                result.push(StyleSpan { range, style });
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn join_and_split_styled_pieces() {
+        let red = Style::default().fg(Color::Red);
+        let bold = Style::default().bold();
+        let (text, spans) = join_styled_pieces([
+            ("let".to_string(), bold),
+            (" ".to_string(), Style::default()),
+            ("fö".to_string(), red),
+            ("o\nbar".to_string(), red),
+            ("".to_string(), bold),
+            (";".to_string(), Style::default()),
+        ]);
+        assert_eq!(text, "let föo\nbar;");
+        assert_eq!(
+            spans,
+            vec![
+                StyleSpan {
+                    range: 0..3,
+                    style: bold
+                },
+                StyleSpan {
+                    range: 3..4,
+                    style: Style::default()
+                },
+                StyleSpan {
+                    range: 4..11,
+                    style: red
+                },
+                StyleSpan {
+                    range: 11..12,
+                    style: Style::default()
+                },
+            ]
+        );
+
+        assert_eq!(
+            split_styled_lines(&text, &spans),
+            vec![
+                (
+                    "let föo".to_string(),
+                    vec![
+                        StyleSpan {
+                            range: 0..3,
+                            style: bold
+                        },
+                        StyleSpan {
+                            range: 3..4,
+                            style: Style::default()
+                        },
+                        StyleSpan {
+                            range: 4..7,
+                            style: red
+                        },
+                    ]
+                ),
+                (
+                    "bar;".to_string(),
+                    vec![
+                        StyleSpan {
+                            range: 0..3,
+                            style: red
+                        },
+                        StyleSpan {
+                            range: 3..4,
+                            style: Style::default()
+                        },
+                    ]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn underline_styled_range_splits_spans() {
+        let red = Style::default().fg(Color::Red);
+        let spans = vec![
+            StyleSpan {
+                range: 0..3,
+                style: red,
+            },
+            StyleSpan {
+                range: 3..6,
+                style: Style::default(),
+            },
+        ];
+        assert_eq!(
+            underline_styled_range(&spans, 2..4),
+            vec![
+                StyleSpan {
+                    range: 0..2,
+                    style: red
+                },
+                StyleSpan {
+                    range: 2..3,
+                    style: red.underline()
+                },
+                StyleSpan {
+                    range: 3..4,
+                    style: Style::default().underline()
+                },
+                StyleSpan {
+                    range: 4..6,
+                    style: Style::default()
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,179 @@
+use std::{cell::RefCell, collections::HashMap, path::Path};
+
+use crate::node_tree::style::{Color, HighlightGroup, Style};
+
+const DARK_THEME: &str = include_str!("../../themes/dark.theme");
+const LIGHT_THEME: &str = include_str!("../../themes/light.theme");
+
+thread_local! {
+    // The theme that syntax highlighting is currently rendered with, both in the debug printer and
+    // the TUI.
+    static CURRENT_THEME: RefCell<Theme> = RefCell::new(Theme::dark());
+}
+
+/// A Theme maps highlight groups to the style that text in that group should be drawn with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    styles: HashMap<HighlightGroup, Style>,
+}
+
+impl Theme {
+    /// When called, returns the built in theme for terminals with a dark background. This is the
+    /// default theme.
+    pub fn dark() -> Self {
+        Self::parse("dark", DARK_THEME).expect("built in dark theme is invalid")
+    }
+
+    /// When called, returns the built in theme for terminals with a light background.
+    pub fn light() -> Self {
+        Self::parse("light", LIGHT_THEME).expect("built in light theme is invalid")
+    }
+
+    /// When called, returns the theme with the given name. Built in themes are looked up by name,
+    /// and anything else is treated as the path to a theme file.
+    pub fn find(name: &str) -> Result<Self, String> {
+        match name {
+            "dark" => Ok(Self::dark()),
+            "light" => Ok(Self::light()),
+            path => Self::load(Path::new(path)),
+        }
+    }
+
+    /// When called, reads and parses the theme file at the given path. The theme is named after
+    /// the file, without its extension.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let source = std::fs::read_to_string(path)
+            .map_err(|_| format!("E185: Cannot find color scheme '{}'", path.display()))?;
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.display().to_string());
+        Self::parse(&name, &source)
+    }
+
+    /// When called, parses the contents of a theme file.
+    ///
+    /// Each line of a theme file maps a highlight group to a style, ie `keyword = bold blue` or
+    /// `status-line = white on blue`. Blank lines and lines starting with `#` are ignored.
+    pub fn parse(name: &str, source: &str) -> Result<Self, String> {
+        let mut styles = HashMap::new();
+        for (index, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let error =
+                |message: String| format!("Error in theme '{name}' line {}: {message}", index + 1);
+            let Some((group_name, style)) = line.split_once('=') else {
+                return Err(error(format!("Expected `group = style`: {line}")));
+            };
+            let Some(group) = HighlightGroup::from_name(group_name.trim()) else {
+                return Err(error(format!(
+                    "E411: Highlight group not found: {}",
+                    group_name.trim()
+                )));
+            };
+            styles.insert(group, Self::parse_style(style).map_err(error)?);
+        }
+
+        Ok(Self {
+            name: name.into(),
+            styles,
+        })
+    }
+
+    /// When called, parses a style written in a theme file, ie `bold white on blue`.
+    fn parse_style(source: &str) -> Result<Style, String> {
+        let mut style = Style::default();
+        let mut words = source.split_whitespace();
+        while let Some(word) = words.next() {
+            style = match word {
+                "bold" => style.bold(),
+                "italic" => style.italic(),
+                "reverse" => style.reverse(),
+                "underline" => style.underline(),
+                "on" => {
+                    let name = words.next().unwrap_or_default();
+                    let Some(color) = Color::from_name(name) else {
+                        return Err(format!("E421: Color name or number not recognized: {name}"));
+                    };
+                    style.bg(color)
+                }
+                name => {
+                    let Some(color) = Color::from_name(name) else {
+                        return Err(format!("E421: Color name or number not recognized: {name}"));
+                    };
+                    style.fg(color)
+                }
+            };
+        }
+        Ok(style)
+    }
+
+    /// When called, returns the style that text in the given highlight group should be drawn with.
+    /// Text that isn't in any group, or in a group the theme doesn't list, is drawn unstyled.
+    pub fn style(&self, group: Option<HighlightGroup>) -> Style {
+        group
+            .and_then(|group| self.styles.get(&group).copied())
+            .unwrap_or_default()
+    }
+}
+
+/// When called, makes `theme` the theme that syntax highlighting is rendered with.
+pub fn set_current_theme(theme: Theme) {
+    CURRENT_THEME.with(|current| *current.borrow_mut() = theme);
+}
+
+/// When called, returns the name of the theme that syntax highlighting is rendered with.
+pub fn current_theme_name() -> String {
+    CURRENT_THEME.with(|current| current.borrow().name.clone())
+}
+
+/// When called, returns the style that text in the given highlight group should be drawn with in
+/// the current theme.
+pub fn current_style(group: Option<HighlightGroup>) -> Style {
+    CURRENT_THEME.with(|current| current.borrow().style(group))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_theme() {
+        let theme = Theme::parse(
+            "test",
+            "# a comment\n\nkeyword = bold blue\nstatus-line = italic white on bright-black\n",
+        )
+        .unwrap();
+        assert_eq!(
+            theme.style(Some(HighlightGroup::Keyword)),
+            Style::default().bold().fg(Color::Blue)
+        );
+        assert_eq!(
+            theme.style(Some(HighlightGroup::StatusLine)),
+            Style::default()
+                .italic()
+                .fg(Color::White)
+                .bg(Color::BrightBlack)
+        );
+        assert_eq!(theme.style(Some(HighlightGroup::Comment)), Style::default());
+        assert_eq!(theme.style(None), Style::default());
+
+        assert_eq!(
+            Theme::parse("test", "keyword = bold\nstring = blu"),
+            Err(
+                "Error in theme 'test' line 2: E421: Color name or number not recognized: blu"
+                    .into()
+            )
+        );
+        assert_eq!(
+            Theme::parse("test", "keywords = bold"),
+            Err("Error in theme 'test' line 1: E411: Highlight group not found: keywords".into())
+        );
+        assert_eq!(Theme::find("dark").unwrap(), Theme::dark());
+        assert_ne!(Theme::light(), Theme::dark());
+    }
+}
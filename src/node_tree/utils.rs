@@ -1,5 +1,8 @@
 use std::fmt::Display;
 
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
 use super::cursor::CursorSeek;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -22,6 +25,9 @@ pub enum Newline {
 
 pub const NEWLINE: &'static char = &'\n';
 
+// Like vim's `tabstop` option, the default number of columns between tab stops
+pub const DEFAULT_TABSTOP: usize = 8;
+
 const CHAR_OF_VALUE_255: &'static char = &(255 as char);
 
 /// Returns true if the given char is a lower word char according to stock vim definitions
@@ -98,12 +104,15 @@ pub fn is_delimiter(buffer: &[char]) -> Option<Delimiter> {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum VimClass {
-    WhiteSpace = 0,
-    Punctuation = 1,
-    Keyword = 2,
-    Multibyte = 3,
+    WhiteSpace,
+    Punctuation,
+    Keyword,
+    Emoji,
+    // Characters from other scripts (ie, Hiragana, CJK ideographs, braille) form words with other
+    // characters from the same script. Like vim, the script is identified by a code point.
+    Script(u32),
 }
 impl Display for VimClass {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -120,33 +129,249 @@ impl Display for VimClass {
  */
 pub fn vim_cls(c: char, cls_bigword: bool) -> VimClass {
     if c == ' ' || c == '\t' || c == '\0' {
-        VimClass::WhiteSpace
-    } else if c.len_utf8() > 1 {
-        // If cls_bigword, report multi-byte chars as class 1.
-        if cls_bigword {
-            VimClass::Punctuation
-        } else {
-            // process code leading/trailing bytes
-            // TODO: https://github.com/vim/vim/blob/659cb28c25b756e59c712c337f8b4650e85f8bcd/src/mbyte.c#L863
-            // return dbcs_class(
-            //     ((unsigned)c >> 8), (c & 0xFF)
-            // );
-            VimClass::Multibyte
-        }
-    // } else if (enc_utf8) {
-    //     // TODO: https://github.com/vim/vim/blob/659cb28c25b756e59c712c337f8b4650e85f8bcd/src/mbyte.c#L2901
-    //     let c = 2 // utf_class(c);
-    //     if c != 0 && cls_bigword {
-    //         1
-    //     } else {
-    //         c
-    //     }
-    } else if cls_bigword {
+        return VimClass::WhiteSpace;
+    }
+
+    let class = utf_class(c);
+    if class != VimClass::WhiteSpace && cls_bigword {
         // If cls_bigword is TRUE, report all non-blanks as class 1.
         VimClass::Punctuation
-    } else if is_lower_word_char(c) {
-        VimClass::Keyword
     } else {
-        VimClass::Punctuation
+        class
+    }
+}
+
+// Ranges of multibyte characters that aren't part of words, or that form words of their own. This
+// mirrors the `classes` table in vim's `utf_class`:
+// https://github.com/vim/vim/blob/659cb28c25b756e59c712c337f8b4650e85f8bcd/src/mbyte.c#L2901
+const UTF_CLASSES: &[(u32, u32, VimClass)] = &[
+    (0x037e, 0x037e, VimClass::Punctuation), // Greek question mark
+    (0x0387, 0x0387, VimClass::Punctuation), // Greek ano teleia
+    (0x055a, 0x055f, VimClass::Punctuation), // Armenian punctuation
+    (0x0589, 0x0589, VimClass::Punctuation), // Armenian full stop
+    (0x05be, 0x05be, VimClass::Punctuation),
+    (0x05c0, 0x05c0, VimClass::Punctuation),
+    (0x05c3, 0x05c3, VimClass::Punctuation),
+    (0x05f3, 0x05f4, VimClass::Punctuation),
+    (0x060c, 0x060c, VimClass::Punctuation),
+    (0x061b, 0x061b, VimClass::Punctuation),
+    (0x061f, 0x061f, VimClass::Punctuation),
+    (0x066a, 0x066d, VimClass::Punctuation),
+    (0x06d4, 0x06d4, VimClass::Punctuation),
+    (0x0700, 0x070d, VimClass::Punctuation), // Syriac punctuation
+    (0x0964, 0x0965, VimClass::Punctuation),
+    (0x0970, 0x0970, VimClass::Punctuation),
+    (0x0df4, 0x0df4, VimClass::Punctuation),
+    (0x0e4f, 0x0e4f, VimClass::Punctuation),
+    (0x0e5a, 0x0e5b, VimClass::Punctuation),
+    (0x0f04, 0x0f12, VimClass::Punctuation),
+    (0x0f3a, 0x0f3d, VimClass::Punctuation),
+    (0x0f85, 0x0f85, VimClass::Punctuation),
+    (0x104a, 0x104f, VimClass::Punctuation), // Myanmar punctuation
+    (0x10fb, 0x10fb, VimClass::Punctuation), // Georgian punctuation
+    (0x1361, 0x1368, VimClass::Punctuation), // Ethiopic punctuation
+    (0x166d, 0x166e, VimClass::Punctuation), // Canadian Syl. punctuation
+    (0x1680, 0x1680, VimClass::WhiteSpace),
+    (0x169b, 0x169c, VimClass::Punctuation),
+    (0x16eb, 0x16ed, VimClass::Punctuation),
+    (0x1735, 0x1736, VimClass::Punctuation),
+    (0x17d4, 0x17dc, VimClass::Punctuation), // Khmer punctuation
+    (0x1800, 0x180a, VimClass::Punctuation), // Mongolian punctuation
+    (0x2000, 0x200b, VimClass::WhiteSpace),  // spaces
+    (0x200c, 0x2027, VimClass::Punctuation), // punctuation and symbols
+    (0x2028, 0x2029, VimClass::WhiteSpace),
+    (0x202a, 0x202e, VimClass::Punctuation), // punctuation and symbols
+    (0x202f, 0x202f, VimClass::WhiteSpace),
+    (0x2030, 0x205e, VimClass::Punctuation), // punctuation and symbols
+    (0x205f, 0x205f, VimClass::WhiteSpace),
+    (0x2060, 0x206f, VimClass::Punctuation), // punctuation and symbols
+    (0x2070, 0x207f, VimClass::Script(0x2070)), // superscript
+    (0x2080, 0x2094, VimClass::Script(0x2080)), // subscript
+    (0x20a0, 0x27ff, VimClass::Punctuation), // all kinds of symbols
+    (0x2800, 0x28ff, VimClass::Script(0x2800)), // braille
+    (0x2900, 0x2998, VimClass::Punctuation), // arrows, brackets, etc.
+    (0x29d8, 0x29db, VimClass::Punctuation),
+    (0x29fc, 0x29fd, VimClass::Punctuation),
+    (0x2e00, 0x2e7f, VimClass::Punctuation), // supplemental punctuation
+    (0x3000, 0x3000, VimClass::WhiteSpace),  // ideographic space
+    (0x3001, 0x3020, VimClass::Punctuation), // ideographic punctuation
+    (0x3030, 0x3030, VimClass::Punctuation),
+    (0x303d, 0x303d, VimClass::Punctuation),
+    (0x3040, 0x309f, VimClass::Script(0x3040)), // Hiragana
+    (0x30a0, 0x30ff, VimClass::Script(0x30a0)), // Katakana
+    (0x3300, 0x9fff, VimClass::Script(0x4e00)), // CJK Ideographs
+    (0xac00, 0xd7a3, VimClass::Script(0xac00)), // Hangul Syllables
+    (0xf900, 0xfaff, VimClass::Script(0x4e00)), // CJK Ideographs
+    (0xfd3e, 0xfd3f, VimClass::Punctuation),
+    (0xfe30, 0xfe6b, VimClass::Punctuation), // punctuation forms
+    (0xff00, 0xff0f, VimClass::Punctuation), // half/fullwidth ASCII
+    (0xff1a, 0xff20, VimClass::Punctuation), // half/fullwidth ASCII
+    (0xff3b, 0xff40, VimClass::Punctuation), // half/fullwidth ASCII
+    (0xff5b, 0xff65, VimClass::Punctuation), // half/fullwidth ASCII
+    (0x1d000, 0x1d24f, VimClass::Punctuation), // Musical notation
+    (0x1d400, 0x1d7ff, VimClass::Punctuation), // Mathematical Alphanumeric Symbols
+    (0x1f000, 0x1f2ff, VimClass::Punctuation), // Game pieces; enclosed characters
+    (0x1f300, 0x1f9ff, VimClass::Punctuation), // Many symbol blocks
+    (0x20000, 0x2a6df, VimClass::Script(0x4e00)), // CJK Ideographs
+    (0x2a700, 0x2b73f, VimClass::Script(0x4e00)), // CJK Ideographs
+    (0x2b740, 0x2b81f, VimClass::Script(0x4e00)), // CJK Ideographs
+    (0x2f800, 0x2fa1f, VimClass::Script(0x4e00)), // CJK Ideographs
+];
+
+/// When called, returns the class of the given character, like vim's `utf_class`. Characters of
+/// the same class (other than whitespace) are grouped together into words.
+pub fn utf_class(c: char) -> VimClass {
+    // First quick check for Latin1 characters, use 'iskeyword'.
+    if (c as u32) < 0x100 {
+        if c == ' ' || c == '\t' || c == '\0' || c == '\u{a0}' {
+            return VimClass::WhiteSpace; // blank
+        }
+        if is_lower_word_char(c) {
+            return VimClass::Keyword; // word character
+        }
+        return VimClass::Punctuation; // punctuation
+    }
+
+    // emoji
+    if is_emoji(c) {
+        return VimClass::Emoji;
+    }
+
+    UTF_CLASSES
+        .iter()
+        .find(|(first, last, _)| (*first..=*last).contains(&(c as u32)))
+        .map(|(_, _, class)| *class)
+        // most other characters are "word" characters
+        .unwrap_or(VimClass::Keyword)
+}
+
+/// When called, returns true if the given character is an emoji, ie, it is drawn as a wide
+/// pictograph.
+fn is_emoji(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1f000..=0x1faff // Mahjong tiles through Symbols and Pictographs Extended-A
+            | 0x2600..=0x27bf // Miscellaneous Symbols and Dingbats
+    ) && c.to_string().width() == 2
+}
+
+/// When called, returns the number of columns the given grapheme cluster takes up when drawn in a
+/// terminal, if it starts at the given (zero indexed) display column. Wide characters (ie, CJK
+/// ideographs and emoji) take up two columns, and a tab takes up the columns until the next
+/// multiple of `tabstop`.
+pub fn grapheme_display_width(grapheme: &str, display_col: usize, tabstop: usize) -> usize {
+    if grapheme == "\t" {
+        let tabstop = tabstop.max(1);
+        tabstop - display_col % tabstop
+    } else {
+        grapheme.width()
+    }
+}
+
+/// When called, returns the number of columns the given text takes up when drawn in a terminal,
+/// starting at the first column. `text` should not contain newlines.
+pub fn display_width(text: &str, tabstop: usize) -> usize {
+    text.graphemes(true).fold(0, |display_col, grapheme| {
+        display_col + grapheme_display_width(grapheme, display_col, tabstop)
+    })
+}
+
+/// When called, strips the quotes from around `text` if it is quoted with `"` or `'`, handling
+/// backslash escapes in double quoted text (ie, `"a\\"b"` becomes `a"b`). Text that isn't
+/// quoted is returned as is.
+pub fn unquote(text: &str) -> String {
+    let is_quoted_with = |quote| text.len() >= 2 && text.starts_with(quote) && text.ends_with(quote);
+    if is_quoted_with('\'') {
+        return text[1..text.len() - 1].into();
+    }
+    if !is_quoted_with('"') {
+        return text.into();
+    }
+
+    let mut unquoted = String::new();
+    let mut chars = text[1..text.len() - 1].chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') => unquoted.push('\n'),
+                Some('t') => unquoted.push('\t'),
+                Some(escaped) => unquoted.push(escaped),
+                None => unquoted.push(c),
+            },
+            c => unquoted.push(c),
+        }
+    }
+    unquoted
+}
+
+/// When called, splits a key path like `dependencies.foo` up into its segments. Segments can be
+/// quoted so that they can contain dots, ie, `tool."black.toml".line`.
+pub fn split_key_path(path: &str) -> Vec<String> {
+    let mut segments = vec![];
+    let mut segment = String::new();
+    let mut quote = None;
+    for c in path.chars() {
+        match (c, quote) {
+            ('.', None) => segments.push(unquote(std::mem::take(&mut segment).trim())),
+            ('"' | '\'', None) => {
+                quote = Some(c);
+                segment.push(c);
+            }
+            (c, Some(open)) if c == open => {
+                quote = None;
+                segment.push(c);
+            }
+            (c, _) => segment.push(c),
+        }
+    }
+    segments.push(unquote(segment.trim()));
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_multibyte_chars() {
+        assert_eq!(vim_cls('a', false), VimClass::Keyword);
+        assert_eq!(vim_cls('é', false), VimClass::Keyword);
+        assert_eq!(vim_cls('.', false), VimClass::Punctuation);
+        assert_eq!(vim_cls('\u{3000}', false), VimClass::WhiteSpace);
+        assert_eq!(vim_cls('「', false), VimClass::Punctuation);
+        assert_eq!(vim_cls('ひ', false), VimClass::Script(0x3040));
+        assert_eq!(vim_cls('カ', false), VimClass::Script(0x30a0));
+        assert_eq!(vim_cls('漢', false), VimClass::Script(0x4e00));
+        assert_eq!(vim_cls('😀', false), VimClass::Emoji);
+        assert_eq!(vim_cls('→', false), VimClass::Punctuation);
+
+        // With cls_bigword, only whitespace is distinguished
+        assert_eq!(vim_cls('漢', true), VimClass::Punctuation);
+        assert_eq!(vim_cls('\u{3000}', true), VimClass::WhiteSpace);
+    }
+
+    #[test]
+    fn measure_display_width() {
+        assert_eq!(display_width("abc", 8), 3);
+        assert_eq!(display_width("漢字", 8), 4);
+        assert_eq!(display_width("😀!", 8), 3);
+        assert_eq!(display_width("👍🏽", 8), 2);
+        assert_eq!(display_width("e\u{301}", 8), 1);
+        assert_eq!(display_width("\tx", 8), 9);
+        assert_eq!(display_width("ab\tx", 4), 5);
+        assert_eq!(display_width("ab\t\tx", 4), 9);
+    }
+
+    #[test]
+    fn split_key_paths() {
+        assert_eq!(split_key_path("dependencies.foo"), vec!["dependencies", "foo"]);
+        assert_eq!(
+            split_key_path("tool.\"black.toml\".'line'"),
+            vec!["tool", "black.toml", "line"]
+        );
+        assert_eq!(split_key_path("jobs.build.steps.0"), vec!["jobs", "build", "steps", "0"]);
+        assert_eq!(unquote("\"a\\\"b\""), "a\"b");
+        assert_eq!(unquote("'a\\b'"), "a\\b");
+        assert_eq!(unquote("\""), "\"");
     }
 }